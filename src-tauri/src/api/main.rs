@@ -7,6 +7,12 @@ use actix_web::{
     web, App, HttpServer,
     middleware::Logger};
 use shared_lib::{db, state::AppState, middleware::auth_middleware::Authenticated};
+use shared_lib::{user_sync_logic, config::UserSyncConfig};
+use shared_lib::{notification_logic, config::SmtpConfig};
+use shared_lib::permission_catalog_logic;
+use shared_lib::{sql_sandbox_logic::SqlSandbox, config::SandboxConfig};
+use shared_lib::config::HelpConfig;
+use shared_lib::{aplicativo_logic::AplicativoCache, middleware::aplicativo_middleware::SelectAplicativo};
 
 // Importamos el cliente MsalAuthClient y AppConfig desde nuestros módulos compartidos.
 use shared_lib::{
@@ -73,7 +79,7 @@ async fn greet(name: String) -> String {
 
 // Importa los módulos de rutas
 mod routes;
-use routes::{auth_route, license_route, user_route, menu_route};
+use routes::{auth_route, license_route, user_route, menu_route, user_import_route, scim_route, identity_route, domain_policy_route, mfa_route, notification_route, me_route, impersonation_route, grant_route, access_request_route, permission_catalog_route, row_security_route, saved_query_route, user_view_route, help_route, aplicativo_route};
 
 // Importaciones para el estado de la aplicación
 // IMPORTANTE: Eliminamos la importación fallida de jwks_rs:
@@ -115,6 +121,33 @@ pub async fn main_api_server() -> std::io::Result<()> {
 
     let jwt_auth_client_mutex = Arc::new(Mutex::new(jwt_auth_client));
 
+    // 4.1 Sincronización programada de usuarios ERP -> riy (USER_SYNC_INTERVAL_MINUTES)
+    let sql_collate_clause = std::env::var("SQL_COLLATE_CLAUSE").unwrap_or_default();
+    user_sync_logic::spawn_scheduled_sync(
+        db_pool.clone(),
+        sql_collate_clause.clone(),
+        UserSyncConfig::from_env(),
+    );
+
+    // 4.2 Worker de la cola de correo (SMTP_HOST) y aviso de licencia por vencer
+    notification_logic::spawn_queue_worker(
+        db_pool.clone(),
+        aplicativo_id.trim().parse().unwrap_or_default(),
+        std::env::var("APLICATIVO").unwrap_or_default(),
+        SmtpConfig::from_env(),
+    );
+
+    // 4.3 Catálogo de permisos desde el archivo declarativo (PERMISSION_CATALOG_FILE)
+    permission_catalog_logic::sync_from_env(
+        &db_pool,
+        aplicativo_id.trim().parse().unwrap_or_default(),
+        &sql_collate_clause,
+    ).await;
+
+    // 4.4 Pool de solo lectura para el SQL definido por usuarios (READONLY_DATABASE_URL)
+    let sql_sandbox = SqlSandbox::connect(&SandboxConfig::from_env()).await
+        .expect("Error al inicializar el pool de solo lectura");
+
     // 5. Crear el estado inicial de la aplicación Actix
     let initial_state = AppState {
         db_pool: Arc::new(db_pool),
@@ -122,6 +155,11 @@ pub async fn main_api_server() -> std::io::Result<()> {
         reqwest_client: reqwest_client.clone(),
         jwks_url: jwks_url.to_string(),
         jwt_auth_client: jwt_auth_client_mutex, 
+        scim_bearer_token: std::env::var("SCIM_BEARER_TOKEN").unwrap_or_default(),
+        mfa_encryption_key: std::env::var("MFA_ENCRYPTION_KEY").unwrap_or_default(),
+        sql_sandbox,
+        help_config: HelpConfig::from_env(),
+        aplicativos: AplicativoCache::default(),
     };
 
     // 6. Configuración de CORS
@@ -134,6 +172,8 @@ pub async fn main_api_server() -> std::io::Result<()> {
     // 7. Servidor HTTP
     HttpServer::new(move || {
         App::new()
+            // Elige el aplicativo por X-Aplicativo o /a/{codigo} antes del ruteo
+            .wrap(SelectAplicativo)
             .wrap(cors.clone()) // Primera capa: maneja CORS
             .wrap(Logger::default()) // Segunda capa: registra todas las solicitudes
             // Esto asegura que cada thread reciba una copia de la referencia del estado
@@ -144,6 +184,8 @@ pub async fn main_api_server() -> std::io::Result<()> {
                 web::scope("/api/public")
                     .configure(license_route::license_config) // license_route::get_license_status
                     .configure(auth_route::auth_config_public) // auth_route::login_user_handler
+                    .configure(mfa_route::mfa_config_public)
+                    .configure(notification_route::notification_config_public)
                     
                     // 🚨 CRÍTICO: Añadir la ruta para manejar los tokens/códigos externos
                     .route("/auth/process-auth", web::post().to(auth_route::process_auth_handler))
@@ -157,6 +199,27 @@ pub async fn main_api_server() -> std::io::Result<()> {
 
                     .configure(user_route::user_config)
                     .configure(menu_route::menu_config)
+                    .configure(user_import_route::user_import_config)
+                    .configure(identity_route::identity_config)
+                    .configure(domain_policy_route::domain_policy_config)
+                    .configure(mfa_route::mfa_config)
+                    .configure(notification_route::notification_config)
+                    .configure(me_route::me_config)
+                    .configure(impersonation_route::impersonation_config)
+                    .configure(grant_route::grant_config)
+                    .configure(access_request_route::access_request_config)
+                    .configure(permission_catalog_route::permission_catalog_config)
+                    .configure(row_security_route::row_security_config)
+                    .configure(saved_query_route::saved_query_config)
+                    .configure(user_view_route::user_view_config)
+                    .configure(help_route::help_config)
+                    .configure(aplicativo_route::aplicativo_config)
+            )
+
+            // Aprovisionamiento SCIM 2.0 (Entra ID); autenticado con SCIM_BEARER_TOKEN
+            .service(
+                web::scope("/scim/v2")
+                    .configure(scim_route::scim_config)
             )
         
     })
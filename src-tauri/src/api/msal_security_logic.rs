@@ -16,6 +16,9 @@ use shared_lib::{
     models::{self}, // Importar models::* para usar models::RiyUser, models::Permission, etc.
 };
 use shared_lib::auth;
+use shared_lib::user_repository;
+use shared_lib::identity_link_logic::{self, IdentityLinkError};
+use shared_lib::domain_policy_logic::{self, DomainPolicyError};
 use shared_lib::utils::get_permissions_by_app;
 use super::errors::CustomError; // Tu tipo de error local
 
@@ -31,6 +34,8 @@ pub struct MsalClaims {
     pub aud: String, // Audience (Debe ser tu MSAL_CLIENT_ID)
     pub exp: u64,
     pub iss: String, // Issuer
+    #[serde(default)]
+    pub oid: Option<String>, // Object ID (identificador estable del usuario en el tenant)
 }
 
 // -------------------------------------------------------------
@@ -174,25 +179,110 @@ pub async fn validate_and_get_user(
     let user_upn = msal_claims.upn;
     // ...
 
-    // Control de Dominio (Lista Blanca Multi-Tenant) 🛡️
-    let domain = user_upn.split('@').nth(1)
-        .ok_or_else(|| Box::new(CustomError::new(400, "Formato de UPN inválido en el token")) as Box<dyn std::error::Error>)?;
-    
-    if !app_state.whitelisted_domains.contains(&domain.to_lowercase()) {
-        eprintln!("Dominio no autorizado: {}", domain);
-        return Err(Box::new(CustomError::new(403, "Dominio no autorizado. Acceso denegado.")) as Box<dyn std::error::Error>);
-    }
+    // Se busca primero por el vínculo (microsoft, oid); el UPN solo se usa en el primer login.
+    let identity = msal_claims.oid.clone().map(|oid| models::ExternalIdentity {
+        provider: identity_link_logic::PROVEEDOR_MICROSOFT.to_string(),
+        subject: oid,
+        issuer: Some(msal_claims.iss.clone()),
+    });
+    let aplicativo_id = app_state.current_aplicativo_id().await;
 
-    // 4. BÚSQUEDA EN BASE DE DATOS
-    
-    // Cargar datos del usuario desde la DB (Usamos la ruta correcta: auth)
-    let riy_user = auth::authenticate_msal_user(
+    // Control de Dominio y Tenant (riy.riy_dominio_permitido) 🛡️
+    let policy_identity = identity.clone().unwrap_or_else(|| models::ExternalIdentity {
+        provider: identity_link_logic::PROVEEDOR_MICROSOFT.to_string(),
+        subject: String::new(),
+        issuer: Some(msal_claims.iss.clone()),
+    });
+    let domain_policy = domain_policy_logic::evaluate_login(
         &app_state.db_pool,
-        &user_upn, 
-        &app_state.sql_collate_clause
+        aplicativo_id,
+        &user_upn,
+        &policy_identity,
+        &app_state.sql_collate_clause,
     ).await
-    .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al buscar usuario: {}", e))) as Box<dyn std::error::Error>)?
-    .ok_or_else(|| Box::new(CustomError::new(404, "Usuario válido de Azure, pero no existe en RIY-DATOS.")) as Box<dyn std::error::Error>)?;
+    .map_err(|e| match e {
+        DomainPolicyError::Forbidden(msg) => {
+            eprintln!("Login MSAL rechazado por política de dominio: {}", msg);
+            Box::new(CustomError::new(403, &msg)) as Box<dyn std::error::Error>
+        }
+        other => Box::new(CustomError::new(500, &other.to_string())) as Box<dyn std::error::Error>,
+    })?;
+
+    // 4. BÚSQUEDA EN BASE DE DATOS
+    
+    let linked_user_id = match &identity {
+        Some(identity) => identity_link_logic::find_user_id_by_identity(&app_state.db_pool, identity).await
+            .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al buscar identidad: {}", e))) as Box<dyn std::error::Error>)?,
+        None => None,
+    };
+
+    let riy_user = match linked_user_id {
+        Some(linked_id) => {
+            // Solo usuarios vigentes: un usuario desactivado no entra por ninguna identidad vinculada
+            let linked_user = user_repository::find_user_by_id(&app_state.db_pool, linked_id, &app_state.sql_collate_clause).await
+                .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al buscar usuario: {}", e))) as Box<dyn std::error::Error>)?
+                .ok_or_else(|| Box::new(CustomError::new(403, "La identidad vinculada apunta a un usuario inexistente o que no está vigente.")) as Box<dyn std::error::Error>)?;
+            if let Some(identity) = &identity {
+                identity_link_logic::touch_identity(&app_state.db_pool, identity, &user_upn).await
+                    .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al registrar acceso: {}", e))) as Box<dyn std::error::Error>)?;
+            }
+            linked_user
+        }
+        None => {
+            // Cargar datos del usuario desde la DB (Usamos la ruta correcta: auth)
+            let existing_user = auth::authenticate_msal_user(
+                &app_state.db_pool,
+                &user_upn, 
+                &app_state.sql_collate_clause
+            ).await
+            .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al buscar usuario: {}", e))) as Box<dyn std::error::Error>)?;
+
+            match existing_user {
+                Some(riy_user) => {
+                    // Solo usuarios vigentes (SCIM o la sincronización con el ERP pueden haberlo dado de baja)
+                    if let Some(usuario_id) = riy_user.usuario_id {
+                        user_repository::find_user_by_id(&app_state.db_pool, usuario_id, &app_state.sql_collate_clause).await
+                            .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al buscar usuario: {}", e))) as Box<dyn std::error::Error>)?
+                            .ok_or_else(|| Box::new(CustomError::new(403, "El usuario no está vigente en RIY-DATOS.")) as Box<dyn std::error::Error>)?;
+                    }
+                    // Primer login con esta identidad: se vincula (o se rechaza si el correo fue reasignado).
+                    if let (Some(identity), Some(usuario_id)) = (&identity, riy_user.usuario_id) {
+                        identity_link_logic::link_identity(&app_state.db_pool, usuario_id, identity, Some(&user_upn), "System").await
+                            .map_err(|e| match e {
+                                IdentityLinkError::Conflict(msg) => Box::new(CustomError::new(403, &msg)) as Box<dyn std::error::Error>,
+                                other => Box::new(CustomError::new(500, &other.to_string())) as Box<dyn std::error::Error>,
+                            })?;
+                    }
+                    riy_user
+                }
+                None if domain_policy.politica == models::DomainPolicyMode::AutoProvision => {
+                    // Auto-aprovisionamiento: crea, vincula y asigna el rol por defecto del dominio.
+                    let user_info = models::UserInfo {
+                        username: user_upn.clone(),
+                        email: user_upn.clone(),
+                        name: None,
+                        identity: identity.clone(),
+                    };
+                    let created = user_repository::find_or_create_user(
+                        &app_state.db_pool,
+                        &user_info,
+                        aplicativo_id,
+                        &app_state.sql_collate_clause,
+                    ).await
+                    .map_err(|e| Box::new(CustomError::new(500, &format!("Error al crear el usuario: {}", e))) as Box<dyn std::error::Error>)?;
+                    models::User {
+                        usuario_id: created.usuario_id,
+                        usuario: created.usuario.unwrap_or_default(),
+                        nombre: created.nombre.unwrap_or_default(),
+                        correo: created.correo.unwrap_or_default(),
+                    }
+                }
+                None => {
+                    return Err(Box::new(CustomError::new(404, "Usuario válido de Azure, pero no existe en RIY-DATOS.")) as Box<dyn std::error::Error>);
+                }
+            }
+        }
+    };
 
     // 5. OBTENER PERMISOS// 🚨 Paso 1: Extraer el i32 de Option<i32> y manejar el error de datos (si el ID fuera None)
     let user_id = riy_user.usuario_id
@@ -207,7 +297,7 @@ pub async fn validate_and_get_user(
             // 🚨 CORRECCIÓN 1: Pasar el ID del usuario (i32), no el nombre de usuario (&String)
             user_id, // Ahora es i32 (no Option<i32>)
             // 🚨 CORRECCIÓN 2: Añadir el argumento faltante: aplicativo_id (i32)
-            *app_state.aplicativo_id.lock().await
+            app_state.current_aplicativo_id().await
         ).await
         .map_err(|e| Box::new(CustomError::new(500, &format!("Error de DB al obtener permisos: {}", e))) as Box<dyn std::error::Error>)?;
 
@@ -0,0 +1,315 @@
+// src/api/routes/access_request_route.rs
+// Solicitudes de acceso a permisos o roles y su aprobación.
+
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::access_request_logic::{self, AccessRequestError, Resolver, PERMISO_SOLICITUDES};
+use shared_lib::access_request_models::{
+    AccessRequestCreate, AccessRequestDecision, AccessRequestQuery, ApproverCreate,
+};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn access_request_error_response(error: AccessRequestError) -> HttpResponse {
+    match error {
+        AccessRequestError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        AccessRequestError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        AccessRequestError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        AccessRequestError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        AccessRequestError::Database(message) => {
+            eprintln!("Error de DB en solicitudes de acceso: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(access_request_error_response(AccessRequestError::NotFound(
+            "Usuario no encontrado".to_string(),
+        ))),
+        Err(e) => Err(access_request_error_response(AccessRequestError::Database(e.to_string()))),
+    }
+}
+
+// Cualquier usuario autenticado puede pedir acceso (página de acceso denegado)
+#[post("/access-requests")]
+async fn create_request(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<AccessRequestCreate>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::create_request(
+        &state.db_pool,
+        aplicativo_id,
+        usuario_id,
+        &claims.sub,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(solicitud) => HttpResponse::Created().json(solicitud),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[get("/access-requests/mine")]
+async fn list_my_requests(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::list_user_requests(&state.db_pool, aplicativo_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(solicitudes) => HttpResponse::Ok().json(solicitudes),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[get("/access-requests/pending")]
+async fn list_pending(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let resolver = Resolver {
+        usuario: &claims.sub,
+        usuario_id,
+        es_administrador: claims.has_permission(PERMISO_SOLICITUDES),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::list_pending_approvals(&state.db_pool, aplicativo_id, &resolver, &state.sql_collate_clause).await {
+        Ok(solicitudes) => HttpResponse::Ok().json(solicitudes),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+// Historial completo de solicitudes
+#[get("/access-requests")]
+async fn list_requests(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<AccessRequestQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SOLICITUDES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::list_requests(&state.db_pool, aplicativo_id, &query, &state.sql_collate_clause).await {
+        Ok(solicitudes) => HttpResponse::Ok().json(solicitudes),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[get("/access-requests/{solicitud_id}/events")]
+async fn list_events(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let resolver = Resolver {
+        usuario: &claims.sub,
+        usuario_id,
+        es_administrador: claims.has_permission(PERMISO_SOLICITUDES),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::list_events(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &resolver,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(eventos) => HttpResponse::Ok().json(eventos),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[post("/access-requests/{solicitud_id}/approve")]
+async fn approve_request(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<AccessRequestDecision>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let resolver = Resolver {
+        usuario: &claims.sub,
+        usuario_id,
+        es_administrador: claims.has_permission(PERMISO_SOLICITUDES),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::approve_request(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &resolver,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(solicitud) => HttpResponse::Ok().json(solicitud),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[post("/access-requests/{solicitud_id}/reject")]
+async fn reject_request(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<AccessRequestDecision>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let resolver = Resolver {
+        usuario: &claims.sub,
+        usuario_id,
+        es_administrador: claims.has_permission(PERMISO_SOLICITUDES),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::reject_request(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &resolver,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(solicitud) => HttpResponse::Ok().json(solicitud),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[post("/access-requests/{solicitud_id}/cancel")]
+async fn cancel_request(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::cancel_request(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        usuario_id,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(solicitud) => HttpResponse::Ok().json(solicitud),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[get("/access-requests/approvers")]
+async fn list_approvers(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_SOLICITUDES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::list_approvers(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(aprobadores) => HttpResponse::Ok().json(aprobadores),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[post("/access-requests/approvers")]
+async fn add_approver(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<ApproverCreate>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SOLICITUDES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::add_approver(&state.db_pool, aplicativo_id, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(aprobador) => HttpResponse::Created().json(aprobador),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+#[delete("/access-requests/approvers/{aprobador_id}")]
+async fn remove_approver(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SOLICITUDES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match access_request_logic::remove_approver(&state.db_pool, aplicativo_id, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => access_request_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn access_request_config(cfg: &mut web::ServiceConfig) {
+    // Rutas fijas antes de las que llevan {solicitud_id}
+    cfg.service(list_my_requests)
+       .service(list_pending)
+       .service(list_approvers)
+       .service(add_approver)
+       .service(remove_approver)
+       .service(create_request)
+       .service(list_requests)
+       .service(list_events)
+       .service(approve_request)
+       .service(reject_request)
+       .service(cancel_request);
+}
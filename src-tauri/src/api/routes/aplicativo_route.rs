@@ -0,0 +1,170 @@
+// src/api/routes/aplicativo_route.rs
+// Administración de aplicativos: alta, configuración y copia de menús y roles.
+
+use actix_web::{get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::aplicativo_logic::{self, AplicativoError, PERMISO_ADMINISTRAR_APLICATIVOS};
+use shared_lib::aplicativo_models::{AplicativoConfigInput, AplicativoCopyRequest, AplicativoInput};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn aplicativo_error_response(error: AplicativoError) -> HttpResponse {
+    match error {
+        AplicativoError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        AplicativoError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        AplicativoError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        AplicativoError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        AplicativoError::Database(message) => {
+            eprintln!("Error de DB en aplicativos: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+#[get("/applications")]
+async fn list_aplicativos(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::list_aplicativos(&state.db_pool, &state.sql_collate_clause).await {
+        Ok(aplicativos) => HttpResponse::Ok().json(aplicativos),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+#[post("/applications")]
+async fn create_aplicativo(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<AplicativoInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::create_aplicativo(&state.db_pool, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(aplicativo) => HttpResponse::Created().json(aplicativo),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+// Aplicativo de la solicitud en curso (el elegido por cabecera o ruta, o el del servidor).
+#[get("/applications/current")]
+async fn get_current_aplicativo(_claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match aplicativo_logic::get_aplicativo(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(aplicativo) => HttpResponse::Ok().json(aplicativo),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+#[get("/applications/{aplicativo_id}")]
+async fn get_aplicativo(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::get_aplicativo(&state.db_pool, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(aplicativo) => HttpResponse::Ok().json(aplicativo),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+#[put("/applications/{aplicativo_id}")]
+async fn update_aplicativo(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<AplicativoInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::update_aplicativo(
+        &state.db_pool,
+        &state.aplicativos,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(aplicativo) => HttpResponse::Ok().json(aplicativo),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+#[put("/applications/{aplicativo_id}/config")]
+async fn update_config(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<AplicativoConfigInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::update_config(
+        &state.db_pool,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(aplicativo) => HttpResponse::Ok().json(aplicativo),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+// Copia menús y/o roles de `origenId` al aplicativo de la ruta; lo existente no se duplica.
+#[post("/applications/{aplicativo_id}/copy")]
+async fn copy_from(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<AplicativoCopyRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_APLICATIVOS) {
+        return forbidden_response();
+    }
+    match aplicativo_logic::copy_from(
+        &state.db_pool,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(resultado) => HttpResponse::Ok().json(resultado),
+        Err(e) => aplicativo_error_response(e),
+    }
+}
+
+pub fn aplicativo_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_aplicativos)
+       .service(create_aplicativo)
+       .service(get_current_aplicativo)
+       .service(get_aplicativo)
+       .service(update_aplicativo)
+       .service(update_config)
+       .service(copy_from);
+}
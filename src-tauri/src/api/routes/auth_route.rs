@@ -12,6 +12,8 @@ use shared_lib::state::AppState;
 use shared_lib::models::LoginData;
 //use shared_lib::models::LoggedInUser;
 use shared_lib::user_logic;
+use shared_lib::mfa_logic;
+use shared_lib::profile_logic;
 //use shared_lib::middleware::auth_claims::Claims;
 use shared_lib::{models::{AuthRequestPayload}, auth}; // AuthResponsePayload
 
@@ -62,10 +64,9 @@ pub async fn external_auth_handler(
         &state.db_pool, 
         payload.into_inner(),
         // Desreferenciar el i32 que está dentro del Mutex
-        *state.aplicativo_id.lock().await, 
+        state.current_aplicativo_id().await, 
         // 🚨 CORRECCIÓN 2: Usar & para obtener la referencia al Arc<Client>
         &state.http_client, 
-        &state.whitelisted_domains,
         &state.msal_client_id,
         // 🚨 ARGUMENTO FALTANTE AÑADIDO: msal_audience_uri
         &state.msal_audience_uri,
@@ -99,6 +100,7 @@ pub fn config_auth_routes(cfg: &mut web::ServiceConfig) {
 // (Usa create_session_response y obtiene permisos de utils)
 #[post("/login")]
 pub async fn login_user_handler(
+    req: HttpRequest,
     state: web::Data<AppState>,
     logindata: web::Json<LoginData>,
 ) -> HttpResponse { // 👈 Change return type to concrete HttpResponse
@@ -117,9 +119,40 @@ pub async fn login_user_handler(
 
     match auth_result {
         Ok(Some(user)) => {
+
+            // Segundo factor: si la política lo exige (o el usuario lo activó) se devuelve
+            // un desafío MFA en lugar del JWT de sesión.
+            match mfa_logic::challenge_for_login(
+                &state.db_pool,
+                state.current_aplicativo_id().await,
+                &user.usuario,
+                &state.jwt_secret,
+                &state.sql_collate_clause,
+            ).await {
+                Ok(Some(challenge)) => return HttpResponse::Ok().json(challenge),
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Error al evaluar MFA: {}", e);
+                    return CustomError::new(500, "Error al evaluar el segundo factor.").error_response();
+                }
+            }
             
+            // Los permisos salen de las asignaciones del usuario real, no de un ID fijo
+            let usuario_id = match mfa_logic::find_user_id_by_username(
+                &state.db_pool,
+                &user.usuario,
+                &state.sql_collate_clause,
+            ).await {
+                Ok(Some(id)) => id,
+                Ok(None) => return CustomError::new(401, "El usuario no está registrado en el sistema de seguridad.").error_response(),
+                Err(e) => {
+                    eprintln!("Error al buscar el usuario: {}", e);
+                    return CustomError::new(500, "Error al buscar el usuario.").error_response();
+                }
+            };
+
             let logged_in_user = shared_lib::models::LoggedInUser {
-                usuario_id: 1, // por ahora user.usuario_id, // Asume que existe este campo
+                usuario_id,
                 // **IMPORTANTE:** Si tu LoggedInUser usa Option<String>, debes usar Some()
                 usuario: Some(user.usuario), // o Some(user.usuario)
                 nombre: Some(user.nombre),   // o Some(user.nombre)
@@ -134,7 +167,7 @@ pub async fn login_user_handler(
             let permissions_result = utils::get_permissions_by_app(
                 &state.db_pool,
                 logged_in_user.usuario_id,
-                *state.aplicativo_id.lock().await, // Desreferencia el i32
+                state.current_aplicativo_id().await, // Desreferencia el i32
             ).await;
 
             // 3. MATCH PERMISSIONS_RESULT para obtener 'permissions' (Línea 145)
@@ -154,6 +187,7 @@ pub async fn login_user_handler(
                 // 🚨 Llamada correcta a generate_jwt con los 3 argumentos (Función SÍNCRONA, sin .await)
                 &logged_in_user, 
                 permissions.clone(), 
+                state.current_aplicativo_id().await,
                 &state.jwt_secret
             ) {
                 // Si es Ok, 't' es el String del JWT. Lo asignamos a token_session.
@@ -168,6 +202,18 @@ pub async fn login_user_handler(
                 }
             };
 
+            // Registro de la sesión para la vista "/me" (no bloquea el login)
+            if let Err(e) = profile_logic::record_session(
+                &state.db_pool,
+                usuario_id,
+                &token_session,
+                profile_logic::ORIGEN_WEB,
+                req.headers().get("User-Agent").and_then(|h| h.to_str().ok()),
+                req.peer_addr().map(|a| a.ip().to_string()).as_deref(),
+            ).await {
+                eprintln!("No se pudo registrar la sesión: {}", e);
+            }
+
             // 5. DEVOLVER RESPUESTA EXITOSA
             // 🚨 CORRECCIÓN CLAVE: Usar AuthResponsePayload (o AuthResponse)
             let response = shared_lib::models::AuthResponsePayload { 
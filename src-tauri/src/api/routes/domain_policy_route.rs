@@ -0,0 +1,130 @@
+// src/api/routes/domain_policy_route.rs
+// Administración de los dominios permitidos para login externo del aplicativo.
+
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::domain_policy_logic::{self, DomainPolicyError};
+use shared_lib::models::DomainPolicyRequest;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+/// Permiso requerido para administrar las políticas de dominio.
+const PERMISO_DOMINIOS: &str = "administrar_dominios";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn policy_error_response(error: DomainPolicyError) -> HttpResponse {
+    match error {
+        DomainPolicyError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        DomainPolicyError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        DomainPolicyError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        DomainPolicyError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        DomainPolicyError::Database(message) => {
+            eprintln!("Error de DB en políticas de dominio: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+#[get("/domain-policies")]
+async fn list_policies(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_DOMINIOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match domain_policy_logic::list_policies(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(policies) => HttpResponse::Ok().json(policies),
+        Err(e) => policy_error_response(e),
+    }
+}
+
+#[post("/domain-policies")]
+async fn create_policy(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<DomainPolicyRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_DOMINIOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match domain_policy_logic::create_policy(
+        &state.db_pool,
+        aplicativo_id,
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(policy) => HttpResponse::Created().json(policy),
+        Err(e) => policy_error_response(e),
+    }
+}
+
+#[put("/domain-policies/{dominio_id}")]
+async fn update_policy(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<DomainPolicyRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_DOMINIOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match domain_policy_logic::update_policy(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(policy) => HttpResponse::Ok().json(policy),
+        Err(e) => policy_error_response(e),
+    }
+}
+
+#[delete("/domain-policies/{dominio_id}")]
+async fn delete_policy(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_DOMINIOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match domain_policy_logic::delete_policy(&state.db_pool, aplicativo_id, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => policy_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn domain_policy_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_policies)
+       .service(create_policy)
+       .service(update_policy)
+       .service(delete_policy);
+}
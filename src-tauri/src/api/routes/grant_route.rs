@@ -0,0 +1,146 @@
+// src/api/routes/grant_route.rs
+// Concesiones temporales de permisos y roles, y delegación entre usuarios.
+
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::grant_logic::{self, GrantError, PERMISO_CONCESIONES};
+use shared_lib::grant_models::{TemporaryGrantQuery, TemporaryGrantRequest};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn grant_error_response(error: GrantError) -> HttpResponse {
+    match error {
+        GrantError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        GrantError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        GrantError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        GrantError::Database(message) => {
+            eprintln!("Error de DB en concesiones temporales: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(grant_error_response(GrantError::NotFound("Usuario no encontrado".to_string()))),
+        Err(e) => Err(grant_error_response(GrantError::Database(e.to_string()))),
+    }
+}
+
+// Un administrador otorga a cualquiera; el resto solo puede delegar (delegar = true)
+#[post("/grants")]
+async fn create_grant(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<TemporaryGrantRequest>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let autor_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match grant_logic::create_grant(
+        &state.db_pool,
+        aplicativo_id,
+        &body,
+        &claims.sub,
+        autor_id,
+        claims.has_permission(PERMISO_CONCESIONES),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(grant) => HttpResponse::Created().json(grant),
+        Err(e) => grant_error_response(e),
+    }
+}
+
+#[get("/grants")]
+async fn list_grants(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<TemporaryGrantQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCESIONES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match grant_logic::list_grants(&state.db_pool, aplicativo_id, &query, &state.sql_collate_clause).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => grant_error_response(e),
+    }
+}
+
+// Concesiones recibidas y delegadas por el usuario conectado
+#[get("/grants/mine")]
+async fn list_my_grants(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match grant_logic::list_user_grants(&state.db_pool, aplicativo_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => grant_error_response(e),
+    }
+}
+
+#[delete("/grants/{concesion_id}")]
+async fn revoke_grant(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match grant_logic::revoke_grant(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &claims.sub,
+        usuario_id,
+        claims.has_permission(PERMISO_CONCESIONES),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(grant) => HttpResponse::Ok().json(grant),
+        Err(e) => grant_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn grant_config(cfg: &mut web::ServiceConfig) {
+    // /grants/mine antes de /grants/{concesion_id}
+    cfg.service(list_my_grants)
+       .service(create_grant)
+       .service(list_grants)
+       .service(revoke_grant);
+}
@@ -0,0 +1,357 @@
+// src/api/routes/help_route.rs
+// Ayudas contextuales: consulta por pantalla y "Administrar ayudas".
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::help_logic::{self, HelpError, PERMISO_ADMINISTRAR_AYUDAS};
+use shared_lib::help_models::{
+    HelpArticleInput, HelpArticleUpdate, HelpAttachmentUpload, HelpLookupQuery, HelpVersionInput,
+};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::config::HelpConfig;
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn help_error_response(error: HelpError) -> HttpResponse {
+    match error {
+        HelpError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        HelpError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        HelpError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        HelpError::Storage(message) => {
+            eprintln!("Error de almacenamiento en ayudas: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::InternalError,
+                message,
+            })
+        }
+        HelpError::Database(message) => {
+            eprintln!("Error de DB en ayudas: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+// --- Consulta desde la pantalla (cualquier usuario autenticado) ---
+
+// Ayuda publicada de la pantalla actual: ?ruta=/usuarios/lista&idioma=es
+#[get("/help")]
+async fn get_help(
+    _claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<HelpLookupQuery>,
+) -> impl Responder {
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::find_published(
+        &state.db_pool,
+        aplicativo_id,
+        &query.ruta,
+        query.idioma.as_deref(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(ayuda) => HttpResponse::Ok().json(ayuda),
+        Err(e) => help_error_response(e),
+    }
+}
+
+/// Tipos que se pueden mostrar dentro de la ayuda. El resto (HTML, SVG, PDF...) se
+/// descarga como archivo para que un adjunto no ejecute scripts en el origen de la API.
+const TIPOS_EN_LINEA: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+// Descarga de un adjunto; sin permiso de administración, solo de ayudas publicadas.
+#[get("/help/attachments/{adjunto_id}")]
+async fn download_attachment(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::read_attachment(
+        &state.db_pool,
+        &state.help_config,
+        aplicativo_id,
+        path.into_inner(),
+        claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS),
+        &state.sql_collate_clause,
+    ).await {
+        Ok((adjunto, bytes)) => {
+            let tipo = adjunto.tipo_contenido.trim().to_ascii_lowercase();
+            let (content_type, disposition) = if TIPOS_EN_LINEA.contains(&tipo.as_str()) {
+                (tipo, DispositionType::Inline)
+            } else {
+                ("application/octet-stream".to_string(), DispositionType::Attachment)
+            };
+            HttpResponse::Ok()
+                .content_type(content_type)
+                .insert_header(ContentDisposition {
+                    disposition,
+                    parameters: vec![DispositionParam::Filename(adjunto.nombre_archivo)],
+                })
+                .insert_header(("X-Content-Type-Options", "nosniff"))
+                .body(bytes)
+        }
+        Err(e) => help_error_response(e),
+    }
+}
+
+// --- Administración ---
+
+#[delete("/help/attachments/{adjunto_id}")]
+async fn delete_attachment(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::delete_attachment(
+        &state.db_pool,
+        &state.help_config,
+        aplicativo_id,
+        path.into_inner(),
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[get("/help/articles")]
+async fn list_articles(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::list_articles(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(articulos) => HttpResponse::Ok().json(articulos),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[post("/help/articles")]
+async fn create_article(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<HelpArticleInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::create_article(&state.db_pool, aplicativo_id, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(articulo) => HttpResponse::Created().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[get("/help/articles/{ayuda_id}")]
+async fn get_article(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::get_article_detail(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(articulo) => HttpResponse::Ok().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[put("/help/articles/{ayuda_id}")]
+async fn update_article(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<HelpArticleUpdate>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::update_article(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(articulo) => HttpResponse::Ok().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[delete("/help/articles/{ayuda_id}")]
+async fn delete_article(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::delete_article(
+        &state.db_pool,
+        &state.help_config,
+        aplicativo_id,
+        path.into_inner(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[post("/help/articles/{ayuda_id}/versions")]
+async fn create_version(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<HelpVersionInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::create_version(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(articulo) => HttpResponse::Created().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[put("/help/articles/{ayuda_id}/versions/{numero}")]
+async fn update_version(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<HelpVersionInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let (ayuda_id, numero) = path.into_inner();
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::update_version(
+        &state.db_pool,
+        aplicativo_id,
+        ayuda_id,
+        numero,
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(articulo) => HttpResponse::Ok().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[post("/help/articles/{ayuda_id}/versions/{numero}/publish")]
+async fn publish_version(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let (ayuda_id, numero) = path.into_inner();
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::publish_version(
+        &state.db_pool,
+        aplicativo_id,
+        ayuda_id,
+        numero,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(articulo) => HttpResponse::Ok().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+#[post("/help/articles/{ayuda_id}/unpublish")]
+async fn unpublish_article(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::unpublish_article(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(articulo) => HttpResponse::Ok().json(articulo),
+        Err(e) => help_error_response(e),
+    }
+}
+
+// Adjunto en Base64 (como la importación de usuarios); se guarda en disco o en la DB según HELP_ATTACHMENTS_DIR.
+// POST /help/articles/{ayuda_id}/attachments: se registra en `help_config` con su propio límite de JSON.
+async fn add_attachment(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<HelpAttachmentUpload>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_ADMINISTRAR_AYUDAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match help_logic::add_attachment(
+        &state.db_pool,
+        &state.help_config,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(adjunto) => HttpResponse::Created().json(adjunto),
+        Err(e) => help_error_response(e),
+    }
+}
+
+/// Límite del cuerpo JSON de `add_attachment`: el default de actix (2 MB) es menor que
+/// HELP_MAX_ATTACHMENT_MB, y el Base64 ocupa 4/3 del archivo más el resto del JSON.
+fn attachment_json_limit() -> usize {
+    HelpConfig::from_env().max_attachment_bytes / 3 * 4 + 64 * 1024
+}
+
+pub fn help_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/help/articles/{ayuda_id}/attachments")
+            .app_data(web::JsonConfig::default().limit(attachment_json_limit()))
+            .route(web::post().to(add_attachment)),
+    );
+    cfg.service(get_help)
+       .service(download_attachment)
+       .service(delete_attachment)
+       .service(list_articles)
+       .service(create_article)
+       .service(get_article)
+       .service(update_article)
+       .service(delete_article)
+       .service(create_version)
+       .service(update_version)
+       .service(publish_version)
+       .service(unpublish_article);
+}
@@ -0,0 +1,153 @@
+// src/api/routes/identity_route.rs
+// Administración de los vínculos entre usuarios de riy e identidades externas.
+
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use shared_lib::state::AppState;
+use shared_lib::identity_link_logic::{self, IdentityLinkError};
+use shared_lib::models::{ExternalIdentity, IdentityLinkRequest};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+/// Permiso requerido para ver y administrar identidades vinculadas.
+const PERMISO_IDENTIDADES: &str = "administrar_identidades";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn identity_error_response(error: IdentityLinkError) -> HttpResponse {
+    match error {
+        IdentityLinkError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        IdentityLinkError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        IdentityLinkError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        IdentityLinkError::Database(message) => {
+            eprintln!("Error de DB en identidades vinculadas: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct IdentityListQuery {
+    pub proveedor: Option<String>,
+}
+
+// Vista de administración: todos los vínculos, filtrables por proveedor
+#[get("/identities")]
+async fn list_identities(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<IdentityListQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IDENTIDADES) {
+        return forbidden_response();
+    }
+    match identity_link_logic::list_identities(
+        &state.db_pool,
+        query.proveedor.as_deref(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(links) => HttpResponse::Ok().json(links),
+        Err(e) => identity_error_response(e),
+    }
+}
+
+// Identidades vinculadas a un usuario
+#[get("/users/{usuario_id}/identities")]
+async fn list_user_identities(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IDENTIDADES) {
+        return forbidden_response();
+    }
+    match identity_link_logic::list_user_identities(
+        &state.db_pool,
+        path.into_inner(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(links) => HttpResponse::Ok().json(links),
+        Err(e) => identity_error_response(e),
+    }
+}
+
+// Vincula manualmente una identidad (p. ej. una segunda cuenta de otro proveedor)
+#[post("/users/{usuario_id}/identities")]
+async fn link_identity(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<IdentityLinkRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IDENTIDADES) {
+        return forbidden_response();
+    }
+    let usuario_id = path.into_inner();
+    let request = body.into_inner();
+    let proveedor = match identity_link_logic::normalize_provider(&request.proveedor) {
+        Ok(p) => p,
+        Err(e) => return identity_error_response(e),
+    };
+    let identity = ExternalIdentity {
+        provider: proveedor,
+        subject: request.sujeto,
+        issuer: None,
+    };
+
+    if let Err(e) = identity_link_logic::link_identity(
+        &state.db_pool,
+        usuario_id,
+        &identity,
+        request.correo.as_deref(),
+        &claims.sub,
+    ).await {
+        return identity_error_response(e);
+    }
+    match identity_link_logic::list_user_identities(&state.db_pool, usuario_id, &state.sql_collate_clause).await {
+        Ok(links) => HttpResponse::Created().json(links),
+        Err(e) => identity_error_response(e),
+    }
+}
+
+// Quita un vínculo; el siguiente login con esa identidad volverá a buscar por correo
+#[delete("/users/{usuario_id}/identities/{identidad_id}")]
+async fn unlink_identity(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IDENTIDADES) {
+        return forbidden_response();
+    }
+    let (usuario_id, identidad_id) = path.into_inner();
+    match identity_link_logic::unlink_identity(&state.db_pool, usuario_id, identidad_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => identity_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn identity_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_identities)
+       .service(list_user_identities)
+       .service(link_identity)
+       .service(unlink_identity);
+}
@@ -0,0 +1,129 @@
+// src/api/routes/impersonation_route.rs
+// Suplantación de usuarios para soporte y su auditoría.
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::impersonation_logic::{self, ImpersonationError, PERMISO_SUPLANTAR};
+use shared_lib::impersonation_models::{ImpersonationAuditQuery, ImpersonationRequest};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn impersonation_error_response(error: ImpersonationError) -> HttpResponse {
+    match error {
+        ImpersonationError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        ImpersonationError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        ImpersonationError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        ImpersonationError::Database(message) => {
+            eprintln!("Error de DB en suplantaciones: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+// Emite el token "actuar como"; `has_permission` ya lo niega si quien llama está suplantando
+#[post("/impersonation")]
+async fn start_impersonation(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<ImpersonationRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SUPLANTAR) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match impersonation_logic::start_impersonation(
+        &state.db_pool,
+        &claims.sub,
+        &claims.permissions,
+        &body,
+        aplicativo_id,
+        &state.jwt_secret,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(token) => HttpResponse::Created().json(token),
+        Err(e) => impersonation_error_response(e),
+    }
+}
+
+// Termina la suplantación en curso (se llama con el propio token de suplantación)
+#[post("/impersonation/end")]
+async fn end_impersonation(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let actor = match &claims.act {
+        Some(actor) => actor,
+        None => {
+            return impersonation_error_response(ImpersonationError::Validation(
+                "La sesión actual no es una suplantación".to_string(),
+            ))
+        }
+    };
+    match impersonation_logic::end_impersonation(&state.db_pool, actor.sid, &actor.sub).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => impersonation_error_response(e),
+    }
+}
+
+#[get("/impersonation/audit")]
+async fn list_impersonations(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<ImpersonationAuditQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SUPLANTAR) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match impersonation_logic::list_impersonations(
+        &state.db_pool,
+        aplicativo_id,
+        query.actor.as_deref(),
+        query.usuario_id,
+        query.limit,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(records) => HttpResponse::Ok().json(records),
+        Err(e) => impersonation_error_response(e),
+    }
+}
+
+#[get("/impersonation/audit/{suplantacion_id}/actions")]
+async fn list_actions(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SUPLANTAR) {
+        return forbidden_response();
+    }
+    match impersonation_logic::list_actions(&state.db_pool, path.into_inner()).await {
+        Ok(actions) => HttpResponse::Ok().json(actions),
+        Err(e) => impersonation_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn impersonation_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(start_impersonation)
+       .service(end_impersonation)
+       .service(list_impersonations)
+       .service(list_actions);
+}
@@ -22,10 +22,7 @@ pub async fn get_license_status(
 ) -> impl Responder {
     println!("get_license_status: Handler llamado.");
 
-    let app_id_guard = state.aplicativo_id.lock().await;
-    println!("get_license_status: app_id guard obtenido.");
-    
-    let app_id = *app_id_guard;
+    let app_id = state.current_aplicativo_id().await;
     println!("get_license_status: app_id obtenido: {}", app_id);
 
     // Llama a la función principal
@@ -36,7 +33,7 @@ pub async fn get_license_status(
         app_id,
         &state.palabra_clave2,
         &state.db_connection_url,
-        &state.aplicativo,
+        &state.current_aplicativo(),
     ).await {
         Ok(result) => {
             println!("get_license_status: Éxito. Estado de licencia: {:?}", result.status);
@@ -84,10 +81,7 @@ pub async fn save_license_credentials_route(
 ) -> impl Responder {
     println!("save_license_credentials_route: Handler llamado.");
 
-    let app_id_guard = state.aplicativo_id.lock().await;
-    println!("save_license_credentials_route: app_id guard obtenido.");
-    
-    let app_id = *app_id_guard;
+    let app_id = state.current_aplicativo_id().await;
     println!("save_license_credentials_route: app_id obtenido: {}", app_id);
 
     // Llama a la función principal
@@ -98,7 +92,7 @@ pub async fn save_license_credentials_route(
         &state.palabra_clave1,
         &state.palabra_clave2,
         &state.db_connection_url,
-        &state.aplicativo,
+        &state.current_aplicativo(),
         // ⭐⭐ CAMBIO CLAVE: Usamos 'body.credentials' para acceder al valor ⭐⭐
         &body.credentials, 
     ).await {
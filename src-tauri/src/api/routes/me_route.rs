@@ -0,0 +1,134 @@
+// src/api/routes/me_route.rs
+// Perfil del usuario conectado: consulta y autogestión.
+
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::profile_logic::{self, ProfileError};
+use shared_lib::profile_models::{EmailChangeConfirm, ProfileUpdateRequest};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn profile_error_response(error: ProfileError) -> HttpResponse {
+    match error {
+        ProfileError::Validation(message) | ProfileError::InvalidCode(message) => {
+            HttpResponse::BadRequest().json(ApiError {
+                code: AppErrorCode::ValidationError,
+                message,
+            })
+        }
+        ProfileError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        ProfileError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        ProfileError::Database(message) => {
+            eprintln!("Error de DB en el perfil: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+/// JWT de la petición, para marcar la sesión actual.
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(profile_error_response(ProfileError::NotFound("Usuario no encontrado".to_string()))),
+        Err(e) => Err(profile_error_response(ProfileError::Database(e.to_string()))),
+    }
+}
+
+async fn profile_response(state: &AppState, req: &HttpRequest, claims: &Claims, usuario_id: i32) -> HttpResponse {
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match profile_logic::get_profile(
+        &state.db_pool,
+        usuario_id,
+        aplicativo_id,
+        bearer_token(req),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(mut profile) => {
+            profile.suplantado_por = claims.act.as_ref().map(|a| a.sub.clone());
+            HttpResponse::Ok().json(profile)
+        }
+        Err(e) => profile_error_response(e),
+    }
+}
+
+#[get("/me")]
+async fn get_me(req: HttpRequest, claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    profile_response(&state, &req, &claims, usuario_id).await
+}
+
+// Nombre y preferencias se aplican de inmediato; el correo queda pendiente de verificación
+#[put("/me")]
+async fn update_me(
+    req: HttpRequest,
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<ProfileUpdateRequest>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    if let Err(e) = profile_logic::update_profile(&state.db_pool, usuario_id, &body, &state.sql_collate_clause).await {
+        return profile_error_response(e);
+    }
+    profile_response(&state, &req, &claims, usuario_id).await
+}
+
+#[post("/me/email/confirm")]
+async fn confirm_email(
+    req: HttpRequest,
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<EmailChangeConfirm>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    if let Err(e) = profile_logic::confirm_email_change(&state.db_pool, usuario_id, body.codigo).await {
+        return profile_error_response(e);
+    }
+    profile_response(&state, &req, &claims, usuario_id).await
+}
+
+// Función de configuración para Actix-Web
+pub fn me_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_me)
+       .service(update_me)
+       .service(confirm_email);
+}
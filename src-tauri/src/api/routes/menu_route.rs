@@ -10,7 +10,8 @@ pub async fn get_all_menus_handler(
     state: web::Data<AppState>,
 ) -> Result<impl Responder, Error> {
     let sql_collate = &state.sql_collate_clause;
-    match menu_logic::get_all_menus_logic(&state.db_pool, sql_collate).await {
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match menu_logic::get_all_menus_logic(&state.db_pool, aplicativo_id, sql_collate).await {
         Ok(menus) => {
             Ok(HttpResponse::Ok().json(menus))
         }
@@ -0,0 +1,353 @@
+// src/api/routes/mfa_route.rs
+// Segundo factor TOTP: segundo paso del login (público) y autogestión/políticas (protegido).
+
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+
+use shared_lib::state::AppState;
+use shared_lib::mfa_logic::{self, MfaError};
+use shared_lib::mfa_models::{
+    MfaChallengeEnrollRequest, MfaCodeRequest, MfaEnrollAndLoginResponse, MfaEnrollmentResult,
+    MfaVerifyRequest,
+};
+use shared_lib::models::{AuthResponsePayload, LoggedInUser};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+use shared_lib::{profile_logic, user_repository, utils};
+
+/// Permiso requerido para administrar las políticas de MFA del aplicativo.
+const PERMISO_MFA: &str = "administrar_mfa";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn mfa_error_response(error: MfaError) -> HttpResponse {
+    match error {
+        MfaError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        MfaError::InvalidCode(message) => HttpResponse::Unauthorized().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        MfaError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        MfaError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        MfaError::Locked(message) => HttpResponse::TooManyRequests().json(ApiError {
+            code: AppErrorCode::TooManyAttempts,
+            message,
+        }),
+        MfaError::Crypto(message) => {
+            eprintln!("Error de cifrado en MFA: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message: "Error interno al procesar el segundo factor.".to_string(),
+            })
+        }
+        MfaError::Database(message) => {
+            eprintln!("Error de DB en MFA: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+/// Emite la sesión (JWT + permisos) una vez superado el segundo factor.
+async fn complete_login(state: &AppState, req: &HttpRequest, usuario_id: i32) -> Result<AuthResponsePayload, HttpResponse> {
+    let user = match user_repository::find_user_by_id(&state.db_pool, usuario_id, &state.sql_collate_clause).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(mfa_error_response(MfaError::NotFound("Usuario no encontrado".to_string()))),
+        Err(e) => return Err(mfa_error_response(MfaError::Database(e.to_string()))),
+    };
+    let logged_in_user = LoggedInUser {
+        usuario_id: Some(usuario_id),
+        usuario: Some(user.usuario),
+        nombre: Some(user.nombre),
+        correo: Some(user.correo),
+        roles: Vec::new(),
+    };
+
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let permissions = utils::get_permissions_by_app(&state.db_pool, usuario_id, aplicativo_id)
+        .await
+        .map_err(|e| mfa_error_response(MfaError::Database(e.to_string())))?;
+    let app_jwt = utils::generate_jwt(&logged_in_user, permissions.clone(), aplicativo_id, &state.jwt_secret)
+        .map_err(|e| mfa_error_response(MfaError::Crypto(e.to_string())))?;
+
+    if let Err(e) = profile_logic::record_session(
+        &state.db_pool,
+        usuario_id,
+        &app_jwt,
+        profile_logic::ORIGEN_WEB,
+        req.headers().get("User-Agent").and_then(|h| h.to_str().ok()),
+        req.peer_addr().map(|a| a.ip().to_string()).as_deref(),
+    ).await {
+        eprintln!("No se pudo registrar la sesión: {}", e);
+    }
+
+    Ok(AuthResponsePayload {
+        app_jwt,
+        user: logged_in_user,
+        permissions,
+    })
+}
+
+/// Resuelve el usuario de la sesión actual a partir del `sub` del JWT.
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(mfa_error_response(MfaError::NotFound("Usuario no encontrado".to_string()))),
+        Err(e) => Err(mfa_error_response(e)),
+    }
+}
+
+// -------------------------------------------------------------------------
+// SEGUNDO PASO DEL LOGIN (público, autenticado por el token de desafío)
+// -------------------------------------------------------------------------
+
+#[post("/auth/mfa/verify")]
+async fn verify_login(req: HttpRequest, state: web::Data<AppState>, body: web::Json<MfaVerifyRequest>) -> impl Responder {
+    let (usuario_id, _) = match mfa_logic::validate_challenge(&body.mfa_token, &state.jwt_secret) {
+        Ok(v) => v,
+        Err(e) => return mfa_error_response(e),
+    };
+    if let Err(e) = mfa_logic::verify_code(&state.db_pool, usuario_id, &body.code, &state.mfa_encryption_key).await {
+        return mfa_error_response(e);
+    }
+    match complete_login(&state, &req, usuario_id).await {
+        Ok(session) => HttpResponse::Ok().json(session),
+        Err(response) => response,
+    }
+}
+
+// Alta obligatoria durante el login (la política exige MFA y el usuario aún no lo tiene)
+#[post("/auth/mfa/enroll")]
+async fn enroll_during_login(
+    state: web::Data<AppState>,
+    body: web::Json<MfaChallengeEnrollRequest>,
+) -> impl Responder {
+    let (usuario_id, usuario) = match mfa_logic::validate_challenge(&body.mfa_token, &state.jwt_secret) {
+        Ok(v) => v,
+        Err(e) => return mfa_error_response(e),
+    };
+    match mfa_logic::start_enrollment(
+        &state.db_pool,
+        usuario_id,
+        &usuario,
+        &state.current_aplicativo(),
+        &state.mfa_encryption_key,
+    ).await {
+        Ok(start) => HttpResponse::Ok().json(start),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+#[post("/auth/mfa/enroll/confirm")]
+async fn confirm_enroll_during_login(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<MfaVerifyRequest>,
+) -> impl Responder {
+    let (usuario_id, _) = match mfa_logic::validate_challenge(&body.mfa_token, &state.jwt_secret) {
+        Ok(v) => v,
+        Err(e) => return mfa_error_response(e),
+    };
+    let recovery_codes = match mfa_logic::confirm_enrollment(
+        &state.db_pool,
+        usuario_id,
+        &body.code,
+        &state.mfa_encryption_key,
+    ).await {
+        Ok(codes) => codes,
+        Err(e) => return mfa_error_response(e),
+    };
+    match complete_login(&state, &req, usuario_id).await {
+        Ok(session) => HttpResponse::Ok().json(MfaEnrollAndLoginResponse { recovery_codes, session }),
+        Err(response) => response,
+    }
+}
+
+// -------------------------------------------------------------------------
+// AUTOGESTIÓN (usuario con sesión)
+// -------------------------------------------------------------------------
+
+#[get("/mfa/status")]
+async fn get_status(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match mfa_logic::status(&state.db_pool, aplicativo_id, usuario_id).await {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+#[post("/mfa/enroll")]
+async fn start_enrollment(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    match mfa_logic::start_enrollment(
+        &state.db_pool,
+        usuario_id,
+        &claims.sub,
+        &state.current_aplicativo(),
+        &state.mfa_encryption_key,
+    ).await {
+        Ok(start) => HttpResponse::Ok().json(start),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+#[post("/mfa/enroll/confirm")]
+async fn confirm_enrollment(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    match mfa_logic::confirm_enrollment(&state.db_pool, usuario_id, &body.code, &state.mfa_encryption_key).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(MfaEnrollmentResult { recovery_codes }),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+// Desactivar exige un código vigente; no se permite si una política lo hace obligatorio
+#[post("/mfa/disable")]
+async fn disable(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match mfa_logic::is_mfa_required(&state.db_pool, aplicativo_id, usuario_id).await {
+        Ok(true) => {
+            return mfa_error_response(MfaError::Conflict(
+                "Una política del aplicativo exige MFA para este usuario".to_string(),
+            ))
+        }
+        Ok(false) => {}
+        Err(e) => return mfa_error_response(e),
+    }
+    if let Err(e) = mfa_logic::verify_code(&state.db_pool, usuario_id, &body.code, &state.mfa_encryption_key).await {
+        return mfa_error_response(e);
+    }
+    match mfa_logic::disable(&state.db_pool, usuario_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+// -------------------------------------------------------------------------
+// POLÍTICAS (administración)
+// -------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaPolicyRequest {
+    /// `None` aplica la política a todo el aplicativo.
+    pub rol_id: Option<i32>,
+    pub requerido: bool,
+}
+
+#[get("/mfa/policies")]
+async fn list_policies(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_MFA) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match mfa_logic::list_policies(&state.db_pool, aplicativo_id).await {
+        Ok(policies) => HttpResponse::Ok().json(policies),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+#[put("/mfa/policies")]
+async fn upsert_policy(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<MfaPolicyRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_MFA) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    if let Err(e) = mfa_logic::upsert_policy(
+        &state.db_pool,
+        aplicativo_id,
+        body.rol_id,
+        body.requerido,
+        &claims.sub,
+    ).await {
+        return mfa_error_response(e);
+    }
+    match mfa_logic::list_policies(&state.db_pool, aplicativo_id).await {
+        Ok(policies) => HttpResponse::Ok().json(policies),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+#[delete("/mfa/policies/{politica_id}")]
+async fn delete_policy(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_MFA) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match mfa_logic::delete_policy(&state.db_pool, aplicativo_id, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => mfa_error_response(e),
+    }
+}
+
+// Rutas públicas: segundo paso del login
+pub fn mfa_config_public(cfg: &mut web::ServiceConfig) {
+    cfg.service(verify_login)
+       .service(enroll_during_login)
+       .service(confirm_enroll_during_login);
+}
+
+// Función de configuración para Actix-Web
+pub fn mfa_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_status)
+       .service(start_enrollment)
+       .service(confirm_enrollment)
+       .service(disable)
+       .service(list_policies)
+       .service(upsert_policy)
+       .service(delete_policy);
+}
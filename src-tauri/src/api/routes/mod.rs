@@ -3,4 +3,19 @@ pub mod auth_route;
 pub mod license_route;
 pub mod user_route;
 pub mod menu_route;
-
+pub mod user_import_route;
+pub mod scim_route;
+pub mod identity_route;
+pub mod domain_policy_route;
+pub mod mfa_route;
+pub mod notification_route;
+pub mod me_route;
+pub mod impersonation_route;
+pub mod grant_route;
+pub mod access_request_route;
+pub mod permission_catalog_route;
+pub mod row_security_route;
+pub mod saved_query_route;
+pub mod user_view_route;
+pub mod help_route;
+pub mod aplicativo_route;
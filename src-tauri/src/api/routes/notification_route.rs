@@ -0,0 +1,180 @@
+// src/api/routes/notification_route.rs
+// Códigos de verificación, restablecimiento de contraseña y administración de la cola de correo.
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::notification_logic::{self, NotificationError};
+use shared_lib::notification_models::{
+    NotificationKind, NotificationQueueQuery, PasswordResetConfirm, PasswordResetRequest,
+    VerificationCodeConfirm,
+};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+/// Permiso requerido para consultar y reintentar la cola de notificaciones.
+const PERMISO_NOTIFICACIONES: &str = "administrar_notificaciones";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn notification_error_response(error: NotificationError) -> HttpResponse {
+    match error {
+        NotificationError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        NotificationError::InvalidCode(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        NotificationError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        NotificationError::Smtp(message) | NotificationError::Database(message) => {
+            eprintln!("Error en notificaciones: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(notification_error_response(NotificationError::NotFound("Usuario no encontrado".to_string()))),
+        Err(e) => Err(notification_error_response(NotificationError::Database(e.to_string()))),
+    }
+}
+
+// -------------------------------------------------------------------------
+// RESTABLECIMIENTO DE CONTRASEÑA (público, cuentas locales)
+// -------------------------------------------------------------------------
+
+// Siempre responde 202 para no revelar qué usuarios existen
+#[post("/auth/password-reset/request")]
+async fn request_password_reset(
+    state: web::Data<AppState>,
+    body: web::Json<PasswordResetRequest>,
+) -> impl Responder {
+    match notification_logic::request_password_reset(&state.db_pool, &body.usuario, &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+#[post("/auth/password-reset/confirm")]
+async fn confirm_password_reset(
+    state: web::Data<AppState>,
+    body: web::Json<PasswordResetConfirm>,
+) -> impl Responder {
+    match notification_logic::confirm_password_reset(
+        &state.db_pool,
+        &body.usuario,
+        body.codigo,
+        &body.nueva_clave,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+// -------------------------------------------------------------------------
+// VERIFICACIÓN DE CORREO (usuario con sesión)
+// -------------------------------------------------------------------------
+
+#[post("/notifications/verification-code")]
+async fn send_verification_code(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    match notification_logic::send_verification_code(
+        &state.db_pool,
+        usuario_id,
+        NotificationKind::CodigoVerificacion,
+        None,
+    ).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+#[post("/notifications/verification-code/confirm")]
+async fn confirm_verification_code(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<VerificationCodeConfirm>,
+) -> impl Responder {
+    if !claims.can_manage_own_security() {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    match notification_logic::consume_verification_code(&state.db_pool, usuario_id, body.codigo).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+// -------------------------------------------------------------------------
+// COLA (administración)
+// -------------------------------------------------------------------------
+
+#[get("/notifications/queue")]
+async fn list_queue(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<NotificationQueueQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_NOTIFICACIONES) {
+        return forbidden_response();
+    }
+    match notification_logic::list_queue(&state.db_pool, query.estado.as_deref(), query.limit).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+#[post("/notifications/queue/{notificacion_id}/retry")]
+async fn retry_notification(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_NOTIFICACIONES) {
+        return forbidden_response();
+    }
+    match notification_logic::retry(&state.db_pool, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => notification_error_response(e),
+    }
+}
+
+// Rutas públicas: restablecimiento de contraseña
+pub fn notification_config_public(cfg: &mut web::ServiceConfig) {
+    cfg.service(request_password_reset)
+       .service(confirm_password_reset);
+}
+
+// Función de configuración para Actix-Web
+pub fn notification_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(send_verification_code)
+       .service(confirm_verification_code)
+       .service(list_queue)
+       .service(retry_notification);
+}
@@ -0,0 +1,166 @@
+// src/api/routes/permission_catalog_route.rs
+// Catálogo de permisos del aplicativo y su reporte de consistencia con el menú.
+
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::permission_catalog_logic::{self, CatalogError, PERMISO_CATALOGO};
+use shared_lib::permission_catalog_models::{CatalogPermissionInput, CatalogSyncRequest};
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn catalog_error_response(error: CatalogError) -> HttpResponse {
+    match error {
+        CatalogError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        CatalogError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        CatalogError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        CatalogError::Database(message) => {
+            eprintln!("Error de DB en el catálogo de permisos: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+#[get("/permissions/catalog")]
+async fn list_permissions(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::list_permissions(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(permisos) => HttpResponse::Ok().json(permisos),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+#[post("/permissions/catalog")]
+async fn create_permission(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<CatalogPermissionInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::create_permission(
+        &state.db_pool,
+        aplicativo_id,
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(permiso) => HttpResponse::Created().json(permiso),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+#[put("/permissions/catalog/{permiso_id}")]
+async fn update_permission(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<CatalogPermissionInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::update_permission(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(permiso) => HttpResponse::Ok().json(permiso),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+#[delete("/permissions/catalog/{permiso_id}")]
+async fn delete_permission(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::delete_permission(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+#[get("/permissions/catalog/consistency")]
+async fn consistency_report(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::consistency_report(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(reporte) => HttpResponse::Ok().json(reporte),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+// Recibe el contenido del archivo declarativo (mismo formato que permisos.json)
+#[post("/permissions/catalog/sync")]
+async fn sync_catalog(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<CatalogSyncRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CATALOGO) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match permission_catalog_logic::sync_catalog(
+        &state.db_pool,
+        aplicativo_id,
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(resumen) => HttpResponse::Ok().json(resumen),
+        Err(e) => catalog_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn permission_catalog_config(cfg: &mut web::ServiceConfig) {
+    // /consistency antes de /{permiso_id}
+    cfg.service(consistency_report)
+       .service(sync_catalog)
+       .service(list_permissions)
+       .service(create_permission)
+       .service(update_permission)
+       .service(delete_permission);
+}
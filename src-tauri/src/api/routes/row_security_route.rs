@@ -0,0 +1,363 @@
+// src/api/routes/row_security_route.rs
+// Seguridad por fila: conceptos, valores por usuario y resolvedor.
+
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::row_security_logic::{self, RowSecurityError, PERMISO_CONCEPTOS, PERMISO_VALORES};
+use shared_lib::row_security_models::{
+    ConceptLookupQuery, ResolveQuery, SecurityConceptInput, UserConceptValuesInput,
+};
+use shared_lib::secured_view_logic;
+use shared_lib::secured_query_models::SecuredViewInput;
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn row_security_error_response(error: RowSecurityError) -> HttpResponse {
+    match error {
+        RowSecurityError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        RowSecurityError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        RowSecurityError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        RowSecurityError::Database(message) => {
+            eprintln!("Error de DB en seguridad por fila: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+/// Usuario a resolver: el indicado (requiere PERMISO_VALORES) o el conectado.
+async fn target_user_id(state: &AppState, claims: &Claims, usuario_id: Option<i32>) -> Result<i32, HttpResponse> {
+    if let Some(id) = usuario_id {
+        if !claims.has_permission(PERMISO_VALORES) {
+            return Err(forbidden_response());
+        }
+        return Ok(id);
+    }
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(row_security_error_response(RowSecurityError::NotFound(
+            "Usuario no encontrado".to_string(),
+        ))),
+        Err(e) => Err(row_security_error_response(RowSecurityError::Database(e.to_string()))),
+    }
+}
+
+#[get("/row-security/concepts")]
+async fn list_concepts(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) && !claims.has_permission(PERMISO_VALORES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::list_concepts(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(conceptos) => HttpResponse::Ok().json(conceptos),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[post("/row-security/concepts")]
+async fn create_concept(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<SecurityConceptInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::create_concept(&state.db_pool, aplicativo_id, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(concepto) => HttpResponse::Created().json(concepto),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[put("/row-security/concepts/{concepto_id}")]
+async fn update_concept(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<SecurityConceptInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::update_concept(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(concepto) => HttpResponse::Ok().json(concepto),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[delete("/row-security/concepts/{concepto_id}")]
+async fn delete_concept(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::delete_concept(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Valores posibles del concepto según su tabla del ERP (para el selector)
+#[get("/row-security/concepts/{concepto_id}/lookup")]
+async fn lookup_values(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<ConceptLookupQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_VALORES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let concepto = match row_security_logic::get_concept(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(concepto) => concepto,
+        Err(e) => return row_security_error_response(e),
+    };
+    match row_security_logic::lookup_values(&state.db_pool, &concepto, query.filtro.as_deref(), &state.sql_collate_clause).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[get("/row-security/concepts/{concepto_id}/values")]
+async fn list_assignments(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_VALORES) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let concepto = match row_security_logic::get_concept(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(concepto) => concepto,
+        Err(e) => return row_security_error_response(e),
+    };
+    match row_security_logic::list_concept_assignments(&state.db_pool, concepto.concepto_id, &state.sql_collate_clause).await {
+        Ok(asignaciones) => HttpResponse::Ok().json(asignaciones),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[get("/row-security/concepts/{concepto_id}/values/{usuario_id}")]
+async fn get_user_values(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_VALORES) {
+        return forbidden_response();
+    }
+    let (concepto_id, usuario_id) = path.into_inner();
+    let aplicativo_id = state.current_aplicativo_id().await;
+    if let Err(e) = row_security_logic::get_concept(&state.db_pool, aplicativo_id, concepto_id, &state.sql_collate_clause).await {
+        return row_security_error_response(e);
+    }
+    match row_security_logic::get_user_values(&state.db_pool, concepto_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Reemplaza el conjunto de valores del usuario para el concepto
+#[put("/row-security/concepts/{concepto_id}/values/{usuario_id}")]
+async fn set_user_values(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<UserConceptValuesInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_VALORES) {
+        return forbidden_response();
+    }
+    let (concepto_id, usuario_id) = path.into_inner();
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let concepto = match row_security_logic::get_concept(&state.db_pool, aplicativo_id, concepto_id, &state.sql_collate_clause).await {
+        Ok(concepto) => concepto,
+        Err(e) => return row_security_error_response(e),
+    };
+    match row_security_logic::set_user_values(
+        &state.db_pool,
+        &concepto,
+        usuario_id,
+        body.todos,
+        &body.valores,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Resolvedor: valores permitidos de todos los conceptos
+#[get("/row-security/resolve")]
+async fn resolve_all(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<ResolveQuery>,
+) -> impl Responder {
+    let usuario_id = match target_user_id(&state, &claims, query.usuario_id).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::resolve_all(&state.db_pool, aplicativo_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[get("/row-security/resolve/{codigo}")]
+async fn resolve_concept(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<ResolveQuery>,
+) -> impl Responder {
+    let usuario_id = match target_user_id(&state, &claims, query.usuario_id).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match row_security_logic::resolve_allowed_values(
+        &state.db_pool,
+        aplicativo_id,
+        usuario_id,
+        &path.into_inner(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Vistas por concepto registradas
+#[get("/row-security/views")]
+async fn list_views(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match secured_view_logic::list_views(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(vistas) => HttpResponse::Ok().json(vistas),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[post("/row-security/views")]
+async fn create_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<SecuredViewInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match secured_view_logic::create_view(&state.db_pool, aplicativo_id, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(vista) => HttpResponse::Created().json(vista),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[delete("/row-security/views/{vista_id}")]
+async fn delete_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match secured_view_logic::delete_view(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Vista previa del script sin ejecutarlo
+#[get("/row-security/views/{vista_id}/script")]
+async fn view_script(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match secured_view_logic::view_script(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(script) => HttpResponse::Ok().json(script),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+#[post("/row-security/views/{vista_id}/generate")]
+async fn generate_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_CONCEPTOS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match secured_view_logic::generate_view(&state.db_pool, aplicativo_id, path.into_inner(), &state.sql_collate_clause).await {
+        Ok(script) => HttpResponse::Ok().json(script),
+        Err(e) => row_security_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn row_security_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_concepts)
+       .service(create_concept)
+       .service(update_concept)
+       .service(delete_concept)
+       .service(lookup_values)
+       .service(list_assignments)
+       .service(get_user_values)
+       .service(set_user_values)
+       .service(resolve_all)
+       .service(resolve_concept)
+       .service(list_views)
+       .service(create_view)
+       .service(delete_view)
+       .service(view_script)
+       .service(generate_view);
+}
@@ -0,0 +1,332 @@
+// src/api/routes/saved_query_route.rs
+// Consultas guardadas: "Mis consultas", "Todas las consultas", ejecución y lookups.
+
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::saved_query_logic::{
+    self, QueryUser, SavedQueryError, PERMISO_MIS_CONSULTAS, PERMISO_TODAS_LAS_CONSULTAS,
+};
+use shared_lib::saved_query_models::{QueryExecutionRequest, SavedQueryInput};
+use shared_lib::row_security_models::ConceptLookupQuery;
+use shared_lib::export_logic::{self, ExportOptions};
+use shared_lib::export_models::{ExportColumn, ExportQuery};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn saved_query_error_response(error: SavedQueryError) -> HttpResponse {
+    match error {
+        SavedQueryError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        SavedQueryError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        SavedQueryError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        SavedQueryError::Timeout(message) => HttpResponse::GatewayTimeout().json(ApiError {
+            code: AppErrorCode::Timeout,
+            message,
+        }),
+        SavedQueryError::Database(message) => {
+            eprintln!("Error de DB en consultas guardadas: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+fn can_use_queries(claims: &Claims) -> bool {
+    claims.has_permission(PERMISO_MIS_CONSULTAS) || claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS)
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(saved_query_error_response(SavedQueryError::NotFound(
+            "Usuario no encontrado".to_string(),
+        ))),
+        Err(e) => Err(saved_query_error_response(SavedQueryError::Database(e.to_string()))),
+    }
+}
+
+#[get("/queries/mine")]
+async fn list_my_queries(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::list_visible(&state.db_pool, aplicativo_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(consultas) => HttpResponse::Ok().json(consultas),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[get("/queries")]
+async fn list_all_queries(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::list_all(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(consultas) => HttpResponse::Ok().json(consultas),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[post("/queries")]
+async fn create_query(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<SavedQueryInput>,
+) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::create_query(&state.db_pool, aplicativo_id, &body, &user, &state.sql_collate_clause).await {
+        Ok(consulta) => HttpResponse::Created().json(consulta),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[get("/queries/{consulta_id}")]
+async fn get_query(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::get_visible_query(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &user,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(consulta) => HttpResponse::Ok().json(consulta),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[put("/queries/{consulta_id}")]
+async fn update_query(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<SavedQueryInput>,
+) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::update_query(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &user,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(consulta) => HttpResponse::Ok().json(consulta),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[delete("/queries/{consulta_id}")]
+async fn delete_query(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::delete_query(&state.db_pool, aplicativo_id, path.into_inner(), &user, &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+#[post("/queries/{consulta_id}/execute")]
+async fn execute_query(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<QueryExecutionRequest>,
+) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::execute_query(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        path.into_inner(),
+        &user,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(resultado) => HttpResponse::Ok().json(resultado),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+// Exporta el resultado completo (sin paginar) en streaming: ?format=csv|xlsx|ndjson&columns=a,b&locale=es|en
+#[post("/queries/{consulta_id}/export")]
+async fn export_query(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<ExportQuery>,
+    body: web::Json<QueryExecutionRequest>,
+) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let (nombre, exportacion) = match saved_query_logic::prepare_export(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        path.into_inner(),
+        &user,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(preparada) => preparada,
+        Err(e) => return saved_query_error_response(e),
+    };
+    let disponibles = exportacion.columnas.iter().cloned().map(ExportColumn::from).collect();
+    let columnas = match export_logic::select_columns(disponibles, query.columns.as_deref()) {
+        Ok(columnas) => columnas,
+        Err(message) => return saved_query_error_response(SavedQueryError::Validation(message)),
+    };
+    let formato = query.format.unwrap_or_default();
+    let opciones = ExportOptions {
+        formato,
+        locale: query.locale.unwrap_or_default(),
+        columnas,
+    };
+    let stream = export_logic::spawn_export(opciones, move |mut writer| async move {
+        exportacion.write_to(&mut writer).await?;
+        Ok(writer)
+    });
+    export_logic::streaming_response(&nombre, formato, stream)
+}
+
+#[get("/queries/{consulta_id}/parameters/{nombre}/lookup")]
+async fn parameter_lookup(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<(i32, String)>,
+    query: web::Query<ConceptLookupQuery>,
+) -> impl Responder {
+    if !can_use_queries(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_CONSULTAS),
+    };
+    let (consulta_id, nombre) = path.into_inner();
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match saved_query_logic::parameter_lookup(
+        &state.db_pool,
+        aplicativo_id,
+        consulta_id,
+        &nombre,
+        &user,
+        query.filtro.as_deref(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(valores) => HttpResponse::Ok().json(valores),
+        Err(e) => saved_query_error_response(e),
+    }
+}
+
+pub fn saved_query_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_my_queries)
+       .service(list_all_queries)
+       .service(create_query)
+       .service(get_query)
+       .service(update_query)
+       .service(delete_query)
+       .service(execute_query)
+       .service(export_query)
+       .service(parameter_lookup);
+}
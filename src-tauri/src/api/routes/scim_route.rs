@@ -0,0 +1,220 @@
+// src/api/routes/scim_route.rs
+// Endpoints SCIM 2.0 para el aprovisionamiento desde Entra ID.
+// Se montan en /scim/v2 fuera de /api/protected: Entra ID se autentica con un
+// token secreto (SCIM_BEARER_TOKEN), no con el JWT de sesión de riy.
+
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use actix_web::http::StatusCode;
+use serde::Serialize;
+
+use shared_lib::state::AppState;
+use shared_lib::scim_logic;
+use shared_lib::scim_models::{ScimError, ScimGroup, ScimListQuery, ScimPatchRequest, ScimUser};
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+const SCIM_BASE_PATH: &str = "/scim/v2";
+
+fn scim_response<T: Serialize>(status: StatusCode, body: &T) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(body)
+}
+
+fn scim_error_response(error: ScimError) -> HttpResponse {
+    if error.status_code() >= 500 {
+        eprintln!("Error SCIM: {}", error.detail);
+    }
+    let status = StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    scim_response(status, &error)
+}
+
+/// Comparación en tiempo constante para no filtrar el token por tiempos de respuesta.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Valida el encabezado `Authorization: Bearer <token>` contra SCIM_BEARER_TOKEN.
+/// Si el token no está configurado, SCIM queda deshabilitado.
+fn authorize(req: &HttpRequest, state: &AppState) -> Result<(), HttpResponse> {
+    let unauthorized = || scim_error_response(ScimError::new(401, None, "Token SCIM inválido o ausente"));
+    if state.scim_bearer_token.is_empty() {
+        return Err(unauthorized());
+    }
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(unauthorized)?;
+    if !constant_time_eq(token.trim().as_bytes(), state.scim_bearer_token.as_bytes()) {
+        return Err(unauthorized());
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------
+// USERS
+// -------------------------------------------------------------------------
+
+#[get("/Users")]
+async fn list_users(req: HttpRequest, state: web::Data<AppState>, query: web::Query<ScimListQuery>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    match scim_logic::list_users(&state.db_pool, &query, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(list) => scim_response(StatusCode::OK, &list),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[get("/Users/{id}")]
+async fn get_user(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::get_user(&state.db_pool, &path, aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(user) => scim_response(StatusCode::OK, &user),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[post("/Users")]
+async fn create_user(req: HttpRequest, state: web::Data<AppState>, body: web::Json<ScimUser>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::create_user(&state.db_pool, body.into_inner(), aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(user) => scim_response(StatusCode::CREATED, &user),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[patch("/Users/{id}")]
+async fn patch_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<ScimPatchRequest>,
+) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::patch_user(
+        &state.db_pool,
+        &path,
+        &body.operations,
+        aplicativo_id,
+        SCIM_BASE_PATH,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(user) => scim_response(StatusCode::OK, &user),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[delete("/Users/{id}")]
+async fn delete_user(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    match scim_logic::delete_user(&state.db_pool, &path, &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+// -------------------------------------------------------------------------
+// GROUPS
+// -------------------------------------------------------------------------
+
+#[get("/Groups")]
+async fn list_groups(req: HttpRequest, state: web::Data<AppState>, query: web::Query<ScimListQuery>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::list_groups(&state.db_pool, &query, aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(list) => scim_response(StatusCode::OK, &list),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[get("/Groups/{id}")]
+async fn get_group(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::get_group(&state.db_pool, &path, aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(group) => scim_response(StatusCode::OK, &group),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[post("/Groups")]
+async fn create_group(req: HttpRequest, state: web::Data<AppState>, body: web::Json<ScimGroup>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::create_group(&state.db_pool, body.into_inner(), aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(group) => scim_response(StatusCode::CREATED, &group),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[patch("/Groups/{id}")]
+async fn patch_group(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<ScimPatchRequest>,
+) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::patch_group(
+        &state.db_pool,
+        &path,
+        &body.operations,
+        aplicativo_id,
+        SCIM_BASE_PATH,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(group) => scim_response(StatusCode::OK, &group),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+#[delete("/Groups/{id}")]
+async fn delete_group(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    if let Err(resp) = authorize(&req, &state) {
+        return resp;
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match scim_logic::delete_group(&state.db_pool, &path, aplicativo_id, SCIM_BASE_PATH, &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => scim_error_response(e),
+    }
+}
+
+// Función de configuración para Actix-Web
+pub fn scim_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_users)
+       .service(get_user)
+       .service(create_user)
+       .service(patch_user)
+       .service(delete_user)
+       .service(list_groups)
+       .service(get_group)
+       .service(create_group)
+       .service(patch_group)
+       .service(delete_group);
+}
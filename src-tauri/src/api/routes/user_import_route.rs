@@ -0,0 +1,167 @@
+// src/api/routes/user_import_route.rs
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::user_import_logic;
+use shared_lib::user_import_models::UserImportRequest;
+use shared_lib::export_logic::{self, ExportOptions};
+use shared_lib::export_models::ExportQuery;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+/// Permiso requerido para importar usuarios. Es propio (y de seguridad) porque la importación
+/// también asigna roles.
+const PERMISO_IMPORTAR: &str = "importar_usuarios";
+/// Permiso requerido para exportar usuarios.
+const PERMISO_EXPORTAR: &str = "lista_usuarios";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+// Fase 1: interpreta y valida el archivo, devuelve la vista previa
+#[post("/users/import")]
+async fn import_users_preview(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<UserImportRequest>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IMPORTAR) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+
+    match user_import_logic::preview_user_import(
+        &state.db_pool,
+        aplicativo_id,
+        body.format,
+        &body.content_base64,
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(preview) => HttpResponse::Ok().json(preview),
+        Err(e) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message: e,
+        }),
+    }
+}
+
+// Fase 2: confirma la vista previa e inserta las filas válidas
+#[post("/users/import/{import_id}/commit")]
+async fn import_users_commit(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_IMPORTAR) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+
+    match user_import_logic::commit_user_import(
+        &state.db_pool,
+        aplicativo_id,
+        &path.into_inner(),
+        &claims.sub,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) => {
+            eprintln!("Error al confirmar la importación de usuarios: {}", e);
+            HttpResponse::UnprocessableEntity().json(ApiError {
+                code: AppErrorCode::ValidationError,
+                message: e,
+            })
+        }
+    }
+}
+
+// Exporta los usuarios con su estado y roles en streaming: ?format=csv|xlsx|ndjson&columns=a,b&locale=es|en
+// CSV y NDJSON se envían fila a fila; XLSX se arma completo en memoria antes de enviarse (ver export_logic).
+#[get("/users/export")]
+async fn export_users(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_EXPORTAR) {
+        return forbidden_response();
+    }
+    let columnas = match export_logic::select_columns(user_import_logic::user_export_columns(), query.columns.as_deref()) {
+        Ok(columnas) => columnas,
+        Err(e) => return HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message: e,
+        }),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    let formato = query.format.unwrap_or_default();
+    let opciones = ExportOptions {
+        formato,
+        locale: query.locale.unwrap_or_default(),
+        columnas,
+    };
+    let pool = state.db_pool.clone();
+    let sql_collate_clause = state.sql_collate_clause.clone();
+    let stream = export_logic::spawn_export(opciones, move |mut writer| async move {
+        user_import_logic::write_users_export(&pool, aplicativo_id, &sql_collate_clause, &mut writer).await?;
+        Ok(writer)
+    });
+    export_logic::streaming_response("usuarios", formato, stream)
+}
+
+#[derive(serde::Deserialize)]
+pub struct ErpExportQuery {
+    #[serde(rename = "searchTerm")]
+    pub search_term: Option<String>,
+    #[serde(flatten)]
+    pub export: ExportQuery,
+}
+
+// Exporta los usuarios del ERP en streaming: mismos parámetros que /users/export más ?searchTerm=
+// Para cientos de miles de filas usar CSV o NDJSON: XLSX no hace streaming.
+#[get("/erp-users/export")]
+async fn export_erp_users(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<ErpExportQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_EXPORTAR) {
+        return forbidden_response();
+    }
+    let query = query.into_inner();
+    let columnas = match export_logic::select_columns(user_import_logic::erp_user_export_columns(), query.export.columns.as_deref()) {
+        Ok(columnas) => columnas,
+        Err(e) => return HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message: e,
+        }),
+    };
+    let formato = query.export.format.unwrap_or_default();
+    let opciones = ExportOptions {
+        formato,
+        locale: query.export.locale.unwrap_or_default(),
+        columnas,
+    };
+    let pool = state.db_pool.clone();
+    let sql_collate_clause = state.sql_collate_clause.clone();
+    let busqueda = query.search_term;
+    let stream = export_logic::spawn_export(opciones, move |mut writer| async move {
+        user_import_logic::write_erp_users_export(&pool, busqueda.as_deref(), &sql_collate_clause, &mut writer).await?;
+        Ok(writer)
+    });
+    export_logic::streaming_response("usuarios_erp", formato, stream)
+}
+
+// Función de configuración para Actix-Web
+pub fn user_import_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(import_users_preview)
+       .service(import_users_commit)
+       .service(export_users)
+       .service(export_erp_users);
+}
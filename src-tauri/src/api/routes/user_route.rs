@@ -3,7 +3,8 @@ use actix_web::{get, post, put, HttpResponse, HttpRequest,
     Responder, web, HttpMessage, Error};
 
 use shared_lib::state::AppState;
-use shared_lib::{models, user_logic};
+use shared_lib::{models, user_logic, user_sync_logic};
+use shared_lib::user_sync_models::UserSyncRequest;
 use shared_lib::app_errors::ApiError;
 use shared_lib::middleware::auth_claims::Claims;
 
@@ -12,20 +13,34 @@ use shared_lib::middleware::auth_claims::Claims;
  
 
 
-// Endpoint para obtener todos los usuarios
+// Endpoint para obtener los usuarios (paginado, ordenable y filtrable).
+// El total de filas que cumplen los filtros se devuelve también en la cabecera X-Total-Count.
 #[get("/users")]
 async fn get_all_users(
-    state: web::Data<AppState>
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<models::UserListQuery>,
 ) -> impl Responder {
-    //let pool_guard = state.db_pool.lock().await;
-    //let pool_ref = pool_guard.as_ref().expect("Pool de DB no disponible");
+    if !claims.has_permission(user_logic::PERMISO_LISTA_USUARIOS) {
+        return forbidden_response();
+    }
     let sql_collate = &state.sql_collate_clause;
 
+    if let Err(e) = user_logic::validate_user_list_query(&query) {
+        return HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message: e,
+        });
+    }
+
     // Llamamos a la lógica centralizada
     match user_logic::get_all_users_logic(
         &state.db_pool, // <--- Accede al pool directamente 
+        &query,
         sql_collate).await {
-        Ok(users) => HttpResponse::Ok().json(users),
+        Ok(page) => HttpResponse::Ok()
+            .insert_header(("X-Total-Count", page.total.to_string()))
+            .json(page),
         Err(e) => HttpResponse::InternalServerError().body(e),
     }
 }
@@ -82,10 +97,14 @@ async fn add_user_handler(
     //let user_state = state.usuario_conectado.lock().await;
     
     // Obtiene el usuario del token (del middleware)
-    let autor = req.extensions()
+    let claims = req.extensions()
                    .get::<Claims>()
-                   .map(|c| c.sub.clone())
+                   .cloned()
                    .ok_or_else(|| actix_web::error::ErrorUnauthorized("Unauthorized"))?;
+    if !claims.has_permission(PERMISO_AGREGAR_USUARIO) {
+        return Ok(forbidden_response());
+    }
+    let autor = claims.sub;
     let sql_collate = &state.sql_collate_clause;
 
     // ❌ ELIMINAR ESTA LÍNEA OBSOLETA:
@@ -150,6 +169,9 @@ async fn update_user(
     // LOCK THE POOL
     //let pool_guard = state.db_pool.lock().await;
     //let pool_ref = pool_guard.as_ref().expect("Pool de DB no disponible");
+    if !autor_claims.has_permission(PERMISO_ADMINISTRAR_USUARIOS) {
+        return Ok(forbidden_response());
+    }
     let user_id = path.into_inner();
 
     println!("Received PUT request for user ID: {}", user_id);
@@ -213,6 +235,107 @@ async fn update_user(
 
 
 
+/// Permiso requerido para ejecutar y consultar la sincronización ERP -> riy.
+const PERMISO_SINCRONIZAR_USUARIOS: &str = "sincronizar_usuarios";
+/// Permiso requerido para dar de alta usuarios.
+const PERMISO_AGREGAR_USUARIO: &str = "agregar_usuario";
+/// Permiso requerido para modificar el correo o el estado de un usuario.
+const PERMISO_ADMINISTRAR_USUARIOS: &str = "administrar_usuarios";
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+// Endpoint para ejecutar la sincronización de usuarios ERP -> riy bajo demanda
+#[post("/users/sync")]
+async fn run_user_sync_handler(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: Option<web::Json<UserSyncRequest>>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SINCRONIZAR_USUARIOS) {
+        return forbidden_response();
+    }
+    if user_sync_logic::is_sync_running() {
+        return HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message: "Ya hay una sincronización de usuarios en curso.".to_string(),
+        });
+    }
+    let request = body.map(|b| b.into_inner()).unwrap_or_default();
+
+    match user_sync_logic::run_user_sync(
+        &state.db_pool,
+        &state.sql_collate_clause,
+        &request,
+        "MANUAL",
+        &claims.sub,
+    ).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            eprintln!("Error en la sincronización de usuarios: {}", e);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message: e,
+            })
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SyncReportsQuery {
+    pub limit: Option<i32>,
+}
+
+// Endpoint para listar los últimos reportes de sincronización
+#[get("/users/sync/reports")]
+async fn list_sync_reports_handler(
+    claims: Claims,
+    state: web::Data<AppState>,
+    query: web::Query<SyncReportsQuery>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SINCRONIZAR_USUARIOS) {
+        return forbidden_response();
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, 200);
+
+    match user_sync_logic::list_sync_reports(&state.db_pool, limit, &state.sql_collate_clause).await {
+        Ok(reports) => HttpResponse::Ok().json(reports),
+        Err(e) => HttpResponse::InternalServerError().json(ApiError {
+            code: AppErrorCode::DatabaseError,
+            message: e,
+        }),
+    }
+}
+
+// Endpoint para obtener el detalle de un reporte de sincronización
+#[get("/users/sync/reports/{id}")]
+async fn get_sync_report_handler(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_SINCRONIZAR_USUARIOS) {
+        return forbidden_response();
+    }
+
+    match user_sync_logic::get_sync_report(&state.db_pool, path.into_inner()).await {
+        Ok(Some(report)) => HttpResponse::Ok().json(report),
+        Ok(None) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message: "No se encontró el reporte de sincronización.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ApiError {
+            code: AppErrorCode::DatabaseError,
+            message: e,
+        }),
+    }
+}
+
+
 // Función de configuración para Actix-Web
 // In src/api/routes/user.rs
 
@@ -220,5 +343,8 @@ pub fn user_config(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all_users)
        .service(search_erp_users)
        .service(add_user_handler)
-       .service(update_user);
+       .service(update_user)
+       .service(run_user_sync_handler)
+       .service(list_sync_reports_handler)
+       .service(get_sync_report_handler);
 }
@@ -0,0 +1,359 @@
+// src/api/routes/user_view_route.rs
+// Vistas de usuario: catálogo de entidades, "Mis vistas", "Todas las vistas" y datos.
+
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+use shared_lib::state::AppState;
+use shared_lib::saved_query_logic::QueryUser;
+use shared_lib::user_view_logic::{self, UserViewError, PERMISO_MIS_VISTAS, PERMISO_TODAS_LAS_VISTAS};
+use shared_lib::user_view_models::{ErpEntityInput, UserViewInput, ViewDataQuery, ViewPreviewRequest};
+use shared_lib::mfa_logic;
+use shared_lib::app_errors::{ApiError, AppErrorCode};
+use shared_lib::middleware::auth_claims::Claims;
+
+fn forbidden_response() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiError {
+        code: AppErrorCode::Forbidden,
+        message: "No tiene permiso para realizar esta operación.".to_string(),
+    })
+}
+
+fn user_view_error_response(error: UserViewError) -> HttpResponse {
+    match error {
+        UserViewError::Validation(message) => HttpResponse::BadRequest().json(ApiError {
+            code: AppErrorCode::ValidationError,
+            message,
+        }),
+        UserViewError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+            code: AppErrorCode::Forbidden,
+            message,
+        }),
+        UserViewError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+            code: AppErrorCode::NotFound,
+            message,
+        }),
+        UserViewError::Conflict(message) => HttpResponse::Conflict().json(ApiError {
+            code: AppErrorCode::Conflict,
+            message,
+        }),
+        UserViewError::Timeout(message) => HttpResponse::GatewayTimeout().json(ApiError {
+            code: AppErrorCode::Timeout,
+            message,
+        }),
+        UserViewError::Database(message) => {
+            eprintln!("Error de DB en vistas de usuario: {}", message);
+            HttpResponse::InternalServerError().json(ApiError {
+                code: AppErrorCode::DatabaseError,
+                message,
+            })
+        }
+    }
+}
+
+fn can_use_views(claims: &Claims) -> bool {
+    claims.has_permission(PERMISO_MIS_VISTAS) || claims.has_permission(PERMISO_TODAS_LAS_VISTAS)
+}
+
+async fn current_user_id(state: &AppState, claims: &Claims) -> Result<i32, HttpResponse> {
+    match mfa_logic::find_user_id_by_username(&state.db_pool, &claims.sub, &state.sql_collate_clause).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(user_view_error_response(UserViewError::NotFound(
+            "Usuario no encontrado".to_string(),
+        ))),
+        Err(e) => Err(user_view_error_response(UserViewError::Database(e.to_string()))),
+    }
+}
+
+// --- Catálogo de entidades ---
+
+// Quien arma vistas ve solo las activas; el administrador ve todas.
+#[get("/views/entities")]
+async fn list_entities(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let solo_activas = !claims.has_permission(PERMISO_TODAS_LAS_VISTAS);
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::list_entities(&state.db_pool, aplicativo_id, solo_activas, &state.sql_collate_clause).await {
+        Ok(entidades) => HttpResponse::Ok().json(entidades),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[post("/views/entities")]
+async fn create_entity(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<ErpEntityInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_TODAS_LAS_VISTAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::create_entity(&state.db_pool, aplicativo_id, &body, &claims.sub, &state.sql_collate_clause).await {
+        Ok(entidad) => HttpResponse::Created().json(entidad),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[put("/views/entities/{entidad_id}")]
+async fn update_entity(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<ErpEntityInput>,
+) -> impl Responder {
+    if !claims.has_permission(PERMISO_TODAS_LAS_VISTAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::update_entity(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(entidad) => HttpResponse::Ok().json(entidad),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[get("/views/entities/{entidad_id}/columns")]
+async fn entity_columns(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::entity_columns(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        path.into_inner(),
+        &state.sql_collate_clause,
+    ).await {
+        Ok(columnas) => HttpResponse::Ok().json(columnas),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+// --- Vistas ---
+
+#[get("/views/mine")]
+async fn list_my_views(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::list_visible(&state.db_pool, aplicativo_id, usuario_id, &state.sql_collate_clause).await {
+        Ok(vistas) => HttpResponse::Ok().json(vistas),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[get("/views")]
+async fn list_all_views(claims: Claims, state: web::Data<AppState>) -> impl Responder {
+    if !claims.has_permission(PERMISO_TODAS_LAS_VISTAS) {
+        return forbidden_response();
+    }
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::list_all(&state.db_pool, aplicativo_id, &state.sql_collate_clause).await {
+        Ok(vistas) => HttpResponse::Ok().json(vistas),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[post("/views")]
+async fn create_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<UserViewInput>,
+) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_VISTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::create_view(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        &body,
+        &user,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(vista) => HttpResponse::Created().json(vista),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[post("/views/preview")]
+async fn preview_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    body: web::Json<ViewPreviewRequest>,
+) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::preview(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        usuario_id,
+        &body,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(resultado) => HttpResponse::Ok().json(resultado),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[get("/views/{vista_id}")]
+async fn get_view(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_VISTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::get_visible_view(
+        &state.db_pool,
+        aplicativo_id,
+        path.into_inner(),
+        &user,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(vista) => HttpResponse::Ok().json(vista),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[put("/views/{vista_id}")]
+async fn update_view(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    body: web::Json<UserViewInput>,
+) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_VISTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::update_view(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        path.into_inner(),
+        &body,
+        &user,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(vista) => HttpResponse::Ok().json(vista),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+#[delete("/views/{vista_id}")]
+async fn delete_view(claims: Claims, state: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_VISTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::delete_view(&state.db_pool, aplicativo_id, path.into_inner(), &user, &state.sql_collate_clause).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+// Datos paginados de la vista: ?pagina=1&tamanoPagina=100
+#[get("/views/{vista_id}/data")]
+async fn view_data(
+    claims: Claims,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<ViewDataQuery>,
+) -> impl Responder {
+    if !can_use_views(&claims) {
+        return forbidden_response();
+    }
+    let usuario_id = match current_user_id(&state, &claims).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let user = QueryUser {
+        usuario: &claims.sub,
+        usuario_id,
+        ve_todas: claims.has_permission(PERMISO_TODAS_LAS_VISTAS),
+    };
+    let aplicativo_id = state.current_aplicativo_id().await;
+    match user_view_logic::view_data(
+        &state.db_pool,
+        state.sql_sandbox.as_ref(),
+        aplicativo_id,
+        path.into_inner(),
+        &user,
+        &query,
+        &state.sql_collate_clause,
+    ).await {
+        Ok(resultado) => HttpResponse::Ok().json(resultado),
+        Err(e) => user_view_error_response(e),
+    }
+}
+
+pub fn user_view_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_entities)
+       .service(create_entity)
+       .service(update_entity)
+       .service(entity_columns)
+       .service(list_my_views)
+       .service(list_all_views)
+       .service(create_view)
+       .service(preview_view)
+       .service(get_view)
+       .service(update_view)
+       .service(delete_view)
+       .service(view_data);
+}
@@ -0,0 +1,152 @@
+// src-tauri/src/connection.rs
+// Perfiles de conexión: alta, prueba, selección y baja de conexiones a SQL Server sin reiniciar.
+
+use tauri::State;
+
+use crate::AppState;
+use shared_lib::connection_profile_logic;
+use shared_lib::connection_profile_models::{
+    ConnectionProfileInput, ConnectionProfileSummary, ConnectionTestResult,
+};
+use std::sync::atomic::Ordering;
+
+use shared_lib::db;
+use shared_lib::license_logic::{self, LicenseCheckResult, LicenseStatus};
+
+/// Conecta a `database_url` y, si todo sale bien, la deja como conexión de la aplicación:
+/// resuelve el aplicativo en esa base, vuelve a verificar la licencia y cierra la sesión del
+/// usuario, que pertenecía a la conexión anterior. Si algo falla antes del cambio, sigue la anterior.
+///
+/// Con una conexión abierta solo se cambia a una base con licencia válida. Sin conexión previa
+/// (al iniciar) se acepta una licencia no válida para poder registrarla desde la pantalla de licencia,
+/// pero no un error al verificarla.
+pub async fn connect(state: &AppState, database_url: String) -> Result<LicenseCheckResult, String> {
+    let pool = db::connect_db(&database_url).await?;
+    let aplicativo_id = match db::get_aplicativo_id(&pool, &state.aplicativo).await {
+        Ok(aplicativo_id) => aplicativo_id,
+        Err(e) => {
+            pool.close().await;
+            return Err(e);
+        }
+    };
+
+    let licencia = match license_logic::check_license_status(
+        &pool,
+        &state.sql_collate_clause,
+        aplicativo_id,
+        &state.palabra_clave2,
+        &database_url,
+        &state.aplicativo,
+    ).await {
+        Ok(licencia) => licencia,
+        Err(e) => {
+            pool.close().await;
+            return Err(e);
+        }
+    };
+
+    let anterior = {
+        let mut pool_guard = state.db_pool.lock().await;
+        if pool_guard.is_some() && !matches!(licencia.status, LicenseStatus::Valid) {
+            drop(pool_guard);
+            pool.close().await;
+            return Err(format!(
+                "No se cambió la conexión: la licencia de esa base no es válida ({})",
+                licencia.message
+            ));
+        }
+        *state.aplicativo_id.lock().await = aplicativo_id;
+        *state.db_connection_url.lock().await = Some(database_url);
+        *state.usuario_conectado.lock().await = None;
+        state.online.store(true, Ordering::Relaxed);
+        pool_guard.replace(pool)
+    };
+    if let Some(anterior) = anterior {
+        anterior.close().await;
+    }
+    Ok(licencia)
+}
+
+/// Al iniciar: conecta al último perfil elegido, si hay uno.
+pub async fn connect_active_profile(state: &AppState) -> Result<Option<LicenseCheckResult>, String> {
+    let perfil = {
+        let store = state.connection_profiles.lock().await;
+        connection_profile_logic::active_profile(&store).map_err(|e| e.to_string())?
+    };
+    match perfil {
+        Some(perfil) => {
+            let url = connection_profile_logic::build_connection_url(&perfil).map_err(|e| e.to_string())?;
+            connect(state, url).await.map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Conexión al iniciar (y al reintentar sin conexión): el perfil activo o, si no hay, `database_url`.
+pub async fn connect_initial(
+    state: &AppState,
+    database_url: Option<String>,
+) -> Result<Option<LicenseCheckResult>, String> {
+    match connect_active_profile(state).await? {
+        Some(licencia) => Ok(Some(licencia)),
+        None => match database_url {
+            Some(url) => connect(state, url).await.map(Some),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Perfiles guardados (sin contraseñas) y cuál es el activo.
+#[tauri::command]
+pub async fn list_connection_profiles(state: State<'_, AppState>) -> Result<Vec<ConnectionProfileSummary>, String> {
+    let store = state.connection_profiles.lock().await;
+    connection_profile_logic::list_profiles(&store).map_err(|e| e.to_string())
+}
+
+/// Crea el perfil o modifica el del mismo nombre; no cambia la conexión actual.
+#[tauri::command]
+pub async fn save_connection_profile(
+    state: State<'_, AppState>,
+    perfil: ConnectionProfileInput,
+) -> Result<ConnectionProfileSummary, String> {
+    let store = state.connection_profiles.lock().await;
+    connection_profile_logic::save_profile(&store, &perfil).map_err(|e| e.to_string())
+}
+
+/// Prueba los datos del formulario sin guardarlos.
+#[tauri::command]
+pub async fn test_connection_profile(
+    state: State<'_, AppState>,
+    perfil: ConnectionProfileInput,
+) -> Result<ConnectionTestResult, String> {
+    let store = state.connection_profiles.lock().await;
+    connection_profile_logic::test_profile(&store, &perfil)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Elimina el perfil. La conexión abierta sigue hasta elegir otra o cerrar la aplicación.
+#[tauri::command]
+pub async fn delete_connection_profile(state: State<'_, AppState>, nombre: String) -> Result<(), String> {
+    let store = state.connection_profiles.lock().await;
+    connection_profile_logic::delete_profile(&store, &nombre).map_err(|e| e.to_string())
+}
+
+/// Cambia a la conexión del perfil y devuelve el resultado de la verificación de licencia.
+#[tauri::command]
+pub async fn select_connection_profile(
+    state: State<'_, AppState>,
+    nombre: String,
+) -> Result<LicenseCheckResult, String> {
+    let url = {
+        let store = state.connection_profiles.lock().await;
+        let perfil = connection_profile_logic::get_profile(&store, &nombre).map_err(|e| e.to_string())?;
+        connection_profile_logic::build_connection_url(&perfil).map_err(|e| e.to_string())?
+    };
+    let licencia = connect(&state, url).await?;
+
+    // Se recuerda solo si se cambió la conexión
+    let store = state.connection_profiles.lock().await;
+    connection_profile_logic::set_active(&store, &nombre).map_err(|e| e.to_string())?;
+    Ok(licencia)
+}
@@ -38,7 +38,8 @@ pub async fn check_license_status_command(state: State<'_, AppState>) -> Result<
     let pool_guard = state.db_pool.lock().await;
     let pool_ref = pool_guard.as_ref().ok_or_else(|| "DB pool not initialized".to_string())?;
 
-   let aplicativo_id = *state.aplicativo_id.lock().await;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    let db_connection_url = state.db_connection_url.lock().await.clone().unwrap_or_default();
 
     // Llama a la función con los 6 argumentos necesarios
     license_logic::check_license_status(
@@ -46,7 +47,7 @@ pub async fn check_license_status_command(state: State<'_, AppState>) -> Result<
         &state.sql_collate_clause,
         aplicativo_id,
         &state.palabra_clave2,
-        &state.db_connection_url,
+        &db_connection_url,
         &state.aplicativo,
     ).await
 }
@@ -65,6 +66,7 @@ pub async fn save_license_credentials_command(
     let pool_guard = state.db_pool.lock().await;
     let pool_ref = pool_guard.as_ref().ok_or_else(|| "DB pool not initialized".to_string())?;
     let aplicativo_id = *state.aplicativo_id.lock().await;
+    let db_connection_url = state.db_connection_url.lock().await.clone().unwrap_or_default();
 
 
     // Llama a la función con los 6 argumentos necesarios
@@ -74,7 +76,7 @@ pub async fn save_license_credentials_command(
         aplicativo_id,
         &state.palabra_clave1,
         &state.palabra_clave2,
-        &state.db_connection_url,
+        &db_connection_url,
         &state.aplicativo,
         &encrypted_credentials_from_user
     ).await
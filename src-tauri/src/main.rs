@@ -12,6 +12,14 @@ use anyhow::{Result, anyhow};
 mod user; 
 mod menu; 
 mod license;
+mod user_import;
+mod profile;
+mod row_security;
+mod connection;
+mod secrets;
+mod oauth_login;
+mod offline;
+mod mfa;
 mod api;    // Define la estructura de las APIs (Actix Web)
 mod shared; // Define módulos compartidos (auth, models, repo, etc.)
 
@@ -21,12 +29,16 @@ use crate::license::{
 };
 
 // Usa el crate actual para encontrar la librería compartida y modelos
-use shared_lib::{db}; 
-use crate::models::LoggedInUser; // Necesario si LoggedInUser se usa en AppState
+use shared_lib::models::LoggedInUser; // Usuario conectado en la conexión actual
+use shared_lib::connection_profile_logic::ProfileStore;
+use shared_lib::secret_store_logic::SecretStore;
+use shared_lib::config::AppConfig;
+use shared_lib::offline_cache_logic::OfflineCache;
 
 // Autenticación Externa (MSAL B2C y Google)
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use tokio::sync::Mutex;
 use reqwest::Client; 
 use jwks_rs::JwksClient; // Cliente para validar JWTs de Azure AD B2C
@@ -40,10 +52,31 @@ use shared_lib::db::get_db_connection_info;
 
 /// Estructura de datos que se comparte en toda la aplicación Tauri.
 pub struct AppState {
-    pub db_pool: Arc<Pool<Mssql>>,
+    // Sin conexión hasta abrir el perfil activo (o DATABASE_URL); se reemplaza al cambiar de perfil.
+    pub db_pool: Arc<Mutex<Option<Pool<Mssql>>>>,
+    pub db_connection_url: Arc<Mutex<Option<String>>>,
+    // ID del aplicativo en la base de datos conectada
+    pub aplicativo_id: Arc<Mutex<i32>>,
+    // Código del aplicativo (APLICATIVO)
+    pub aplicativo: String,
+    pub sql_collate_clause: String,
+    pub palabra_clave1: String,
+    pub palabra_clave2: String,
+    pub usuario_conectado: Arc<Mutex<Option<LoggedInUser>>>,
+    // Archivo cifrado con los perfiles de conexión
+    pub connection_profiles: Arc<Mutex<ProfileStore>>,
+    // Archivo cifrado de secretos; sus cambios se aplican al reiniciar
+    pub secrets: Arc<Mutex<SecretStore>>,
+    // Configuración leída al iniciar (entorno + secretos)
+    pub config: AppConfig,
+    // Cancela el login en el navegador que esté esperando
+    pub oauth_login_cancel: Arc<Mutex<Option<tokio::sync::oneshot::Sender<()>>>>,
+    // Copia local de menús, permisos y perfil para trabajar sin conexión
+    pub offline_cache: Arc<OfflineCache>,
+    // Si SQL Server responde; lo actualiza el monitor de conectividad
+    pub online: Arc<AtomicBool>,
     pub jwt_auth_client: Arc<Mutex<JwksClient>>, // Cliente JWKS para validar tokens
     pub reqwest_client: Arc<Client>, // Cliente HTTP para peticiones (como Google o MSAL)
-    pub aplicativo_id: String,
     // La URL de JWKS se guarda aquí como referencia, aunque el cliente ya está inicializado.
     pub jwks_url: String, 
 }
@@ -57,19 +90,35 @@ async fn main() -> Result<()> {
     // Carga variables de entorno desde el archivo .env
     dotenv().ok();
 
-    // Carga variables de entorno CRÍTICAS para la BD y el JWKS
-    let database_url = std::env::var("DATABASE_URL")
-        .map_err(|_| anyhow!("DATABASE_URL no está definida"))?;
-    let aplicativo_id = std::env::var("APLICATIVO_ID")
-        .map_err(|_| anyhow!("APLICATIVO_ID no está definida"))?;
+    // Carpeta de configuración: secretos y perfiles de conexión
+    let context = tauri::generate_context!();
+    let config_dir = tauri::api::path::app_config_dir(context.config())
+        .ok_or_else(|| anyhow!("No se pudo determinar la carpeta de configuración"))?;
+
+    // Secretos cifrados (SECRETS_PASSPHRASE si el archivo tiene frase); reemplazan a los del .env
+    let secrets = SecretStore::new(&config_dir, std::env::var("SECRETS_PASSPHRASE").ok());
+    let secret_values = secrets.load().unwrap_or_else(|e| {
+        eprintln!("No se pudieron leer los secretos cifrados, se usan las variables de entorno: {}", e);
+        Default::default()
+    });
+    let app_config = AppConfig::from_env_with_secrets(&secret_values);
+
+    // Carga variables de entorno CRÍTICAS para el aplicativo y el JWKS.
+    // DATABASE_URL es opcional: solo se usa si no hay un perfil de conexión elegido.
+    let database_url = app_config.database_url.clone();
+    let aplicativo = std::env::var("APLICATIVO")
+        .map_err(|_| anyhow!("APLICATIVO no está definida"))?;
     // 🚨 CRÍTICO para B2C: Cargar la URL del JWKS
     let jwks_url = std::env::var("JWKS_URL")
         .map_err(|_| anyhow!("JWKS_URL (Azure AD B2C) no está definida"))?;
     
-    // 1. Inicializa el Pool de la Base de Datos
-    let db_pool = db::initialize_db(&database_url).await
-        .map_err(|e| anyhow!("Error al inicializar la base de datos: {}", e))?;
-    let db_pool_arc = Arc::new(db_pool);
+    // 1. Perfiles de conexión (la conexión se abre después de iniciar, en `setup`)
+    let connection_profiles = ProfileStore::new(&config_dir)
+        .map_err(|e| anyhow!("Error al abrir los perfiles de conexión: {}", e))?;
+
+    // 1.1 Caché local para trabajar sin conexión
+    let offline_cache = OfflineCache::open(&config_dir).await
+        .map_err(|e| anyhow!("Error al abrir la caché local: {}", e))?;
 
     // 2. Inicializa el Cliente HTTP (reqwest)
     let reqwest_client = Arc::new(Client::new());
@@ -86,17 +135,40 @@ async fn main() -> Result<()> {
 
     // 4. Crear el estado inicial de la aplicación Tauri
     let initial_state = AppState {
-        db_pool: db_pool_arc.clone(),
-        aplicativo_id: aplicativo_id.clone(),
+        db_pool: Arc::new(Mutex::new(None)),
+        db_connection_url: Arc::new(Mutex::new(None)),
+        aplicativo_id: Arc::new(Mutex::new(0)),
+        aplicativo,
+        sql_collate_clause: app_config.sql_collate_clause.clone(),
+        palabra_clave1: app_config.palabra_clave1.clone(),
+        palabra_clave2: app_config.palabra_clave2.clone(),
+        usuario_conectado: Arc::new(Mutex::new(None)),
+        connection_profiles: Arc::new(Mutex::new(connection_profiles)),
+        secrets: Arc::new(Mutex::new(secrets)),
         reqwest_client: reqwest_client.clone(),
         jwks_url: jwks_url.to_string(), 
         jwt_auth_client: jwt_auth_client_mutex, // <--- Cliente JWKS
+        oauth_login_cancel: Arc::new(Mutex::new(None)),
+        offline_cache: Arc::new(offline_cache),
+        online: Arc::new(AtomicBool::new(false)),
+        config: app_config,
     };
     
     // 5. Construir y ejecutar la aplicación Tauri
     tauri::Builder::default()
         .setup(move |app| {
-            println!("Pool de base de datos y aplicativo ID inicializados exitosamente.");
+            // Conexión en segundo plano: la ventana abre aunque el servidor no responda
+            let handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                let state = handle.state::<AppState>();
+                match connection::connect_initial(&state, database_url.clone()).await {
+                    Ok(Some(licencia)) => println!("Conexión inicial establecida. Licencia: {}", licencia.message),
+                    Ok(None) => println!("Sin perfil de conexión activo; elija uno desde la aplicación."),
+                    Err(e) => eprintln!("No se pudo abrir la conexión inicial: {}", e),
+                }
+                // Detecta caídas y reconexiones del servidor y resincroniza la caché local
+                offline::run_connectivity_monitor(handle.clone(), database_url).await;
+            });
             Ok(())
         })
         .manage(initial_state)
@@ -105,21 +177,52 @@ async fn main() -> Result<()> {
             save_license_credentials_command,
             check_license_status_command,
             get_db_connection_info_command, 
+            connection::list_connection_profiles,
+            connection::save_connection_profile,
+            connection::test_connection_profile,
+            connection::delete_connection_profile,
+            connection::select_connection_profile,
+            secrets::get_secrets_status,
+            secrets::set_secret,
+            secrets::delete_secret,
+            secrets::change_secrets_passphrase,
             
             // Comandos de Usuario (Autenticación)
             user::user_login, // Login interno tradicional
+            mfa::user_login_mfa_verify, // Segundo paso del login cuando corresponde MFA
+            mfa::user_login_mfa_enroll,
+            mfa::user_login_mfa_enroll_confirm,
             user::user_login_external, // 🚨 CRÍTICO: Login externo (MSAL/Google)
+            oauth_login::user_login_browser, // Login externo en el navegador del sistema
+            oauth_login::cancel_login_browser,
             
             // Comandos de Gestión
             user::get_users,
             user::add_user,
             user::search_erp_users,
             user::update_user,
+            user_import::import_users_preview,
+            user_import::import_users_commit,
+            user_import::export_users,
+            profile::get_my_profile,
+            profile::update_my_profile,
+            profile::confirm_my_email_change,
+            row_security::list_row_security_concepts,
+            row_security::save_row_security_concept,
+            row_security::delete_row_security_concept,
+            row_security::lookup_concept_values,
+            row_security::list_concept_assignments,
+            row_security::set_user_concept_values,
+            row_security::resolve_my_concept_values,
             
             // Comandos de Menú
             menu::get_all_menus_command,
+
+            // Sin conexión (caché local)
+            offline::get_my_permissions,
+            offline::get_connectivity_status,
         ])
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error al ejecutar la aplicación Tauri");
 
     Ok(())
@@ -130,15 +233,16 @@ async fn main() -> Result<()> {
 // 3. COMANDOS BRIDGE (Tauri Commands)
 // --------------------------------------------------------------------------------
 
-// Comando puente para obtener la información de conexión a la BD
+// Comando puente para obtener (servidor, base de datos) de la conexión actual
 // Llama a la lógica de la librería compartida `shared_lib::db::get_db_connection_info`
 #[tauri::command]
 async fn get_db_connection_info_command(
     state: tauri::State<'_, AppState>,
 ) -> Result<(String, String), String> {
-    let pool = state.db_pool.clone();
+    let database_url = state.db_connection_url.lock().await.clone()
+        .ok_or_else(|| "No hay una conexión activa".to_string())?;
     // Llama a la lógica de la librería compartida
-    get_db_connection_info(&pool)
+    get_db_connection_info(&database_url)
         .await
         .map_err(|e| format!("Error al obtener info de conexión: {}", e))
 } 
\ No newline at end of file
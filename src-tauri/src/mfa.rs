@@ -0,0 +1,89 @@
+// src-tauri/src/mfa.rs
+//
+// Segundo paso del login ERP/Local en el escritorio. Mismo flujo que
+// POST /auth/mfa/verify y /auth/mfa/enroll[/confirm] de la API: `user_login`
+// devuelve el desafío y la sesión solo se abre al verificar el código.
+
+use tauri::State;
+
+use crate::AppState;
+use crate::user::{open_session, TauriLoginResponse};
+use shared_lib::{mfa_logic, user_repository};
+use shared_lib::mfa_models::MfaEnrollmentStart;
+
+/// Resultado del alta obligatoria: los códigos de recuperación y la sesión ya abierta.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TauriMfaEnrollAndLoginResponse {
+    pub recovery_codes: Vec<String>,
+    pub session: TauriLoginResponse,
+}
+
+/// Abre la sesión del usuario del desafío ya verificado.
+async fn complete_login(
+    state: &State<'_, AppState>,
+    pool_ref: &sqlx::Pool<sqlx::Mssql>,
+    usuario_id: i32,
+) -> Result<TauriLoginResponse, String> {
+    let user = user_repository::find_user_by_id(pool_ref, usuario_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "El usuario no existe o no está vigente.".to_string())?;
+    open_session(state, pool_ref, user.usuario, user.nombre).await
+}
+
+/// Verifica el código TOTP (o de recuperación) del desafío y abre la sesión.
+#[tauri::command]
+pub async fn user_login_mfa_verify(
+    state: State<'_, AppState>,
+    mfa_token: String,
+    code: String,
+) -> Result<TauriLoginResponse, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no disponible".to_string())?;
+    let (usuario_id, _) = mfa_logic::validate_challenge(&mfa_token, &state.config.app_jwt_secret)
+        .map_err(|e| e.to_string())?;
+    mfa_logic::verify_code(pool_ref, usuario_id, &code, &state.config.mfa_encryption_key)
+        .await
+        .map_err(|e| e.to_string())?;
+    complete_login(&state, pool_ref, usuario_id).await
+}
+
+/// Alta obligatoria durante el login (la política exige MFA y el usuario aún no lo tiene).
+#[tauri::command]
+pub async fn user_login_mfa_enroll(
+    state: State<'_, AppState>,
+    mfa_token: String,
+) -> Result<MfaEnrollmentStart, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no disponible".to_string())?;
+    let (usuario_id, usuario) = mfa_logic::validate_challenge(&mfa_token, &state.config.app_jwt_secret)
+        .map_err(|e| e.to_string())?;
+    mfa_logic::start_enrollment(
+        pool_ref,
+        usuario_id,
+        &usuario,
+        &state.aplicativo,
+        &state.config.mfa_encryption_key,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Confirma el alta con el primer código y abre la sesión.
+#[tauri::command]
+pub async fn user_login_mfa_enroll_confirm(
+    state: State<'_, AppState>,
+    mfa_token: String,
+    code: String,
+) -> Result<TauriMfaEnrollAndLoginResponse, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no disponible".to_string())?;
+    let (usuario_id, _) = mfa_logic::validate_challenge(&mfa_token, &state.config.app_jwt_secret)
+        .map_err(|e| e.to_string())?;
+    let recovery_codes = mfa_logic::confirm_enrollment(pool_ref, usuario_id, &code, &state.config.mfa_encryption_key)
+        .await
+        .map_err(|e| e.to_string())?;
+    let session = complete_login(&state, pool_ref, usuario_id).await?;
+    Ok(TauriMfaEnrollAndLoginResponse { recovery_codes, session })
+}
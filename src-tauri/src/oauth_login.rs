@@ -0,0 +1,76 @@
+// src-tauri/src/oauth_login.rs
+// Login con Google o Microsoft en el navegador del sistema (loopback + PKCE).
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::AppState;
+use shared_lib::models::AuthResponsePayload;
+use shared_lib::oauth_loopback_logic::{self, LoopbackListener};
+use shared_lib::oauth_loopback_models::OAuthProvider;
+use shared_lib::user_logic;
+
+/// Abre el navegador en la página del proveedor, espera la respuesta en el listener local y
+/// autentica al usuario con el mismo flujo que el resto de logins. Un login anterior que
+/// siguiera esperando se cancela.
+#[tauri::command]
+pub async fn user_login_browser(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    proveedor: OAuthProvider,
+) -> Result<AuthResponsePayload, String> {
+    let listener = LoopbackListener::bind().await.map_err(|e| e.to_string())?;
+    let redirect_uri = listener.redirect_uri().to_string();
+    let pkce = oauth_loopback_logic::new_pkce();
+    let url = oauth_loopback_logic::authorize_url(proveedor, &state.config, &redirect_uri, &pkce)
+        .map_err(|e| e.to_string())?;
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    if let Some(anterior) = state.oauth_login_cancel.lock().await.replace(cancel_tx) {
+        let _ = anterior.send(());
+    }
+
+    tauri::api::shell::open(&app.shell_scope(), url, None)
+        .map_err(|e| format!("No se pudo abrir el navegador: {}", e))?;
+
+    let timeout = Duration::from_secs(state.config.oauth_loopback.timeout_seconds);
+    let code = listener.wait_for_code(&pkce.state, timeout, cancel_rx).await;
+    {
+        // Solo se limpia si sigue siendo el de este login (su receptor ya se soltó)
+        let mut cancel_guard = state.oauth_login_cancel.lock().await;
+        if cancel_guard.as_ref().map_or(false, |tx| tx.is_closed()) {
+            *cancel_guard = None;
+        }
+    }
+    let code = code.map_err(|e| e.to_string())?;
+
+    let payload = oauth_loopback_logic::auth_payload(
+        proveedor,
+        &state.reqwest_client,
+        &state.config,
+        code,
+        &redirect_uri,
+        &pkce,
+    ).await.map_err(|e| e.to_string())?;
+
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "DB Pool no disponible".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    let response = user_logic::authenticate_user(pool_ref, &state.config, aplicativo_id, payload)
+        .await
+        .map_err(|e| format!("Error de autenticación: {}", e))?;
+    *state.usuario_conectado.lock().await = Some(response.user.clone());
+    Ok(response)
+}
+
+/// Cancela el login en el navegador en curso; `false` si no había ninguno.
+#[tauri::command]
+pub async fn cancel_login_browser(state: State<'_, AppState>) -> Result<bool, String> {
+    match state.oauth_login_cancel.lock().await.take() {
+        Some(cancel_tx) => Ok(cancel_tx.send(()).is_ok()),
+        None => Ok(false),
+    }
+}
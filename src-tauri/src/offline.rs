@@ -0,0 +1,180 @@
+// src-tauri/src/offline.rs
+// Trabajo sin conexión: menús, permisos y perfil desde la caché local cuando SQL Server no
+// responde, y monitor de conectividad que reconecta y resincroniza.
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use sqlx::{Mssql, Pool};
+use tauri::{AppHandle, Manager, State};
+
+use crate::connection;
+use crate::AppState;
+use shared_lib::offline_cache_logic;
+use shared_lib::offline_cache_models::{CacheKind, CachedResponse, ConnectivityEvent};
+use shared_lib::{db, menu_logic, mfa_logic, profile_logic, utils};
+
+/// Evento que se emite a la pantalla al perder o recuperar la conexión.
+pub const EVENTO_CONECTIVIDAD: &str = "conectividad";
+
+/// Ámbito de la caché y usuario de la sesión actual. Sin sesión no hay nada que servir: tras
+/// reiniciar la aplicación con SQL Server caído no se puede iniciar sesión ni usar la caché.
+async fn session_scope(state: &AppState) -> Result<(String, String), String> {
+    let usuario = state
+        .usuario_conectado
+        .lock()
+        .await
+        .as_ref()
+        .and_then(|u| u.usuario.clone())
+        .ok_or_else(|| "No hay un usuario conectado".to_string())?;
+    let database_url = state
+        .db_connection_url
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No hay una conexión activa".to_string())?;
+    let (servidor, base_datos) = db::parse_mssql_connection_url(&database_url)?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    Ok((offline_cache_logic::scope(&servidor, &base_datos, aplicativo_id), usuario))
+}
+
+/// `SELECT 1` con un tiempo máximo corto.
+async fn ping(pool: &Pool<Mssql>) -> bool {
+    matches!(
+        tokio::time::timeout(Duration::from_secs(5), sqlx::query("SELECT 1").execute(pool)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Datos de `tipo` desde SQL Server.
+async fn fetch(
+    state: &AppState,
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    tipo: CacheKind,
+) -> Result<serde_json::Value, String> {
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    let usuario_id = || async {
+        mfa_logic::find_user_id_by_username(pool, usuario, &state.sql_collate_clause)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("El usuario '{}' no existe", usuario))
+    };
+    let datos = match tipo {
+        CacheKind::Menus => serde_json::to_value(
+            menu_logic::get_all_menus_logic(pool, aplicativo_id, &state.sql_collate_clause).await?,
+        ),
+        CacheKind::Permisos => serde_json::to_value(
+            utils::get_permissions_by_app(pool, usuario_id().await?, aplicativo_id)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        CacheKind::Perfil => serde_json::to_value(
+            profile_logic::get_profile(pool, usuario_id().await?, aplicativo_id, None, &state.sql_collate_clause)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+    };
+    datos.map_err(|e| e.to_string())
+}
+
+/// Datos de `tipo` del usuario conectado: del servidor (y se actualiza la copia local) o, si
+/// SQL Server no responde, la última copia local con `offline = true`.
+pub async fn cached(state: &AppState, tipo: CacheKind) -> Result<CachedResponse, String> {
+    let (ambito, usuario) = session_scope(state).await?;
+
+    if state.online.load(Ordering::Relaxed) {
+        let pool_guard = state.db_pool.lock().await;
+        if let Some(pool) = pool_guard.as_ref() {
+            match fetch(state, pool, &usuario, tipo).await {
+                Ok(datos) => {
+                    if let Err(e) = state.offline_cache.store(&ambito, &usuario, tipo, &datos).await {
+                        eprintln!("No se pudo actualizar la caché local ({}): {}", tipo.as_str(), e);
+                    }
+                    return Ok(CachedResponse {
+                        datos,
+                        offline: false,
+                        fecha_sincronizacion: Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+                    });
+                }
+                // Con el servidor respondiendo, el error es de la consulta y no se oculta
+                Err(e) if ping(pool).await => return Err(e),
+                Err(e) => {
+                    eprintln!("Sin conexión con SQL Server, se usa la caché local: {}", e);
+                    state.online.store(false, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    state
+        .offline_cache
+        .load(&ambito, &usuario, tipo)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Sin conexión con el servidor y sin copia local de estos datos.".to_string())
+}
+
+/// Vuelve a llenar la caché del usuario conectado; `false` si no hay sesión o algo falló.
+async fn resync(state: &AppState) -> bool {
+    if state.usuario_conectado.lock().await.is_none() {
+        return false;
+    }
+    let mut completo = true;
+    for tipo in [CacheKind::Menus, CacheKind::Permisos, CacheKind::Perfil] {
+        match cached(state, tipo).await {
+            Ok(respuesta) if !respuesta.offline => {}
+            Ok(_) => completo = false,
+            Err(e) => {
+                eprintln!("No se pudo resincronizar la caché local ({}): {}", tipo.as_str(), e);
+                completo = false;
+            }
+        }
+    }
+    completo
+}
+
+/// Cada OFFLINE_CHECK_INTERVAL_SECONDS (30 por defecto) comprueba el servidor: con conexión
+/// abierta hace ping; sin ella reintenta la conexión inicial. En cada cambio avisa a la pantalla
+/// con el evento `conectividad` y, al volver, resincroniza la caché.
+pub async fn run_connectivity_monitor(app: AppHandle, database_url: Option<String>) {
+    let intervalo = std::env::var("OFFLINE_CHECK_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|v: &u64| *v > 0)
+        .unwrap_or(30);
+    loop {
+        tokio::time::sleep(Duration::from_secs(intervalo)).await;
+        let state = app.state::<AppState>();
+
+        let pool = state.db_pool.lock().await.clone();
+        let (online, reconectado) = match pool {
+            Some(pool) => (ping(&pool).await, false),
+            None => {
+                let ok = matches!(connection::connect_initial(&state, database_url.clone()).await, Ok(Some(_)));
+                (ok, ok)
+            }
+        };
+        let antes = state.online.swap(online, Ordering::Relaxed);
+        // connect() ya marca `online`: una reconexión cuenta como cambio aunque el valor no cambie
+        if online == antes && !reconectado {
+            continue;
+        }
+
+        let resincronizado = online && resync(&state).await;
+        println!("Conectividad con SQL Server: {}", if online { "restablecida" } else { "perdida" });
+        let _ = app.emit_all(EVENTO_CONECTIVIDAD, ConnectivityEvent { online, resincronizado });
+    }
+}
+
+/// Permisos del usuario conectado en el aplicativo, con la caché local si no hay conexión.
+#[tauri::command]
+pub async fn get_my_permissions(state: State<'_, AppState>) -> Result<CachedResponse, String> {
+    cached(&state, CacheKind::Permisos).await
+}
+
+/// Si SQL Server responde según el último chequeo.
+#[tauri::command]
+pub async fn get_connectivity_status(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.online.load(Ordering::Relaxed))
+}
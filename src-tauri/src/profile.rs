@@ -0,0 +1,62 @@
+// src-tauri/src/profile.rs
+
+use tauri::State;
+
+use crate::AppState;
+use crate::offline;
+use crate::user::get_logged_in_username;
+use shared_lib::mfa_logic;
+use shared_lib::profile_logic;
+use shared_lib::profile_models::{ProfileUpdateRequest, UserProfile};
+use shared_lib::offline_cache_models::{CacheKind, CachedResponse};
+
+/// ID del usuario conectado en el escritorio.
+async fn logged_in_user_id(state: &State<'_, AppState>, pool: &sqlx::Pool<sqlx::Mssql>) -> Result<i32, String> {
+    let usuario = get_logged_in_username(state).await?;
+    mfa_logic::find_user_id_by_username(pool, &usuario, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("El usuario '{}' no existe", usuario))
+}
+
+/// Perfil del usuario conectado (equivalente a `GET /api/protected/me`).
+/// Sin conexión con SQL Server devuelve la última copia local (`offline = true`).
+#[tauri::command]
+pub async fn get_my_profile(state: State<'_, AppState>) -> Result<CachedResponse, String> {
+    offline::cached(&state, CacheKind::Perfil).await
+}
+
+/// Actualiza nombre y preferencias; el correo nuevo queda pendiente de verificación.
+#[tauri::command]
+pub async fn update_my_profile(
+    state: State<'_, AppState>,
+    request: ProfileUpdateRequest,
+) -> Result<UserProfile, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let usuario_id = logged_in_user_id(&state, pool_ref).await?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    profile_logic::update_profile(pool_ref, usuario_id, &request, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?;
+    profile_logic::get_profile(pool_ref, usuario_id, aplicativo_id, None, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Confirma el cambio de correo con el código recibido.
+#[tauri::command]
+pub async fn confirm_my_email_change(state: State<'_, AppState>, codigo: i32) -> Result<UserProfile, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let usuario_id = logged_in_user_id(&state, pool_ref).await?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    profile_logic::confirm_email_change(pool_ref, usuario_id, codigo)
+        .await
+        .map_err(|e| e.to_string())?;
+    profile_logic::get_profile(pool_ref, usuario_id, aplicativo_id, None, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,173 @@
+// src-tauri/src/row_security.rs
+
+use tauri::State;
+
+use crate::AppState;
+use crate::user::get_logged_in_username;
+use shared_lib::{mfa_logic, utils};
+use shared_lib::row_security_logic::{self, PERMISO_CONCEPTOS, PERMISO_VALORES};
+use shared_lib::row_security_models::{
+    AllowedValues, ConceptLookupValue, SecurityConcept, SecurityConceptInput, UserConceptValues,
+    UserConceptValuesInput,
+};
+
+/// ID del usuario conectado; si se indica `permiso`, verifica que lo tenga.
+pub(crate) async fn logged_in_user_id(
+    state: &State<'_, AppState>,
+    pool: &sqlx::Pool<sqlx::Mssql>,
+    aplicativo_id: i32,
+    permiso: Option<&str>,
+) -> Result<(String, i32), String> {
+    let usuario = get_logged_in_username(state).await?;
+    let usuario_id = mfa_logic::find_user_id_by_username(pool, &usuario, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("El usuario '{}' no existe", usuario))?;
+    if let Some(permiso) = permiso {
+        let permisos = utils::get_permissions_by_app(pool, usuario_id, aplicativo_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !permisos.iter().any(|p| p == permiso) {
+            return Err("No tiene permiso para realizar esta operación.".to_string());
+        }
+    }
+    Ok((usuario, usuario_id))
+}
+
+/// Lista de conceptos de seguridad por fila.
+#[tauri::command]
+pub async fn list_row_security_concepts(state: State<'_, AppState>) -> Result<Vec<SecurityConcept>, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_CONCEPTOS)).await?;
+
+    row_security_logic::list_concepts(pool_ref, aplicativo_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Crea el concepto, o lo modifica si se indica `concepto_id`.
+#[tauri::command]
+pub async fn save_row_security_concept(
+    state: State<'_, AppState>,
+    concepto_id: Option<i32>,
+    concepto: SecurityConceptInput,
+) -> Result<SecurityConcept, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    let (autor, _) = logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_CONCEPTOS)).await?;
+
+    match concepto_id {
+        Some(id) => row_security_logic::update_concept(pool_ref, aplicativo_id, id, &concepto, &autor, &state.sql_collate_clause).await,
+        None => row_security_logic::create_concept(pool_ref, aplicativo_id, &concepto, &autor, &state.sql_collate_clause).await,
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_row_security_concept(state: State<'_, AppState>, concepto_id: i32) -> Result<(), String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_CONCEPTOS)).await?;
+
+    row_security_logic::delete_concept(pool_ref, aplicativo_id, concepto_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Valores posibles del concepto, leídos de su tabla del ERP.
+#[tauri::command]
+pub async fn lookup_concept_values(
+    state: State<'_, AppState>,
+    concepto_id: i32,
+    filtro: Option<String>,
+) -> Result<Vec<ConceptLookupValue>, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_VALORES)).await?;
+
+    let concepto = row_security_logic::get_concept(pool_ref, aplicativo_id, concepto_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?;
+    row_security_logic::lookup_values(pool_ref, &concepto, filtro.as_deref(), &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Asignaciones de todos los usuarios para el concepto.
+#[tauri::command]
+pub async fn list_concept_assignments(
+    state: State<'_, AppState>,
+    concepto_id: i32,
+) -> Result<Vec<UserConceptValues>, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_VALORES)).await?;
+
+    row_security_logic::get_concept(pool_ref, aplicativo_id, concepto_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?;
+    row_security_logic::list_concept_assignments(pool_ref, concepto_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Reemplaza los valores permitidos de un usuario para el concepto.
+#[tauri::command]
+pub async fn set_user_concept_values(
+    state: State<'_, AppState>,
+    concepto_id: i32,
+    usuario_id: i32,
+    valores: UserConceptValuesInput,
+) -> Result<UserConceptValues, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    let (autor, _) = logged_in_user_id(&state, pool_ref, aplicativo_id, Some(PERMISO_VALORES)).await?;
+
+    let concepto = row_security_logic::get_concept(pool_ref, aplicativo_id, concepto_id, &state.sql_collate_clause)
+        .await
+        .map_err(|e| e.to_string())?;
+    row_security_logic::set_user_values(
+        pool_ref,
+        &concepto,
+        usuario_id,
+        valores.todos,
+        &valores.valores,
+        &autor,
+        &state.sql_collate_clause,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Resolvedor para el usuario conectado: un concepto, o todos si no se indica.
+#[tauri::command]
+pub async fn resolve_my_concept_values(
+    state: State<'_, AppState>,
+    codigo: Option<String>,
+) -> Result<Vec<AllowedValues>, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    let (_, usuario_id) = logged_in_user_id(&state, pool_ref, aplicativo_id, None).await?;
+
+    match codigo {
+        Some(codigo) => row_security_logic::resolve_allowed_values(
+            pool_ref,
+            aplicativo_id,
+            usuario_id,
+            &codigo,
+            &state.sql_collate_clause,
+        )
+        .await
+        .map(|valores| vec![valores]),
+        None => row_security_logic::resolve_all(pool_ref, aplicativo_id, usuario_id, &state.sql_collate_clause).await,
+    }
+    .map_err(|e| e.to_string())
+}
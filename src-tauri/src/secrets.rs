@@ -0,0 +1,62 @@
+// src-tauri/src/secrets.rs
+// Pantalla de administración de los secretos locales (archivo cifrado junto a la configuración).
+// Los valores nunca vuelven a la pantalla y los cambios se aplican al reiniciar la aplicación.
+
+use tauri::State;
+
+use crate::AppState;
+use crate::row_security::logged_in_user_id;
+use shared_lib::secret_store_logic::PERMISO_ADMINISTRAR_SECRETOS;
+use shared_lib::secret_store_models::SecretStoreStatus;
+
+/// Exige un usuario conectado con permiso para administrar los secretos.
+async fn require_admin(state: &State<'_, AppState>) -> Result<(), String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(state, pool_ref, aplicativo_id, Some(PERMISO_ADMINISTRAR_SECRETOS)).await?;
+    Ok(())
+}
+
+/// Secretos admitidos, de dónde sale cada uno y si el archivo tiene frase.
+#[tauri::command]
+pub async fn get_secrets_status(state: State<'_, AppState>) -> Result<SecretStoreStatus, String> {
+    require_admin(&state).await?;
+    let store = state.secrets.lock().await;
+    store.status().map_err(|e| e.to_string())
+}
+
+/// Guarda o rota el valor de un secreto.
+#[tauri::command]
+pub async fn set_secret(
+    state: State<'_, AppState>,
+    nombre: String,
+    valor: String,
+) -> Result<SecretStoreStatus, String> {
+    require_admin(&state).await?;
+    let store = state.secrets.lock().await;
+    store.set(&nombre, &valor).map_err(|e| e.to_string())?;
+    store.status().map_err(|e| e.to_string())
+}
+
+/// Quita el secreto del archivo; se vuelve a usar la variable de entorno, si existe.
+#[tauri::command]
+pub async fn delete_secret(state: State<'_, AppState>, nombre: String) -> Result<SecretStoreStatus, String> {
+    require_admin(&state).await?;
+    let store = state.secrets.lock().await;
+    store.delete(&nombre).map_err(|e| e.to_string())?;
+    store.status().map_err(|e| e.to_string())
+}
+
+/// Vuelve a cifrar el archivo con otra frase; sin `frase_nueva`, solo con la clave del equipo.
+/// La nueva frase debe quedar en SECRETS_PASSPHRASE para el próximo inicio.
+#[tauri::command]
+pub async fn change_secrets_passphrase(
+    state: State<'_, AppState>,
+    frase_nueva: Option<String>,
+) -> Result<SecretStoreStatus, String> {
+    require_admin(&state).await?;
+    let mut store = state.secrets.lock().await;
+    store.change_passphrase(frase_nueva).map_err(|e| e.to_string())?;
+    store.status().map_err(|e| e.to_string())
+}
@@ -0,0 +1,695 @@
+// src/shared/access_request_logic.rs
+/*
+Solicitudes de acceso: el usuario que llega a la página de acceso denegado pide un
+permiso o un rol, un aprobador la aprueba o rechaza y, al aprobarse, se crea una
+concesión temporal (grant_logic) por los días indicados.
+
+Aprobadores:
+  - de un rol: los registrados en riy_aprobador con ese rolID,
+  - de un permiso: los registrados en el nodo de riy_SeguridadMenu que lo exige o en
+    cualquiera de sus nodos padre.
+Quien tiene `administrar_solicitudes_acceso` puede resolver cualquier solicitud.
+Cada transición de estado queda en riy_solicitud_acceso_evento.
+
+CREATE TABLE riy.riy_aprobador (
+    aprobadorID    INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID   INT          NOT NULL,
+    rolID          INT          NULL,
+    menuID         INT          NULL,
+    usuarioID      INT          NOT NULL,
+    autor          VARCHAR(50)  NOT NULL,
+    fechaCreacion  DATETIME     NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT CK_riy_aprobador_objeto
+        CHECK ((rolID IS NULL AND menuID IS NOT NULL) OR (rolID IS NOT NULL AND menuID IS NULL))
+);
+
+CREATE TABLE riy.riy_solicitud_acceso (
+    solicitudID      INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID     INT           NOT NULL,
+    usuarioID        INT           NOT NULL,
+    codigoPermiso    VARCHAR(50)   NULL,
+    rolID            INT           NULL,
+    motivo           NVARCHAR(500) NOT NULL,
+    dias             INT           NOT NULL,
+    estado           VARCHAR(20)   NOT NULL DEFAULT 'Pendiente', -- Pendiente, Aprobada, Rechazada, Cancelada
+    fechaCreacion    DATETIME      NOT NULL DEFAULT GETDATE(),
+    resueltoPor      VARCHAR(50)   NULL,
+    fechaResolucion  DATETIME      NULL,
+    comentario       NVARCHAR(500) NULL,
+    concesionID      INT           NULL,
+    CONSTRAINT CK_riy_solicitud_acceso_objeto
+        CHECK ((codigoPermiso IS NULL AND rolID IS NOT NULL) OR (codigoPermiso IS NOT NULL AND rolID IS NULL))
+);
+
+CREATE TABLE riy.riy_solicitud_acceso_evento (
+    eventoID        INT IDENTITY(1,1) PRIMARY KEY,
+    solicitudID     INT           NOT NULL,
+    estadoAnterior  VARCHAR(20)   NULL,
+    estadoNuevo     VARCHAR(20)   NOT NULL,
+    usuario         VARCHAR(50)   NOT NULL,
+    comentario      NVARCHAR(500) NULL,
+    fecha           DATETIME      NOT NULL DEFAULT GETDATE()
+);
+*/
+
+use std::fmt;
+
+use chrono::{Duration, Local};
+use sqlx::{Mssql, Pool};
+
+use crate::access_request_models::{
+    AccessRequest, AccessRequestCreate, AccessRequestDecision, AccessRequestEvent, AccessRequestQuery,
+    Approver, ApproverCreate,
+};
+use crate::grant_logic::{self, GrantError};
+use crate::grant_models::TemporaryGrantRequest;
+use crate::{role_logic, utils};
+
+/// Permiso para resolver cualquier solicitud y administrar los aprobadores.
+pub const PERMISO_SOLICITUDES: &str = "administrar_solicitudes_acceso";
+
+pub const ESTADO_PENDIENTE: &str = "Pendiente";
+pub const ESTADO_APROBADA: &str = "Aprobada";
+pub const ESTADO_RECHAZADA: &str = "Rechazada";
+pub const ESTADO_CANCELADA: &str = "Cancelada";
+const ESTADOS: &[&str] = &[ESTADO_PENDIENTE, ESTADO_APROBADA, ESTADO_RECHAZADA, ESTADO_CANCELADA];
+
+const DEFAULT_DIAS: i32 = 30;
+const MAX_DIAS: i32 = 365;
+
+#[derive(Debug)]
+pub enum AccessRequestError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for AccessRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessRequestError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            AccessRequestError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            AccessRequestError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            AccessRequestError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            AccessRequestError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AccessRequestError {}
+
+impl From<GrantError> for AccessRequestError {
+    fn from(e: GrantError) -> Self {
+        match e {
+            GrantError::Validation(msg) => AccessRequestError::Validation(msg),
+            GrantError::Forbidden(msg) => AccessRequestError::Forbidden(msg),
+            GrantError::NotFound(msg) => AccessRequestError::NotFound(msg),
+            GrantError::Database(msg) => AccessRequestError::Database(msg),
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> AccessRequestError {
+    AccessRequestError::Database(e.to_string())
+}
+
+/// Usuario que resuelve o consulta solicitudes.
+pub struct Resolver<'a> {
+    pub usuario: &'a str,
+    pub usuario_id: i32,
+    /// Tiene PERMISO_SOLICITUDES.
+    pub es_administrador: bool,
+}
+
+/// CTE con cada nodo del menú que exige un permiso y todos sus nodos padre.
+const NODOS_CTE: &str = "WITH nodos (menuID, papaID, codigoRaiz) AS (
+        SELECT m.menuID, m.papaID, m.codigoPermiso
+          FROM riy.riy_SeguridadMenu m WITH(NOLOCK)
+         WHERE m.codigoPermiso IS NOT NULL
+        UNION ALL
+        SELECT p.menuID, p.papaID, n.codigoRaiz
+          FROM riy.riy_SeguridadMenu p WITH(NOLOCK)
+          JOIN nodos n ON p.menuID = n.papaID
+    )";
+
+/// Condición "el usuario `@p{n}` aprueba la solicitud `s`"; requiere NODOS_CTE.
+fn approver_condition(param: usize) -> String {
+    format!(
+        "(s.rolID IN (SELECT a.rolID FROM riy.riy_aprobador a WITH(NOLOCK)
+                       WHERE a.usuarioID = @p{0} AND a.aplicativoID = s.aplicativoID AND a.rolID IS NOT NULL)
+          OR s.codigoPermiso IN (SELECT n.codigoRaiz FROM nodos n
+                                   JOIN riy.riy_aprobador a WITH(NOLOCK) ON a.menuID = n.menuID
+                                  WHERE a.usuarioID = @p{0} AND a.aplicativoID = s.aplicativoID))",
+        param
+    )
+}
+
+fn request_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT s.solicitudID as solicitud_id,
+                s.aplicativoID as aplicativo_id,
+                s.usuarioID as usuario_id,
+                u.usuario {0} as usuario,
+                s.codigoPermiso {0} as codigo_permiso,
+                s.rolID as rol_id,
+                r.rol {0} as rol,
+                s.motivo,
+                s.dias,
+                s.estado {0} as estado,
+                CONVERT(VARCHAR(19), s.fechaCreacion, 120) as fecha_creacion,
+                s.resueltoPor {0} as resuelto_por,
+                CONVERT(VARCHAR(19), s.fechaResolucion, 120) as fecha_resolucion,
+                s.comentario,
+                s.concesionID as concesion_id
+           FROM riy.riy_solicitud_acceso s WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = s.usuarioID
+           LEFT JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = s.rolID",
+        sql_collate_clause
+    )
+}
+
+async fn record_event(
+    pool: &Pool<Mssql>,
+    solicitud_id: i32,
+    estado_anterior: Option<&str>,
+    estado_nuevo: &str,
+    usuario: &str,
+    comentario: Option<&str>,
+) -> Result<(), AccessRequestError> {
+    sqlx::query(
+        "INSERT INTO riy.riy_solicitud_acceso_evento (solicitudID, estadoAnterior, estadoNuevo, usuario, comentario, fecha)
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE())"
+    )
+    .bind(solicitud_id)
+    .bind(estado_anterior)
+    .bind(estado_nuevo)
+    .bind(usuario)
+    .bind(comentario)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+pub async fn get_request(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    let sql_query = format!(
+        "{} WHERE s.solicitudID = @p1 AND s.aplicativoID = @p2",
+        request_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, AccessRequest>(&sql_query)
+        .bind(solicitud_id)
+        .bind(aplicativo_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| AccessRequestError::NotFound(format!("Solicitud {} no encontrada", solicitud_id)))
+}
+
+/// Registra una solicitud pendiente del usuario conectado.
+pub async fn create_request(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    usuario: &str,
+    request: &AccessRequestCreate,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    let codigo_permiso = request
+        .codigo_permiso
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    match (codigo_permiso, request.rol_id) {
+        (Some(_), None) | (None, Some(_)) => {}
+        _ => {
+            return Err(AccessRequestError::Validation(
+                "Indique un código de permiso o un rol (solo uno)".to_string(),
+            ))
+        }
+    }
+    let motivo = request.motivo.trim();
+    if motivo.is_empty() {
+        return Err(AccessRequestError::Validation("El motivo es obligatorio".to_string()));
+    }
+    let dias = request.dias.unwrap_or(DEFAULT_DIAS);
+    if !(1..=MAX_DIAS).contains(&dias) {
+        return Err(AccessRequestError::Validation(format!("Los días deben estar entre 1 y {}", MAX_DIAS)));
+    }
+
+    if let Some(codigo) = codigo_permiso {
+        let propios = utils::get_permissions_by_app(pool, usuario_id, aplicativo_id)
+            .await
+            .map_err(|e| AccessRequestError::Database(e.to_string()))?;
+        if propios.iter().any(|p| p == codigo) {
+            return Err(AccessRequestError::Conflict(format!("Ya tiene el permiso '{}'", codigo)));
+        }
+    }
+    if let Some(rol_id) = request.rol_id {
+        let roles = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+            .await
+            .map_err(AccessRequestError::Database)?;
+        if !roles.iter().any(|r| r.rol_id == rol_id) {
+            return Err(AccessRequestError::NotFound(format!("El rol {} no existe en el aplicativo", rol_id)));
+        }
+    }
+
+    let (pendientes,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_solicitud_acceso WITH(NOLOCK)
+          WHERE aplicativoID = @p1 AND usuarioID = @p2 AND estado = 'Pendiente'
+            AND ((@p3 IS NOT NULL AND codigoPermiso = @p3) OR (@p4 IS NOT NULL AND rolID = @p4))"
+    )
+    .bind(aplicativo_id)
+    .bind(usuario_id)
+    .bind(codigo_permiso)
+    .bind(request.rol_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if pendientes > 0 {
+        return Err(AccessRequestError::Conflict("Ya tiene una solicitud pendiente para ese acceso".to_string()));
+    }
+
+    let (solicitud_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_solicitud_acceso (aplicativoID, usuarioID, codigoPermiso, rolID, motivo, dias, estado, fechaCreacion)
+         OUTPUT INSERTED.solicitudID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, 'Pendiente', GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(usuario_id)
+    .bind(codigo_permiso)
+    .bind(request.rol_id)
+    .bind(motivo)
+    .bind(dias)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    record_event(pool, solicitud_id, None, ESTADO_PENDIENTE, usuario, Some(motivo)).await?;
+
+    get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await
+}
+
+/// Historial completo (administración), filtrable por usuario y estado.
+pub async fn list_requests(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    query: &AccessRequestQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<AccessRequest>, AccessRequestError> {
+    if let Some(estado) = query.estado.as_deref() {
+        if !ESTADOS.contains(&estado) {
+            return Err(AccessRequestError::Validation(format!("Estado desconocido: '{}'", estado)));
+        }
+    }
+    let sql_query = format!(
+        "{} WHERE s.aplicativoID = @p1 AND (@p2 IS NULL OR s.usuarioID = @p2) AND (@p3 IS NULL OR s.estado = @p3)
+          ORDER BY s.fechaCreacion DESC, s.solicitudID DESC",
+        request_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, AccessRequest>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(query.usuario_id)
+        .bind(query.estado.as_deref())
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn list_user_requests(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<AccessRequest>, AccessRequestError> {
+    list_requests(
+        pool,
+        aplicativo_id,
+        &AccessRequestQuery { usuario_id: Some(usuario_id), estado: None },
+        sql_collate_clause,
+    ).await
+}
+
+/// Solicitudes pendientes que el usuario puede resolver (todas, si es administrador).
+pub async fn list_pending_approvals(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    resolver: &Resolver<'_>,
+    sql_collate_clause: &str,
+) -> Result<Vec<AccessRequest>, AccessRequestError> {
+    let sql_query = format!(
+        "{} {} WHERE s.aplicativoID = @p1 AND s.estado = 'Pendiente' AND s.usuarioID <> @p2
+              AND (@p3 = 1 OR {})
+          ORDER BY s.fechaCreacion, s.solicitudID",
+        NODOS_CTE,
+        request_select(sql_collate_clause),
+        approver_condition(2)
+    );
+    sqlx::query_as::<_, AccessRequest>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(resolver.usuario_id)
+        .bind(resolver.es_administrador)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+async fn is_approver(
+    pool: &Pool<Mssql>,
+    solicitud_id: i32,
+    usuario_id: i32,
+) -> Result<bool, AccessRequestError> {
+    let sql_query = format!(
+        "{} SELECT COUNT(*) FROM riy.riy_solicitud_acceso s WITH(NOLOCK)
+          WHERE s.solicitudID = @p1 AND {}",
+        NODOS_CTE,
+        approver_condition(2)
+    );
+    let (total,): (i32,) = sqlx::query_as(&sql_query)
+        .bind(solicitud_id)
+        .bind(usuario_id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(total > 0)
+}
+
+/// Carga la solicitud pendiente y verifica que `resolver` pueda resolverla.
+async fn pending_for_resolver(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    resolver: &Resolver<'_>,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    let solicitud = get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await?;
+    if solicitud.estado != ESTADO_PENDIENTE {
+        return Err(AccessRequestError::Conflict(format!(
+            "La solicitud {} ya está {}", solicitud_id, solicitud.estado.to_lowercase()
+        )));
+    }
+    if solicitud.usuario_id == resolver.usuario_id {
+        return Err(AccessRequestError::Forbidden("No puede resolver su propia solicitud".to_string()));
+    }
+    if !resolver.es_administrador && !is_approver(pool, solicitud_id, resolver.usuario_id).await? {
+        return Err(AccessRequestError::Forbidden("No es aprobador de esta solicitud".to_string()));
+    }
+    Ok(solicitud)
+}
+
+/// Cambia el estado solo si sigue pendiente; `false` si otro la resolvió antes.
+async fn transition_from_pending(
+    pool: &Pool<Mssql>,
+    solicitud_id: i32,
+    estado: &str,
+    resuelto_por: &str,
+    comentario: Option<&str>,
+) -> Result<bool, AccessRequestError> {
+    let result = sqlx::query(
+        "UPDATE riy.riy_solicitud_acceso
+            SET estado = @p1, resueltoPor = @p2, fechaResolucion = GETDATE(), comentario = @p3
+          WHERE solicitudID = @p4 AND estado = 'Pendiente'"
+    )
+    .bind(estado)
+    .bind(resuelto_por)
+    .bind(comentario)
+    .bind(solicitud_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn already_resolved(solicitud_id: i32) -> AccessRequestError {
+    AccessRequestError::Conflict(format!("La solicitud {} ya fue resuelta", solicitud_id))
+}
+
+/// Aprueba la solicitud y crea la concesión temporal correspondiente.
+pub async fn approve_request(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    resolver: &Resolver<'_>,
+    decision: &AccessRequestDecision,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    let solicitud = pending_for_resolver(pool, aplicativo_id, solicitud_id, resolver, sql_collate_clause).await?;
+    let dias = decision.dias.unwrap_or(solicitud.dias);
+    if !(1..=MAX_DIAS).contains(&dias) {
+        return Err(AccessRequestError::Validation(format!("Los días deben estar entre 1 y {}", MAX_DIAS)));
+    }
+    let comentario = decision.comentario.as_deref().map(str::trim).filter(|c| !c.is_empty());
+
+    // Se toma la solicitud antes de crear la concesión para no duplicarla si dos aprobadores coinciden
+    if !transition_from_pending(pool, solicitud_id, ESTADO_APROBADA, resolver.usuario, comentario).await? {
+        return Err(already_resolved(solicitud_id));
+    }
+
+    let grant_request = TemporaryGrantRequest {
+        usuario_id: solicitud.usuario_id,
+        codigo_permiso: solicitud.codigo_permiso.clone(),
+        rol_id: solicitud.rol_id,
+        valido_desde: None,
+        valido_hasta: (Local::now().naive_local() + Duration::days(dias as i64))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string(),
+        motivo: format!("Solicitud {}: {}", solicitud_id, solicitud.motivo),
+        delegar: false,
+    };
+    let grant = match grant_logic::create_grant(
+        pool,
+        aplicativo_id,
+        &grant_request,
+        resolver.usuario,
+        resolver.usuario_id,
+        true,
+        sql_collate_clause,
+    ).await {
+        Ok(grant) => grant,
+        Err(e) => {
+            // Devuelve la solicitud a pendiente para que pueda volver a intentarse
+            sqlx::query(
+                "UPDATE riy.riy_solicitud_acceso
+                    SET estado = 'Pendiente', resueltoPor = NULL, fechaResolucion = NULL, comentario = NULL
+                  WHERE solicitudID = @p1"
+            )
+            .bind(solicitud_id)
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
+            return Err(e.into());
+        }
+    };
+
+    sqlx::query("UPDATE riy.riy_solicitud_acceso SET concesionID = @p1, dias = @p2 WHERE solicitudID = @p3")
+        .bind(grant.concesion_id)
+        .bind(dias)
+        .bind(solicitud_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    record_event(pool, solicitud_id, Some(ESTADO_PENDIENTE), ESTADO_APROBADA, resolver.usuario, comentario).await?;
+
+    get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await
+}
+
+pub async fn reject_request(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    resolver: &Resolver<'_>,
+    decision: &AccessRequestDecision,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    pending_for_resolver(pool, aplicativo_id, solicitud_id, resolver, sql_collate_clause).await?;
+    let comentario = decision.comentario.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    if comentario.is_none() {
+        return Err(AccessRequestError::Validation("Indique el motivo del rechazo".to_string()));
+    }
+    if !transition_from_pending(pool, solicitud_id, ESTADO_RECHAZADA, resolver.usuario, comentario).await? {
+        return Err(already_resolved(solicitud_id));
+    }
+    record_event(pool, solicitud_id, Some(ESTADO_PENDIENTE), ESTADO_RECHAZADA, resolver.usuario, comentario).await?;
+    get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await
+}
+
+/// El solicitante retira su propia solicitud pendiente.
+pub async fn cancel_request(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    usuario_id: i32,
+    usuario: &str,
+    sql_collate_clause: &str,
+) -> Result<AccessRequest, AccessRequestError> {
+    let solicitud = get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await?;
+    if solicitud.usuario_id != usuario_id {
+        return Err(AccessRequestError::Forbidden("Solo puede cancelar sus propias solicitudes".to_string()));
+    }
+    if !transition_from_pending(pool, solicitud_id, ESTADO_CANCELADA, usuario, None).await? {
+        return Err(already_resolved(solicitud_id));
+    }
+    record_event(pool, solicitud_id, Some(ESTADO_PENDIENTE), ESTADO_CANCELADA, usuario, None).await?;
+    get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await
+}
+
+/// Transiciones de una solicitud; visibles para el solicitante, sus aprobadores y administradores.
+pub async fn list_events(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solicitud_id: i32,
+    resolver: &Resolver<'_>,
+    sql_collate_clause: &str,
+) -> Result<Vec<AccessRequestEvent>, AccessRequestError> {
+    let solicitud = get_request(pool, aplicativo_id, solicitud_id, sql_collate_clause).await?;
+    if !resolver.es_administrador
+        && solicitud.usuario_id != resolver.usuario_id
+        && !is_approver(pool, solicitud_id, resolver.usuario_id).await?
+    {
+        return Err(AccessRequestError::Forbidden("No puede consultar esta solicitud".to_string()));
+    }
+    let sql_query = format!(
+        "SELECT eventoID as evento_id,
+                solicitudID as solicitud_id,
+                estadoAnterior {0} as estado_anterior,
+                estadoNuevo {0} as estado_nuevo,
+                usuario {0} as usuario,
+                comentario,
+                CONVERT(VARCHAR(19), fecha, 120) as fecha
+           FROM riy.riy_solicitud_acceso_evento WITH(NOLOCK)
+          WHERE solicitudID = @p1
+          ORDER BY fecha, eventoID",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, AccessRequestEvent>(&sql_query)
+        .bind(solicitud_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn list_approvers(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<Approver>, AccessRequestError> {
+    let sql_query = format!(
+        "SELECT a.aprobadorID as aprobador_id,
+                a.aplicativoID as aplicativo_id,
+                a.rolID as rol_id,
+                r.rol {0} as rol,
+                a.menuID as menu_id,
+                m.nombre {0} as menu,
+                a.usuarioID as usuario_id,
+                u.usuario {0} as usuario,
+                a.autor {0} as autor,
+                CONVERT(VARCHAR(19), a.fechaCreacion, 120) as fecha_creacion
+           FROM riy.riy_aprobador a WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = a.usuarioID
+           LEFT JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = a.rolID
+           LEFT JOIN riy.riy_SeguridadMenu m WITH(NOLOCK) ON m.menuID = a.menuID
+          WHERE a.aplicativoID = @p1
+          ORDER BY r.rol, m.nombre, u.usuario",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, Approver>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn add_approver(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    request: &ApproverCreate,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<Approver, AccessRequestError> {
+    match (request.rol_id, request.menu_id) {
+        (Some(_), None) | (None, Some(_)) => {}
+        _ => {
+            return Err(AccessRequestError::Validation(
+                "Indique un rol o un nodo del menú (solo uno)".to_string(),
+            ))
+        }
+    }
+    if let Some(rol_id) = request.rol_id {
+        let roles = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+            .await
+            .map_err(AccessRequestError::Database)?;
+        if !roles.iter().any(|r| r.rol_id == rol_id) {
+            return Err(AccessRequestError::NotFound(format!("El rol {} no existe en el aplicativo", rol_id)));
+        }
+    }
+    if let Some(menu_id) = request.menu_id {
+        let existe: Option<(i32,)> = sqlx::query_as(
+            "SELECT menuID FROM riy.riy_SeguridadMenu WITH(NOLOCK) WHERE menuID = @p1"
+        )
+        .bind(menu_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+        if existe.is_none() {
+            return Err(AccessRequestError::NotFound(format!("El nodo de menú {} no existe", menu_id)));
+        }
+    }
+
+    let (duplicados,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_aprobador WITH(NOLOCK)
+          WHERE aplicativoID = @p1 AND usuarioID = @p2
+            AND ((@p3 IS NOT NULL AND rolID = @p3) OR (@p4 IS NOT NULL AND menuID = @p4))"
+    )
+    .bind(aplicativo_id)
+    .bind(request.usuario_id)
+    .bind(request.rol_id)
+    .bind(request.menu_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if duplicados > 0 {
+        return Err(AccessRequestError::Conflict("El usuario ya es aprobador de ese elemento".to_string()));
+    }
+
+    let (aprobador_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_aprobador (aplicativoID, rolID, menuID, usuarioID, autor, fechaCreacion)
+         OUTPUT INSERTED.aprobadorID
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(request.rol_id)
+    .bind(request.menu_id)
+    .bind(request.usuario_id)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    list_approvers(pool, aplicativo_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .find(|a| a.aprobador_id == aprobador_id)
+        .ok_or_else(|| AccessRequestError::NotFound(format!("Aprobador {} no encontrado", aprobador_id)))
+}
+
+pub async fn remove_approver(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    aprobador_id: i32,
+) -> Result<(), AccessRequestError> {
+    let result = sqlx::query("DELETE FROM riy.riy_aprobador WHERE aprobadorID = @p1 AND aplicativoID = @p2")
+        .bind(aprobador_id)
+        .bind(aplicativo_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(AccessRequestError::NotFound(format!("Aprobador {} no encontrado", aprobador_id)));
+    }
+    Ok(())
+}
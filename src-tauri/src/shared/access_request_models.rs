@@ -0,0 +1,93 @@
+// src/shared/access_request_models.rs
+// Modelos de las solicitudes de acceso (permiso o rol) y sus aprobadores.
+
+use serde::{Deserialize, Serialize};
+
+/// Solicitud de un permiso o un rol (`riy.riy_solicitud_acceso`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRequest {
+    pub solicitud_id: i32,
+    pub aplicativo_id: i32,
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub codigo_permiso: Option<String>,
+    pub rol_id: Option<i32>,
+    pub rol: Option<String>,
+    pub motivo: String,
+    /// Días de vigencia de la concesión que se crea al aprobar.
+    pub dias: i32,
+    /// `Pendiente`, `Aprobada`, `Rechazada` o `Cancelada`.
+    pub estado: String,
+    pub fecha_creacion: String,
+    pub resuelto_por: Option<String>,
+    pub fecha_resolucion: Option<String>,
+    pub comentario: Option<String>,
+    /// Concesión temporal creada al aprobar (`riy.riy_permiso_temporal`).
+    pub concesion_id: Option<i32>,
+}
+
+/// Alta de una solicitud: exactamente uno de `codigo_permiso` o `rol_id`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRequestCreate {
+    pub codigo_permiso: Option<String>,
+    pub rol_id: Option<i32>,
+    pub motivo: String,
+    /// Por defecto, 30 días.
+    pub dias: Option<i32>,
+}
+
+/// Cuerpo de aprobar o rechazar.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRequestDecision {
+    pub comentario: Option<String>,
+    /// Al aprobar, permite acortar o ampliar los días solicitados.
+    pub dias: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRequestQuery {
+    pub usuario_id: Option<i32>,
+    pub estado: Option<String>,
+}
+
+/// Transición de estado de una solicitud (`riy.riy_solicitud_acceso_evento`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRequestEvent {
+    pub evento_id: i32,
+    pub solicitud_id: i32,
+    pub estado_anterior: Option<String>,
+    pub estado_nuevo: String,
+    pub usuario: String,
+    pub comentario: Option<String>,
+    pub fecha: String,
+}
+
+/// Aprobador de un rol o de un nodo del menú (`riy.riy_aprobador`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Approver {
+    pub aprobador_id: i32,
+    pub aplicativo_id: i32,
+    pub rol_id: Option<i32>,
+    pub rol: Option<String>,
+    pub menu_id: Option<i32>,
+    pub menu: Option<String>,
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub autor: String,
+    pub fecha_creacion: String,
+}
+
+/// Alta de un aprobador: exactamente uno de `rol_id` o `menu_id`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApproverCreate {
+    pub usuario_id: i32,
+    pub rol_id: Option<i32>,
+    pub menu_id: Option<i32>,
+}
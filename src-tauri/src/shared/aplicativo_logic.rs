@@ -0,0 +1,533 @@
+// src/shared/aplicativo_logic.rs
+/*
+Administración de aplicativos y selección del aplicativo por solicitud.
+
+Un mismo servidor API atiende varios aplicativos: el middleware SelectAplicativo toma el
+código de la cabecera `X-Aplicativo` o del prefijo `/a/{codigo}` de la ruta, lo resuelve
+con db::get_aplicativo_id (con caché) y ejecuta la solicitud dentro de un ámbito en el que
+AppState::current_aplicativo_id devuelve ese aplicativo. Sin cabecera ni prefijo se usa el
+de APLICATIVO_ID, como antes.
+
+Columnas agregadas a la tabla existente y tabla de configuración:
+
+ALTER TABLE riy.riy_SeguridadAplicativo ADD
+    nombre             NVARCHAR(100) NULL,
+    descripcion        NVARCHAR(500) NULL,
+    activo             BIT           NOT NULL DEFAULT 1,
+    autor              VARCHAR(50)   NULL,
+    fechaCreacion      DATETIME      NULL DEFAULT GETDATE(),
+    modificadoPor      VARCHAR(50)   NULL,
+    fechaModificacion  DATETIME      NULL;
+
+CREATE TABLE riy.riy_SeguridadAplicativoConfig (
+    aplicativoID       INT            NOT NULL,
+    clave              VARCHAR(100)   NOT NULL,
+    valor              NVARCHAR(1000) NOT NULL,
+    modificadoPor      VARCHAR(50)    NOT NULL,
+    fechaModificacion  DATETIME       NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT PK_riy_SeguridadAplicativoConfig PRIMARY KEY (aplicativoID, clave)
+);
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sqlx::{Mssql, Pool};
+use tokio::sync::RwLock;
+
+use crate::aplicativo_models::{
+    Aplicativo, AplicativoConfigInput, AplicativoCopyRequest, AplicativoCopyResult, AplicativoInput,
+};
+use crate::db;
+
+/// Ver, crear y configurar aplicativos y copiar menús y roles entre ellos.
+pub const PERMISO_ADMINISTRAR_APLICATIVOS: &str = "administrar_aplicativos";
+
+/// Cabecera con el código del aplicativo de la solicitud.
+pub const HEADER_APLICATIVO: &str = "X-Aplicativo";
+/// Prefijo de ruta alternativo a la cabecera: `/a/{codigo}/api/...`.
+pub const PREFIJO_APLICATIVO: &str = "/a/";
+
+/// Vigencia de una entrada de la caché de códigos.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub enum AplicativoError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for AplicativoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AplicativoError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            AplicativoError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            AplicativoError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            AplicativoError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            AplicativoError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AplicativoError {}
+
+fn db_error(e: sqlx::Error) -> AplicativoError {
+    AplicativoError::Database(e.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Aplicativo de la solicitud en curso
+// ---------------------------------------------------------------------------
+
+/// Aplicativo elegido para la solicitud.
+#[derive(Debug, Clone)]
+pub struct AplicativoActual {
+    pub aplicativo_id: i32,
+    pub aplicativo: String,
+}
+
+tokio::task_local! {
+    static APLICATIVO_ACTUAL: AplicativoActual;
+}
+
+/// Aplicativo elegido por SelectAplicativo, si la solicitud en curso eligió uno.
+pub fn selected_aplicativo() -> Option<AplicativoActual> {
+    APLICATIVO_ACTUAL.try_with(|actual| actual.clone()).ok()
+}
+
+/// Ejecuta `fut` con `actual` como aplicativo de la solicitud.
+pub async fn with_aplicativo<F: Future>(actual: AplicativoActual, fut: F) -> F::Output {
+    APLICATIVO_ACTUAL.scope(actual, fut).await
+}
+
+/// Caché código -> aplicativoID compartida por los workers.
+#[derive(Debug, Clone, Default)]
+pub struct AplicativoCache {
+    entradas: Arc<RwLock<HashMap<String, (i32, Instant)>>>,
+}
+
+impl AplicativoCache {
+    /// Resuelve el código con db::get_aplicativo_id; rechaza aplicativos inactivos.
+    pub async fn resolve(&self, pool: &Pool<Mssql>, codigo: &str) -> Result<AplicativoActual, AplicativoError> {
+        let clave = codigo.trim().to_ascii_lowercase();
+        if let Some((aplicativo_id, desde)) = self.entradas.read().await.get(&clave) {
+            if desde.elapsed() < CACHE_TTL {
+                return Ok(AplicativoActual { aplicativo_id: *aplicativo_id, aplicativo: codigo.trim().to_string() });
+            }
+        }
+
+        let aplicativo_id = db::get_aplicativo_id(pool, codigo.trim())
+            .await
+            .map_err(|_| AplicativoError::NotFound(format!("Aplicativo '{}' no encontrado", codigo.trim())))?;
+        let (activo,): (bool,) = sqlx::query_as(
+            "SELECT activo FROM riy.riy_SeguridadAplicativo WITH(NOLOCK) WHERE aplicativoID = @p1"
+        )
+        .bind(aplicativo_id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+        if !activo {
+            return Err(AplicativoError::Forbidden(format!("El aplicativo '{}' está inactivo", codigo.trim())));
+        }
+
+        self.entradas.write().await.insert(clave, (aplicativo_id, Instant::now()));
+        Ok(AplicativoActual { aplicativo_id, aplicativo: codigo.trim().to_string() })
+    }
+
+    /// Descarta la caché (tras crear o modificar un aplicativo).
+    pub async fn invalidate(&self) {
+        self.entradas.write().await.clear();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Aplicativos
+// ---------------------------------------------------------------------------
+
+type AplicativoRow = (i32, String, Option<String>, Option<String>, bool, Option<String>, Option<String>, Option<String>, Option<String>);
+
+fn aplicativo_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT aplicativoID, aplicativo {0}, nombre, descripcion, activo, autor {0},
+                CONVERT(VARCHAR(19), fechaCreacion, 120),
+                modificadoPor {0},
+                CONVERT(VARCHAR(19), fechaModificacion, 120)
+           FROM riy.riy_SeguridadAplicativo WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+async fn load_config(
+    pool: &Pool<Mssql>,
+    aplicativo_id: Option<i32>,
+    sql_collate_clause: &str,
+) -> Result<HashMap<i32, BTreeMap<String, String>>, AplicativoError> {
+    let sql_query = format!(
+        "SELECT aplicativoID, clave {0}, valor
+           FROM riy.riy_SeguridadAplicativoConfig WITH(NOLOCK)
+          WHERE @p1 IS NULL OR aplicativoID = @p1",
+        sql_collate_clause
+    );
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut configuracion: HashMap<i32, BTreeMap<String, String>> = HashMap::new();
+    for (id, clave, valor) in rows {
+        configuracion.entry(id).or_default().insert(clave, valor);
+    }
+    Ok(configuracion)
+}
+
+fn to_aplicativo(row: AplicativoRow, configuracion: &mut HashMap<i32, BTreeMap<String, String>>) -> Aplicativo {
+    let (aplicativo_id, aplicativo, nombre, descripcion, activo, autor, fecha_creacion, modificado_por,
+         fecha_modificacion) = row;
+    Aplicativo {
+        aplicativo_id,
+        aplicativo,
+        nombre,
+        descripcion,
+        activo,
+        configuracion: configuracion.remove(&aplicativo_id).unwrap_or_default(),
+        autor,
+        fecha_creacion,
+        modificado_por,
+        fecha_modificacion,
+    }
+}
+
+pub async fn list_aplicativos(
+    pool: &Pool<Mssql>,
+    sql_collate_clause: &str,
+) -> Result<Vec<Aplicativo>, AplicativoError> {
+    let sql_query = format!("{} ORDER BY aplicativo", aplicativo_select(sql_collate_clause));
+    let rows: Vec<AplicativoRow> = sqlx::query_as(&sql_query)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut configuracion = load_config(pool, None, sql_collate_clause).await?;
+    Ok(rows.into_iter().map(|row| to_aplicativo(row, &mut configuracion)).collect())
+}
+
+pub async fn get_aplicativo(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Aplicativo, AplicativoError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1", aplicativo_select(sql_collate_clause));
+    let row: AplicativoRow = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| AplicativoError::NotFound(format!("Aplicativo {} no encontrado", aplicativo_id)))?;
+    let mut configuracion = load_config(pool, Some(aplicativo_id), sql_collate_clause).await?;
+    Ok(to_aplicativo(row, &mut configuracion))
+}
+
+fn validate_codigo(codigo: &str) -> Result<(), AplicativoError> {
+    if codigo.is_empty()
+        || codigo.len() > 50
+        || !codigo.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(AplicativoError::Validation(format!(
+            "Código de aplicativo inválido: '{}' (letras, números, '_' o '-')", codigo
+        )));
+    }
+    Ok(())
+}
+
+pub async fn create_aplicativo(
+    pool: &Pool<Mssql>,
+    input: &AplicativoInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<Aplicativo, AplicativoError> {
+    let codigo = input.aplicativo.trim();
+    validate_codigo(codigo)?;
+    if db::get_aplicativo_id(pool, codigo).await.is_ok() {
+        return Err(AplicativoError::Conflict(format!("El aplicativo '{}' ya existe", codigo)));
+    }
+    let (aplicativo_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_SeguridadAplicativo (aplicativo, nombre, descripcion, activo, autor, fechaCreacion)
+         OUTPUT INSERTED.aplicativoID
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE())"
+    )
+    .bind(codigo)
+    .bind(input.nombre.as_deref().map(str::trim))
+    .bind(input.descripcion.as_deref().map(str::trim))
+    .bind(input.activo)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    get_aplicativo(pool, aplicativo_id, sql_collate_clause).await
+}
+
+/// Modifica nombre, descripción y estado. El código no cambia: identifica al aplicativo
+/// en las licencias y en los clientes que lo eligen por cabecera o ruta.
+pub async fn update_aplicativo(
+    pool: &Pool<Mssql>,
+    cache: &AplicativoCache,
+    aplicativo_id: i32,
+    input: &AplicativoInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<Aplicativo, AplicativoError> {
+    let actual = get_aplicativo(pool, aplicativo_id, sql_collate_clause).await?;
+    if !actual.aplicativo.eq_ignore_ascii_case(input.aplicativo.trim()) {
+        return Err(AplicativoError::Validation("El código del aplicativo no se puede cambiar".to_string()));
+    }
+    sqlx::query(
+        "UPDATE riy.riy_SeguridadAplicativo
+            SET nombre = @p1, descripcion = @p2, activo = @p3, modificadoPor = @p4, fechaModificacion = GETDATE()
+          WHERE aplicativoID = @p5"
+    )
+    .bind(input.nombre.as_deref().map(str::trim))
+    .bind(input.descripcion.as_deref().map(str::trim))
+    .bind(input.activo)
+    .bind(autor)
+    .bind(aplicativo_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    cache.invalidate().await;
+    get_aplicativo(pool, aplicativo_id, sql_collate_clause).await
+}
+
+/// Reemplaza la configuración del aplicativo.
+pub async fn update_config(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &AplicativoConfigInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<Aplicativo, AplicativoError> {
+    get_aplicativo(pool, aplicativo_id, sql_collate_clause).await?;
+    for clave in input.configuracion.keys() {
+        if clave.trim().is_empty() || clave.len() > 100 {
+            return Err(AplicativoError::Validation(format!("Clave de configuración inválida: '{}'", clave)));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_SeguridadAplicativoConfig WHERE aplicativoID = @p1")
+        .bind(aplicativo_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    for (clave, valor) in input.configuracion.iter().filter(|(_, v)| !v.trim().is_empty()) {
+        sqlx::query(
+            "INSERT INTO riy.riy_SeguridadAplicativoConfig (aplicativoID, clave, valor, modificadoPor, fechaModificacion)
+             VALUES (@p1, @p2, @p3, @p4, GETDATE())"
+        )
+        .bind(aplicativo_id)
+        .bind(clave.trim())
+        .bind(valor.trim())
+        .bind(autor)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+    get_aplicativo(pool, aplicativo_id, sql_collate_clause).await
+}
+
+// ---------------------------------------------------------------------------
+// Copia de menús y roles
+// ---------------------------------------------------------------------------
+
+type MenuRow = (i32, Option<i32>, String, Option<String>, String, String, String, i32);
+
+/// Copia el árbol de menús respetando la jerarquía. Los ítems cuya ruta ya existe en el
+/// destino no se duplican, pero sus hijos se cuelgan del ítem existente.
+async fn copy_menus(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    origen_id: i32,
+    destino_id: i32,
+    autor: &str,
+    sql_collate_clause: &str,
+    resultado: &mut AplicativoCopyResult,
+) -> Result<(), AplicativoError> {
+    let sql_query = format!(
+        "SELECT menuID, papaID, nombre {0}, codigoPermiso {0}, tipoElemento {0}, segmentoRuta {0}, ruta {0}, orden
+           FROM riy.riy_SeguridadMenu WITH(NOLOCK)
+          WHERE aplicativoID = @p1",
+        sql_collate_clause
+    );
+    let origen: Vec<MenuRow> = sqlx::query_as(&sql_query)
+        .bind(origen_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let sql_query = format!(
+        "SELECT ruta {0}, menuID FROM riy.riy_SeguridadMenu WITH(NOLOCK) WHERE aplicativoID = @p1",
+        sql_collate_clause
+    );
+    let existentes: HashMap<String, i32> = sqlx::query_as::<_, (String, i32)>(&sql_query)
+        .bind(destino_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|(ruta, id)| (ruta.to_lowercase(), id))
+        .collect();
+
+    // menuID de origen -> menuID de destino
+    let mut equivalentes: HashMap<i32, i32> = HashMap::new();
+    let mut pendientes = origen;
+    loop {
+        let (listos, resto): (Vec<MenuRow>, Vec<MenuRow>) = pendientes
+            .into_iter()
+            .partition(|m| m.1.map_or(true, |papa| equivalentes.contains_key(&papa)));
+        if listos.is_empty() {
+            // Ítems cuyo padre no pertenece al aplicativo de origen
+            resultado.menus_omitidos += resto.len();
+            break;
+        }
+        for (menu_id, papa_id, nombre, codigo_permiso, tipo_elemento, segmento_ruta, ruta, orden) in listos {
+            if let Some(existente) = existentes.get(&ruta.to_lowercase()) {
+                equivalentes.insert(menu_id, *existente);
+                resultado.menus_omitidos += 1;
+                continue;
+            }
+            let (nuevo_id,): (i32,) = sqlx::query_as(
+                "INSERT INTO riy.riy_SeguridadMenu
+                    (aplicativoID, papaID, nombre, codigoPermiso, tipoElemento, segmentoRuta, ruta, orden, autor, fechaCreacion)
+                 OUTPUT INSERTED.menuID
+                 VALUES (@p1, @p2, @p3, @p4, @p5, @p6, @p7, @p8, @p9, GETDATE())"
+            )
+            .bind(destino_id)
+            .bind(papa_id.and_then(|papa| equivalentes.get(&papa).copied()))
+            .bind(&nombre)
+            .bind(codigo_permiso.as_deref())
+            .bind(&tipo_elemento)
+            .bind(&segmento_ruta)
+            .bind(&ruta)
+            .bind(orden)
+            .bind(autor)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error)?;
+            equivalentes.insert(menu_id, nuevo_id);
+            resultado.menus_copiados += 1;
+        }
+        if resto.is_empty() {
+            break;
+        }
+        pendientes = resto;
+    }
+    Ok(())
+}
+
+/// Copia los roles (por nombre) y sus permisos; no copia las asignaciones a usuarios.
+async fn copy_roles(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    origen_id: i32,
+    destino_id: i32,
+    autor: &str,
+    sql_collate_clause: &str,
+    resultado: &mut AplicativoCopyResult,
+) -> Result<(), AplicativoError> {
+    let sql_query = format!(
+        "SELECT rolID, rol {0}, descripcion {0} FROM riy.riy_SeguridadRol WITH(NOLOCK) WHERE aplicativoID = @p1",
+        sql_collate_clause
+    );
+    let origen: Vec<(i32, String, Option<String>)> = sqlx::query_as(&sql_query)
+        .bind(origen_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let existentes: HashMap<String, i32> = sqlx::query_as::<_, (i32, String, Option<String>)>(&sql_query)
+        .bind(destino_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|(id, rol, _)| (rol.to_lowercase(), id))
+        .collect();
+
+    for (rol_id, rol, descripcion) in origen {
+        let destino_rol_id = match existentes.get(&rol.to_lowercase()) {
+            Some(id) => {
+                resultado.roles_omitidos += 1;
+                *id
+            }
+            None => {
+                let (nuevo_id,): (i32,) = sqlx::query_as(
+                    "INSERT INTO riy.riy_SeguridadRol (aplicativoID, rol, descripcion, autor, fechaCreacion)
+                     OUTPUT INSERTED.rolID
+                     VALUES (@p1, @p2, @p3, @p4, GETDATE())"
+                )
+                .bind(destino_id)
+                .bind(&rol)
+                .bind(descripcion.as_deref())
+                .bind(autor)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(db_error)?;
+                resultado.roles_copiados += 1;
+                nuevo_id
+            }
+        };
+        // Permisos del rol de origen que el rol de destino aún no tiene
+        let insertados = sqlx::query(
+            "INSERT INTO riy.riy_SeguridadRolPermiso (rolID, codigoPermiso, autor, fechaCreacion)
+             SELECT @p1, rp.codigoPermiso, @p2, GETDATE()
+               FROM riy.riy_SeguridadRolPermiso rp
+              WHERE rp.rolID = @p3
+                AND NOT EXISTS (SELECT 1 FROM riy.riy_SeguridadRolPermiso d
+                                 WHERE d.rolID = @p1 AND d.codigoPermiso = rp.codigoPermiso)"
+        )
+        .bind(destino_rol_id)
+        .bind(autor)
+        .bind(rol_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+        resultado.permisos_copiados += insertados as usize;
+    }
+    Ok(())
+}
+
+/// Copia menús y/o roles del aplicativo `origen_id` al `destino_id`, en una transacción.
+pub async fn copy_from(
+    pool: &Pool<Mssql>,
+    destino_id: i32,
+    request: &AplicativoCopyRequest,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<AplicativoCopyResult, AplicativoError> {
+    if request.origen_id == destino_id {
+        return Err(AplicativoError::Validation("El origen y el destino son el mismo aplicativo".to_string()));
+    }
+    if !request.menus && !request.roles {
+        return Err(AplicativoError::Validation("Indique si se copian menús, roles o ambos".to_string()));
+    }
+    get_aplicativo(pool, request.origen_id, sql_collate_clause).await?;
+    get_aplicativo(pool, destino_id, sql_collate_clause).await?;
+
+    let mut resultado = AplicativoCopyResult::default();
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    if request.menus {
+        copy_menus(&mut tx, request.origen_id, destino_id, autor, sql_collate_clause, &mut resultado).await?;
+    }
+    if request.roles {
+        copy_roles(&mut tx, request.origen_id, destino_id, autor, sql_collate_clause, &mut resultado).await?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    println!(
+        "aplicativo_logic: '{}' copió del aplicativo {} al {}: {:?}",
+        autor, request.origen_id, destino_id, resultado
+    );
+    Ok(resultado)
+}
@@ -0,0 +1,67 @@
+// src/shared/aplicativo_models.rs
+// Modelos de la administración de aplicativos.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Aplicativo (`riy.riy_SeguridadAplicativo`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Aplicativo {
+    pub aplicativo_id: i32,
+    /// Código con el que se elige el aplicativo (`X-Aplicativo` o `/a/{codigo}`).
+    pub aplicativo: String,
+    pub nombre: Option<String>,
+    pub descripcion: Option<String>,
+    pub activo: bool,
+    /// Parámetros propios del aplicativo (`riy.riy_SeguridadAplicativoConfig`).
+    pub configuracion: BTreeMap<String, String>,
+    pub autor: Option<String>,
+    pub fecha_creacion: Option<String>,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AplicativoInput {
+    pub aplicativo: String,
+    pub nombre: Option<String>,
+    pub descripcion: Option<String>,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Reemplaza la configuración completa; un valor vacío elimina la clave.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AplicativoConfigInput {
+    pub configuracion: BTreeMap<String, String>,
+}
+
+/// Copia de menús y/o roles de otro aplicativo al indicado en la ruta.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AplicativoCopyRequest {
+    pub origen_id: i32,
+    #[serde(default = "default_true")]
+    pub menus: bool,
+    #[serde(default = "default_true")]
+    pub roles: bool,
+}
+
+/// Resultado de la copia. Lo que ya existía en el destino (misma ruta o mismo rol) se omite.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AplicativoCopyResult {
+    pub menus_copiados: usize,
+    pub menus_omitidos: usize,
+    pub roles_copiados: usize,
+    pub roles_omitidos: usize,
+    pub permisos_copiados: usize,
+}
@@ -28,6 +28,26 @@ pub enum AppErrorCode {
     // Error de solicitud inválida del cliente
     #[serde(rename = "BAD_REQUEST")]
     BadRequest,
+
+    // Cuando el usuario autenticado no tiene el permiso requerido
+    #[serde(rename = "FORBIDDEN")]
+    Forbidden,
+
+    // Cuando un recurso (distinto de un usuario) no existe
+    #[serde(rename = "NOT_FOUND")]
+    NotFound,
+
+    // Cuando la operación choca con el estado actual (ej. proceso ya en curso)
+    #[serde(rename = "CONFLICT")]
+    Conflict,
+
+    // Cuando una consulta supera su tiempo máximo de ejecución
+    #[serde(rename = "TIMEOUT")]
+    Timeout,
+
+    // Cuando se bloquea una operación por demasiados intentos fallidos (ej. códigos MFA)
+    #[serde(rename = "TOO_MANY_ATTEMPTS")]
+    TooManyAttempts,
 }
 
 // Estructura que enviamos al frontend
@@ -30,8 +30,10 @@ use crate::models::{
     Usuario, // Necesario para la función ERP
     // 🏆 CORRECCIÓN: Agregamos la importación del tipo User
     User, 
+    ExternalIdentity,
 }; 
 use super::user_repository;
+use super::identity_link_logic;
 // use super::config::AppConfig; 
 
 pub type DbPool = Pool<Mssql>;
@@ -54,6 +56,9 @@ pub struct MsalClaims {
     pub aud: String, 
     // Expiration Time
     pub exp: u64, 
+    // Object ID del usuario en el tenant (identificador estable)
+    #[serde(default)]
+    pub oid: Option<String>,
     // Otros claims comunes de JWT que pueden estar presentes, si es necesario, 
     // pero los errores solo mencionaron upn, aud, exp como disponibles.
 }
@@ -135,6 +140,7 @@ pub async fn validate_msal_token(
         upn: "mock.user@empresa.com".to_string(), 
         aud: client_id.to_string(),
         exp: (Utc::now() + Duration::hours(1)).timestamp() as u64,
+        oid: None,
     };
     
     // 4. CONSTRUIR USERINFO A PARTIR DE CLAIMS
@@ -142,6 +148,11 @@ pub async fn validate_msal_token(
         username: claims.upn.clone(), 
         email: claims.upn, 
         name: Some("Usuario MSAL de Prueba".to_string()),
+        identity: claims.oid.map(|oid| ExternalIdentity {
+            provider: identity_link_logic::PROVEEDOR_MICROSOFT.to_string(),
+            subject: oid,
+            issuer: None,
+        }),
     };
 
     Ok(user_info)
@@ -166,23 +177,25 @@ pub async fn authenticate_erp_user(
     sql_collate_clause: &str
 ) -> Result<UserInfo> {
     
-    // 1. Verificar si el usuario existe en la tabla de la aplicación (riy.riy_usuario)
+    // 1. Verificar si el usuario existe y está vigente en la tabla de la aplicación (riy.riy_usuario).
+    // La sincronización con el ERP marca como inactivos a los usuarios que ya no están vigentes allí.
     let user_exists_query = format!(
         "SELECT usuario {0} as usuario, nombre {0} as nombre, correo {0} as correo
          FROM riy.riy_usuario WITH(NOLOCK)
-         WHERE usuario = @p1 {0}", 
+         WHERE usuario = @p1 {0} AND estado = @p2 {0}", 
         sql_collate_clause
     );
 
     let riy_user_result: Option<Usuario> = sqlx::query_as(&user_exists_query)
         .bind(usuario)
+        .bind(user_repository::ESTADO_VIGENTE)
         .fetch_optional(pool)
         .await
         .map_err(|e| anyhow!("DB Error al verificar usuario en riy.riy_usuario: {}", e))?;
     
-    // Si no existe en riy.riy_usuario, la autenticación falla para esta app.
+    // Si no existe (o no está vigente) en riy.riy_usuario, la autenticación falla para esta app.
     let riy_user = riy_user_result
-        .ok_or_else(|| anyhow!("Usuario no encontrado en la base de datos de la aplicación."))?;
+        .ok_or_else(|| anyhow!("Usuario no encontrado o no vigente en la base de datos de la aplicación."))?;
 
 
     // 2. Obtener la clave cifrada del ERP (dbo.Usuario)
@@ -214,6 +227,7 @@ pub async fn authenticate_erp_user(
             username: riy_user.usuario, // Mapea el antiguo 'sub'
             name: Some(riy_user.nombre),
             email: riy_user.correo,
+            identity: None,
         })
     } else {
         Err(anyhow!("Contraseña inválida."))
@@ -292,6 +306,7 @@ pub async fn authenticate_user_local_db(
         username: Some(user_record.usuario),
         name: user_record.nombre,
         email: user_record.correo,
+        identity: None,
         //preferred_username: Some(user_record.nombre_usuario),
     })
 }
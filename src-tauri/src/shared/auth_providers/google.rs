@@ -14,6 +14,9 @@ use anyhow::Result;
 use std::sync::Arc; // 👈 ¡AÑADIR ESTA LÍNEA!
 //use std::env;
 
+/// Emisor (`iss`) de los ID tokens de Google que se aceptan.
+pub const GOOGLE_ISSUER: &str = "https://accounts.google.com";
+
 // Tipo de alias para el resultado de identidad: (email, unique_id)
 pub type IdentityResult = Result<(String, String), anyhow::Error>;
 
@@ -47,6 +50,8 @@ pub async fn validate_google_code(
     http_client: &Arc<Client>, 
     google_client_id: &str,
     google_client_secret: &str,
+    // Verificador PKCE, si el código se pidió con `code_challenge`
+    code_verifier: Option<&str>,
 ) -> IdentityResult {
 //pub async fn validate_google_code(code: &str, redirect_uri: &str) -> IdentityResult {
     
@@ -58,13 +63,16 @@ pub async fn validate_google_code(
 
     // 2. Intercambio de Código por ID Token
     // 🚨 USAMOS EL CLIENTE HTTP INYECTADO (http_client) 🚨
-    let params = [
+    let mut params = vec![
         ("code", code),
         ("client_id", client_id), // Usamos el argumento inyectado
         ("client_secret", client_secret), // Usamos el argumento inyectado
         ("redirect_uri", redirect_uri),
         ("grant_type", "authorization_code"),
     ];
+    if let Some(code_verifier) = code_verifier {
+        params.push(("code_verifier", code_verifier));
+    }
 
     let token_res = http_client // 👈 USAMOS LA REFERENCIA INYECTADA
         .post("https://oauth2.googleapis.com/token")
@@ -96,7 +104,7 @@ pub async fn validate_google_code(
     let mut validation = Validation::new(Algorithm::RS256);
     // 🚨 Aquí usamos la variable local 'client_id', que ahora es el argumento inyectado
     validation.set_audience(&[client_id.to_string()]); // Verifica que el token sea para usted
-    validation.set_issuer(&["https://accounts.google.com"]); // Verifica la fuente
+    validation.set_issuer(&[GOOGLE_ISSUER]); // Verifica la fuente
 
     let token_data = decode::<GoogleClaims>(
         &token_res.id_token,
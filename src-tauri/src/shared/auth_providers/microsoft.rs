@@ -11,7 +11,6 @@ use anyhow::Result;
 
 use std::sync::Arc; // 👈 Asegúrate de importar esto
 use reqwest::Client; // 👈 Asegúrate de importar esto
-use std::collections::HashSet; // 👈 Asegúrate de importar esto
 
 // Tipo de alias para el resultado de identidad: (email, unique_id)
 pub type IdentityResult = Result<(String, String), anyhow::Error>;
@@ -44,7 +43,6 @@ pub async fn validate_microsoft_token(
     msal_audience_uri: &str, // 🚨 Argumento NUEVO/AJUSTADO
     // ...
     msal_jwks_url: &str,
-    whitelisted_domains: &HashSet<String>, // El conjunto de dominios para validación
 ) -> IdentityResult {
 
     eprintln!("Token MSAL (Longitud: {}): {}", token.len(), token); // 🚨 Añadir este LOG
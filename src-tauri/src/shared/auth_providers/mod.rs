@@ -2,4 +2,21 @@
 
 pub mod google;
 pub mod microsoft;
-pub mod jwks_security;
\ No newline at end of file
+pub mod jwks_security;
+
+use crate::models::{ExternalIdentity, UserInfo};
+
+/// Convierte la identidad `(email, unique_id)` que devuelven los validadores en `UserInfo`,
+/// conservando el oid/sub para buscar el vínculo en `riy.riy_usuario_identidad`.
+pub fn to_user_info(provider: &str, email: String, unique_id: String, issuer: Option<String>) -> UserInfo {
+    UserInfo {
+        username: email.clone(),
+        email,
+        name: None,
+        identity: Some(ExternalIdentity {
+            provider: provider.to_string(),
+            subject: unique_id,
+            issuer,
+        }),
+    }
+}
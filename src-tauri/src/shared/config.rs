@@ -1,5 +1,7 @@
 // src-tauri/src/shared/config.rs
 
+use crate::secret_store_models::SecretValues;
+
 /**
  * Estructura para contener la configuración de la aplicación
  * (ej. claves secretas, URLs de servicios, flags de entorno).
@@ -10,6 +12,8 @@
 pub struct AppConfig {
     /// Clave secreta utilizada para firmar los JWT de sesión internos de la aplicación.
     pub app_jwt_secret: String,
+    /// Clave AES-256 (hex) con la que se cifran los secretos TOTP del segundo factor.
+    pub mfa_encryption_key: String,
     
     // CAMPOS AÑADIDOS para la lógica de autenticación y base de datos
     /// URL para obtener las claves JWKS de MSAL/Azure AD.
@@ -18,6 +22,22 @@ pub struct AppConfig {
     pub msal_client_id: String,
     /// Cláusula de colación de SQL Server para búsquedas sensibles a mayúsculas/minúsculas.
     pub sql_collate_clause: String,
+    /// Credenciales OAuth de Google para el intercambio del código de autorización.
+    pub google_client_id: String,
+    pub google_client_secret: String,
+    /// Servidor SMTP y cola de reintentos de las notificaciones por correo.
+    pub smtp: SmtpConfig,
+    /// Conexión de solo lectura y límites para el SQL definido por usuarios.
+    pub sandbox: SandboxConfig,
+    /// Almacenamiento de los adjuntos de las ayudas.
+    pub help: HelpConfig,
+    /// Palabras clave de la licencia (cifrado y hash).
+    pub palabra_clave1: String,
+    pub palabra_clave2: String,
+    /// Conexión por defecto del escritorio cuando no hay perfil de conexión activo.
+    pub database_url: Option<String>,
+    /// Login del escritorio con el navegador del sistema (Google / Microsoft).
+    pub oauth_loopback: OAuthLoopbackConfig,
 }
 
 impl AppConfig {
@@ -27,10 +47,252 @@ impl AppConfig {
     pub fn new_default() -> Self {
         AppConfig {
             app_jwt_secret: "clave_secreta_de_ejemplo_debe_ser_larga_y_fuerte".to_string(),
+            mfa_encryption_key: String::new(),
             // Valores por defecto para que la lógica de autenticación compile:
             msal_jwks_url: "https://ejemplo.microsoft.com/.well-known/openid-configuration/jwks".to_string(),
             msal_client_id: "client-id-msal-ejemplo".to_string(),
             sql_collate_clause: "COLLATE SQL_Latin1_General_CP1_CI_AS".to_string(),
+            google_client_id: String::new(),
+            google_client_secret: String::new(),
+            smtp: SmtpConfig::from_env(),
+            sandbox: SandboxConfig::from_env(),
+            help: HelpConfig::from_env(),
+            palabra_clave1: String::new(),
+            palabra_clave2: String::new(),
+            database_url: None,
+            oauth_loopback: OAuthLoopbackConfig::from_env(),
+        }
+    }
+
+    /// Lee la configuración del entorno; los secretos guardados en el archivo cifrado
+    /// (secret_store_logic) reemplazan a la variable de entorno del mismo nombre.
+    pub fn from_env_with_secrets(secretos: &SecretValues) -> Self {
+        let var = |name: &str| {
+            secretos
+                .get(name)
+                .map(|v| v.to_string())
+                .or_else(|| std::env::var(name).ok().map(|v| v.trim().to_string()))
+                .filter(|v| !v.is_empty())
+        };
+        let mut smtp = SmtpConfig::from_env();
+        if let Some(password) = var("SMTP_PASSWORD") {
+            smtp.password = Some(password);
+        }
+        AppConfig {
+            app_jwt_secret: var("JWT_SECRET").unwrap_or_default(),
+            mfa_encryption_key: var("MFA_ENCRYPTION_KEY").unwrap_or_default(),
+            msal_jwks_url: var("JWKS_URL").unwrap_or_default(),
+            msal_client_id: var("MSAL_CLIENT_ID").unwrap_or_default(),
+            sql_collate_clause: var("SQL_COLLATE_CLAUSE").unwrap_or_default(),
+            google_client_id: var("GOOGLE_CLIENT_ID").unwrap_or_default(),
+            google_client_secret: var("GOOGLE_CLIENT_SECRET").unwrap_or_default(),
+            smtp,
+            sandbox: SandboxConfig::from_env(),
+            help: HelpConfig::from_env(),
+            palabra_clave1: var("PALABRA_CLAVE_1").unwrap_or_default(),
+            palabra_clave2: var("PALABRA_CLAVE_2").unwrap_or_default(),
+            database_url: var("DATABASE_URL"),
+            oauth_loopback: OAuthLoopbackConfig::from_env(),
+        }
+    }
+}
+
+/**
+ * Configuración de la sincronización programada de usuarios ERP -> riy.
+ * Se carga desde variables de entorno en el servidor API.
+ */
+#[derive(Debug, Clone)]
+pub struct UserSyncConfig {
+    /// Minutos entre ejecuciones programadas. `0` desactiva la programación.
+    pub interval_minutes: u64,
+    /// Si las ejecuciones programadas solo generan el reporte sin modificar datos.
+    pub dry_run: bool,
+    /// Si las ejecuciones programadas desactivan usuarios sin cuenta ERP vigente.
+    pub auto_deactivate: bool,
+}
+
+impl UserSyncConfig {
+    /// Lee `USER_SYNC_INTERVAL_MINUTES`, `USER_SYNC_DRY_RUN` y `USER_SYNC_AUTO_DEACTIVATE`.
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            std::env::var(name)
+                .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "si" | "yes"))
+                .unwrap_or(false)
+        };
+        UserSyncConfig {
+            interval_minutes: std::env::var("USER_SYNC_INTERVAL_MINUTES")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(0),
+            dry_run: flag("USER_SYNC_DRY_RUN"),
+            auto_deactivate: flag("USER_SYNC_AUTO_DEACTIVATE"),
+        }
+    }
+}
+
+/// Modo de cifrado de la conexión SMTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Sin cifrado; pensado para un receptor local de pruebas (MailHog, smtp4dev).
+    None,
+    /// STARTTLS sobre el puerto de envío (587).
+    StartTls,
+    /// TLS implícito (465).
+    Tls,
+}
+
+/**
+ * Configuración del envío de notificaciones por correo.
+ * Con `SMTP_HOST` vacío la cola se sigue llenando pero el worker no envía nada.
+ */
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Remitente, p. ej. `riy <no-responder@empresa.com>`.
+    pub from: String,
+    /// Destinatarios de los avisos administrativos (licencia por vencer), separados por coma.
+    pub admin_recipients: Vec<String>,
+    /// Segundos entre pasadas del worker de la cola.
+    pub queue_interval_seconds: u64,
+    /// Intentos antes de marcar una notificación como fallida.
+    pub max_attempts: i32,
+    /// Días de anticipación para avisar que la licencia está por vencer. `0` desactiva el aviso.
+    pub license_warning_days: i64,
+}
+
+impl SmtpConfig {
+    /// Lee `SMTP_HOST`, `SMTP_PORT`, `SMTP_TLS` (none | starttls | tls), `SMTP_USERNAME`,
+    /// `SMTP_PASSWORD`, `SMTP_FROM`, `SMTP_ADMIN_RECIPIENTS`, `SMTP_QUEUE_INTERVAL_SECONDS`,
+    /// `SMTP_MAX_ATTEMPTS` y `LICENSE_WARNING_DAYS`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let tls = match var("SMTP_TLS").unwrap_or_default().to_lowercase().as_str() {
+            "none" | "no" | "plain" => SmtpTls::None,
+            "tls" | "ssl" => SmtpTls::Tls,
+            _ => SmtpTls::StartTls,
+        };
+        let default_port = match tls {
+            SmtpTls::None => 1025,
+            SmtpTls::StartTls => 587,
+            SmtpTls::Tls => 465,
+        };
+        SmtpConfig {
+            host: var("SMTP_HOST").unwrap_or_default(),
+            port: var("SMTP_PORT").and_then(|v| v.parse().ok()).unwrap_or(default_port),
+            tls,
+            username: var("SMTP_USERNAME"),
+            password: var("SMTP_PASSWORD"),
+            from: var("SMTP_FROM").unwrap_or_else(|| "riy <no-responder@localhost>".to_string()),
+            admin_recipients: var("SMTP_ADMIN_RECIPIENTS")
+                .map(|v| v.split(',').map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect())
+                .unwrap_or_default(),
+            queue_interval_seconds: var("SMTP_QUEUE_INTERVAL_SECONDS").and_then(|v| v.parse().ok()).unwrap_or(60),
+            max_attempts: var("SMTP_MAX_ATTEMPTS").and_then(|v| v.parse().ok()).unwrap_or(5),
+            license_warning_days: var("LICENSE_WARNING_DAYS").and_then(|v| v.parse().ok()).unwrap_or(15),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.host.is_empty()
+    }
+}
+
+/**
+ * Configuración del ejecutor de SQL de solo lectura (consultas definidas por usuarios).
+ * Usa un login distinto al del pool principal, sin permisos de escritura ni acceso al
+ * esquema riy. Con `READONLY_DATABASE_URL` vacía el sandbox queda deshabilitado.
+ */
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub database_url: String,
+    /// Filas máximas devueltas por ejecución.
+    pub max_rows: i64,
+    /// Filas máximas de una exportación (se envían en streaming, no en una sola respuesta).
+    pub max_export_rows: i64,
+    /// Segundos máximos por ejecución; una consulta guardada puede pedir menos.
+    pub timeout_seconds: u64,
+}
+
+impl SandboxConfig {
+    /// Lee `READONLY_DATABASE_URL`, `SANDBOX_MAX_ROWS`, `SANDBOX_MAX_EXPORT_ROWS` y
+    /// `SANDBOX_TIMEOUT_SECONDS`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        SandboxConfig {
+            database_url: var("READONLY_DATABASE_URL").unwrap_or_default(),
+            max_rows: var("SANDBOX_MAX_ROWS").and_then(|v| v.parse().ok()).unwrap_or(5000),
+            max_export_rows: var("SANDBOX_MAX_EXPORT_ROWS").and_then(|v| v.parse().ok()).unwrap_or(1_000_000),
+            timeout_seconds: var("SANDBOX_TIMEOUT_SECONDS").and_then(|v| v.parse().ok()).unwrap_or(60),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.database_url.is_empty()
+    }
+}
+
+/**
+ * Configuración de los adjuntos de las ayudas contextuales.
+ * Con `HELP_ATTACHMENTS_DIR` definida se guardan en disco; si no, en la base de datos.
+ */
+#[derive(Debug, Clone)]
+pub struct HelpConfig {
+    /// Carpeta de los adjuntos (vacía = en la base de datos).
+    pub attachments_dir: String,
+    /// Tamaño máximo de un adjunto, en bytes.
+    pub max_attachment_bytes: usize,
+}
+
+impl HelpConfig {
+    /// Lee `HELP_ATTACHMENTS_DIR` y `HELP_MAX_ATTACHMENT_MB`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        HelpConfig {
+            attachments_dir: var("HELP_ATTACHMENTS_DIR").unwrap_or_default(),
+            max_attachment_bytes: var("HELP_MAX_ATTACHMENT_MB")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(10)
+                * 1024
+                * 1024,
         }
     }
-}
\ No newline at end of file
+
+    pub fn stores_on_disk(&self) -> bool {
+        !self.attachments_dir.is_empty()
+    }
+}
+
+/**
+ * Configuración del login del escritorio con el navegador del sistema: la aplicación abre la
+ * página del proveedor y recibe la respuesta en `http://127.0.0.1:{puerto}/callback`.
+ * Los Client ID y el secreto de Google son los de AppConfig.
+ */
+#[derive(Debug, Clone)]
+pub struct OAuthLoopbackConfig {
+    /// Autoridad de Microsoft: `https://login.microsoftonline.com/{tenant}` o, en B2C,
+    /// `https://{tenant}.b2clogin.com/{tenant}.onmicrosoft.com/{política}`.
+    pub msal_authority: String,
+    /// Scopes pedidos a Microsoft (separados por espacio).
+    pub msal_scopes: String,
+    /// Segundos que se espera la respuesta del navegador.
+    pub timeout_seconds: u64,
+}
+
+impl OAuthLoopbackConfig {
+    /// Lee `MSAL_AUTHORITY`, `MSAL_SCOPES` y `OAUTH_LOGIN_TIMEOUT_SECONDS`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        OAuthLoopbackConfig {
+            msal_authority: var("MSAL_AUTHORITY")
+                .unwrap_or_else(|| "https://login.microsoftonline.com/common".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            msal_scopes: var("MSAL_SCOPES").unwrap_or_else(|| "openid profile email".to_string()),
+            timeout_seconds: var("OAUTH_LOGIN_TIMEOUT_SECONDS").and_then(|v| v.parse().ok()).unwrap_or(180),
+        }
+    }
+}
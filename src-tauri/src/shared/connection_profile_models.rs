@@ -0,0 +1,63 @@
+// src/shared/connection_profile_models.rs
+// Modelos de los perfiles de conexión de la aplicación de escritorio.
+
+use serde::{Deserialize, Serialize};
+
+/// Conexión con nombre a un SQL Server. Solo vive dentro del archivo cifrado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfile {
+    pub nombre: String,
+    pub servidor: String,
+    pub puerto: Option<u16>,
+    pub base_datos: String,
+    pub usuario: String,
+    pub password: String,
+    /// Parámetros extra de la URL, p. ej. `trust_server_certificate=true`.
+    #[serde(default)]
+    pub opciones: Option<String>,
+}
+
+/// Contenido del archivo de perfiles: los perfiles y el último elegido.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfileFile {
+    pub activo: Option<String>,
+    #[serde(default)]
+    pub perfiles: Vec<ConnectionProfile>,
+}
+
+/// Perfil como lo ve la pantalla: nunca incluye la contraseña.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfileSummary {
+    pub nombre: String,
+    pub servidor: String,
+    pub puerto: Option<u16>,
+    pub base_datos: String,
+    pub usuario: String,
+    pub opciones: Option<String>,
+    pub activo: bool,
+}
+
+/// Alta o modificación de un perfil (por nombre). Sin `password` al modificar se conserva la guardada.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfileInput {
+    pub nombre: String,
+    pub servidor: String,
+    pub puerto: Option<u16>,
+    pub base_datos: String,
+    pub usuario: String,
+    pub password: Option<String>,
+    pub opciones: Option<String>,
+}
+
+/// Resultado de probar una conexión.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTestResult {
+    pub servidor: String,
+    pub base_datos: String,
+    pub version: String,
+}
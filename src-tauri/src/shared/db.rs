@@ -13,7 +13,11 @@ use url::Url;
 /// Conecta a la base de datos usando la URL proporcionada.
 pub async fn connect_db(database_url: &str
     ) -> Result<Pool<Mssql>, String> {
-    println!("Connecting to database at: {}", database_url);
+    // Sin la URL completa en el log: lleva usuario y contraseña
+    match parse_mssql_connection_url(database_url) {
+        Ok((host, db_name)) => println!("Connecting to database {} at {}", db_name, host),
+        Err(_) => println!("Connecting to database..."),
+    }
 
     let pool = MssqlPoolOptions::new()
         .max_connections(5)
@@ -58,7 +62,7 @@ pub fn parse_mssql_connection_url(url: &str
     let db_name = parsed_url.path_segments().and_then(|mut p| p.next()).unwrap_or("").to_string();
 
     if host.is_empty() || db_name.is_empty() {
-        return Err(format!("URL de conexión de MSSQL inválida: {}", url));
+        return Err("URL de conexión de MSSQL inválida: falta el servidor o la base de datos".to_string());
     }
 
     Ok((host, db_name))
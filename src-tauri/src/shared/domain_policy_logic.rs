@@ -0,0 +1,402 @@
+// src/shared/domain_policy_logic.rs
+/*
+Dominios de correo permitidos para login externo, por aplicativo. Reemplaza la lista
+en memoria `AppState.whitelisted_domains`; se consulta en todos los logins externos
+(MSAL y Google) desde `user_repository::find_or_create_user`.
+
+CREATE TABLE riy.riy_dominio_permitido (
+    dominioID              INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID           INT           NOT NULL,
+    dominio                VARCHAR(255)  NOT NULL,
+    politica               VARCHAR(20)   NOT NULL,  -- auto_provision | require_existing
+    rolPorDefectoID        INT           NULL,
+    proveedoresPermitidos  VARCHAR(100)  NULL,      -- separados por coma; NULL = todos
+    emisoresPermitidos     VARCHAR(2000) NULL,      -- separados por coma; NULL = cualquiera
+    activo                 BIT           NOT NULL DEFAULT 1,
+    autor                  VARCHAR(50)   NOT NULL,
+    fechaCreacion          DATETIME      NOT NULL DEFAULT GETDATE(),
+    modificadoPor          VARCHAR(50)   NULL,
+    fechaModificacion      DATETIME      NULL,
+    CONSTRAINT UQ_riy_dominio_permitido UNIQUE (aplicativoID, dominio)
+);
+*/
+
+use std::fmt;
+
+use sqlx::{FromRow, Mssql, Pool, Row};
+
+use crate::identity_link_logic;
+use crate::models::{DomainPolicy, DomainPolicyMode, DomainPolicyRequest, ExternalIdentity};
+
+#[derive(Debug)]
+pub enum DomainPolicyError {
+    Validation(String),
+    NotFound(String),
+    Conflict(String),
+    /// El login externo no cumple la política del dominio.
+    Forbidden(String),
+    Database(String),
+}
+
+impl fmt::Display for DomainPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DomainPolicyError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            DomainPolicyError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            DomainPolicyError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            DomainPolicyError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            DomainPolicyError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DomainPolicyError {}
+
+fn db_error(e: sqlx::Error) -> DomainPolicyError {
+    DomainPolicyError::Database(e.to_string())
+}
+
+#[derive(Debug, FromRow)]
+struct DomainPolicyRow {
+    dominio_id: i32,
+    aplicativo_id: i32,
+    dominio: String,
+    politica: String,
+    rol_por_defecto_id: Option<i32>,
+    proveedores_permitidos: Option<String>,
+    emisores_permitidos: Option<String>,
+    activo: bool,
+}
+
+fn split_list(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn join_list(values: &[String]) -> Option<String> {
+    let joined = values
+        .iter()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<&str>>()
+        .join(",");
+    if joined.is_empty() { None } else { Some(joined) }
+}
+
+impl From<DomainPolicyRow> for DomainPolicy {
+    fn from(row: DomainPolicyRow) -> Self {
+        DomainPolicy {
+            dominio_id: row.dominio_id,
+            aplicativo_id: row.aplicativo_id,
+            dominio: row.dominio,
+            // Un valor desconocido en la tabla se trata como la política más restrictiva.
+            politica: DomainPolicyMode::parse(&row.politica).unwrap_or(DomainPolicyMode::RequireExisting),
+            rol_por_defecto_id: row.rol_por_defecto_id,
+            proveedores_permitidos: split_list(row.proveedores_permitidos),
+            emisores_permitidos: split_list(row.emisores_permitidos),
+            activo: row.activo,
+        }
+    }
+}
+
+fn policy_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT dominioID as dominio_id,
+                aplicativoID as aplicativo_id,
+                dominio {0} as dominio,
+                politica {0} as politica,
+                rolPorDefectoID as rol_por_defecto_id,
+                proveedoresPermitidos {0} as proveedores_permitidos,
+                emisoresPermitidos {0} as emisores_permitidos,
+                activo
+           FROM riy.riy_dominio_permitido WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+/// Extrae y normaliza el dominio de un correo.
+pub fn email_domain(email: &str) -> Option<String> {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim().to_lowercase())
+        .filter(|domain| !domain.is_empty())
+}
+
+fn validate_request(request: &DomainPolicyRequest) -> Result<(String, Vec<String>), DomainPolicyError> {
+    let dominio = request.dominio.trim().trim_start_matches('@').to_lowercase();
+    if dominio.is_empty() || !dominio.contains('.') || dominio.contains(char::is_whitespace) {
+        return Err(DomainPolicyError::Validation(format!("Dominio inválido: {}", request.dominio)));
+    }
+    let proveedores = request
+        .proveedores_permitidos
+        .iter()
+        .map(|p| identity_link_logic::normalize_provider(p).map_err(|e| DomainPolicyError::Validation(e.to_string())))
+        .collect::<Result<Vec<String>, DomainPolicyError>>()?;
+    Ok((dominio, proveedores))
+}
+
+/// Políticas de dominio del aplicativo.
+pub async fn list_policies(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<DomainPolicy>, DomainPolicyError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1 ORDER BY dominio", policy_select(sql_collate_clause));
+    let rows = sqlx::query_as::<_, DomainPolicyRow>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows.into_iter().map(DomainPolicy::from).collect())
+}
+
+/// Política de un dominio concreto (activa o no).
+pub async fn get_policy_for_domain(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    dominio: &str,
+    sql_collate_clause: &str,
+) -> Result<Option<DomainPolicy>, DomainPolicyError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND dominio = @p2 {}",
+        policy_select(sql_collate_clause), sql_collate_clause
+    );
+    let row = sqlx::query_as::<_, DomainPolicyRow>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(dominio.to_lowercase())
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(DomainPolicy::from))
+}
+
+async fn get_policy_by_id(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    dominio_id: i32,
+    sql_collate_clause: &str,
+) -> Result<DomainPolicy, DomainPolicyError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1 AND dominioID = @p2", policy_select(sql_collate_clause));
+    sqlx::query_as::<_, DomainPolicyRow>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(dominio_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .map(DomainPolicy::from)
+        .ok_or_else(|| DomainPolicyError::NotFound(format!("Política de dominio {} no encontrada", dominio_id)))
+}
+
+/// Alta de una política de dominio.
+pub async fn create_policy(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    request: &DomainPolicyRequest,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<DomainPolicy, DomainPolicyError> {
+    let (dominio, proveedores) = validate_request(request)?;
+    if get_policy_for_domain(pool, aplicativo_id, &dominio, sql_collate_clause).await?.is_some() {
+        return Err(DomainPolicyError::Conflict(format!("El dominio {} ya tiene una política", dominio)));
+    }
+
+    let row = sqlx::query(
+        "INSERT INTO riy.riy_dominio_permitido
+            (aplicativoID, dominio, politica, rolPorDefectoID, proveedoresPermitidos, emisoresPermitidos, activo, autor, fechaCreacion)
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, @p7, @p8, GETDATE());
+         SELECT CAST(SCOPE_IDENTITY() AS INT) AS dominio_id;"
+    )
+    .bind(aplicativo_id)
+    .bind(&dominio)
+    .bind(request.politica.as_str())
+    .bind(request.rol_por_defecto_id)
+    .bind(join_list(&proveedores))
+    .bind(join_list(&request.emisores_permitidos))
+    .bind(request.activo)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    let dominio_id: i32 = row.try_get("dominio_id").map_err(db_error)?;
+
+    get_policy_by_id(pool, aplicativo_id, dominio_id, sql_collate_clause).await
+}
+
+/// Modificación de una política de dominio.
+pub async fn update_policy(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    dominio_id: i32,
+    request: &DomainPolicyRequest,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<DomainPolicy, DomainPolicyError> {
+    let (dominio, proveedores) = validate_request(request)?;
+    get_policy_by_id(pool, aplicativo_id, dominio_id, sql_collate_clause).await?;
+    if let Some(other) = get_policy_for_domain(pool, aplicativo_id, &dominio, sql_collate_clause).await? {
+        if other.dominio_id != dominio_id {
+            return Err(DomainPolicyError::Conflict(format!("El dominio {} ya tiene una política", dominio)));
+        }
+    }
+
+    sqlx::query(
+        "UPDATE riy.riy_dominio_permitido
+            SET dominio = @p1, politica = @p2, rolPorDefectoID = @p3, proveedoresPermitidos = @p4,
+                emisoresPermitidos = @p5, activo = @p6, modificadoPor = @p7, fechaModificacion = GETDATE()
+          WHERE dominioID = @p8 AND aplicativoID = @p9"
+    )
+    .bind(&dominio)
+    .bind(request.politica.as_str())
+    .bind(request.rol_por_defecto_id)
+    .bind(join_list(&proveedores))
+    .bind(join_list(&request.emisores_permitidos))
+    .bind(request.activo)
+    .bind(autor)
+    .bind(dominio_id)
+    .bind(aplicativo_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    get_policy_by_id(pool, aplicativo_id, dominio_id, sql_collate_clause).await
+}
+
+/// Baja de una política de dominio.
+pub async fn delete_policy(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    dominio_id: i32,
+) -> Result<(), DomainPolicyError> {
+    let result = sqlx::query("DELETE FROM riy.riy_dominio_permitido WHERE dominioID = @p1 AND aplicativoID = @p2")
+        .bind(dominio_id)
+        .bind(aplicativo_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(DomainPolicyError::NotFound(format!("Política de dominio {} no encontrada", dominio_id)));
+    }
+    Ok(())
+}
+
+/// Emisores de Microsoft Entra ID (v2.0 y v1) de un tenant, sin la barra final.
+fn microsoft_issuers(tenant_id: &str) -> [String; 2] {
+    [
+        format!("https://login.microsoftonline.com/{}/v2.0", tenant_id),
+        format!("https://sts.windows.net/{}", tenant_id),
+    ]
+}
+
+/// El emisor es aceptado si coincide completo con una entrada. Una entrada sin esquema
+/// (`://`) es un ID de tenant y equivale a los emisores de Microsoft de ese tenant; nunca se
+/// acepta un emisor solo porque contenga la entrada.
+fn issuer_allowed(policy: &DomainPolicy, issuer: Option<&str>) -> bool {
+    if policy.emisores_permitidos.is_empty() {
+        return true;
+    }
+    let issuer = match issuer {
+        Some(i) => i.trim().trim_end_matches('/').to_lowercase(),
+        None => return false,
+    };
+    policy.emisores_permitidos.iter().any(|allowed| {
+        let allowed = allowed.trim().trim_end_matches('/').to_lowercase();
+        if allowed.contains("://") {
+            issuer == allowed
+        } else {
+            !allowed.is_empty() && microsoft_issuers(&allowed).contains(&issuer)
+        }
+    })
+}
+
+/// Evalúa un login externo contra la política de su dominio.
+/// Devuelve la política aplicable o `Forbidden` si el dominio, el proveedor o el emisor no están permitidos.
+pub async fn evaluate_login(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    email: &str,
+    identity: &ExternalIdentity,
+    sql_collate_clause: &str,
+) -> Result<DomainPolicy, DomainPolicyError> {
+    let dominio = email_domain(email)
+        .ok_or_else(|| DomainPolicyError::Forbidden(format!("Correo inválido: {}", email)))?;
+
+    let policy = get_policy_for_domain(pool, aplicativo_id, &dominio, sql_collate_clause)
+        .await?
+        .filter(|p| p.activo)
+        .ok_or_else(|| DomainPolicyError::Forbidden(format!("Dominio no autorizado: {}", dominio)))?;
+
+    if !policy.proveedores_permitidos.is_empty()
+        && !policy.proveedores_permitidos.iter().any(|p| p.eq_ignore_ascii_case(&identity.provider))
+    {
+        return Err(DomainPolicyError::Forbidden(format!(
+            "El proveedor {} no está permitido para el dominio {}", identity.provider, dominio
+        )));
+    }
+    if !issuer_allowed(&policy, identity.issuer.as_deref()) {
+        return Err(DomainPolicyError::Forbidden(format!(
+            "El emisor del token no está permitido para el dominio {}", dominio
+        )));
+    }
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TENANT: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+
+    fn politica(emisores: &[&str]) -> DomainPolicy {
+        DomainPolicy {
+            dominio_id: 1,
+            aplicativo_id: 1,
+            dominio: "empresa.com".to_string(),
+            politica: DomainPolicyMode::RequireExisting,
+            rol_por_defecto_id: None,
+            proveedores_permitidos: Vec::new(),
+            emisores_permitidos: emisores.iter().map(|e| e.to_string()).collect(),
+            activo: true,
+        }
+    }
+
+    #[test]
+    fn sin_lista_de_emisores_acepta_cualquiera() {
+        assert!(issuer_allowed(&politica(&[]), None));
+        assert!(issuer_allowed(&politica(&[]), Some("https://accounts.google.com")));
+    }
+
+    #[test]
+    fn compara_el_emisor_completo() {
+        let google = politica(&["https://accounts.google.com"]);
+
+        assert!(issuer_allowed(&google, Some("https://accounts.google.com")));
+        assert!(issuer_allowed(&google, Some("https://Accounts.Google.com/")));
+        assert!(!issuer_allowed(&google, Some("https://evil.example/accounts.google.com")));
+        assert!(!issuer_allowed(&google, Some("https://accounts.google.com.evil.example")));
+        assert!(!issuer_allowed(&google, None));
+    }
+
+    #[test]
+    fn un_id_de_tenant_solo_acepta_los_emisores_de_microsoft_de_ese_tenant() {
+        let tenant = politica(&[TENANT]);
+
+        assert!(issuer_allowed(&tenant, Some(&format!("https://login.microsoftonline.com/{}/v2.0", TENANT))));
+        assert!(issuer_allowed(&tenant, Some(&format!("https://sts.windows.net/{}/", TENANT))));
+        assert!(!issuer_allowed(&tenant, Some(&format!("https://evil.example/{}", TENANT))));
+        assert!(!issuer_allowed(&tenant, Some(&format!("https://login.microsoftonline.com/{}x/v2.0", TENANT))));
+        assert!(!issuer_allowed(&tenant, Some("https://accounts.google.com")));
+    }
+
+    #[test]
+    fn acepta_si_coincide_cualquiera_de_las_entradas() {
+        let mixta = politica(&["https://accounts.google.com", TENANT]);
+
+        assert!(issuer_allowed(&mixta, Some("https://accounts.google.com")));
+        assert!(issuer_allowed(&mixta, Some(&format!("https://login.microsoftonline.com/{}/v2.0", TENANT))));
+        assert!(!issuer_allowed(&mixta, Some("https://login.microsoftonline.com/otro-tenant/v2.0")));
+    }
+}
@@ -0,0 +1,402 @@
+// src/shared/export_logic.rs
+/*
+Exportación en streaming para los handlers de actix (CSV, XLSX y NDJSON).
+
+El productor (consulta de usuarios, consulta guardada, ...) corre en su propia tarea y
+escribe fila a fila en un ExportWriter, que codifica en bloques de ~64 KB y los envía por
+un canal acotado. Si el cliente descarga más lento de lo que se leen las filas, el envío
+espera (backpressure) y el productor deja de pedir filas a SQL Server, así que en memoria
+hay a lo sumo CANAL_BLOQUES bloques.
+
+XLSX es la excepción: rust_xlsxwriter arma el libro completo antes de serializarlo, por lo
+que las celdas se acumulan hasta el final. Para exportaciones muy grandes usar CSV o NDJSON.
+
+Si el productor falla a mitad de camino las cabeceras ya se enviaron: el stream termina
+con error y el cliente ve la descarga interrumpida.
+*/
+
+use std::future::Future;
+use std::io;
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::HttpResponse;
+use bytes::Bytes;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use futures::channel::mpsc;
+use futures::{SinkExt, Stream};
+use rust_xlsxwriter::{Format, Workbook};
+use serde_json::{Map, Value};
+
+use crate::export_models::{ExportColumn, ExportFormat, ExportLocale};
+
+/// Tamaño aproximado de cada bloque enviado al cliente.
+const BLOQUE_BYTES: usize = 64 * 1024;
+/// Bloques en cola antes de que el productor tenga que esperar.
+const CANAL_BLOQUES: usize = 8;
+/// Filas por hoja de Excel (incluida la cabecera).
+const XLSX_FILAS_POR_HOJA: u32 = 1_048_576;
+
+pub type ExportChunk = Result<Bytes, io::Error>;
+
+/// Formato, formato regional y columnas (ya seleccionadas) de una exportación.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub formato: ExportFormat,
+    pub locale: ExportLocale,
+    pub columnas: Vec<ExportColumn>,
+}
+
+/// Aplica la selección `a,b,c` sobre las columnas disponibles, en el orden pedido.
+pub fn select_columns(disponibles: Vec<ExportColumn>, seleccion: Option<&str>) -> Result<Vec<ExportColumn>, String> {
+    let nombres: Vec<&str> = seleccion
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .collect();
+    if nombres.is_empty() {
+        return Ok(disponibles);
+    }
+    let mut columnas = Vec::with_capacity(nombres.len());
+    for nombre in nombres {
+        let columna = disponibles
+            .iter()
+            .find(|c| c.nombre.eq_ignore_ascii_case(nombre))
+            .ok_or_else(|| format!("Columna desconocida: '{}'", nombre))?;
+        if !columnas.iter().any(|c: &ExportColumn| c.nombre == columna.nombre) {
+            columnas.push(columna.clone());
+        }
+    }
+    Ok(columnas)
+}
+
+/// Agrupa miles y cambia el separador decimal de un número en notación simple.
+fn localize_number(numero: &str, locale: ExportLocale) -> String {
+    let (miles, decimal) = match locale {
+        ExportLocale::Es => ('.', ','),
+        ExportLocale::En => (',', '.'),
+    };
+    let (signo, resto) = match numero.strip_prefix('-') {
+        Some(r) => ("-", r),
+        None => ("", numero),
+    };
+    let (entera, fraccion) = match resto.split_once('.') {
+        Some((e, f)) => (e, Some(f)),
+        None => (resto, None),
+    };
+    if entera.is_empty()
+        || !entera.chars().all(|c| c.is_ascii_digit())
+        || !fraccion.unwrap_or_default().chars().all(|c| c.is_ascii_digit())
+    {
+        return numero.to_string();
+    }
+    let mut agrupada = String::with_capacity(entera.len() + entera.len() / 3);
+    for (i, c) in entera.chars().enumerate() {
+        if i > 0 && (entera.len() - i) % 3 == 0 {
+            agrupada.push(miles);
+        }
+        agrupada.push(c);
+    }
+    match fraccion {
+        Some(f) => format!("{}{}{}{}", signo, agrupada, decimal, f),
+        None => format!("{}{}", signo, agrupada),
+    }
+}
+
+/// Fecha (y hora, si la tiene) en los formatos que devuelven las consultas.
+fn parse_date(valor: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    for formato in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(fecha) = NaiveDateTime::parse_from_str(valor, formato) {
+            return Some((fecha.date(), Some(fecha.time())));
+        }
+    }
+    NaiveDate::parse_from_str(valor, "%Y-%m-%d").ok().map(|fecha| (fecha, None))
+}
+
+fn date_pattern(locale: ExportLocale) -> &'static str {
+    match locale {
+        ExportLocale::Es => "%d/%m/%Y",
+        ExportLocale::En => "%m/%d/%Y",
+    }
+}
+
+/// Texto de la celda según el tipo de la columna y el formato regional (CSV).
+fn format_value(valor: Option<&Value>, tipo: &str, locale: ExportLocale) -> String {
+    match valor {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::Bool(b)) => match (locale, b) {
+            (ExportLocale::Es, true) => "Sí".to_string(),
+            (ExportLocale::En, true) => "Yes".to_string(),
+            (_, false) => "No".to_string(),
+        },
+        Some(Value::Number(n)) => localize_number(&n.to_string(), locale),
+        Some(Value::String(s)) => match tipo {
+            "fecha" => match parse_date(s) {
+                Some((fecha, None)) => fecha.format(date_pattern(locale)).to_string(),
+                Some((fecha, Some(hora))) => format!(
+                    "{} {}",
+                    fecha.format(date_pattern(locale)),
+                    hora.format("%H:%M:%S")
+                ),
+                None => s.clone(),
+            },
+            "entero" | "decimal" => localize_number(s.trim(), locale),
+            _ => s.clone(),
+        },
+        Some(otro) => otro.to_string(),
+    }
+}
+
+/// Número de serie de Excel (días desde 1899-12-30 más la fracción del día).
+fn excel_serial(fecha: NaiveDate, hora: Option<NaiveTime>) -> f64 {
+    let base = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default();
+    let dias = (fecha - base).num_days() as f64;
+    dias + hora.map(|h| h.num_seconds_from_midnight() as f64 / 86_400.0).unwrap_or(0.0)
+}
+
+struct XlsxFormats {
+    cabecera: Format,
+    entero: Format,
+    decimal: Format,
+    fecha: Format,
+    fecha_hora: Format,
+}
+
+struct XlsxState {
+    workbook: Workbook,
+    hojas: usize,
+    hoja: usize,
+    fila: u32,
+    formatos: XlsxFormats,
+}
+
+/// Destino de las filas de una exportación; ver el comentario del módulo.
+pub struct ExportWriter {
+    sender: mpsc::Sender<ExportChunk>,
+    opciones: ExportOptions,
+    buffer: Vec<u8>,
+    xlsx: Option<XlsxState>,
+    filas: u64,
+}
+
+impl ExportWriter {
+    fn new(opciones: ExportOptions, sender: mpsc::Sender<ExportChunk>) -> Result<Self, String> {
+        let mut writer = ExportWriter {
+            sender,
+            opciones,
+            buffer: Vec::with_capacity(BLOQUE_BYTES),
+            xlsx: None,
+            filas: 0,
+        };
+        match writer.opciones.formato {
+            ExportFormat::Csv => {
+                // BOM para que Excel reconozca UTF-8
+                writer.buffer.extend_from_slice("\u{feff}".as_bytes());
+                let titulos: Vec<String> = writer.opciones.columnas.iter().map(|c| c.titulo.clone()).collect();
+                let linea = writer.csv_line(&titulos)?;
+                writer.buffer.extend_from_slice(&linea);
+            }
+            ExportFormat::Xlsx => {
+                let (fecha, fecha_hora) = match writer.opciones.locale {
+                    ExportLocale::Es => ("dd/mm/yyyy", "dd/mm/yyyy hh:mm:ss"),
+                    ExportLocale::En => ("mm/dd/yyyy", "mm/dd/yyyy hh:mm:ss"),
+                };
+                writer.xlsx = Some(XlsxState {
+                    workbook: Workbook::new(),
+                    hojas: 0,
+                    hoja: 0,
+                    fila: 0,
+                    formatos: XlsxFormats {
+                        cabecera: Format::new().set_bold(),
+                        entero: Format::new().set_num_format("#,##0"),
+                        decimal: Format::new().set_num_format("#,##0.00"),
+                        fecha: Format::new().set_num_format(fecha),
+                        fecha_hora: Format::new().set_num_format(fecha_hora),
+                    },
+                });
+                writer.add_sheet()?;
+            }
+            ExportFormat::Ndjson => {}
+        }
+        Ok(writer)
+    }
+
+    fn csv_line(&self, valores: &[String]) -> Result<Vec<u8>, String> {
+        let delimitador = match self.opciones.locale {
+            ExportLocale::Es => b';',
+            ExportLocale::En => b',',
+        };
+        let mut csv_writer = csv::WriterBuilder::new().delimiter(delimitador).from_writer(Vec::new());
+        csv_writer.write_record(valores).map_err(|e| format!("Error al escribir el CSV: {}", e))?;
+        csv_writer.into_inner().map_err(|e| format!("Error al escribir el CSV: {}", e))
+    }
+
+    /// Agrega una hoja con la fila de encabezados.
+    fn add_sheet(&mut self) -> Result<(), String> {
+        let columnas = &self.opciones.columnas;
+        let xlsx = self.xlsx.as_mut().ok_or("Libro XLSX no inicializado")?;
+        let worksheet = xlsx.workbook.add_worksheet();
+        for (col, columna) in columnas.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, col as u16, &columna.titulo, &xlsx.formatos.cabecera)
+                .map_err(|e| format!("Error al escribir el XLSX: {}", e))?;
+        }
+        xlsx.hoja = xlsx.hojas;
+        xlsx.hojas += 1;
+        xlsx.fila = 1;
+        Ok(())
+    }
+
+    fn write_xlsx_row(&mut self, fila: &Map<String, Value>) -> Result<(), String> {
+        if self.xlsx.as_ref().map(|x| x.fila >= XLSX_FILAS_POR_HOJA).unwrap_or(false) {
+            self.add_sheet()?;
+        }
+        let columnas = &self.opciones.columnas;
+        let xlsx = self.xlsx.as_mut().ok_or("Libro XLSX no inicializado")?;
+        let numero_fila = xlsx.fila;
+        let formatos = &xlsx.formatos;
+        let worksheet = xlsx
+            .workbook
+            .worksheet_from_index(xlsx.hoja)
+            .map_err(|e| format!("Error al escribir el XLSX: {}", e))?;
+        for (col, columna) in columnas.iter().enumerate() {
+            let col = col as u16;
+            let resultado = match fila.get(&columna.nombre) {
+                None | Some(Value::Null) => continue,
+                Some(Value::Bool(b)) => worksheet.write_boolean(numero_fila, col, *b),
+                Some(Value::Number(n)) => {
+                    let formato = if n.is_f64() { &formatos.decimal } else { &formatos.entero };
+                    worksheet.write_number_with_format(numero_fila, col, n.as_f64().unwrap_or_default(), formato)
+                }
+                Some(Value::String(s)) => match (columna.tipo.as_str(), parse_date(s), s.trim().parse::<f64>()) {
+                    ("fecha", Some((fecha, None)), _) => {
+                        worksheet.write_number_with_format(numero_fila, col, excel_serial(fecha, None), &formatos.fecha)
+                    }
+                    ("fecha", Some((fecha, hora)), _) => {
+                        worksheet.write_number_with_format(numero_fila, col, excel_serial(fecha, hora), &formatos.fecha_hora)
+                    }
+                    ("entero", _, Ok(n)) => worksheet.write_number_with_format(numero_fila, col, n, &formatos.entero),
+                    ("decimal", _, Ok(n)) => worksheet.write_number_with_format(numero_fila, col, n, &formatos.decimal),
+                    _ => worksheet.write_string(numero_fila, col, s),
+                },
+                Some(otro) => worksheet.write_string(numero_fila, col, otro.to_string()),
+            };
+            resultado.map_err(|e| format!("Error al escribir el XLSX: {}", e))?;
+        }
+        xlsx.fila += 1;
+        Ok(())
+    }
+
+    /// Escribe una fila; las columnas que no estén en la fila quedan vacías.
+    pub async fn write_row(&mut self, fila: &Map<String, Value>) -> Result<(), String> {
+        match self.opciones.formato {
+            ExportFormat::Csv => {
+                let valores: Vec<String> = self
+                    .opciones
+                    .columnas
+                    .iter()
+                    .map(|c| format_value(fila.get(&c.nombre), &c.tipo, self.opciones.locale))
+                    .collect();
+                let linea = self.csv_line(&valores)?;
+                self.buffer.extend_from_slice(&linea);
+            }
+            ExportFormat::Ndjson => {
+                let objeto: Map<String, Value> = self
+                    .opciones
+                    .columnas
+                    .iter()
+                    .map(|c| (c.nombre.clone(), fila.get(&c.nombre).cloned().unwrap_or(Value::Null)))
+                    .collect();
+                serde_json::to_writer(&mut self.buffer, &objeto)
+                    .map_err(|e| format!("Error al escribir el NDJSON: {}", e))?;
+                self.buffer.push(b'\n');
+            }
+            ExportFormat::Xlsx => self.write_xlsx_row(fila)?,
+        }
+        self.filas += 1;
+        if self.buffer.len() >= BLOQUE_BYTES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Envía lo acumulado; espera si el canal está lleno.
+    async fn flush(&mut self) -> Result<(), String> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let bloque = Bytes::from(std::mem::replace(&mut self.buffer, Vec::with_capacity(BLOQUE_BYTES)));
+        self.sender
+            .send(Ok(bloque))
+            .await
+            .map_err(|_| "El cliente cerró la descarga".to_string())
+    }
+
+    /// Cierra el archivo (serializa el XLSX) y envía lo pendiente. Devuelve las filas escritas.
+    async fn finish(mut self) -> Result<u64, String> {
+        if let Some(mut xlsx) = self.xlsx.take() {
+            let contenido = xlsx
+                .workbook
+                .save_to_buffer()
+                .map_err(|e| format!("Error al generar el XLSX: {}", e))?;
+            for parte in contenido.chunks(BLOQUE_BYTES) {
+                self.buffer.extend_from_slice(parte);
+                self.flush().await?;
+            }
+        }
+        self.flush().await?;
+        Ok(self.filas)
+    }
+}
+
+/// Lanza el productor en una tarea y devuelve el stream de bytes del archivo.
+/// El productor recibe el writer, escribe las filas y lo devuelve para cerrarlo.
+pub fn spawn_export<F, Fut>(opciones: ExportOptions, producir: F) -> impl Stream<Item = ExportChunk>
+where
+    F: FnOnce(ExportWriter) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<ExportWriter, String>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(CANAL_BLOQUES);
+    let mut sender_error = sender.clone();
+    let formato = opciones.formato;
+    tokio::spawn(async move {
+        let resultado = match ExportWriter::new(opciones, sender) {
+            Ok(writer) => match producir(writer).await {
+                Ok(writer) => writer.finish().await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        match resultado {
+            Ok(filas) => println!("[EXPORT] {} filas exportadas en {}", filas, formato.extension()),
+            Err(e) => {
+                eprintln!("[EXPORT] Exportación interrumpida: {}", e);
+                let _ = sender_error.send(Err(io::Error::new(io::ErrorKind::Other, e))).await;
+            }
+        }
+    });
+    receiver
+}
+
+/// Respuesta de descarga: `{nombre}_{AAAAMMDD_HHMMSS}.{ext}` con el stream como cuerpo.
+pub fn streaming_response<S>(nombre: &str, formato: ExportFormat, stream: S) -> HttpResponse
+where
+    S: Stream<Item = ExportChunk> + 'static,
+{
+    let nombre: String = nombre
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    HttpResponse::Ok()
+        .content_type(formato.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "{}_{}.{}",
+                nombre,
+                chrono::Local::now().format("%Y%m%d_%H%M%S"),
+                formato.extension()
+            ))],
+        })
+        .streaming(stream)
+}
@@ -0,0 +1,87 @@
+// src/shared/export_models.rs
+// Modelos de la exportación en streaming (CSV, XLSX, NDJSON).
+
+use serde::{Deserialize, Serialize};
+
+use crate::sql_sandbox_models::SandboxColumn;
+
+/// Formato del archivo exportado.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+    /// Un objeto JSON por línea.
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+/// Formato regional de números, fechas y booleanos. NDJSON no se localiza.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportLocale {
+    /// `1.234,56`, `31/12/2025`, `Sí`; CSV separado por `;`.
+    #[default]
+    Es,
+    /// `1,234.56`, `12/31/2025`, `Yes`; CSV separado por `,`.
+    En,
+}
+
+/// Parámetros de exportación recibidos en la query string.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    /// Columnas a exportar, separadas por coma y en el orden deseado. Vacío = todas.
+    pub columns: Option<String>,
+    pub locale: Option<ExportLocale>,
+}
+
+/// Columna exportable.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportColumn {
+    /// Clave en la fila.
+    pub nombre: String,
+    /// Encabezado en el archivo.
+    pub titulo: String,
+    /// texto, entero, decimal, fecha o booleano; define el formato regional.
+    pub tipo: String,
+}
+
+impl ExportColumn {
+    pub fn new(nombre: &str, tipo: &str) -> Self {
+        ExportColumn {
+            nombre: nombre.to_string(),
+            titulo: nombre.to_string(),
+            tipo: tipo.to_string(),
+        }
+    }
+}
+
+impl From<SandboxColumn> for ExportColumn {
+    fn from(columna: SandboxColumn) -> Self {
+        ExportColumn {
+            titulo: columna.nombre.clone(),
+            nombre: columna.nombre,
+            tipo: columna.tipo,
+        }
+    }
+}
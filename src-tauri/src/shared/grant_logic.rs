@@ -0,0 +1,355 @@
+// src/shared/grant_logic.rs
+/*
+Concesiones temporales de permisos o roles, y delegación entre usuarios.
+
+Una concesión otorga un código de permiso o un rol (sus permisos en
+riy_SeguridadRolPermiso) durante [validoDesde, validoHasta). No hay proceso de
+vencimiento: utils::get_permissions_by_app solo suma las concesiones vigentes al
+emitir el token, así que una concesión vencida o revocada desaparece en el siguiente
+login.
+
+La delegación (cobertura de vacaciones) la crea el propio usuario sobre un permiso o
+rol que tiene; no se pueden delegar permisos de seguridad.
+
+CREATE TABLE riy.riy_permiso_temporal (
+    concesionID      INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID     INT           NOT NULL,
+    usuarioID        INT           NOT NULL,
+    codigoPermiso    VARCHAR(50)   NULL,
+    rolID            INT           NULL,
+    validoDesde      DATETIME      NOT NULL,
+    validoHasta      DATETIME      NOT NULL,
+    delegadoPorID    INT           NULL,
+    motivo           NVARCHAR(500) NOT NULL,
+    autor            VARCHAR(50)   NOT NULL,
+    fechaCreacion    DATETIME      NOT NULL DEFAULT GETDATE(),
+    revocadoPor      VARCHAR(50)   NULL,
+    fechaRevocacion  DATETIME      NULL,
+    CONSTRAINT CK_riy_permiso_temporal_objeto
+        CHECK ((codigoPermiso IS NULL AND rolID IS NOT NULL) OR (codigoPermiso IS NOT NULL AND rolID IS NULL))
+);
+CREATE INDEX IX_riy_permiso_temporal_usuario ON riy.riy_permiso_temporal (usuarioID, aplicativoID, validoHasta);
+*/
+
+use std::fmt;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use sqlx::{Mssql, Pool};
+
+use crate::grant_models::{TemporaryGrant, TemporaryGrantQuery, TemporaryGrantRequest};
+use crate::middleware::auth_claims::PERMISOS_SEGURIDAD;
+use crate::{role_logic, utils};
+
+/// Permiso requerido para otorgar, listar y revocar cualquier concesión.
+pub const PERMISO_CONCESIONES: &str = "administrar_permisos_temporales";
+
+/// Duración máxima de una delegación entre usuarios.
+const MAX_DELEGATION_DAYS: i64 = 90;
+
+#[derive(Debug)]
+pub enum GrantError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Database(String),
+}
+
+impl fmt::Display for GrantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrantError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            GrantError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            GrantError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            GrantError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GrantError {}
+
+fn db_error(e: sqlx::Error) -> GrantError {
+    GrantError::Database(e.to_string())
+}
+
+/// Condición SQL de concesión vigente sobre el alias `g`.
+const VIGENTE: &str = "g.fechaRevocacion IS NULL AND g.validoDesde <= GETDATE() AND g.validoHasta > GETDATE()";
+
+fn parse_datetime(value: &str, field: &str) -> Result<NaiveDateTime, GrantError> {
+    let value = value.trim();
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| GrantError::Validation(format!("Fecha inválida en '{}': '{}'", field, value)))
+}
+
+/// Permisos que aportan las concesiones vigentes del usuario (directas o por rol).
+pub async fn active_grant_permissions(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    aplicativo_id: i32,
+) -> Result<Vec<String>, GrantError> {
+    let sql_query = format!(
+        "SELECT g.codigoPermiso
+           FROM riy.riy_permiso_temporal g WITH(NOLOCK)
+          WHERE g.usuarioID = @p1 AND g.aplicativoID = @p2 AND g.codigoPermiso IS NOT NULL AND {0}
+         UNION
+         SELECT rp.codigoPermiso
+           FROM riy.riy_permiso_temporal g WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRolPermiso rp WITH(NOLOCK) ON rp.rolID = g.rolID
+          WHERE g.usuarioID = @p1 AND g.aplicativoID = @p2 AND g.rolID IS NOT NULL AND {0}",
+        VIGENTE
+    );
+    let rows: Vec<(String,)> = sqlx::query_as(&sql_query)
+        .bind(usuario_id)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows.into_iter().map(|(codigo,)| codigo).collect())
+}
+
+fn grant_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT g.concesionID as concesion_id,
+                g.aplicativoID as aplicativo_id,
+                g.usuarioID as usuario_id,
+                u.usuario {0} as usuario,
+                g.codigoPermiso {0} as codigo_permiso,
+                g.rolID as rol_id,
+                r.rol {0} as rol,
+                CONVERT(VARCHAR(19), g.validoDesde, 120) as valido_desde,
+                CONVERT(VARCHAR(19), g.validoHasta, 120) as valido_hasta,
+                g.delegadoPorID as delegado_por_id,
+                d.usuario {0} as delegado_por,
+                g.motivo,
+                g.autor {0} as autor,
+                CONVERT(VARCHAR(19), g.fechaCreacion, 120) as fecha_creacion,
+                g.revocadoPor {0} as revocado_por,
+                CONVERT(VARCHAR(19), g.fechaRevocacion, 120) as fecha_revocacion,
+                CASE WHEN g.fechaRevocacion IS NOT NULL THEN 'Revocada'
+                     WHEN g.validoHasta <= GETDATE() THEN 'Vencida'
+                     WHEN g.validoDesde > GETDATE() THEN 'Programada'
+                     ELSE 'Vigente' END as estado
+           FROM riy.riy_permiso_temporal g WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = g.usuarioID
+           LEFT JOIN riy.riy_usuario d WITH(NOLOCK) ON d.usuario_id = g.delegadoPorID
+           LEFT JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = g.rolID",
+        sql_collate_clause
+    )
+}
+
+pub async fn get_grant(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    concesion_id: i32,
+    sql_collate_clause: &str,
+) -> Result<TemporaryGrant, GrantError> {
+    let sql_query = format!(
+        "{} WHERE g.concesionID = @p1 AND g.aplicativoID = @p2",
+        grant_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, TemporaryGrant>(&sql_query)
+        .bind(concesion_id)
+        .bind(aplicativo_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| GrantError::NotFound(format!("Concesión {} no encontrada", concesion_id)))
+}
+
+/// Vista de administración. Sin `incluir_historial` solo devuelve programadas y vigentes.
+pub async fn list_grants(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    query: &TemporaryGrantQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<TemporaryGrant>, GrantError> {
+    let historial = if query.incluir_historial {
+        ""
+    } else {
+        "AND g.fechaRevocacion IS NULL AND g.validoHasta > GETDATE()"
+    };
+    let sql_query = format!(
+        "{} WHERE g.aplicativoID = @p1 AND (@p2 IS NULL OR g.usuarioID = @p2) {}
+          ORDER BY g.validoDesde DESC, g.concesionID DESC",
+        grant_select(sql_collate_clause),
+        historial
+    );
+    sqlx::query_as::<_, TemporaryGrant>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(query.usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Concesiones recibidas por el usuario y las que él delegó.
+pub async fn list_user_grants(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<TemporaryGrant>, GrantError> {
+    let sql_query = format!(
+        "{} WHERE g.aplicativoID = @p1 AND (g.usuarioID = @p2 OR g.delegadoPorID = @p2)
+          ORDER BY g.validoDesde DESC, g.concesionID DESC",
+        grant_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, TemporaryGrant>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Crea una concesión. `es_administrador` indica que quien la crea tiene
+/// PERMISO_CONCESIONES; sin él solo puede delegar lo que él mismo tiene.
+pub async fn create_grant(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    request: &TemporaryGrantRequest,
+    autor: &str,
+    autor_id: i32,
+    es_administrador: bool,
+    sql_collate_clause: &str,
+) -> Result<TemporaryGrant, GrantError> {
+    let codigo_permiso = request
+        .codigo_permiso
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    match (codigo_permiso, request.rol_id) {
+        (Some(_), None) | (None, Some(_)) => {}
+        _ => {
+            return Err(GrantError::Validation(
+                "Indique un código de permiso o un rol (solo uno)".to_string(),
+            ))
+        }
+    }
+    if request.motivo.trim().is_empty() {
+        return Err(GrantError::Validation("El motivo es obligatorio".to_string()));
+    }
+
+    let desde = match request.valido_desde.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(v) => parse_datetime(v, "validoDesde")?,
+        None => Local::now().naive_local(),
+    };
+    let hasta = parse_datetime(&request.valido_hasta, "validoHasta")?;
+    if hasta <= desde {
+        return Err(GrantError::Validation("validoHasta debe ser posterior a validoDesde".to_string()));
+    }
+    if hasta <= Local::now().naive_local() {
+        return Err(GrantError::Validation("validoHasta ya pasó".to_string()));
+    }
+
+    // Verifica que el rol pertenezca al aplicativo
+    if let Some(rol_id) = request.rol_id {
+        let roles = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+            .await
+            .map_err(GrantError::Database)?;
+        if !roles.iter().any(|r| r.rol_id == rol_id) {
+            return Err(GrantError::NotFound(format!("El rol {} no existe en el aplicativo", rol_id)));
+        }
+    }
+
+    let delegado_por_id = if request.delegar {
+        if request.usuario_id == autor_id {
+            return Err(GrantError::Validation("No puede delegarse a sí mismo".to_string()));
+        }
+        if hasta - desde > Duration::days(MAX_DELEGATION_DAYS) {
+            return Err(GrantError::Validation(format!(
+                "Una delegación no puede superar {} días", MAX_DELEGATION_DAYS
+            )));
+        }
+        if let Some(codigo) = codigo_permiso {
+            if PERMISOS_SEGURIDAD.contains(&codigo) {
+                return Err(GrantError::Forbidden(format!("El permiso '{}' no se puede delegar", codigo)));
+            }
+            let propios = utils::get_permissions_by_app(pool, autor_id, aplicativo_id)
+                .await
+                .map_err(|e| GrantError::Database(e.to_string()))?;
+            if !propios.iter().any(|p| p == codigo) {
+                return Err(GrantError::Forbidden(format!("No tiene el permiso '{}' para delegarlo", codigo)));
+            }
+        }
+        if let Some(rol_id) = request.rol_id {
+            let (tiene_rol,): (i32,) = sqlx::query_as(
+                "SELECT COUNT(*) FROM riy.riy_SeguridadRolUsuario WITH(NOLOCK) WHERE rolID = @p1 AND usuarioID = @p2"
+            )
+            .bind(rol_id)
+            .bind(autor_id)
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?;
+            if tiene_rol == 0 {
+                return Err(GrantError::Forbidden(format!("No tiene el rol {} para delegarlo", rol_id)));
+            }
+        }
+        Some(autor_id)
+    } else {
+        if !es_administrador {
+            return Err(GrantError::Forbidden(
+                "Solo puede delegar sus propios permisos o roles".to_string(),
+            ));
+        }
+        None
+    };
+
+    let (concesion_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_permiso_temporal
+            (aplicativoID, usuarioID, codigoPermiso, rolID, validoDesde, validoHasta, delegadoPorID, motivo, autor, fechaCreacion)
+         OUTPUT INSERTED.concesionID
+         VALUES (@p1, @p2, @p3, @p4, CONVERT(DATETIME, @p5, 120), CONVERT(DATETIME, @p6, 120), @p7, @p8, @p9, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(request.usuario_id)
+    .bind(codigo_permiso)
+    .bind(request.rol_id)
+    // sqlx no codifica chrono para MSSQL: las fechas viajan como texto ODBC canónico
+    .bind(desde.format("%Y-%m-%d %H:%M:%S").to_string())
+    .bind(hasta.format("%Y-%m-%d %H:%M:%S").to_string())
+    .bind(delegado_por_id)
+    .bind(request.motivo.trim())
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    get_grant(pool, aplicativo_id, concesion_id, sql_collate_clause).await
+}
+
+/// Revoca una concesión; pueden hacerlo un administrador o quien la delegó.
+pub async fn revoke_grant(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    concesion_id: i32,
+    revocado_por: &str,
+    revocado_por_id: i32,
+    es_administrador: bool,
+    sql_collate_clause: &str,
+) -> Result<TemporaryGrant, GrantError> {
+    let grant = get_grant(pool, aplicativo_id, concesion_id, sql_collate_clause).await?;
+    if !es_administrador && grant.delegado_por_id != Some(revocado_por_id) {
+        return Err(GrantError::Forbidden("Solo puede revocar las concesiones que delegó".to_string()));
+    }
+    if grant.fecha_revocacion.is_some() {
+        return Ok(grant);
+    }
+    sqlx::query(
+        "UPDATE riy.riy_permiso_temporal
+            SET revocadoPor = @p1, fechaRevocacion = GETDATE()
+          WHERE concesionID = @p2 AND fechaRevocacion IS NULL"
+    )
+    .bind(revocado_por)
+    .bind(concesion_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    get_grant(pool, aplicativo_id, concesion_id, sql_collate_clause).await
+}
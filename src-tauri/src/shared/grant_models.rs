@@ -0,0 +1,55 @@
+// src/shared/grant_models.rs
+// Modelos de las concesiones temporales de permisos y roles.
+
+use serde::{Deserialize, Serialize};
+
+/// Concesión de un permiso o un rol con vigencia (`riy.riy_permiso_temporal`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporaryGrant {
+    pub concesion_id: i32,
+    pub aplicativo_id: i32,
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub codigo_permiso: Option<String>,
+    pub rol_id: Option<i32>,
+    pub rol: Option<String>,
+    pub valido_desde: String,
+    pub valido_hasta: String,
+    /// Usuario que delegó sus propios permisos (cobertura de vacaciones); `None` si la
+    /// otorgó un administrador.
+    pub delegado_por_id: Option<i32>,
+    pub delegado_por: Option<String>,
+    pub motivo: String,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub revocado_por: Option<String>,
+    pub fecha_revocacion: Option<String>,
+    /// `Programada`, `Vigente`, `Vencida` o `Revocada`.
+    pub estado: String,
+}
+
+/// Alta de una concesión: exactamente uno de `codigo_permiso` o `rol_id`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporaryGrantRequest {
+    pub usuario_id: i32,
+    pub codigo_permiso: Option<String>,
+    pub rol_id: Option<i32>,
+    /// `YYYY-MM-DD HH:MM:SS` (o ISO 8601); por defecto, ahora.
+    pub valido_desde: Option<String>,
+    pub valido_hasta: String,
+    pub motivo: String,
+    /// Si es `true`, quien la crea delega un permiso o rol que él mismo tiene.
+    #[serde(default)]
+    pub delegar: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporaryGrantQuery {
+    pub usuario_id: Option<i32>,
+    /// Incluye concesiones vencidas y revocadas.
+    #[serde(default)]
+    pub incluir_historial: bool,
+}
@@ -0,0 +1,896 @@
+// src/shared/help_logic.rs
+/*
+Ayudas contextuales (menú "ADMINISTRAR AYUDAS").
+
+Cada artículo corresponde a una pantalla (ruta del menú) en un idioma y tiene versiones en
+Markdown: se editan como borrador y solo una queda publicada; al publicar otra, la anterior
+pasa a archivada. La pantalla pide su ayuda con `GET /help?ruta=...`: si no hay artículo para
+la ruta exacta se usa el de la ruta padre más cercana, y si no está en el idioma pedido se
+usa el idioma base, luego el idioma por defecto y por último cualquiera.
+
+Los adjuntos se guardan en disco (HELP_ATTACHMENTS_DIR, como `{ayudaID}/{adjuntoID}`) o, sin
+esa variable, en la base de datos en Base64.
+
+CREATE TABLE riy.riy_ayuda (
+    ayudaID            INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID       INT          NOT NULL,
+    menuID             INT          NULL,
+    ruta               VARCHAR(256) NOT NULL,
+    idioma             VARCHAR(10)  NOT NULL,
+    autor              VARCHAR(50)  NOT NULL,
+    fechaCreacion      DATETIME     NOT NULL DEFAULT GETDATE(),
+    modificadoPor      VARCHAR(50)  NULL,
+    fechaModificacion  DATETIME     NULL,
+    CONSTRAINT UQ_riy_ayuda UNIQUE (aplicativoID, ruta, idioma)
+);
+
+CREATE TABLE riy.riy_ayuda_version (
+    ayudaID           INT           NOT NULL,
+    numero            INT           NOT NULL,
+    titulo            NVARCHAR(200) NOT NULL,
+    contenido         NVARCHAR(MAX) NOT NULL,
+    estado            VARCHAR(20)   NOT NULL DEFAULT 'borrador', -- borrador | publicado | archivado
+    autor             VARCHAR(50)   NOT NULL,
+    fechaCreacion     DATETIME      NOT NULL DEFAULT GETDATE(),
+    publicadoPor      VARCHAR(50)   NULL,
+    fechaPublicacion  DATETIME      NULL,
+    CONSTRAINT PK_riy_ayuda_version PRIMARY KEY (ayudaID, numero)
+);
+
+CREATE TABLE riy.riy_ayuda_adjunto (
+    adjuntoID        INT IDENTITY(1,1) PRIMARY KEY,
+    ayudaID          INT           NOT NULL,
+    nombreArchivo    NVARCHAR(255) NOT NULL,
+    tipoContenido    VARCHAR(100)  NOT NULL,
+    tamano           BIGINT        NOT NULL,
+    rutaArchivo      VARCHAR(300)  NULL,     -- en disco, relativa a HELP_ATTACHMENTS_DIR
+    contenidoBase64  VARCHAR(MAX)  NULL,     -- en la base de datos
+    autor            VARCHAR(50)   NOT NULL,
+    fechaCreacion    DATETIME      NOT NULL DEFAULT GETDATE()
+);
+*/
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use sqlx::{Mssql, Pool};
+
+use crate::config::HelpConfig;
+use crate::help_models::{
+    HelpArticle, HelpArticleDetail, HelpArticleInput, HelpArticleUpdate, HelpAttachment,
+    HelpAttachmentUpload, HelpVersion, HelpVersionInput, HelpVersionState, PublishedHelp,
+};
+
+/// Administrar artículos, versiones y adjuntos.
+pub const PERMISO_ADMINISTRAR_AYUDAS: &str = "administrar_ayudas";
+
+/// Idioma de los artículos sin idioma explícito y último recurso de la búsqueda.
+pub const DEFAULT_IDIOMA: &str = "es";
+
+const MAX_TITULO: usize = 200;
+const MAX_RUTA: usize = 256;
+
+#[derive(Debug)]
+pub enum HelpError {
+    Validation(String),
+    NotFound(String),
+    Conflict(String),
+    Storage(String),
+    Database(String),
+}
+
+impl fmt::Display for HelpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HelpError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            HelpError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            HelpError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            HelpError::Storage(msg) => write!(f, "Error de almacenamiento: {}", msg),
+            HelpError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for HelpError {}
+
+fn db_error(e: sqlx::Error) -> HelpError {
+    HelpError::Database(e.to_string())
+}
+
+/// `/Usuarios/Lista/?x=1` -> `/Usuarios/Lista`.
+pub fn normalize_ruta(ruta: &str) -> Result<String, HelpError> {
+    let sin_query = ruta.split(['?', '#']).next().unwrap_or_default().trim();
+    let sin_barra = sin_query.trim_end_matches('/');
+    let normalizada = if sin_barra.starts_with('/') {
+        sin_barra.to_string()
+    } else {
+        format!("/{}", sin_barra)
+    };
+    if normalizada.len() > MAX_RUTA || normalizada.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(HelpError::Validation(format!("Ruta inválida: '{}'", ruta)));
+    }
+    Ok(normalizada)
+}
+
+/// `es`, `en`, `es-AR` -> en minúsculas; vacío = idioma por defecto.
+pub fn normalize_idioma(idioma: Option<&str>) -> Result<String, HelpError> {
+    let idioma = idioma.map(str::trim).filter(|i| !i.is_empty()).unwrap_or(DEFAULT_IDIOMA).to_ascii_lowercase();
+    let valido = match idioma.split_once('-') {
+        Some((base, region)) => base.len() == 2 && region.len() == 2,
+        None => idioma.len() == 2,
+    } && idioma.chars().all(|c| c.is_ascii_lowercase() || c == '-');
+    if !valido {
+        return Err(HelpError::Validation(format!("Idioma inválido: '{}'", idioma)));
+    }
+    Ok(idioma)
+}
+
+/// La ruta y sus padres, del más específico al más general (`/a/b/c`, `/a/b`, `/a`).
+fn ruta_candidates(ruta: &str) -> Vec<String> {
+    let mut candidatas = Vec::new();
+    let mut actual = ruta.to_string();
+    while !actual.is_empty() && actual != "/" {
+        candidatas.push(actual.clone());
+        actual = match actual.rfind('/') {
+            Some(pos) => actual[..pos].to_string(),
+            None => String::new(),
+        };
+    }
+    if candidatas.is_empty() {
+        candidatas.push("/".to_string());
+    }
+    candidatas
+}
+
+/// Preferencia de idioma: exacto, idioma base, por defecto, cualquiera.
+fn idioma_rank(disponible: &str, pedido: &str) -> u8 {
+    let base = pedido.split('-').next().unwrap_or(pedido);
+    if disponible.eq_ignore_ascii_case(pedido) {
+        0
+    } else if disponible.eq_ignore_ascii_case(base) {
+        1
+    } else if disponible.eq_ignore_ascii_case(DEFAULT_IDIOMA) {
+        2
+    } else {
+        3
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Consulta desde la pantalla
+// ---------------------------------------------------------------------------
+
+/// Ayuda publicada para la pantalla actual.
+pub async fn find_published(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ruta: &str,
+    idioma: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<PublishedHelp, HelpError> {
+    let ruta = normalize_ruta(ruta)?;
+    let idioma = normalize_idioma(idioma)?;
+    let candidatas = ruta_candidates(&ruta);
+
+    let marcadores: Vec<String> = (0..candidatas.len()).map(|i| format!("@p{}", i + 2)).collect();
+    let sql_query = format!(
+        "SELECT a.ayudaID, a.ruta {0}, a.idioma {0}
+           FROM riy.riy_ayuda a WITH(NOLOCK)
+          WHERE a.aplicativoID = @p1
+            AND a.ruta {0} IN ({1})
+            AND EXISTS (SELECT 1 FROM riy.riy_ayuda_version v WITH(NOLOCK)
+                         WHERE v.ayudaID = a.ayudaID AND v.estado = 'publicado')",
+        sql_collate_clause,
+        marcadores.join(", ")
+    );
+    let mut query = sqlx::query_as::<_, (i32, String, String)>(&sql_query).bind(aplicativo_id);
+    for candidata in &candidatas {
+        query = query.bind(candidata);
+    }
+    let encontrados = query.fetch_all(pool).await.map_err(db_error)?;
+
+    let (ayuda_id, ruta_articulo, idioma_articulo) = encontrados
+        .into_iter()
+        .min_by_key(|(_, r, i)| {
+            let profundidad = candidatas.iter().position(|c| c.eq_ignore_ascii_case(r)).unwrap_or(usize::MAX);
+            (profundidad, idioma_rank(i, &idioma))
+        })
+        .ok_or_else(|| HelpError::NotFound(format!("No hay ayuda publicada para '{}'", ruta)))?;
+
+    let (version, titulo, contenido, fecha_publicacion): (i32, String, String, Option<String>) = sqlx::query_as(
+        "SELECT numero, titulo, contenido, CONVERT(VARCHAR(19), fechaPublicacion, 120)
+           FROM riy.riy_ayuda_version WITH(NOLOCK)
+          WHERE ayudaID = @p1 AND estado = 'publicado'"
+    )
+    .bind(ayuda_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(PublishedHelp {
+        ayuda_id,
+        ruta: ruta_articulo,
+        idioma: idioma_articulo,
+        version,
+        titulo,
+        contenido,
+        adjuntos: list_attachments(pool, ayuda_id, sql_collate_clause).await?,
+        fecha_publicacion,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Artículos
+// ---------------------------------------------------------------------------
+
+type ArticleRow = (i32, Option<i32>, String, String, Option<i32>, Option<i32>, Option<String>, String, String, Option<String>, Option<String>);
+
+fn article_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT a.ayudaID, a.menuID, a.ruta {0}, a.idioma {0}, p.numero, u.numero, u.titulo,
+                a.autor {0},
+                CONVERT(VARCHAR(19), a.fechaCreacion, 120),
+                a.modificadoPor {0},
+                CONVERT(VARCHAR(19), a.fechaModificacion, 120)
+           FROM riy.riy_ayuda a WITH(NOLOCK)
+          OUTER APPLY (SELECT TOP 1 v.numero, v.titulo FROM riy.riy_ayuda_version v WITH(NOLOCK)
+                        WHERE v.ayudaID = a.ayudaID ORDER BY v.numero DESC) u
+          OUTER APPLY (SELECT TOP 1 v.numero FROM riy.riy_ayuda_version v WITH(NOLOCK)
+                        WHERE v.ayudaID = a.ayudaID AND v.estado = 'publicado') p",
+        sql_collate_clause
+    )
+}
+
+fn to_article(row: ArticleRow) -> HelpArticle {
+    let (ayuda_id, menu_id, ruta, idioma, version_publicada, ultima_version, titulo, autor,
+         fecha_creacion, modificado_por, fecha_modificacion) = row;
+    HelpArticle {
+        ayuda_id,
+        menu_id,
+        ruta,
+        idioma,
+        version_publicada,
+        ultima_version: ultima_version.unwrap_or_default(),
+        titulo: titulo.unwrap_or_default(),
+        autor,
+        fecha_creacion,
+        modificado_por,
+        fecha_modificacion,
+    }
+}
+
+pub async fn list_articles(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<HelpArticle>, HelpError> {
+    let sql_query = format!("{} WHERE a.aplicativoID = @p1 ORDER BY a.ruta, a.idioma", article_select(sql_collate_clause));
+    let rows: Vec<ArticleRow> = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows.into_iter().map(to_article).collect())
+}
+
+async fn get_article(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    sql_collate_clause: &str,
+) -> Result<HelpArticle, HelpError> {
+    let sql_query = format!("{} WHERE a.aplicativoID = @p1 AND a.ayudaID = @p2", article_select(sql_collate_clause));
+    let row: ArticleRow = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(ayuda_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| HelpError::NotFound(format!("Ayuda {} no encontrada", ayuda_id)))?;
+    Ok(to_article(row))
+}
+
+pub async fn get_article_detail(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    let articulo = get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    let sql_query = format!(
+        "SELECT numero, titulo, contenido, estado {0}, autor {0},
+                CONVERT(VARCHAR(19), fechaCreacion, 120),
+                publicadoPor {0},
+                CONVERT(VARCHAR(19), fechaPublicacion, 120)
+           FROM riy.riy_ayuda_version WITH(NOLOCK)
+          WHERE ayudaID = @p1
+          ORDER BY numero DESC",
+        sql_collate_clause
+    );
+    let versiones = sqlx::query_as::<_, (i32, String, String, String, String, String, Option<String>, Option<String>)>(&sql_query)
+        .bind(ayuda_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|(numero, titulo, contenido, estado, autor, fecha_creacion, publicado_por, fecha_publicacion)| HelpVersion {
+            numero,
+            titulo,
+            contenido,
+            estado: HelpVersionState::parse(&estado),
+            autor,
+            fecha_creacion,
+            publicado_por,
+            fecha_publicacion,
+        })
+        .collect();
+    Ok(HelpArticleDetail {
+        articulo,
+        versiones,
+        adjuntos: list_attachments(pool, ayuda_id, sql_collate_clause).await?,
+    })
+}
+
+/// Ruta de la pantalla: la del ítem de menú si viene `menu_id`, si no la indicada.
+async fn resolve_ruta(
+    pool: &Pool<Mssql>,
+    menu_id: Option<i32>,
+    ruta: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<String, HelpError> {
+    if let Some(menu_id) = menu_id {
+        let sql_query = format!(
+            "SELECT ruta {} FROM riy.riy_SeguridadMenu WITH(NOLOCK) WHERE menuID = @p1",
+            sql_collate_clause
+        );
+        let (ruta_menu,): (String,) = sqlx::query_as(&sql_query)
+            .bind(menu_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(|| HelpError::NotFound(format!("Ítem de menú {} no encontrado", menu_id)))?;
+        return normalize_ruta(&ruta_menu);
+    }
+    match ruta.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(ruta) => normalize_ruta(ruta),
+        None => Err(HelpError::Validation("Indique el ítem de menú o la ruta".to_string())),
+    }
+}
+
+async fn ensure_unique(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ruta: &str,
+    idioma: &str,
+    excluir: Option<i32>,
+    sql_collate_clause: &str,
+) -> Result<(), HelpError> {
+    let sql_query = format!(
+        "SELECT COUNT(*) FROM riy.riy_ayuda WITH(NOLOCK)
+          WHERE aplicativoID = @p1 AND ruta {0} = @p2 AND idioma {0} = @p3 AND ayudaID <> @p4",
+        sql_collate_clause
+    );
+    let (existentes,): (i32,) = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(ruta)
+        .bind(idioma)
+        .bind(excluir.unwrap_or(0))
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if existentes > 0 {
+        return Err(HelpError::Conflict(format!("Ya existe una ayuda para '{}' en '{}'", ruta, idioma)));
+    }
+    Ok(())
+}
+
+fn validate_version(titulo: &str) -> Result<(), HelpError> {
+    let titulo = titulo.trim();
+    if titulo.is_empty() || titulo.chars().count() > MAX_TITULO {
+        return Err(HelpError::Validation(format!("El título es obligatorio (máximo {} caracteres)", MAX_TITULO)));
+    }
+    Ok(())
+}
+
+/// Crea el artículo con la versión 1 en borrador.
+pub async fn create_article(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &HelpArticleInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    validate_version(&input.titulo)?;
+    let ruta = resolve_ruta(pool, input.menu_id, input.ruta.as_deref(), sql_collate_clause).await?;
+    let idioma = normalize_idioma(input.idioma.as_deref())?;
+    ensure_unique(pool, aplicativo_id, &ruta, &idioma, None, sql_collate_clause).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let (ayuda_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_ayuda (aplicativoID, menuID, ruta, idioma, autor, fechaCreacion)
+         OUTPUT INSERTED.ayudaID
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(input.menu_id)
+    .bind(&ruta)
+    .bind(&idioma)
+    .bind(autor)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query(
+        "INSERT INTO riy.riy_ayuda_version (ayudaID, numero, titulo, contenido, estado, autor, fechaCreacion)
+         VALUES (@p1, 1, @p2, @p3, 'borrador', @p4, GETDATE())"
+    )
+    .bind(ayuda_id)
+    .bind(input.titulo.trim())
+    .bind(&input.contenido)
+    .bind(autor)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+/// Cambia la pantalla o el idioma del artículo.
+pub async fn update_article(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    input: &HelpArticleUpdate,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    let actual = get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    let (menu_id, ruta) = if input.menu_id.is_some() || input.ruta.is_some() {
+        (input.menu_id, resolve_ruta(pool, input.menu_id, input.ruta.as_deref(), sql_collate_clause).await?)
+    } else {
+        (actual.menu_id, actual.ruta)
+    };
+    let idioma = match input.idioma.as_deref() {
+        Some(idioma) => normalize_idioma(Some(idioma))?,
+        None => actual.idioma,
+    };
+    ensure_unique(pool, aplicativo_id, &ruta, &idioma, Some(ayuda_id), sql_collate_clause).await?;
+
+    sqlx::query(
+        "UPDATE riy.riy_ayuda
+            SET menuID = @p1, ruta = @p2, idioma = @p3, modificadoPor = @p4, fechaModificacion = GETDATE()
+          WHERE ayudaID = @p5"
+    )
+    .bind(menu_id)
+    .bind(&ruta)
+    .bind(&idioma)
+    .bind(autor)
+    .bind(ayuda_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+/// Elimina el artículo con sus versiones y adjuntos (también los archivos en disco).
+pub async fn delete_article(
+    pool: &Pool<Mssql>,
+    config: &HelpConfig,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(), HelpError> {
+    get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    let archivos: Vec<(Option<String>,)> = sqlx::query_as(
+        "SELECT rutaArchivo FROM riy.riy_ayuda_adjunto WITH(NOLOCK) WHERE ayudaID = @p1"
+    )
+    .bind(ayuda_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    for tabla in ["riy.riy_ayuda_adjunto", "riy.riy_ayuda_version", "riy.riy_ayuda"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE ayudaID = @p1", tabla))
+            .bind(ayuda_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    for ruta_archivo in archivos.into_iter().filter_map(|(r,)| r) {
+        remove_file(config, &ruta_archivo).await;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Versiones
+// ---------------------------------------------------------------------------
+
+async fn touch_article(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    ayuda_id: i32,
+    autor: &str,
+) -> Result<(), HelpError> {
+    sqlx::query("UPDATE riy.riy_ayuda SET modificadoPor = @p1, fechaModificacion = GETDATE() WHERE ayudaID = @p2")
+        .bind(autor)
+        .bind(ayuda_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+async fn version_state(
+    pool: &Pool<Mssql>,
+    ayuda_id: i32,
+    numero: i32,
+) -> Result<HelpVersionState, HelpError> {
+    let (estado,): (String,) = sqlx::query_as(
+        "SELECT estado FROM riy.riy_ayuda_version WITH(NOLOCK) WHERE ayudaID = @p1 AND numero = @p2"
+    )
+    .bind(ayuda_id)
+    .bind(numero)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| HelpError::NotFound(format!("Versión {} no encontrada", numero)))?;
+    Ok(HelpVersionState::parse(&estado))
+}
+
+/// Nueva versión en borrador, con el número siguiente a la última.
+pub async fn create_version(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    input: &HelpVersionInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    validate_version(&input.titulo)?;
+    let articulo = get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "INSERT INTO riy.riy_ayuda_version (ayudaID, numero, titulo, contenido, estado, autor, fechaCreacion)
+         VALUES (@p1, @p2, @p3, @p4, 'borrador', @p5, GETDATE())"
+    )
+    .bind(ayuda_id)
+    .bind(articulo.ultima_version + 1)
+    .bind(input.titulo.trim())
+    .bind(&input.contenido)
+    .bind(autor)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+/// Modifica un borrador; las versiones publicadas o archivadas no se editan.
+pub async fn update_version(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    numero: i32,
+    input: &HelpVersionInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    validate_version(&input.titulo)?;
+    get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    if version_state(pool, ayuda_id, numero).await? != HelpVersionState::Borrador {
+        return Err(HelpError::Conflict(format!(
+            "La versión {} ya fue publicada; cree una nueva versión", numero
+        )));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_ayuda_version SET titulo = @p1, contenido = @p2, autor = @p3
+          WHERE ayudaID = @p4 AND numero = @p5"
+    )
+    .bind(input.titulo.trim())
+    .bind(&input.contenido)
+    .bind(autor)
+    .bind(ayuda_id)
+    .bind(numero)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+/// Publica la versión (un borrador o una archivada, para volver atrás); la publicada
+/// anterior pasa a archivada.
+pub async fn publish_version(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    numero: i32,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    if version_state(pool, ayuda_id, numero).await? == HelpVersionState::Publicado {
+        return get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await;
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("UPDATE riy.riy_ayuda_version SET estado = 'archivado' WHERE ayudaID = @p1 AND estado = 'publicado'")
+        .bind(ayuda_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_ayuda_version
+            SET estado = 'publicado', publicadoPor = @p1, fechaPublicacion = GETDATE()
+          WHERE ayudaID = @p2 AND numero = @p3"
+    )
+    .bind(autor)
+    .bind(ayuda_id)
+    .bind(numero)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+/// Retira la versión publicada: la pantalla deja de mostrar el artículo.
+pub async fn unpublish_article(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpArticleDetail, HelpError> {
+    get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("UPDATE riy.riy_ayuda_version SET estado = 'archivado' WHERE ayudaID = @p1 AND estado = 'publicado'")
+        .bind(ayuda_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+    get_article_detail(pool, aplicativo_id, ayuda_id, sql_collate_clause).await
+}
+
+// ---------------------------------------------------------------------------
+// Adjuntos
+// ---------------------------------------------------------------------------
+
+async fn list_attachments(
+    pool: &Pool<Mssql>,
+    ayuda_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<HelpAttachment>, HelpError> {
+    let sql_query = format!(
+        "SELECT adjuntoID, nombreArchivo, tipoContenido {0}, tamano, autor {0},
+                CONVERT(VARCHAR(19), fechaCreacion, 120)
+           FROM riy.riy_ayuda_adjunto WITH(NOLOCK)
+          WHERE ayudaID = @p1
+          ORDER BY nombreArchivo",
+        sql_collate_clause
+    );
+    let adjuntos = sqlx::query_as::<_, (i32, String, String, i64, String, String)>(&sql_query)
+        .bind(ayuda_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|(adjunto_id, nombre_archivo, tipo_contenido, tamano, autor, fecha_creacion)| HelpAttachment {
+            adjunto_id,
+            nombre_archivo,
+            tipo_contenido,
+            tamano,
+            autor,
+            fecha_creacion,
+        })
+        .collect();
+    Ok(adjuntos)
+}
+
+/// Ruta absoluta de un adjunto en disco; rechaza rutas que salgan de la carpeta.
+fn attachment_path(config: &HelpConfig, ruta_archivo: &str) -> Result<PathBuf, HelpError> {
+    let relativa = Path::new(ruta_archivo);
+    if relativa.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        return Err(HelpError::Storage(format!("Ruta de adjunto inválida: '{}'", ruta_archivo)));
+    }
+    Ok(Path::new(&config.attachments_dir).join(relativa))
+}
+
+async fn remove_file(config: &HelpConfig, ruta_archivo: &str) {
+    if let Ok(path) = attachment_path(config, ruta_archivo) {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("No se pudo eliminar el adjunto {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+pub async fn add_attachment(
+    pool: &Pool<Mssql>,
+    config: &HelpConfig,
+    aplicativo_id: i32,
+    ayuda_id: i32,
+    upload: &HelpAttachmentUpload,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<HelpAttachment, HelpError> {
+    get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?;
+    // Solo el nombre, sin carpetas
+    let nombre_archivo = upload
+        .nombre_archivo
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if nombre_archivo.is_empty() || nombre_archivo.chars().count() > 255 {
+        return Err(HelpError::Validation("Nombre de archivo inválido".to_string()));
+    }
+    let tipo_contenido = upload
+        .tipo_contenido
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let bytes = general_purpose::STANDARD
+        .decode(upload.content_base64.trim())
+        .map_err(|e| HelpError::Validation(format!("El contenido no es Base64 válido: {}", e)))?;
+    if bytes.is_empty() || bytes.len() > config.max_attachment_bytes {
+        return Err(HelpError::Validation(format!(
+            "El adjunto debe tener entre 1 byte y {} MB", config.max_attachment_bytes / (1024 * 1024)
+        )));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let (adjunto_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_ayuda_adjunto (ayudaID, nombreArchivo, tipoContenido, tamano, autor, fechaCreacion)
+         OUTPUT INSERTED.adjuntoID
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE())"
+    )
+    .bind(ayuda_id)
+    .bind(&nombre_archivo)
+    .bind(&tipo_contenido)
+    .bind(bytes.len() as i64)
+    .bind(autor)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    if config.stores_on_disk() {
+        let ruta_archivo = format!("{}/{}", ayuda_id, adjunto_id);
+        let path = attachment_path(config, &ruta_archivo)?;
+        if let Some(carpeta) = path.parent() {
+            tokio::fs::create_dir_all(carpeta)
+                .await
+                .map_err(|e| HelpError::Storage(format!("No se pudo crear {}: {}", carpeta.display(), e)))?;
+        }
+        tokio::fs::write(&path, &bytes)
+            .await
+            .map_err(|e| HelpError::Storage(format!("No se pudo escribir {}: {}", path.display(), e)))?;
+        sqlx::query("UPDATE riy.riy_ayuda_adjunto SET rutaArchivo = @p1 WHERE adjuntoID = @p2")
+            .bind(&ruta_archivo)
+            .bind(adjunto_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    } else {
+        sqlx::query("UPDATE riy.riy_ayuda_adjunto SET contenidoBase64 = @p1 WHERE adjuntoID = @p2")
+            .bind(general_purpose::STANDARD.encode(&bytes))
+            .bind(adjunto_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    list_attachments(pool, ayuda_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .find(|a| a.adjunto_id == adjunto_id)
+        .ok_or_else(|| HelpError::NotFound(format!("Adjunto {} no encontrado", adjunto_id)))
+}
+
+type AttachmentRow = (i32, String, String, i64, String, String, Option<String>, Option<String>);
+
+async fn fetch_attachment(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    adjunto_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(i32, AttachmentRow), HelpError> {
+    let sql_query = format!(
+        "SELECT j.ayudaID, j.adjuntoID, j.nombreArchivo, j.tipoContenido {0}, j.tamano, j.autor {0},
+                CONVERT(VARCHAR(19), j.fechaCreacion, 120), j.rutaArchivo {0}, j.contenidoBase64
+           FROM riy.riy_ayuda_adjunto j WITH(NOLOCK)
+           JOIN riy.riy_ayuda a WITH(NOLOCK) ON a.ayudaID = j.ayudaID
+          WHERE a.aplicativoID = @p1 AND j.adjuntoID = @p2",
+        sql_collate_clause
+    );
+    let (ayuda_id, id, nombre, tipo, tamano, autor, fecha, ruta_archivo, contenido): (
+        i32, i32, String, String, i64, String, String, Option<String>, Option<String>,
+    ) = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(adjunto_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| HelpError::NotFound(format!("Adjunto {} no encontrado", adjunto_id)))?;
+    Ok((ayuda_id, (id, nombre, tipo, tamano, autor, fecha, ruta_archivo, contenido)))
+}
+
+/// Contenido de un adjunto. Sin `administrador`, solo de artículos publicados.
+pub async fn read_attachment(
+    pool: &Pool<Mssql>,
+    config: &HelpConfig,
+    aplicativo_id: i32,
+    adjunto_id: i32,
+    administrador: bool,
+    sql_collate_clause: &str,
+) -> Result<(HelpAttachment, Vec<u8>), HelpError> {
+    let (ayuda_id, (id, nombre_archivo, tipo_contenido, tamano, autor, fecha_creacion, ruta_archivo, contenido)) =
+        fetch_attachment(pool, aplicativo_id, adjunto_id, sql_collate_clause).await?;
+    if !administrador
+        && get_article(pool, aplicativo_id, ayuda_id, sql_collate_clause).await?.version_publicada.is_none()
+    {
+        return Err(HelpError::NotFound(format!("Adjunto {} no encontrado", adjunto_id)));
+    }
+
+    let bytes = match (ruta_archivo, contenido) {
+        (Some(ruta_archivo), _) => {
+            let path = attachment_path(config, &ruta_archivo)?;
+            tokio::fs::read(&path)
+                .await
+                .map_err(|e| HelpError::Storage(format!("No se pudo leer {}: {}", path.display(), e)))?
+        }
+        (None, Some(contenido)) => general_purpose::STANDARD
+            .decode(contenido.trim())
+            .map_err(|e| HelpError::Storage(format!("Adjunto {} dañado: {}", adjunto_id, e)))?,
+        (None, None) => return Err(HelpError::Storage(format!("El adjunto {} no tiene contenido", adjunto_id))),
+    };
+    Ok((
+        HelpAttachment { adjunto_id: id, nombre_archivo, tipo_contenido, tamano, autor, fecha_creacion },
+        bytes,
+    ))
+}
+
+pub async fn delete_attachment(
+    pool: &Pool<Mssql>,
+    config: &HelpConfig,
+    aplicativo_id: i32,
+    adjunto_id: i32,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<(), HelpError> {
+    let (ayuda_id, (_, _, _, _, _, _, ruta_archivo, _)) =
+        fetch_attachment(pool, aplicativo_id, adjunto_id, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_ayuda_adjunto WHERE adjuntoID = @p1")
+        .bind(adjunto_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    touch_article(&mut tx, ayuda_id, autor).await?;
+    tx.commit().await.map_err(db_error)?;
+    if let Some(ruta_archivo) = ruta_archivo {
+        remove_file(config, &ruta_archivo).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+// src/shared/help_models.rs
+// Modelos de las ayudas contextuales ("Administrar ayudas").
+
+use serde::{Deserialize, Serialize};
+
+/// Estado de una versión de la ayuda.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HelpVersionState {
+    Borrador,
+    Publicado,
+    /// Versión publicada reemplazada por una posterior.
+    Archivado,
+}
+
+impl HelpVersionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HelpVersionState::Borrador => "borrador",
+            HelpVersionState::Publicado => "publicado",
+            HelpVersionState::Archivado => "archivado",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "publicado" => HelpVersionState::Publicado,
+            "archivado" => HelpVersionState::Archivado,
+            _ => HelpVersionState::Borrador,
+        }
+    }
+}
+
+/// Artículo de ayuda de una pantalla en un idioma (`riy.riy_ayuda`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpArticle {
+    pub ayuda_id: i32,
+    pub menu_id: Option<i32>,
+    pub ruta: String,
+    pub idioma: String,
+    /// Número de la versión publicada, si hay una.
+    pub version_publicada: Option<i32>,
+    /// Última versión (publicada o no).
+    pub ultima_version: i32,
+    pub titulo: String,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+/// Versión del contenido (`riy.riy_ayuda_version`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpVersion {
+    pub numero: i32,
+    pub titulo: String,
+    /// Cuerpo en Markdown.
+    pub contenido: String,
+    pub estado: HelpVersionState,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub publicado_por: Option<String>,
+    pub fecha_publicacion: Option<String>,
+}
+
+/// Adjunto de la ayuda (`riy.riy_ayuda_adjunto`), sin el contenido.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpAttachment {
+    pub adjunto_id: i32,
+    pub nombre_archivo: String,
+    pub tipo_contenido: String,
+    pub tamano: i64,
+    pub autor: String,
+    pub fecha_creacion: String,
+}
+
+/// Artículo con sus versiones y adjuntos, para el administrador.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpArticleDetail {
+    #[serde(flatten)]
+    pub articulo: HelpArticle,
+    pub versiones: Vec<HelpVersion>,
+    pub adjuntos: Vec<HelpAttachment>,
+}
+
+/// Ayuda publicada que se muestra en la pantalla.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedHelp {
+    pub ayuda_id: i32,
+    pub ruta: String,
+    pub idioma: String,
+    pub version: i32,
+    pub titulo: String,
+    pub contenido: String,
+    pub adjuntos: Vec<HelpAttachment>,
+    pub fecha_publicacion: Option<String>,
+}
+
+/// Alta de un artículo: `menu_id` o `ruta` identifican la pantalla; se crea la versión 1 como borrador.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpArticleInput {
+    pub menu_id: Option<i32>,
+    pub ruta: Option<String>,
+    pub idioma: Option<String>,
+    pub titulo: String,
+    #[serde(default)]
+    pub contenido: String,
+}
+
+/// Cambio de pantalla o idioma de un artículo existente.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpArticleUpdate {
+    pub menu_id: Option<i32>,
+    pub ruta: Option<String>,
+    pub idioma: Option<String>,
+}
+
+/// Nueva versión en borrador.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpVersionInput {
+    pub titulo: String,
+    pub contenido: String,
+}
+
+/// Carga de un adjunto (contenido en Base64, como la importación de usuarios).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpAttachmentUpload {
+    pub nombre_archivo: String,
+    pub tipo_contenido: Option<String>,
+    pub content_base64: String,
+}
+
+/// `GET /help?ruta=/usuarios/lista&idioma=es`
+#[derive(Debug, Clone, Deserialize)]
+pub struct HelpLookupQuery {
+    pub ruta: String,
+    pub idioma: Option<String>,
+}
@@ -0,0 +1,247 @@
+// src/shared/identity_link_logic.rs
+/*
+Vínculos entre usuarios de riy e identidades externas (Microsoft oid, Google sub).
+El login externo busca primero por (proveedor, sujeto); el correo solo se usa para
+el primer vínculo, de modo que un cambio de correo en el IdP no duplica usuarios
+ni permite tomar la cuenta de otro.
+
+CREATE TABLE riy.riy_usuario_identidad (
+    identidadID        INT IDENTITY(1,1) PRIMARY KEY,
+    usuarioID          INT          NOT NULL,
+    proveedor          VARCHAR(20)  NOT NULL,
+    sujeto             VARCHAR(255) NOT NULL,
+    correo             VARCHAR(255) NULL,
+    autor              VARCHAR(50)  NOT NULL,
+    fechaCreacion      DATETIME     NOT NULL DEFAULT GETDATE(),
+    fechaUltimoAcceso  DATETIME     NULL,
+    CONSTRAINT UQ_riy_usuario_identidad_sujeto UNIQUE (proveedor, sujeto),
+    CONSTRAINT UQ_riy_usuario_identidad_usuario UNIQUE (usuarioID, proveedor)
+);
+*/
+
+use std::fmt;
+
+use sqlx::{Mssql, Pool};
+
+use crate::models::{ExternalIdentity, IdentityLink};
+
+pub const PROVEEDOR_MICROSOFT: &str = "microsoft";
+pub const PROVEEDOR_GOOGLE: &str = "google";
+const PROVEEDORES: [&str; 2] = [PROVEEDOR_MICROSOFT, PROVEEDOR_GOOGLE];
+
+#[derive(Debug)]
+pub enum IdentityLinkError {
+    Validation(String),
+    NotFound(String),
+    /// La identidad ya pertenece a otro usuario, o el usuario ya tiene otra identidad del proveedor.
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for IdentityLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdentityLinkError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            IdentityLinkError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            IdentityLinkError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            IdentityLinkError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IdentityLinkError {}
+
+fn db_error(e: sqlx::Error) -> IdentityLinkError {
+    IdentityLinkError::Database(e.to_string())
+}
+
+/// Normaliza y valida el nombre del proveedor.
+pub fn normalize_provider(proveedor: &str) -> Result<String, IdentityLinkError> {
+    let proveedor = proveedor.trim().to_lowercase();
+    if PROVEEDORES.contains(&proveedor.as_str()) {
+        Ok(proveedor)
+    } else {
+        Err(IdentityLinkError::Validation(format!("Proveedor no soportado: {}", proveedor)))
+    }
+}
+
+fn link_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT i.identidadID as identidad_id,
+                i.usuarioID as usuario_id,
+                u.usuario {0} as usuario,
+                i.proveedor {0} as proveedor,
+                i.sujeto {0} as sujeto,
+                i.correo {0} as correo,
+                i.autor {0} as autor,
+                CONVERT(VARCHAR(19), i.fechaCreacion, 120) {0} as fecha_creacion,
+                CONVERT(VARCHAR(19), i.fechaUltimoAcceso, 120) {0} as fecha_ultimo_acceso
+           FROM riy.riy_usuario_identidad i WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = i.usuarioID",
+        sql_collate_clause
+    )
+}
+
+/// Devuelve el `usuario_id` vinculado a la identidad externa, si existe.
+pub async fn find_user_id_by_identity(
+    pool: &Pool<Mssql>,
+    identity: &ExternalIdentity,
+) -> Result<Option<i32>, IdentityLinkError> {
+    let row: Option<(i32,)> = sqlx::query_as(
+        "SELECT usuarioID FROM riy.riy_usuario_identidad WITH(NOLOCK)
+          WHERE proveedor = @p1 AND sujeto = @p2"
+    )
+    .bind(&identity.provider)
+    .bind(&identity.subject)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(row.map(|(usuario_id,)| usuario_id))
+}
+
+/// Registra un acceso con la identidad y guarda el correo que informó el IdP.
+pub async fn touch_identity(
+    pool: &Pool<Mssql>,
+    identity: &ExternalIdentity,
+    correo: &str,
+) -> Result<(), IdentityLinkError> {
+    sqlx::query(
+        "UPDATE riy.riy_usuario_identidad
+            SET correo = @p1, fechaUltimoAcceso = GETDATE()
+          WHERE proveedor = @p2 AND sujeto = @p3"
+    )
+    .bind(correo.to_lowercase())
+    .bind(&identity.provider)
+    .bind(&identity.subject)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Vincula la identidad externa al usuario.
+/// Falla con `Conflict` si la identidad ya es de otro usuario o si el usuario ya
+/// tiene vinculada otra identidad del mismo proveedor (p. ej. un correo reasignado en el IdP).
+pub async fn link_identity(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    identity: &ExternalIdentity,
+    correo: Option<&str>,
+    autor: &str,
+) -> Result<(), IdentityLinkError> {
+    let proveedor = normalize_provider(&identity.provider)?;
+    let sujeto = identity.subject.trim();
+    if sujeto.is_empty() {
+        return Err(IdentityLinkError::Validation("El sujeto de la identidad es obligatorio".to_string()));
+    }
+
+    let existing: Vec<(i32, String)> = sqlx::query_as(
+        "SELECT usuarioID, sujeto FROM riy.riy_usuario_identidad WITH(NOLOCK)
+          WHERE proveedor = @p1 AND (sujeto = @p2 OR usuarioID = @p3)"
+    )
+    .bind(&proveedor)
+    .bind(sujeto)
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    if existing.iter().any(|(u, s)| *u == usuario_id && s == sujeto) {
+        // Ya vinculada; solo se registra el acceso.
+        return match correo {
+            Some(correo) => touch_identity(pool, identity, correo).await,
+            None => Ok(()),
+        };
+    }
+    if existing.iter().any(|(_, s)| s == sujeto) {
+        return Err(IdentityLinkError::Conflict(format!(
+            "La identidad {} ya está vinculada a otro usuario", proveedor
+        )));
+    }
+    if !existing.is_empty() {
+        return Err(IdentityLinkError::Conflict(format!(
+            "El usuario ya tiene vinculada otra identidad de {}", proveedor
+        )));
+    }
+
+    sqlx::query(
+        "INSERT INTO riy.riy_usuario_identidad (usuarioID, proveedor, sujeto, correo, autor, fechaCreacion, fechaUltimoAcceso)
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE(), GETDATE())"
+    )
+    .bind(usuario_id)
+    .bind(&proveedor)
+    .bind(sujeto)
+    .bind(correo.map(|c| c.to_lowercase()))
+    .bind(autor)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    println!("identity_link_logic: identidad {} vinculada al usuario {}", proveedor, usuario_id);
+    Ok(())
+}
+
+/// Quita un vínculo del usuario.
+pub async fn unlink_identity(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    identidad_id: i32,
+) -> Result<(), IdentityLinkError> {
+    let result = sqlx::query(
+        "DELETE FROM riy.riy_usuario_identidad WHERE identidadID = @p1 AND usuarioID = @p2"
+    )
+    .bind(identidad_id)
+    .bind(usuario_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(IdentityLinkError::NotFound(format!(
+            "Vínculo {} no encontrado para el usuario {}", identidad_id, usuario_id
+        )));
+    }
+    Ok(())
+}
+
+/// Identidades vinculadas a un usuario.
+pub async fn list_user_identities(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<IdentityLink>, IdentityLinkError> {
+    let sql_query = format!("{} WHERE i.usuarioID = @p1 ORDER BY i.proveedor", link_select(sql_collate_clause));
+    sqlx::query_as::<_, IdentityLink>(&sql_query)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Vista de administración: todos los vínculos, opcionalmente filtrados por proveedor.
+pub async fn list_identities(
+    pool: &Pool<Mssql>,
+    proveedor: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<Vec<IdentityLink>, IdentityLinkError> {
+    match proveedor {
+        Some(p) => {
+            let proveedor = normalize_provider(p)?;
+            let sql_query = format!(
+                "{} WHERE i.proveedor = @p1 ORDER BY u.usuario, i.proveedor",
+                link_select(sql_collate_clause)
+            );
+            sqlx::query_as::<_, IdentityLink>(&sql_query)
+                .bind(proveedor)
+                .fetch_all(pool)
+                .await
+                .map_err(db_error)
+        }
+        None => {
+            let sql_query = format!("{} ORDER BY u.usuario, i.proveedor", link_select(sql_collate_clause));
+            sqlx::query_as::<_, IdentityLink>(&sql_query)
+                .fetch_all(pool)
+                .await
+                .map_err(db_error)
+        }
+    }
+}
@@ -0,0 +1,379 @@
+// src/shared/impersonation_logic.rs
+/*
+Suplantación de usuarios ("actuar como") para soporte.
+
+Quien tiene el permiso `suplantar_usuarios` obtiene un JWT de vida corta para otro
+usuario, con los permisos de ese usuario (get_permissions_by_app) y el claim `act`
+con su propio usuario. Mientras se usa ese token:
+  - los permisos de PERMISOS_SEGURIDAD se consideran ausentes (Claims::has_permission), así
+    que cada ruta de administración de seguridad lo rechaza por el permiso que exige,
+  - las rutas de autogestión de la propia seguridad lo rechazan (Claims::can_manage_own_security),
+  - además, el middleware rechaza toda modificación en las rutas de seguridad y de usuarios
+    (is_blocked_route), aunque una ruta olvide comprobar su permiso,
+  - cada petición que modifica datos queda registrada en riy_suplantacion_accion.
+
+Solo se puede suplantar a un usuario cuyos permisos en el aplicativo tenga también quien
+suplanta: la suplantación no sirve para obtener privilegios que no se tienen.
+
+CREATE TABLE riy.riy_suplantacion (
+    suplantacionID  INT IDENTITY(1,1) PRIMARY KEY,
+    actorUsuario    VARCHAR(50)   NOT NULL,
+    usuarioID       INT           NOT NULL,
+    aplicativoID    INT           NOT NULL,
+    motivo          NVARCHAR(500) NOT NULL,
+    fechaInicio     DATETIME      NOT NULL DEFAULT GETDATE(),
+    fechaExpira     DATETIME      NOT NULL,
+    fechaFin        DATETIME      NULL        -- terminada antes de vencer
+);
+
+CREATE TABLE riy.riy_suplantacion_accion (
+    accionID         INT IDENTITY(1,1) PRIMARY KEY,
+    suplantacionID   INT           NOT NULL,
+    metodo           VARCHAR(10)   NOT NULL,
+    ruta             VARCHAR(400)  NOT NULL,
+    codigoRespuesta  INT           NOT NULL,
+    fecha            DATETIME      NOT NULL DEFAULT GETDATE()
+);
+*/
+
+use std::fmt;
+
+use sqlx::{Mssql, Pool};
+
+use crate::impersonation_models::{
+    ImpersonationAction, ImpersonationRecord, ImpersonationRequest, ImpersonationToken,
+};
+use crate::middleware::auth_claims::ActorClaim;
+use crate::models::LoggedInUser;
+use crate::user_repository::ESTADO_VIGENTE;
+use crate::utils;
+
+/// Permiso requerido para suplantar usuarios.
+pub const PERMISO_SUPLANTAR: &str = "suplantar_usuarios";
+
+const DEFAULT_MINUTES: i64 = 30;
+const MAX_MINUTES: i64 = 60;
+
+/// Prefijos (dentro de /api/protected) que no se pueden modificar durante una suplantación.
+const RUTAS_SEGURIDAD: &[&str] = &[
+    "/users",
+    "/me",
+    "/mfa",
+    "/identities",
+    "/domain-policies",
+    "/notifications",
+    "/impersonation",
+    "/grants",
+    "/access-requests",
+    "/permissions",
+    "/row-security",
+    "/applications",
+    "/help",
+];
+/// Única ruta de seguridad permitida: terminar la propia suplantación.
+const RUTA_TERMINAR: &str = "/impersonation/end";
+
+#[derive(Debug)]
+pub enum ImpersonationError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Database(String),
+}
+
+impl fmt::Display for ImpersonationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImpersonationError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            ImpersonationError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            ImpersonationError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            ImpersonationError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImpersonationError {}
+
+fn db_error(e: sqlx::Error) -> ImpersonationError {
+    ImpersonationError::Database(e.to_string())
+}
+
+/// Indica si la petición (método y ruta relativa a /api/protected) modifica usuarios o
+/// configuración de seguridad y debe rechazarse con un token de suplantación.
+pub fn is_blocked_route(method: &str, path: &str) -> bool {
+    if matches!(method, "GET" | "HEAD" | "OPTIONS") {
+        return false;
+    }
+    let path = path.strip_prefix("/api/protected").unwrap_or(path);
+    if path == RUTA_TERMINAR {
+        return false;
+    }
+    RUTAS_SEGURIDAD
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+}
+
+/// Permisos del usuario suplantado que no tiene quien suplanta.
+fn missing_permissions<'a>(target: &'a [String], actor: &[String]) -> Vec<&'a str> {
+    target
+        .iter()
+        .filter(|p| !actor.contains(p))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Emite un token de suplantación para `usuario_id` y lo registra en la auditoría.
+/// `actor_permissions` son los permisos de quien suplanta en el aplicativo (los de su token).
+pub async fn start_impersonation(
+    pool: &Pool<Mssql>,
+    actor: &str,
+    actor_permissions: &[String],
+    request: &ImpersonationRequest,
+    aplicativo_id: i32,
+    jwt_secret: &str,
+    sql_collate_clause: &str,
+) -> Result<ImpersonationToken, ImpersonationError> {
+    let usuario_id = request.usuario_id;
+    let motivo = request.motivo.trim();
+    if motivo.is_empty() {
+        return Err(ImpersonationError::Validation("El motivo de la suplantación es obligatorio".to_string()));
+    }
+    let minutos = request.minutos.unwrap_or(DEFAULT_MINUTES);
+    if !(1..=MAX_MINUTES).contains(&minutos) {
+        return Err(ImpersonationError::Validation(format!(
+            "La vigencia debe estar entre 1 y {} minutos", MAX_MINUTES
+        )));
+    }
+
+    let sql_query = format!(
+        "SELECT usuario {0}, nombre {0}, correo {0}, estado {0} FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario_id = @p1",
+        sql_collate_clause
+    );
+    let (usuario, nombre, correo, estado): (String, String, String, String) = sqlx::query_as(&sql_query)
+        .bind(usuario_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| ImpersonationError::NotFound(format!("Usuario {} no encontrado", usuario_id)))?;
+
+    if usuario.eq_ignore_ascii_case(actor) {
+        return Err(ImpersonationError::Validation("No puede suplantarse a sí mismo".to_string()));
+    }
+    if !estado.trim().eq_ignore_ascii_case(ESTADO_VIGENTE) {
+        return Err(ImpersonationError::Validation(format!("El usuario '{}' no está vigente", usuario)));
+    }
+
+    let permissions = utils::get_permissions_by_app(pool, usuario_id, aplicativo_id)
+        .await
+        .map_err(|e| ImpersonationError::Database(e.to_string()))?;
+    if permissions.iter().any(|p| p == PERMISO_SUPLANTAR) {
+        return Err(ImpersonationError::Forbidden(
+            "No se puede suplantar a otro usuario con permiso de suplantación".to_string(),
+        ));
+    }
+    let faltantes = missing_permissions(&permissions, actor_permissions);
+    if !faltantes.is_empty() {
+        return Err(ImpersonationError::Forbidden(format!(
+            "El usuario '{}' tiene permisos que usted no tiene: {}",
+            usuario,
+            faltantes.join(", ")
+        )));
+    }
+
+    let (suplantacion_id, fecha_expira): (i32, String) = sqlx::query_as(
+        "INSERT INTO riy.riy_suplantacion (actorUsuario, usuarioID, aplicativoID, motivo, fechaInicio, fechaExpira)
+         OUTPUT INSERTED.suplantacionID, CONVERT(VARCHAR(19), INSERTED.fechaExpira, 120)
+         VALUES (@p1, @p2, @p3, @p4, GETDATE(), DATEADD(MINUTE, @p5, GETDATE()))"
+    )
+    .bind(actor)
+    .bind(usuario_id)
+    .bind(aplicativo_id)
+    .bind(motivo)
+    .bind(minutos as i32)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    let user = LoggedInUser {
+        usuario_id: Some(usuario_id),
+        usuario: Some(usuario),
+        nombre: Some(nombre),
+        correo: Some(correo),
+        roles: Vec::new(),
+    };
+    let app_jwt = utils::generate_impersonation_jwt(
+        &user,
+        permissions.clone(),
+        ActorClaim { sub: actor.to_string(), sid: suplantacion_id },
+        minutos,
+        aplicativo_id,
+        jwt_secret,
+    )
+    .map_err(|e| ImpersonationError::Validation(e.to_string()))?;
+
+    println!(
+        "impersonation_logic: '{}' suplanta al usuario {} (suplantación {}): {}",
+        actor, usuario_id, suplantacion_id, motivo
+    );
+    Ok(ImpersonationToken {
+        app_jwt,
+        user,
+        permissions,
+        actor: actor.to_string(),
+        suplantacion_id,
+        fecha_expira,
+    })
+}
+
+/// La suplantación sigue vigente (no venció ni se terminó).
+pub async fn is_active(pool: &Pool<Mssql>, suplantacion_id: i32) -> Result<bool, ImpersonationError> {
+    let row: Option<(i32,)> = sqlx::query_as(
+        "SELECT suplantacionID FROM riy.riy_suplantacion WITH(NOLOCK)
+          WHERE suplantacionID = @p1 AND fechaFin IS NULL AND fechaExpira > GETDATE()"
+    )
+    .bind(suplantacion_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(row.is_some())
+}
+
+/// Termina la suplantación; solo el actor que la inició puede hacerlo.
+pub async fn end_impersonation(
+    pool: &Pool<Mssql>,
+    suplantacion_id: i32,
+    actor: &str,
+) -> Result<(), ImpersonationError> {
+    let result = sqlx::query(
+        "UPDATE riy.riy_suplantacion SET fechaFin = GETDATE()
+          WHERE suplantacionID = @p1 AND actorUsuario = @p2 AND fechaFin IS NULL"
+    )
+    .bind(suplantacion_id)
+    .bind(actor)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(ImpersonationError::NotFound(format!(
+            "Suplantación {} no encontrada o ya terminada", suplantacion_id
+        )));
+    }
+    Ok(())
+}
+
+/// Registra una petición hecha con un token de suplantación.
+pub async fn record_action(
+    pool: &Pool<Mssql>,
+    suplantacion_id: i32,
+    metodo: &str,
+    ruta: &str,
+    codigo_respuesta: u16,
+) -> Result<(), ImpersonationError> {
+    sqlx::query(
+        "INSERT INTO riy.riy_suplantacion_accion (suplantacionID, metodo, ruta, codigoRespuesta, fecha)
+         VALUES (@p1, @p2, @p3, @p4, GETDATE())"
+    )
+    .bind(suplantacion_id)
+    .bind(metodo)
+    .bind(ruta.chars().take(400).collect::<String>())
+    .bind(i32::from(codigo_respuesta))
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Auditoría de suplantaciones, más recientes primero.
+pub async fn list_impersonations(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    actor: Option<&str>,
+    usuario_id: Option<i32>,
+    limit: Option<i32>,
+    sql_collate_clause: &str,
+) -> Result<Vec<ImpersonationRecord>, ImpersonationError> {
+    let limit = limit.unwrap_or(100).clamp(1, 500);
+    let sql_query = format!(
+        "SELECT TOP ({1}) s.suplantacionID as suplantacion_id,
+                s.actorUsuario {0} as actor,
+                s.usuarioID as usuario_id,
+                u.usuario {0} as usuario,
+                s.aplicativoID as aplicativo_id,
+                s.motivo,
+                CONVERT(VARCHAR(19), s.fechaInicio, 120) as fecha_inicio,
+                CONVERT(VARCHAR(19), s.fechaExpira, 120) as fecha_expira,
+                CONVERT(VARCHAR(19), s.fechaFin, 120) as fecha_fin,
+                (SELECT COUNT(*) FROM riy.riy_suplantacion_accion a WITH(NOLOCK)
+                  WHERE a.suplantacionID = s.suplantacionID) as acciones
+           FROM riy.riy_suplantacion s WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = s.usuarioID
+          WHERE s.aplicativoID = @p1
+            AND (@p2 IS NULL OR s.actorUsuario = @p2 {0})
+            AND (@p3 IS NULL OR s.usuarioID = @p3)
+          ORDER BY s.suplantacionID DESC",
+        sql_collate_clause, limit
+    );
+    sqlx::query_as::<_, ImpersonationRecord>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(actor)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Peticiones registradas para una suplantación.
+pub async fn list_actions(
+    pool: &Pool<Mssql>,
+    suplantacion_id: i32,
+) -> Result<Vec<ImpersonationAction>, ImpersonationError> {
+    sqlx::query_as::<_, ImpersonationAction>(
+        "SELECT accionID as accion_id, metodo, ruta, codigoRespuesta as codigo_respuesta,
+                CONVERT(VARCHAR(19), fecha, 120) as fecha
+           FROM riy.riy_suplantacion_accion WITH(NOLOCK)
+          WHERE suplantacionID = @p1
+          ORDER BY accionID"
+    )
+    .bind(suplantacion_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permisos(codigos: &[&str]) -> Vec<String> {
+        codigos.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn bloquea_modificaciones_de_usuarios_y_seguridad() {
+        assert!(is_blocked_route("POST", "/api/protected/users"));
+        assert!(is_blocked_route("PUT", "/api/protected/users/15"));
+        assert!(is_blocked_route("POST", "/api/protected/users/15/identities"));
+        assert!(is_blocked_route("PUT", "/api/protected/help/articles/3"));
+        assert!(is_blocked_route("POST", "/api/protected/impersonation"));
+        assert!(is_blocked_route("DELETE", "/api/protected/grants/4"));
+    }
+
+    #[test]
+    fn permite_lecturas_el_resto_de_rutas_y_terminar_la_suplantacion() {
+        assert!(!is_blocked_route("GET", "/api/protected/users"));
+        assert!(!is_blocked_route("POST", "/api/protected/impersonation/end"));
+        assert!(!is_blocked_route("POST", "/api/protected/queries/3/execute"));
+        assert!(!is_blocked_route("POST", "/api/protected/usersx"));
+    }
+
+    #[test]
+    fn detecta_permisos_que_no_tiene_quien_suplanta() {
+        let actor = permisos(&["inicio", "lista_usuarios", "suplantar_usuarios"]);
+
+        assert!(missing_permissions(&permisos(&["inicio", "lista_usuarios"]), &actor).is_empty());
+        assert_eq!(
+            missing_permissions(&permisos(&["inicio", "administrar_permisos"]), &actor),
+            vec!["administrar_permisos"]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+// src/shared/impersonation_models.rs
+// Modelos de la suplantación de usuarios ("actuar como") y su auditoría.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::LoggedInUser;
+
+/// Solicitud de suplantación.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationRequest {
+    pub usuario_id: i32,
+    /// Motivo obligatorio (p. ej. número de ticket); queda en la auditoría.
+    pub motivo: String,
+    /// Vigencia del token en minutos (por defecto 30, máximo 60).
+    pub minutos: Option<i64>,
+}
+
+/// Token de suplantación emitido. El frontend debe mostrar `actor` mientras se use.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationToken {
+    #[serde(rename = "appJWT")]
+    pub app_jwt: String,
+    pub user: LoggedInUser,
+    pub permissions: Vec<String>,
+    /// Usuario del administrador que actúa en nombre de `user`.
+    pub actor: String,
+    pub suplantacion_id: i32,
+    pub fecha_expira: String,
+}
+
+/// Registro de `riy.riy_suplantacion`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationRecord {
+    pub suplantacion_id: i32,
+    pub actor: String,
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub aplicativo_id: i32,
+    pub motivo: String,
+    pub fecha_inicio: String,
+    pub fecha_expira: String,
+    pub fecha_fin: Option<String>,
+    pub acciones: i32,
+}
+
+/// Petición realizada con un token de suplantación (`riy.riy_suplantacion_accion`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationAction {
+    pub accion_id: i32,
+    pub metodo: String,
+    pub ruta: String,
+    pub codigo_respuesta: i32,
+    pub fecha: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationAuditQuery {
+    pub actor: Option<String>,
+    pub usuario_id: Option<i32>,
+    pub limit: Option<i32>,
+}
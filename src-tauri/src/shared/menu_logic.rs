@@ -3,8 +3,10 @@
 use sqlx::{Pool, Mssql};
 use crate::menu_models::MenuItem; // Asume que tus structs están en este path
 
+/// Menús del aplicativo.
 pub async fn get_all_menus_logic(
     pool: &Pool<Mssql>,
+    aplicativo_id: i32,
     sql_collate_clause: &str,
 ) -> Result<Vec<MenuItem>, String> {
     let sql_query = format!(
@@ -23,11 +25,13 @@ pub async fn get_all_menus_logic(
             modificadoPor {0} as modificado_por,
             CONVERT(VARCHAR, fechaModificacion, 120) {0} as fecha_modificacion
         FROM riy.riy_SeguridadMenu WITH(NOLOCK)
+        WHERE aplicativoID = @p1
         "#,
         sql_collate_clause
     );
     
     let menus = sqlx::query_as::<_, MenuItem>(&sql_query)
+        .bind(aplicativo_id)
         .fetch_all(pool)
         .await
         .map_err(|e| format!("Error al leer todos los umenú ítems: {}", e))?;
@@ -0,0 +1,693 @@
+// src/shared/mfa_logic.rs
+/*
+Segundo factor TOTP (RFC 6238: HMAC-SHA1, 6 dígitos, pasos de 30 s) para los logins
+ERP/Local. El secreto se guarda cifrado con AES-256-GCM (MFA_ENCRYPTION_KEY, 32 bytes
+en hexadecimal, mismo formato nonce||ciphertext en Base64 que las licencias) y los
+códigos de recuperación se guardan como hash SHA-256.
+
+Tras MAX_INTENTOS_FALLIDOS códigos incorrectos seguidos la verificación queda bloqueada
+BLOQUEO_MINUTOS; como el bloqueo dura más que el token de desafío, el login en curso
+queda invalidado y hay que volver a empezar con usuario y contraseña.
+
+CREATE TABLE riy.riy_usuario_mfa (
+    usuarioID          INT           PRIMARY KEY,
+    secretoCifrado     VARCHAR(200)  NOT NULL,
+    confirmado         BIT           NOT NULL DEFAULT 0,
+    ultimoPaso         BIGINT        NOT NULL DEFAULT 0,   -- evita reutilizar un código
+    intentosFallidos   INT           NOT NULL DEFAULT 0,   -- códigos incorrectos seguidos
+    fechaBloqueo       DATETIME      NULL,                 -- bloqueo por intentos fallidos
+    fechaCreacion      DATETIME      NOT NULL DEFAULT GETDATE(),
+    fechaConfirmacion  DATETIME      NULL
+);
+
+CREATE TABLE riy.riy_usuario_mfa_recuperacion (
+    codigoID    INT IDENTITY(1,1) PRIMARY KEY,
+    usuarioID   INT          NOT NULL,
+    codigoHash  CHAR(64)     NOT NULL,
+    usado       BIT          NOT NULL DEFAULT 0,
+    fechaUso    DATETIME     NULL
+);
+
+CREATE TABLE riy.riy_mfa_politica (
+    politicaID    INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID  INT         NOT NULL,
+    rolID         INT         NULL,        -- NULL = todo el aplicativo
+    requerido     BIT         NOT NULL DEFAULT 1,
+    autor         VARCHAR(50) NOT NULL,
+    fechaCreacion DATETIME    NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT UQ_riy_mfa_politica UNIQUE (aplicativoID, rolID)
+);
+*/
+
+use std::fmt;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::{Mssql, Pool};
+
+use crate::mfa_models::{MfaChallengeResponse, MfaEnrollmentStart, MfaPolicy, MfaStatus};
+
+type HmacSha1 = Hmac<Sha1>;
+
+const TOTP_STEP_SECONDS: i64 = 30;
+const TOTP_DIGITS_MODULO: u32 = 1_000_000;
+/// Pasos de tolerancia hacia atrás y hacia adelante por desfase de reloj.
+const TOTP_WINDOW: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Vigencia del token de desafío entre el primer y el segundo paso del login.
+pub const CHALLENGE_TTL_SECONDS: i64 = 300;
+const CHALLENGE_PURPOSE: &str = "mfa";
+/// Códigos incorrectos seguidos antes de bloquear la verificación.
+const MAX_INTENTOS_FALLIDOS: i32 = 5;
+/// Duración del bloqueo; mayor que CHALLENGE_TTL_SECONDS para que el desafío en curso venza.
+const BLOQUEO_MINUTOS: i32 = 15;
+
+#[derive(Debug)]
+pub enum MfaError {
+    Validation(String),
+    /// Código TOTP/recuperación incorrecto, o token de desafío inválido/vencido.
+    InvalidCode(String),
+    NotFound(String),
+    Conflict(String),
+    /// Demasiados códigos incorrectos: la verificación está bloqueada por un tiempo.
+    Locked(String),
+    Crypto(String),
+    Database(String),
+}
+
+impl fmt::Display for MfaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MfaError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            MfaError::InvalidCode(msg) => write!(f, "Código inválido: {}", msg),
+            MfaError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            MfaError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            MfaError::Locked(msg) => write!(f, "Bloqueado: {}", msg),
+            MfaError::Crypto(msg) => write!(f, "Error de cifrado: {}", msg),
+            MfaError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for MfaError {}
+
+fn db_error(e: sqlx::Error) -> MfaError {
+    MfaError::Database(e.to_string())
+}
+
+// -------------------------------------------------------------------------
+// TOTP
+// -------------------------------------------------------------------------
+
+/// Genera un secreto aleatorio de 160 bits codificado en Base32 (sin relleno).
+pub fn generate_secret() -> String {
+    let bytes: [u8; SECRET_BYTES] = rand::random();
+    base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &bytes)
+}
+
+fn decode_secret(secret_b32: &str) -> Result<Vec<u8>, MfaError> {
+    base32::decode(base32::Alphabet::Rfc4648 { padding: false }, secret_b32)
+        .ok_or_else(|| MfaError::Crypto("Secreto TOTP con formato inválido".to_string()))
+}
+
+/// HOTP (RFC 4226) con truncamiento dinámico.
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = <HmacSha1 as Mac>::new_from_slice(secret).expect("HMAC admite claves de cualquier longitud");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = ((hash[offset] as u32 & 0x7f) << 24)
+        | ((hash[offset + 1] as u32) << 16)
+        | ((hash[offset + 2] as u32) << 8)
+        | (hash[offset + 3] as u32);
+    binary % TOTP_DIGITS_MODULO
+}
+
+/// Verifica un código TOTP y devuelve el paso que coincidió.
+/// Solo acepta pasos posteriores a `last_step` para impedir reutilizar un código.
+fn verify_totp(secret_b32: &str, code: &str, last_step: i64) -> Result<Option<i64>, MfaError> {
+    verify_totp_at(secret_b32, code, last_step, Utc::now().timestamp())
+}
+
+/// verify_totp con la hora (segundos Unix) indicada.
+fn verify_totp_at(secret_b32: &str, code: &str, last_step: i64, now: i64) -> Result<Option<i64>, MfaError> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let expected: u32 = code.parse().unwrap_or(u32::MAX);
+    let secret = decode_secret(secret_b32)?;
+    let current_step = now / TOTP_STEP_SECONDS;
+
+    for step in (current_step - TOTP_WINDOW)..=(current_step + TOTP_WINDOW) {
+        if step > last_step && hotp(&secret, step as u64) == expected {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+/// URI `otpauth://` para el código QR de la app autenticadora.
+pub fn provisioning_uri(issuer: &str, account: &str, secret_b32: &str) -> String {
+    let enc = |v: &str| url::form_urlencoded::byte_serialize(v.as_bytes()).collect::<String>();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits=6&period={}",
+        enc(issuer), enc(account), secret_b32, enc(issuer), TOTP_STEP_SECONDS
+    )
+}
+
+// -------------------------------------------------------------------------
+// CIFRADO DEL SECRETO
+// -------------------------------------------------------------------------
+
+fn cipher_from_hex(key_hex: &str) -> Result<Aes256Gcm, MfaError> {
+    let key_bytes = hex::decode(key_hex.trim())
+        .map_err(|e| MfaError::Crypto(format!("MFA_ENCRYPTION_KEY no es hexadecimal válido: {}", e)))?;
+    if key_bytes.len() != 32 {
+        return Err(MfaError::Crypto("MFA_ENCRYPTION_KEY debe ser una clave de 32 bytes (256 bits).".to_string()));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes)))
+}
+
+fn encrypt_secret(secret_b32: &str, key_hex: &str) -> Result<String, MfaError> {
+    let cipher = cipher_from_hex(key_hex)?;
+    let nonce_bytes: [u8; 12] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), secret_b32.as_bytes())
+        .map_err(|_| MfaError::Crypto("Fallo al cifrar el secreto TOTP".to_string()))?;
+    let mut payload = nonce_bytes.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+fn decrypt_secret(encrypted_b64: &str, key_hex: &str) -> Result<String, MfaError> {
+    let cipher = cipher_from_hex(key_hex)?;
+    let payload = general_purpose::STANDARD
+        .decode(encrypted_b64)
+        .map_err(|e| MfaError::Crypto(format!("Secreto cifrado con Base64 inválido: {}", e)))?;
+    if payload.len() < 12 + 16 {
+        return Err(MfaError::Crypto("Secreto cifrado demasiado corto".to_string()));
+    }
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&payload[..12]), &payload[12..])
+        .map_err(|_| MfaError::Crypto("Fallo al descifrar el secreto TOTP".to_string()))?;
+    String::from_utf8(plaintext).map_err(|e| MfaError::Crypto(e.to_string()))
+}
+
+// -------------------------------------------------------------------------
+// CÓDIGOS DE RECUPERACIÓN
+// -------------------------------------------------------------------------
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace(['-', ' '], "").to_lowercase()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    hex::encode(Sha256::digest(normalize_recovery_code(code).as_bytes()))
+}
+
+/// Genera códigos de un solo uso con formato `xxxx-xxxx`.
+fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = hex::encode(rand::random::<[u8; 4]>());
+            format!("{}-{}", &raw[..4], &raw[4..])
+        })
+        .collect()
+}
+
+// -------------------------------------------------------------------------
+// ALTA / BAJA
+// -------------------------------------------------------------------------
+
+/// Estado guardado del TOTP del usuario: (secreto cifrado, confirmado, último paso usado).
+async fn load_enrollment(pool: &Pool<Mssql>, usuario_id: i32) -> Result<Option<(String, bool, i64)>, MfaError> {
+    sqlx::query_as(
+        "SELECT secretoCifrado, confirmado, ultimoPaso FROM riy.riy_usuario_mfa WITH(NOLOCK) WHERE usuarioID = @p1"
+    )
+    .bind(usuario_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)
+}
+
+/// Indica si el usuario tiene un TOTP confirmado.
+pub async fn is_enrolled(pool: &Pool<Mssql>, usuario_id: i32) -> Result<bool, MfaError> {
+    Ok(matches!(load_enrollment(pool, usuario_id).await?, Some((_, true, _))))
+}
+
+/// Inicia (o reinicia, si no estaba confirmada) el alta de TOTP.
+pub async fn start_enrollment(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    account: &str,
+    issuer: &str,
+    key_hex: &str,
+) -> Result<MfaEnrollmentStart, MfaError> {
+    if is_enrolled(pool, usuario_id).await? {
+        return Err(MfaError::Conflict("El usuario ya tiene MFA activo".to_string()));
+    }
+    let secret = generate_secret();
+    let encrypted = encrypt_secret(&secret, key_hex)?;
+
+    sqlx::query(
+        "MERGE riy.riy_usuario_mfa AS t
+         USING (SELECT @p1 AS usuarioID) AS s ON t.usuarioID = s.usuarioID
+         WHEN MATCHED THEN UPDATE SET secretoCifrado = @p2, confirmado = 0, ultimoPaso = 0, fechaCreacion = GETDATE()
+         WHEN NOT MATCHED THEN INSERT (usuarioID, secretoCifrado, confirmado, ultimoPaso, fechaCreacion)
+              VALUES (@p1, @p2, 0, 0, GETDATE());"
+    )
+    .bind(usuario_id)
+    .bind(&encrypted)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(MfaEnrollmentStart {
+        provisioning_uri: provisioning_uri(issuer, account, &secret),
+        secret,
+    })
+}
+
+/// Confirma el alta con el primer código y devuelve los códigos de recuperación.
+pub async fn confirm_enrollment(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    code: &str,
+    key_hex: &str,
+) -> Result<Vec<String>, MfaError> {
+    let (encrypted, confirmado, last_step) = load_enrollment(pool, usuario_id)
+        .await?
+        .ok_or_else(|| MfaError::NotFound("No hay un alta de MFA pendiente".to_string()))?;
+    if confirmado {
+        return Err(MfaError::Conflict("El usuario ya tiene MFA activo".to_string()));
+    }
+    let secret = decrypt_secret(&encrypted, key_hex)?;
+    let step = verify_totp(&secret, code, last_step)?
+        .ok_or_else(|| MfaError::InvalidCode("El código no coincide".to_string()))?;
+
+    let codes = generate_recovery_codes();
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_usuario_mfa SET confirmado = 1, ultimoPaso = @p1, fechaConfirmacion = GETDATE()
+          WHERE usuarioID = @p2"
+    )
+    .bind(step)
+    .bind(usuario_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_usuario_mfa_recuperacion WHERE usuarioID = @p1")
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    for code in &codes {
+        sqlx::query("INSERT INTO riy.riy_usuario_mfa_recuperacion (usuarioID, codigoHash, usado) VALUES (@p1, @p2, 0)")
+            .bind(usuario_id)
+            .bind(hash_recovery_code(code))
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    println!("mfa_logic: MFA activado para el usuario {}", usuario_id);
+    Ok(codes)
+}
+
+/// Verifica el segundo factor: primero como TOTP y, si no coincide, como código de recuperación.
+/// Cada código incorrecto cuenta para el bloqueo por intentos fallidos; uno correcto lo reinicia.
+pub async fn verify_code(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    code: &str,
+    key_hex: &str,
+) -> Result<(), MfaError> {
+    let bloqueado: Option<(i32,)> = sqlx::query_as(
+        "SELECT usuarioID FROM riy.riy_usuario_mfa WITH(NOLOCK)
+          WHERE usuarioID = @p1 AND fechaBloqueo > DATEADD(MINUTE, -@p2, GETDATE())"
+    )
+    .bind(usuario_id)
+    .bind(BLOQUEO_MINUTOS)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    if bloqueado.is_some() {
+        return Err(MfaError::Locked(format!(
+            "Demasiados códigos incorrectos; vuelva a iniciar sesión en {} minutos", BLOQUEO_MINUTOS
+        )));
+    }
+
+    match check_code(pool, usuario_id, code, key_hex).await {
+        Ok(()) => {
+            sqlx::query("UPDATE riy.riy_usuario_mfa SET intentosFallidos = 0 WHERE usuarioID = @p1")
+                .bind(usuario_id)
+                .execute(pool)
+                .await
+                .map_err(db_error)?;
+            Ok(())
+        }
+        Err(MfaError::InvalidCode(msg)) => {
+            // Al llegar al máximo se bloquea y el contador vuelve a cero para después del bloqueo
+            let (bloqueo,): (i32,) = sqlx::query_as(
+                "UPDATE riy.riy_usuario_mfa
+                    SET intentosFallidos = CASE WHEN intentosFallidos + 1 >= @p2 THEN 0 ELSE intentosFallidos + 1 END,
+                        fechaBloqueo = CASE WHEN intentosFallidos + 1 >= @p2 THEN GETDATE() ELSE fechaBloqueo END
+                 OUTPUT CASE WHEN INSERTED.intentosFallidos = 0 THEN 1 ELSE 0 END
+                  WHERE usuarioID = @p1"
+            )
+            .bind(usuario_id)
+            .bind(MAX_INTENTOS_FALLIDOS)
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?;
+            if bloqueo == 1 {
+                println!("mfa_logic: verificación bloqueada para el usuario {} por intentos fallidos", usuario_id);
+                return Err(MfaError::Locked(format!(
+                    "Demasiados códigos incorrectos; vuelva a iniciar sesión en {} minutos", BLOQUEO_MINUTOS
+                )));
+            }
+            Err(MfaError::InvalidCode(msg))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Comprueba el código sin contar intentos.
+async fn check_code(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    code: &str,
+    key_hex: &str,
+) -> Result<(), MfaError> {
+    let (encrypted, _, last_step) = load_enrollment(pool, usuario_id)
+        .await?
+        .filter(|(_, confirmado, _)| *confirmado)
+        .ok_or_else(|| MfaError::NotFound("El usuario no tiene MFA activo".to_string()))?;
+
+    let secret = decrypt_secret(&encrypted, key_hex)?;
+    if let Some(step) = verify_totp(&secret, code, last_step)? {
+        // La condición sobre ultimoPaso evita que dos peticiones simultáneas usen el mismo código.
+        let result = sqlx::query(
+            "UPDATE riy.riy_usuario_mfa SET ultimoPaso = @p1 WHERE usuarioID = @p2 AND ultimoPaso < @p1"
+        )
+        .bind(step)
+        .bind(usuario_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+        if result.rows_affected() == 1 {
+            return Ok(());
+        }
+        return Err(MfaError::InvalidCode("El código ya fue utilizado".to_string()));
+    }
+
+    let result = sqlx::query(
+        "UPDATE TOP (1) riy.riy_usuario_mfa_recuperacion SET usado = 1, fechaUso = GETDATE()
+          WHERE usuarioID = @p1 AND codigoHash = @p2 AND usado = 0"
+    )
+    .bind(usuario_id)
+    .bind(hash_recovery_code(code))
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 1 {
+        println!("mfa_logic: código de recuperación usado por el usuario {}", usuario_id);
+        return Ok(());
+    }
+    Err(MfaError::InvalidCode("El código no coincide".to_string()))
+}
+
+/// Desactiva el MFA del usuario y borra sus códigos de recuperación.
+pub async fn disable(pool: &Pool<Mssql>, usuario_id: i32) -> Result<(), MfaError> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_usuario_mfa_recuperacion WHERE usuarioID = @p1")
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_usuario_mfa WHERE usuarioID = @p1")
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+/// Estado del MFA del usuario para la pantalla de seguridad.
+pub async fn status(pool: &Pool<Mssql>, aplicativo_id: i32, usuario_id: i32) -> Result<MfaStatus, MfaError> {
+    let enrolled = is_enrolled(pool, usuario_id).await?;
+    let (remaining,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_usuario_mfa_recuperacion WITH(NOLOCK) WHERE usuarioID = @p1 AND usado = 0"
+    )
+    .bind(usuario_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(MfaStatus {
+        enrolled,
+        required: is_mfa_required(pool, aplicativo_id, usuario_id).await?,
+        recovery_codes_remaining: remaining,
+    })
+}
+
+// -------------------------------------------------------------------------
+// POLÍTICAS
+// -------------------------------------------------------------------------
+
+/// Indica si alguna política del aplicativo (general o de un rol del usuario) exige MFA.
+pub async fn is_mfa_required(pool: &Pool<Mssql>, aplicativo_id: i32, usuario_id: i32) -> Result<bool, MfaError> {
+    let (count,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_mfa_politica p WITH(NOLOCK)
+          WHERE p.aplicativoID = @p1 AND p.requerido = 1
+            AND (p.rolID IS NULL
+                 OR p.rolID IN (SELECT ru.rolID FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK) WHERE ru.usuarioID = @p2))"
+    )
+    .bind(aplicativo_id)
+    .bind(usuario_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(count > 0)
+}
+
+/// El login ERP/Local pide el segundo factor si la política lo exige o si el usuario ya lo activó.
+pub async fn login_requires_mfa(pool: &Pool<Mssql>, aplicativo_id: i32, usuario_id: i32) -> Result<bool, MfaError> {
+    Ok(is_enrolled(pool, usuario_id).await? || is_mfa_required(pool, aplicativo_id, usuario_id).await?)
+}
+
+pub async fn list_policies(pool: &Pool<Mssql>, aplicativo_id: i32) -> Result<Vec<MfaPolicy>, MfaError> {
+    sqlx::query_as::<_, MfaPolicy>(
+        "SELECT politicaID as politica_id, aplicativoID as aplicativo_id, rolID as rol_id, requerido
+           FROM riy.riy_mfa_politica WITH(NOLOCK)
+          WHERE aplicativoID = @p1
+          ORDER BY rolID"
+    )
+    .bind(aplicativo_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)
+}
+
+/// Crea o actualiza la política del aplicativo (`rol_id = None`) o de un rol.
+pub async fn upsert_policy(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    rol_id: Option<i32>,
+    requerido: bool,
+    autor: &str,
+) -> Result<(), MfaError> {
+    sqlx::query(
+        "MERGE riy.riy_mfa_politica AS t
+         USING (SELECT @p1 AS aplicativoID, @p2 AS rolID) AS s
+            ON t.aplicativoID = s.aplicativoID AND ((t.rolID IS NULL AND s.rolID IS NULL) OR t.rolID = s.rolID)
+         WHEN MATCHED THEN UPDATE SET requerido = @p3
+         WHEN NOT MATCHED THEN INSERT (aplicativoID, rolID, requerido, autor, fechaCreacion)
+              VALUES (@p1, @p2, @p3, @p4, GETDATE());"
+    )
+    .bind(aplicativo_id)
+    .bind(rol_id)
+    .bind(requerido)
+    .bind(autor)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+pub async fn delete_policy(pool: &Pool<Mssql>, aplicativo_id: i32, politica_id: i32) -> Result<(), MfaError> {
+    let result = sqlx::query("DELETE FROM riy.riy_mfa_politica WHERE politicaID = @p1 AND aplicativoID = @p2")
+        .bind(politica_id)
+        .bind(aplicativo_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(MfaError::NotFound(format!("Política MFA {} no encontrada", politica_id)));
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------
+// TOKEN DE DESAFÍO
+// -------------------------------------------------------------------------
+
+/// Claims del token de desafío. No incluye `permissions`, por lo que el middleware
+/// de sesión no lo acepta; además se firma con una clave derivada de la de sesión.
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaChallengeClaims {
+    sub: String,
+    uid: i32,
+    purpose: String,
+    exp: u64,
+}
+
+fn challenge_secret(jwt_secret: &str) -> String {
+    format!("{}:{}", jwt_secret, CHALLENGE_PURPOSE)
+}
+
+/// Emite el token de corta duración que reemplaza al JWT de sesión en el primer paso.
+pub fn issue_challenge(usuario_id: i32, usuario: &str, jwt_secret: &str) -> Result<String, MfaError> {
+    let claims = MfaChallengeClaims {
+        sub: usuario.to_string(),
+        uid: usuario_id,
+        purpose: CHALLENGE_PURPOSE.to_string(),
+        exp: (Utc::now() + Duration::seconds(CHALLENGE_TTL_SECONDS)).timestamp() as u64,
+    };
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(challenge_secret(jwt_secret).as_bytes()),
+    )
+    .map_err(|e| MfaError::Crypto(format!("Error al crear el token de desafío: {}", e)))
+}
+
+/// Valida el token de desafío y devuelve `(usuario_id, usuario)`.
+pub fn validate_challenge(token: &str, jwt_secret: &str) -> Result<(i32, String), MfaError> {
+    let data = decode::<MfaChallengeClaims>(
+        token,
+        &DecodingKey::from_secret(challenge_secret(jwt_secret).as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map_err(|_| MfaError::InvalidCode("Token de desafío inválido o vencido".to_string()))?;
+    if data.claims.purpose != CHALLENGE_PURPOSE {
+        return Err(MfaError::InvalidCode("Token de desafío inválido".to_string()));
+    }
+    Ok((data.claims.uid, data.claims.sub))
+}
+
+/// Busca el ID de un usuario por su nombre de usuario (el `sub` del JWT de sesión).
+pub async fn find_user_id_by_username(
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    sql_collate_clause: &str,
+) -> Result<Option<i32>, MfaError> {
+    let sql_query = format!(
+        "SELECT usuario_id FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario = @p1 {}",
+        sql_collate_clause
+    );
+    let row: Option<(i32,)> = sqlx::query_as(&sql_query)
+        .bind(usuario)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(|(id,)| id))
+}
+
+/// Primer paso del login ERP/Local: si corresponde MFA devuelve el desafío que
+/// reemplaza a la sesión; `None` si el login puede completarse directamente.
+pub async fn challenge_for_login(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario: &str,
+    jwt_secret: &str,
+    sql_collate_clause: &str,
+) -> Result<Option<MfaChallengeResponse>, MfaError> {
+    let usuario_id = find_user_id_by_username(pool, usuario, sql_collate_clause)
+        .await?
+        .ok_or_else(|| MfaError::NotFound(format!("Usuario {} no encontrado", usuario)))?;
+    if !login_requires_mfa(pool, aplicativo_id, usuario_id).await? {
+        return Ok(None);
+    }
+    Ok(Some(MfaChallengeResponse {
+        mfa_required: true,
+        mfa_token: issue_challenge(usuario_id, usuario, jwt_secret)?,
+        expires_in: CHALLENGE_TTL_SECONDS,
+        enrollment_required: !is_enrolled(pool, usuario_id).await?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Semilla SHA-1 de los vectores del RFC 6238 ("12345678901234567890").
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn rfc_secret_b32() -> String {
+        base32::encode(base32::Alphabet::Rfc4648 { padding: false }, RFC_SECRET)
+    }
+
+    #[test]
+    fn hotp_cumple_los_vectores_sha1_del_rfc_6238() {
+        // RFC 6238, apéndice B (8 dígitos); con 6 dígitos son los seis últimos
+        let vectores: [(i64, u32); 6] = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+        for (tiempo, esperado) in vectores {
+            let paso = (tiempo / TOTP_STEP_SECONDS) as u64;
+            assert_eq!(hotp(RFC_SECRET, paso), esperado % TOTP_DIGITS_MODULO, "T = {}", tiempo);
+        }
+    }
+
+    #[test]
+    fn totp_acepta_un_paso_de_desfase_y_no_mas() {
+        let secreto = rfc_secret_b32();
+        let ahora = 1111111111;
+        let paso = ahora / TOTP_STEP_SECONDS;
+        let codigo = |p: i64| format!("{:06}", hotp(RFC_SECRET, p as u64));
+
+        for desfase in -TOTP_WINDOW..=TOTP_WINDOW {
+            assert_eq!(
+                verify_totp_at(&secreto, &codigo(paso + desfase), 0, ahora).unwrap(),
+                Some(paso + desfase)
+            );
+        }
+        assert_eq!(verify_totp_at(&secreto, &codigo(paso - TOTP_WINDOW - 1), 0, ahora).unwrap(), None);
+        assert_eq!(verify_totp_at(&secreto, &codigo(paso + TOTP_WINDOW + 1), 0, ahora).unwrap(), None);
+    }
+
+    #[test]
+    fn totp_rechaza_codigos_de_pasos_ya_usados() {
+        let secreto = rfc_secret_b32();
+        let ahora = 1111111111;
+        let paso = ahora / TOTP_STEP_SECONDS;
+        let codigo = format!("{:06}", hotp(RFC_SECRET, paso as u64));
+
+        assert_eq!(verify_totp_at(&secreto, &codigo, paso, ahora).unwrap(), None);
+        assert_eq!(verify_totp_at(&secreto, &codigo, paso - 1, ahora).unwrap(), Some(paso));
+    }
+
+    #[test]
+    fn totp_ignora_codigos_mal_formados() {
+        let secreto = rfc_secret_b32();
+        for codigo in ["", "12345", "1234567", "12a456"] {
+            assert_eq!(verify_totp_at(&secreto, codigo, 0, 1111111111).unwrap(), None);
+        }
+    }
+}
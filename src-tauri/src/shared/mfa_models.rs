@@ -0,0 +1,84 @@
+// src/shared/mfa_models.rs
+// Modelos del segundo factor TOTP (RFC 6238).
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::AuthResponsePayload;
+
+/// Primer paso del alta: secreto y URI `otpauth://` para generar el código QR.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaEnrollmentStart {
+    /// Secreto en Base32, para ingresarlo manualmente en la app autenticadora.
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// Confirmación del alta con el primer código generado por la app.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+/// Resultado del alta: códigos de recuperación (solo se muestran una vez).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaEnrollmentResult {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaStatus {
+    pub enrolled: bool,
+    pub required: bool,
+    pub recovery_codes_remaining: i32,
+}
+
+/// Respuesta del primer paso del login cuando se exige MFA: reemplaza a `AuthResponsePayload`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    /// Token de corta duración que identifica el login pendiente.
+    pub mfa_token: String,
+    pub expires_in: i64,
+    /// El usuario aún no tiene TOTP: debe darse de alta antes de completar el login.
+    pub enrollment_required: bool,
+}
+
+/// Segundo paso del login: código TOTP o de recuperación.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaVerifyRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+/// Alta de TOTP durante un login pendiente.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallengeEnrollRequest {
+    pub mfa_token: String,
+}
+
+/// Alta confirmada durante el login: códigos de recuperación y la sesión ya emitida.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaEnrollAndLoginResponse {
+    pub recovery_codes: Vec<String>,
+    pub session: AuthResponsePayload,
+}
+
+/// Política de MFA: para todo el aplicativo (`rol_id = None`) o para un rol.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaPolicy {
+    #[serde(default)]
+    pub politica_id: i32,
+    #[serde(default)]
+    pub aplicativo_id: i32,
+    pub rol_id: Option<i32>,
+    pub requerido: bool,
+}
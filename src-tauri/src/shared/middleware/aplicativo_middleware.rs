@@ -0,0 +1,142 @@
+// src/shared/middleware/aplicativo_middleware.rs
+/*
+Elige el aplicativo de cada solicitud para que un solo servidor API atienda varios:
+1. Si la ruta empieza con `/a/{codigo}/`, toma el código y quita el prefijo antes del ruteo
+   (`/a/ventas/api/protected/users` se atiende como `/api/protected/users`).
+2. Si no, usa la cabecera `X-Aplicativo`.
+3. Sin prefijo ni cabecera la solicitud sigue con el aplicativo del servidor (APLICATIVO_ID).
+El código se resuelve con la caché de AppState y el resto de la cadena se ejecuta dentro de
+aplicativo_logic::with_aplicativo. Se registra con `App::wrap` para actuar antes del ruteo.
+*/
+
+use crate::state::AppState;
+
+use actix_web::{
+    body::BoxBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::Uri,
+    web, Error, HttpResponse,
+};
+use futures_util::{
+    future::{self, LocalBoxFuture, Ready},
+    FutureExt,
+};
+use std::{rc::Rc, task::Poll};
+
+use crate::aplicativo_logic::{self, AplicativoError, HEADER_APLICATIVO, PREFIJO_APLICATIVO};
+use crate::app_errors::{ApiError, AppErrorCode};
+
+pub struct SelectAplicativo;
+
+impl<S> Transform<S, ServiceRequest> for SelectAplicativo
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SelectAplicativoMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ok(SelectAplicativoMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct SelectAplicativoMiddleware<S> {
+    service: Rc<S>,
+}
+
+/// `/a/{codigo}/resto?query` -> (`codigo`, `/resto?query`).
+fn split_prefix(uri: &Uri) -> Option<(String, String)> {
+    let resto = uri.path().strip_prefix(PREFIJO_APLICATIVO)?;
+    let (codigo, ruta) = match resto.find('/') {
+        Some(pos) => (&resto[..pos], &resto[pos..]),
+        None => (resto, "/"),
+    };
+    if codigo.is_empty() {
+        return None;
+    }
+    let ruta = match uri.query() {
+        Some(query) => format!("{}?{}", ruta, query),
+        None => ruta.to_string(),
+    };
+    Some((codigo.to_string(), ruta))
+}
+
+impl<S> Service<ServiceRequest> for SelectAplicativoMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let codigo = match split_prefix(req.uri()) {
+            Some((codigo, ruta)) => match ruta.parse::<Uri>() {
+                Ok(uri) => {
+                    // Igual que NormalizePath: la ruta nueva se usa para el ruteo
+                    req.match_info_mut().get_mut().update(&uri);
+                    req.head_mut().uri = uri;
+                    Some(codigo)
+                }
+                Err(_) => None,
+            },
+            None => req
+                .headers()
+                .get(HEADER_APLICATIVO)
+                .and_then(|h| h.to_str().ok())
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty()),
+        };
+
+        let svc = self.service.clone();
+        let codigo = match codigo {
+            Some(codigo) => codigo,
+            None => return svc.call(req).boxed_local(),
+        };
+        let state = match req.app_data::<web::Data<AppState>>() {
+            Some(state) => state.clone(),
+            None => {
+                let (req_parts, _pl) = req.into_parts();
+                let res = HttpResponse::InternalServerError().finish().map_into_boxed_body();
+                return future::ready(Ok(ServiceResponse::new(req_parts, res))).boxed_local();
+            }
+        };
+
+        Box::pin(async move {
+            match state.aplicativos.resolve(&state.db_pool, &codigo).await {
+                Ok(actual) => aplicativo_logic::with_aplicativo(actual, svc.call(req)).await,
+                Err(e) => {
+                    let res = match e {
+                        AplicativoError::NotFound(message) => HttpResponse::NotFound().json(ApiError {
+                            code: AppErrorCode::NotFound,
+                            message,
+                        }),
+                        AplicativoError::Forbidden(message) => HttpResponse::Forbidden().json(ApiError {
+                            code: AppErrorCode::Forbidden,
+                            message,
+                        }),
+                        other => {
+                            eprintln!("Middleware: No se pudo resolver el aplicativo '{}': {}", codigo, other);
+                            HttpResponse::InternalServerError().json(ApiError {
+                                code: AppErrorCode::DatabaseError,
+                                message: other.to_string(),
+                            })
+                        }
+                    };
+                    Ok(ServiceResponse::new(req.into_parts().0, res.map_into_boxed_body()))
+                }
+            }
+        })
+    }
+}
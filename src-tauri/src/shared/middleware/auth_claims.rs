@@ -11,11 +11,70 @@ use futures_util::future::{self, Ready};
 use serde::{Serialize, Deserialize};
 
 
+/// Permisos de administración de seguridad que nunca se ejercen durante una suplantación.
+/// Además, el middleware rechaza las modificaciones de rutas de seguridad y de usuarios
+/// (`impersonation_logic::is_blocked_route`).
+pub const PERMISOS_SEGURIDAD: &[&str] = &[
+    "suplantar_usuarios",
+    "agregar_usuario",
+    "administrar_usuarios",
+    "importar_usuarios",
+    "sincronizar_usuarios",
+    "administrar_identidades",
+    "administrar_dominios",
+    "administrar_mfa",
+    "administrar_notificaciones",
+    "administrar_permisos_temporales",
+    "administrar_solicitudes_acceso",
+    "administrar_permisos",
+    "sxf_conceptos",
+    "sxf_valores",
+    "administrar_aplicativos",
+    "administrar_secretos",
+    "administrar_ayudas",
+];
+
+/// Claim `act` (RFC 8693): quién actúa en nombre de `sub` durante una suplantación.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActorClaim {
+    /// Usuario del administrador que suplanta.
+    pub sub: String,
+    /// ID del registro en `riy.riy_suplantacion`.
+    pub sid: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
     pub permissions: Vec<String>,
     pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaim>,
+    /// Aplicativo para el que se emitió el token; no sirve en solicitudes de otro aplicativo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apl: Option<i32>,
+}
+
+impl Claims {
+    /// Indica si el token incluye el código de permiso indicado.
+    /// Durante una suplantación los permisos de seguridad se consideran ausentes.
+    pub fn has_permission(&self, codigo_permiso: &str) -> bool {
+        if self.is_impersonation() && PERMISOS_SEGURIDAD.contains(&codigo_permiso) {
+            return false;
+        }
+        self.permissions.iter().any(|p| p == codigo_permiso)
+    }
+
+    /// Cambios de la propia seguridad (correo, MFA, códigos de verificación, delegaciones y
+    /// solicitudes de acceso): cualquier usuario puede hacerlos, pero no con un token de suplantación.
+    pub fn can_manage_own_security(&self) -> bool {
+        !self.is_impersonation()
+    }
+
+    /// El token fue emitido para actuar en nombre de otro usuario.
+    pub fn is_impersonation(&self) -> bool {
+        self.act.is_some()
+    }
 }
 
 impl FromRequest for Claims {
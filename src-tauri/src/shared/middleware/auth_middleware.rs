@@ -28,11 +28,23 @@ use std::{rc::Rc, task::Poll};
 
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use crate::middleware::auth_claims::Claims;
+use crate::impersonation_logic;
+use crate::app_errors::{ApiError, AppErrorCode};
 
 
 
 pub struct Authenticated;
 
+/// El token no sirve para el aplicativo de la solicitud (ver SelectAplicativo): se emitió para
+/// otro o no indica ninguno. Los tokens sin `apl` son anteriores al aislamiento por aplicativo y
+/// llevan los permisos de uno solo, así que se rechazan y obligan a iniciar sesión de nuevo.
+async fn is_other_aplicativo(state: &AppState, apl: Option<i32>) -> bool {
+    match apl {
+        Some(apl) => apl != state.current_aplicativo_id().await,
+        None => true,
+    }
+}
+
 impl<S> Transform<S, ServiceRequest> for Authenticated
 where
     S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
@@ -71,8 +83,8 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         println!("Middleware: Starting authentication process for {}", req.uri());
 
-        let secret = match req.app_data::<web::Data<AppState>>() {
-            Some(state) => state.jwt_secret.clone(),
+        let (secret, pool, state) = match req.app_data::<web::Data<AppState>>() {
+            Some(state) => (state.jwt_secret.clone(), state.db_pool.clone(), state.clone()),
             None => {
                 let (req_parts, _pl) = req.into_parts();
                 let res = HttpResponse::InternalServerError().finish().map_into_boxed_body();
@@ -101,12 +113,65 @@ where
 
         if let Some(token) = token_data {
             println!("Middleware: Token is valid. Attaching claims to request extensions.");
+            let apl = token.claims.apl;
+
+            // Token de suplantación: vigencia en la auditoría, rutas de seguridad y registro
+            if let Some(actor) = token.claims.act.clone() {
+                let method = http_req.method().as_str().to_string();
+                let path = http_req.path().to_string();
+                http_req.extensions_mut().insert(token.claims.clone());
+                let updated_req = ServiceRequest::from_parts(http_req, pl);
+
+                return Box::pin(async move {
+                    if is_other_aplicativo(&state, apl).await {
+                        let res = HttpResponse::Unauthorized().finish().map_into_boxed_body();
+                        return Ok(ServiceResponse::new(updated_req.into_parts().0, res));
+                    }
+                    match impersonation_logic::is_active(&pool, actor.sid).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            let res = HttpResponse::Unauthorized().finish().map_into_boxed_body();
+                            return Ok(ServiceResponse::new(updated_req.into_parts().0, res));
+                        }
+                        Err(e) => {
+                            eprintln!("Middleware: Error al validar la suplantación: {}", e);
+                            let res = HttpResponse::InternalServerError().finish().map_into_boxed_body();
+                            return Ok(ServiceResponse::new(updated_req.into_parts().0, res));
+                        }
+                    }
+                    // Además del permiso que exige cada ruta (Claims::has_permission), ninguna
+                    // modificación de usuarios o de seguridad pasa con un token de suplantación.
+                    if impersonation_logic::is_blocked_route(&method, &path) {
+                        println!("Middleware: '{}' intentó {} {} durante una suplantación.", actor.sub, method, path);
+                        let res = HttpResponse::Forbidden().json(ApiError {
+                            code: AppErrorCode::Forbidden,
+                            message: "Los usuarios y la configuración de seguridad no pueden modificarse durante una suplantación.".to_string(),
+                        }).map_into_boxed_body();
+                        let _ = impersonation_logic::record_action(&pool, actor.sid, &method, &path, 403).await;
+                        return Ok(ServiceResponse::new(updated_req.into_parts().0, res));
+                    }
+                    let res = svc.call(updated_req).await?;
+                    if !matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS") {
+                        if let Err(e) = impersonation_logic::record_action(
+                            &pool, actor.sid, &method, &path, res.status().as_u16(),
+                        ).await {
+                            eprintln!("Middleware: No se pudo auditar la acción suplantada: {}", e);
+                        }
+                    }
+                    Ok(res.map_into_boxed_body())
+                });
+            }
             
             http_req.extensions_mut().insert(token.claims.clone());
 
             let updated_req = ServiceRequest::from_parts(http_req, pl);
             
             Box::pin(async move {
+                if is_other_aplicativo(&state, apl).await {
+                    println!("Middleware: Token sin aplicativo o emitido para otro. Returning 401.");
+                    let res = HttpResponse::Unauthorized().finish().map_into_boxed_body();
+                    return Ok(ServiceResponse::new(updated_req.into_parts().0, res));
+                }
                 let res = svc.call(updated_req).await?;
                 println!("Middleware: Request handled successfully.");
                 Ok(res.map_into_boxed_body())
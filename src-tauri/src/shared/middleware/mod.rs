@@ -1,4 +1,5 @@
 // src-tauri/src/shared/middleware/mod.rs
 
 pub mod auth_claims;
-pub mod auth_middleware;
\ No newline at end of file
+pub mod auth_middleware;
+pub mod aplicativo_middleware;
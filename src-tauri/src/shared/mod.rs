@@ -13,3 +13,52 @@ pub mod user_repository;
 pub mod utils; // 👈 DECLARACIÓN NECESARIA
 pub mod auth_providers; // es una carpeta
 pub mod config; // Asegúrate de que esta línea esté presente
+pub mod user_sync_models;
+pub mod user_sync_logic;
+
+pub mod role_logic;
+pub mod user_import_models;
+pub mod user_import_logic;
+pub mod scim_models;
+pub mod scim_logic;
+pub mod identity_link_logic;
+pub mod domain_policy_logic;
+pub mod mfa_models;
+pub mod mfa_logic;
+pub mod notification_models;
+pub mod notification_logic;
+pub mod profile_models;
+pub mod profile_logic;
+pub mod impersonation_models;
+pub mod impersonation_logic;
+pub mod grant_models;
+pub mod grant_logic;
+pub mod access_request_models;
+pub mod access_request_logic;
+pub mod permission_catalog_models;
+pub mod permission_catalog_logic;
+pub mod row_security_models;
+pub mod row_security_logic;
+pub mod secured_query_models;
+pub mod secured_query_logic;
+pub mod secured_view_logic;
+pub mod saved_query_models;
+pub mod saved_query_logic;
+pub mod sql_sandbox_models;
+pub mod sql_sandbox_logic;
+pub mod export_models;
+pub mod export_logic;
+pub mod user_view_models;
+pub mod user_view_logic;
+pub mod help_models;
+pub mod help_logic;
+pub mod aplicativo_models;
+pub mod aplicativo_logic;
+pub mod connection_profile_models;
+pub mod connection_profile_logic;
+pub mod secret_store_models;
+pub mod secret_store_logic;
+pub mod oauth_loopback_models;
+pub mod oauth_loopback_logic;
+pub mod offline_cache_models;
+pub mod offline_cache_logic;
@@ -28,6 +28,8 @@ pub enum LoginType {
     Erp,
     /// Autenticación contra la tabla de usuarios local (e.g., para administradores).
     Local,
+    /// Autenticación con Google (código de autorización + `redirect_uri`).
+    Google,
 }
 
 // --- Modelos de persistencia ---
@@ -48,6 +50,21 @@ pub struct Usuario {
     pub fecha_codigo_verificacion: Option<String>, // Usar un tipo de dato de fecha/hora más preciso
 }
 
+/// Fila del listado de usuarios: `Usuario` sin el código de verificación ni su fecha,
+/// que no deben salir del servidor.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UsuarioResumen {
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub nombre: String,
+    pub correo: String,
+    pub estado: String,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
 /// Estructura para crear un nuevo usuario (sin el ID).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewUsuario {
@@ -113,7 +130,9 @@ pub struct AuthRequestPayload {
     pub proof_of_identity: String,
     /// URI necesaria para el intercambio de código (solo para Google)
     pub redirect_uri: Option<String>, // Lo hacemos opcional ya que no siempre es necesario
-    
+    /// Verificador PKCE del código de autorización (login desde el escritorio con el navegador).
+    #[serde(default)]
+    pub code_verifier: Option<String>,
 }
 
 /// Estructura para la respuesta de autenticación (Output) que se envía al frontend.
@@ -151,9 +170,22 @@ pub struct UserInfo {
     pub username: String, // Clave principal de identificación
     pub email: String,
     pub name: Option<String>,
+    /// Identidad estable del proveedor externo (oid/sub); `None` para ERP y Local.
+    #[serde(default)]
+    pub identity: Option<ExternalIdentity>,
     // Podríamos añadir más datos como roles si la fuente externa los proporciona.
 }
 
+/// Proveedor + sujeto (oid de Microsoft, sub de Google) que identifica al usuario en el IdP.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    /// Emisor del token (`iss`); identifica el tenant de Microsoft. Se usa en la política de dominios.
+    #[serde(default)]
+    pub issuer: Option<String>,
+}
+
 /// Estructura para la ASIGNACIÓN DE ROL ESPECÍFICA DE LA APLICACIÓN.
 #[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
 pub struct UserRoleAssignment {
@@ -162,3 +194,165 @@ pub struct UserRoleAssignment {
     pub sub_rol: String, 
 }
 
+
+
+// --- Modelos de Listado Paginado ---
+
+/// Dirección de ordenamiento para los listados paginados.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Columnas visibles de `riy.riy_usuario` por las que se puede ordenar el listado.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UsuarioSortColumn {
+    #[default]
+    UsuarioId,
+    Usuario,
+    Nombre,
+    Correo,
+    Estado,
+    Autor,
+    FechaCreacion,
+    ModificadoPor,
+    FechaModificacion,
+}
+
+/// Parámetros de consulta para el listado de usuarios (`GET /users` y comando `get_users`).
+///
+/// Se admite paginación por desplazamiento (`page`/`pageSize`) o por cursor (`cursor`),
+/// este último devuelto como `nextCursor` en la página anterior.
+/// Las fechas se esperan en formato `YYYY-MM-DD` y los rangos son inclusivos.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListQuery {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+    pub cursor: Option<String>,
+    pub sort_by: Option<UsuarioSortColumn>,
+    pub sort_dir: Option<SortDirection>,
+    pub estado: Option<String>,
+    pub autor: Option<String>,
+    pub fecha_creacion_desde: Option<String>,
+    pub fecha_creacion_hasta: Option<String>,
+    pub fecha_modificacion_desde: Option<String>,
+    pub fecha_modificacion_hasta: Option<String>,
+    /// Texto libre que se busca en `usuario`, `nombre` y `correo`.
+    pub search: Option<String>,
+}
+
+/// Página de resultados del listado de usuarios.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListPage {
+    pub items: Vec<UsuarioResumen>,
+    /// Total de filas que cumplen los filtros (sin paginar).
+    pub total: i64,
+    pub page: u32,
+    pub page_size: u32,
+    /// Cursor para pedir la página siguiente por keyset; `None` si no hay más filas.
+    pub next_cursor: Option<String>,
+}
+
+/// Rol definido para un aplicativo (`riy.riy_SeguridadRol`).
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rol {
+    pub rol_id: i32,
+    pub aplicativo_id: i32,
+    pub rol: String,
+    pub descripcion: Option<String>,
+}
+
+/// Vínculo entre un usuario de riy y una identidad externa (`riy.riy_usuario_identidad`).
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityLink {
+    pub identidad_id: i32,
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub proveedor: String,
+    pub sujeto: String,
+    /// Correo informado por el IdP la última vez que se usó el vínculo.
+    pub correo: Option<String>,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub fecha_ultimo_acceso: Option<String>,
+}
+
+/// Vinculación manual de una identidad externa a un usuario (vista de administración).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityLinkRequest {
+    pub proveedor: String,
+    pub sujeto: String,
+    pub correo: Option<String>,
+}
+
+/// Política aplicada a los logins externos de un dominio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainPolicyMode {
+    /// Crea el usuario en riy en su primer login.
+    AutoProvision,
+    /// Solo permite el acceso a usuarios que ya existen en riy.
+    RequireExisting,
+}
+
+impl DomainPolicyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainPolicyMode::AutoProvision => "auto_provision",
+            DomainPolicyMode::RequireExisting => "require_existing",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto_provision" => Some(DomainPolicyMode::AutoProvision),
+            "require_existing" => Some(DomainPolicyMode::RequireExisting),
+            _ => None,
+        }
+    }
+}
+
+/// Dominio permitido para login externo en un aplicativo (`riy.riy_dominio_permitido`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainPolicy {
+    pub dominio_id: i32,
+    pub aplicativo_id: i32,
+    pub dominio: String,
+    pub politica: DomainPolicyMode,
+    /// Rol asignado a los usuarios creados por auto-aprovisionamiento.
+    pub rol_por_defecto_id: Option<i32>,
+    /// Proveedores aceptados (`microsoft`, `google`); vacío = todos.
+    pub proveedores_permitidos: Vec<String>,
+    /// Emisores aceptados (`iss` completo, o ID de tenant de Microsoft); vacío = cualquiera.
+    pub emisores_permitidos: Vec<String>,
+    pub activo: bool,
+}
+
+/// Alta o modificación de una política de dominio.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainPolicyRequest {
+    pub dominio: String,
+    pub politica: DomainPolicyMode,
+    pub rol_por_defecto_id: Option<i32>,
+    #[serde(default)]
+    pub proveedores_permitidos: Vec<String>,
+    #[serde(default)]
+    pub emisores_permitidos: Vec<String>,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
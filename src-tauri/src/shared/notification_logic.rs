@@ -0,0 +1,603 @@
+// src/shared/notification_logic.rs
+/*
+Notificaciones por correo: plantillas, cola con reintentos y envío SMTP.
+
+Los correos nunca se envían en la misma petición que los origina: se encolan en
+riy_notificacion_cola y un worker del servidor API los entrega, reintentando con
+espera exponencial hasta SMTP_MAX_ATTEMPTS. Para probar sin un servidor real basta
+con SMTP_HOST=localhost y SMTP_TLS=none contra MailHog/smtp4dev (puerto 1025).
+
+CREATE TABLE riy.riy_notificacion_cola (
+    notificacionID  INT IDENTITY(1,1) PRIMARY KEY,
+    tipo            VARCHAR(40)    NOT NULL,
+    destinatario    VARCHAR(255)   NOT NULL,
+    asunto          NVARCHAR(255)  NOT NULL,
+    cuerpoTexto     NVARCHAR(MAX)  NOT NULL,
+    cuerpoHtml      NVARCHAR(MAX)  NOT NULL,
+    estado          VARCHAR(20)    NOT NULL DEFAULT 'Pendiente',  -- Pendiente | Enviado | Fallido
+    intentos        INT            NOT NULL DEFAULT 0,
+    ultimoError     NVARCHAR(1000) NULL,
+    proximoIntento  DATETIME       NOT NULL DEFAULT GETDATE(),
+    fechaCreacion   DATETIME       NOT NULL DEFAULT GETDATE(),
+    fechaEnvio      DATETIME       NULL
+);
+CREATE INDEX IX_riy_notificacion_cola_pendientes ON riy.riy_notificacion_cola (estado, proximoIntento);
+*/
+
+use std::fmt;
+
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rand::Rng;
+use sqlx::{Mssql, Pool};
+
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::notification_models::{NotificationKind, QueueRunSummary, QueuedNotification, RenderedEmail};
+
+pub const ESTADO_PENDIENTE: &str = "Pendiente";
+pub const ESTADO_ENVIADO: &str = "Enviado";
+pub const ESTADO_FALLIDO: &str = "Fallido";
+
+/// Vigencia de los códigos de verificación y de restablecimiento.
+pub const CODE_TTL_MINUTES: i32 = 15;
+/// Notificaciones procesadas por pasada del worker.
+const QUEUE_BATCH_SIZE: i32 = 50;
+/// Espera máxima entre reintentos.
+const MAX_BACKOFF_SECONDS: i64 = 3600;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Debug)]
+pub enum NotificationError {
+    Validation(String),
+    /// Código de verificación incorrecto o vencido.
+    InvalidCode(String),
+    NotFound(String),
+    Smtp(String),
+    Database(String),
+}
+
+impl fmt::Display for NotificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotificationError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            NotificationError::InvalidCode(msg) => write!(f, "Código inválido: {}", msg),
+            NotificationError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            NotificationError::Smtp(msg) => write!(f, "Error SMTP: {}", msg),
+            NotificationError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for NotificationError {}
+
+fn db_error(e: sqlx::Error) -> NotificationError {
+    NotificationError::Database(e.to_string())
+}
+
+// -------------------------------------------------------------------------
+// PLANTILLAS
+// -------------------------------------------------------------------------
+
+/// Asunto y cuerpo (texto plano) de cada tipo; las variables van como `{{nombre}}`.
+fn template(kind: NotificationKind) -> (&'static str, &'static str) {
+    match kind {
+        NotificationKind::NuevaCuenta => (
+            "Bienvenido a riy",
+            "Hola {{nombre}}:\n\nSe creó tu cuenta en riy con el usuario {{usuario}}.\n\
+             Ya puedes iniciar sesión con el método de acceso que te indicó el administrador.",
+        ),
+        NotificationKind::CodigoVerificacion => (
+            "Código de verificación",
+            "Hola {{nombre}}:\n\nTu código de verificación es {{codigo}}.\n\
+             Vence en {{minutos}} minutos. Si no lo solicitaste, ignora este correo.",
+        ),
+        NotificationKind::RestablecerContrasena => (
+            "Restablecer contraseña",
+            "Hola {{nombre}}:\n\nRecibimos una solicitud para restablecer la contraseña del usuario {{usuario}}.\n\
+             Tu código es {{codigo}} y vence en {{minutos}} minutos.\n\
+             Si no la solicitaste, ignora este correo: tu contraseña no cambiará.",
+        ),
+        NotificationKind::LicenciaPorVencer => (
+            "La licencia de riy está por vencer",
+            "La licencia del aplicativo {{aplicativo}} vence el {{fecha_caducidad}} ({{dias}} días).\n\
+             Renueva las credenciales antes de esa fecha para evitar la interrupción del servicio.",
+        ),
+        NotificationKind::CuentaBloqueada => (
+            "Tu cuenta fue bloqueada",
+            "Hola {{nombre}}:\n\nTu cuenta {{usuario}} fue bloqueada.\nMotivo: {{motivo}}\n\
+             Comunícate con el administrador si crees que se trata de un error.",
+        ),
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn fill(template: &str, vars: &[(&str, &str)], escape: bool) -> String {
+    vars.iter().fold(template.to_string(), |acc, (key, value)| {
+        let value = if escape { escape_html(value) } else { value.to_string() };
+        acc.replace(&format!("{{{{{}}}}}", key), &value)
+    })
+}
+
+/// Renderiza la plantilla del tipo indicado con las variables dadas.
+pub fn render(kind: NotificationKind, vars: &[(&str, &str)]) -> RenderedEmail {
+    let (subject, body) = template(kind);
+    let body_html = fill(body, vars, true)
+        .split("\n\n")
+        .map(|p| format!("<p>{}</p>", p.replace('\n', "<br>")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    RenderedEmail {
+        subject: fill(subject, vars, false),
+        body_text: fill(body, vars, false),
+        body_html: format!("<html><body style=\"font-family:sans-serif\">\n{}\n</body></html>", body_html),
+    }
+}
+
+// -------------------------------------------------------------------------
+// COLA
+// -------------------------------------------------------------------------
+
+/// Renderiza y encola una notificación. Devuelve el ID en la cola.
+pub async fn enqueue(
+    pool: &Pool<Mssql>,
+    kind: NotificationKind,
+    destinatario: &str,
+    vars: &[(&str, &str)],
+) -> Result<i32, NotificationError> {
+    let destinatario = destinatario.trim();
+    if destinatario.parse::<Mailbox>().is_err() {
+        return Err(NotificationError::Validation(format!("Destinatario inválido: '{}'", destinatario)));
+    }
+    let email = render(kind, vars);
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_notificacion_cola (tipo, destinatario, asunto, cuerpoTexto, cuerpoHtml, estado)
+         OUTPUT INSERTED.notificacionID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6)"
+    )
+    .bind(kind.as_str())
+    .bind(destinatario)
+    .bind(&email.subject)
+    .bind(&email.body_text)
+    .bind(&email.body_html)
+    .bind(ESTADO_PENDIENTE)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(id)
+}
+
+const QUEUE_COLUMNS: &str = "notificacionID as notificacion_id, tipo, destinatario, asunto,
+        cuerpoTexto as cuerpo_texto, cuerpoHtml as cuerpo_html, estado, intentos,
+        ultimoError as ultimo_error,
+        CONVERT(VARCHAR(19), fechaCreacion, 120) as fecha_creacion,
+        CONVERT(VARCHAR(19), proximoIntento, 120) as proximo_intento,
+        CONVERT(VARCHAR(19), fechaEnvio, 120) as fecha_envio";
+
+/// Lista la cola (más recientes primero), opcionalmente filtrada por estado.
+pub async fn list_queue(
+    pool: &Pool<Mssql>,
+    estado: Option<&str>,
+    limit: Option<i32>,
+) -> Result<Vec<QueuedNotification>, NotificationError> {
+    let limit = limit.unwrap_or(100).clamp(1, 500);
+    let sql_query = format!(
+        "SELECT TOP ({}) {} FROM riy.riy_notificacion_cola WITH(NOLOCK)
+          WHERE (@p1 IS NULL OR estado = @p1)
+          ORDER BY notificacionID DESC",
+        limit, QUEUE_COLUMNS
+    );
+    sqlx::query_as::<_, QueuedNotification>(&sql_query)
+        .bind(estado)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Vuelve a poner en cola una notificación fallida (reinicia los intentos).
+pub async fn retry(pool: &Pool<Mssql>, notificacion_id: i32) -> Result<(), NotificationError> {
+    let result = sqlx::query(
+        "UPDATE riy.riy_notificacion_cola
+            SET estado = @p1, intentos = 0, ultimoError = NULL, proximoIntento = GETDATE()
+          WHERE notificacionID = @p2 AND estado <> @p3"
+    )
+    .bind(ESTADO_PENDIENTE)
+    .bind(notificacion_id)
+    .bind(ESTADO_ENVIADO)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(NotificationError::NotFound(format!(
+            "Notificación {} no encontrada o ya enviada", notificacion_id
+        )));
+    }
+    Ok(())
+}
+
+fn build_transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, NotificationError> {
+    let builder = match config.tls {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .map_err(|e| NotificationError::Smtp(e.to_string()))?,
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+            .map_err(|e| NotificationError::Smtp(e.to_string()))?,
+    }
+    .port(config.port);
+
+    let builder = match (&config.username, &config.password) {
+        (Some(user), Some(password)) => builder.credentials(Credentials::new(user.clone(), password.clone())),
+        _ => builder,
+    };
+    Ok(builder.build())
+}
+
+async fn deliver(
+    transport: &AsyncSmtpTransport<Tokio1Executor>,
+    from: &Mailbox,
+    notification: &QueuedNotification,
+) -> Result<(), NotificationError> {
+    let to: Mailbox = notification
+        .destinatario
+        .parse()
+        .map_err(|e| NotificationError::Validation(format!("Destinatario inválido: {}", e)))?;
+    let message = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&notification.asunto)
+        .multipart(MultiPart::alternative_plain_html(
+            notification.cuerpo_texto.clone(),
+            notification.cuerpo_html.clone(),
+        ))
+        .map_err(|e| NotificationError::Smtp(e.to_string()))?;
+    transport
+        .send(message)
+        .await
+        .map_err(|e| NotificationError::Smtp(e.to_string()))?;
+    Ok(())
+}
+
+/// Espera antes del siguiente intento: 1, 2, 4... minutos, con tope de una hora.
+fn backoff_seconds(intentos: i32) -> i64 {
+    let exponent = (intentos.max(1) - 1).min(10) as u32;
+    (60_i64 * 2_i64.pow(exponent)).min(MAX_BACKOFF_SECONDS)
+}
+
+/// Envía las notificaciones pendientes cuyo próximo intento ya venció.
+pub async fn process_queue(pool: &Pool<Mssql>, config: &SmtpConfig) -> Result<QueueRunSummary, NotificationError> {
+    let mut summary = QueueRunSummary::default();
+    if !config.is_enabled() {
+        return Ok(summary);
+    }
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| NotificationError::Validation(format!("SMTP_FROM inválido: {}", e)))?;
+    let transport = build_transport(config)?;
+
+    let sql_query = format!(
+        "SELECT TOP ({}) {} FROM riy.riy_notificacion_cola WITH(NOLOCK)
+          WHERE estado = @p1 AND proximoIntento <= GETDATE()
+          ORDER BY proximoIntento, notificacionID",
+        QUEUE_BATCH_SIZE, QUEUE_COLUMNS
+    );
+    let pending = sqlx::query_as::<_, QueuedNotification>(&sql_query)
+        .bind(ESTADO_PENDIENTE)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    for notification in pending {
+        match deliver(&transport, &from, &notification).await {
+            Ok(()) => {
+                sqlx::query(
+                    "UPDATE riy.riy_notificacion_cola
+                        SET estado = @p1, intentos = intentos + 1, ultimoError = NULL, fechaEnvio = GETDATE()
+                      WHERE notificacionID = @p2"
+                )
+                .bind(ESTADO_ENVIADO)
+                .bind(notification.notificacion_id)
+                .execute(pool)
+                .await
+                .map_err(db_error)?;
+                summary.enviados += 1;
+            }
+            Err(e) => {
+                let intentos = notification.intentos + 1;
+                let estado = if intentos >= config.max_attempts { ESTADO_FALLIDO } else { ESTADO_PENDIENTE };
+                eprintln!(
+                    "notification_logic: fallo al enviar la notificación {} (intento {}): {}",
+                    notification.notificacion_id, intentos, e
+                );
+                sqlx::query(
+                    "UPDATE riy.riy_notificacion_cola
+                        SET estado = @p1, intentos = @p2, ultimoError = @p3,
+                            proximoIntento = DATEADD(SECOND, @p4, GETDATE())
+                      WHERE notificacionID = @p5"
+                )
+                .bind(estado)
+                .bind(intentos)
+                .bind(e.to_string())
+                .bind(backoff_seconds(intentos))
+                .bind(notification.notificacion_id)
+                .execute(pool)
+                .await
+                .map_err(db_error)?;
+                if estado == ESTADO_FALLIDO {
+                    summary.fallidos += 1;
+                } else {
+                    summary.reintentos += 1;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+// -------------------------------------------------------------------------
+// NOTIFICACIONES DE NEGOCIO
+// -------------------------------------------------------------------------
+
+/// (usuario, nombre, correo) del usuario.
+async fn load_contact(pool: &Pool<Mssql>, usuario_id: i32) -> Result<(String, String, String), NotificationError> {
+    sqlx::query_as::<_, (String, String, String)>(
+        "SELECT usuario, nombre, correo FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario_id = @p1"
+    )
+    .bind(usuario_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| NotificationError::NotFound(format!("Usuario {} no encontrado", usuario_id)))
+}
+
+/// Aviso de bienvenida al crear una cuenta. No hace nada si el usuario no tiene correo.
+pub async fn notify_new_account(pool: &Pool<Mssql>, usuario_id: i32) -> Result<(), NotificationError> {
+    let (usuario, nombre, correo) = load_contact(pool, usuario_id).await?;
+    if correo.trim().is_empty() {
+        return Ok(());
+    }
+    enqueue(pool, NotificationKind::NuevaCuenta, &correo, &[("usuario", &usuario), ("nombre", &nombre)]).await?;
+    Ok(())
+}
+
+/// Aviso de cuenta bloqueada/desactivada.
+pub async fn notify_account_locked(pool: &Pool<Mssql>, usuario_id: i32, motivo: &str) -> Result<(), NotificationError> {
+    let (usuario, nombre, correo) = load_contact(pool, usuario_id).await?;
+    if correo.trim().is_empty() {
+        return Ok(());
+    }
+    enqueue(
+        pool,
+        NotificationKind::CuentaBloqueada,
+        &correo,
+        &[("usuario", &usuario), ("nombre", &nombre), ("motivo", motivo)],
+    ).await?;
+    Ok(())
+}
+
+/// Genera un código numérico de 6 dígitos, lo guarda en `codigo_verificacion` y lo envía
+/// con la plantilla indicada (`CodigoVerificacion` o `RestablecerContrasena`).
+/// Si `destinatario` es `None` se usa el correo registrado del usuario.
+pub async fn send_verification_code(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    kind: NotificationKind,
+    destinatario: Option<&str>,
+) -> Result<(), NotificationError> {
+    let (usuario, nombre, correo) = load_contact(pool, usuario_id).await?;
+    let destinatario = destinatario.unwrap_or(&correo);
+    if destinatario.trim().is_empty() {
+        return Err(NotificationError::Validation("El usuario no tiene un correo registrado".to_string()));
+    }
+    let codigo: i32 = rand::thread_rng().gen_range(100_000..1_000_000);
+
+    sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET codigo_verificacion = @p1, fecha_codigo_verificacion = GETDATE()
+          WHERE usuario_id = @p2"
+    )
+    .bind(codigo)
+    .bind(usuario_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    let codigo = codigo.to_string();
+    let minutos = CODE_TTL_MINUTES.to_string();
+    enqueue(
+        pool,
+        kind,
+        destinatario,
+        &[("usuario", &usuario), ("nombre", &nombre), ("codigo", &codigo), ("minutos", &minutos)],
+    ).await?;
+    Ok(())
+}
+
+/// Comprueba y consume el código de verificación vigente del usuario.
+pub async fn consume_verification_code(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    codigo: i32,
+) -> Result<(), NotificationError> {
+    let result = sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET codigo_verificacion = NULL, fecha_codigo_verificacion = NULL
+          WHERE usuario_id = @p1
+            AND codigo_verificacion = @p2
+            AND DATEDIFF(MINUTE, fecha_codigo_verificacion, GETDATE()) < @p3"
+    )
+    .bind(usuario_id)
+    .bind(codigo)
+    .bind(CODE_TTL_MINUTES)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(NotificationError::InvalidCode("El código es incorrecto o ya venció".to_string()));
+    }
+    Ok(())
+}
+
+/// Busca una cuenta local (con `clave`) por nombre de usuario.
+async fn find_local_user_id(
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    sql_collate_clause: &str,
+) -> Result<Option<i32>, NotificationError> {
+    let sql_query = format!(
+        "SELECT usuario_id FROM riy.riy_usuario WITH(NOLOCK)
+          WHERE usuario = @p1 {} AND clave IS NOT NULL",
+        sql_collate_clause
+    );
+    let row: Option<(i32,)> = sqlx::query_as(&sql_query)
+        .bind(usuario.trim())
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(|(id,)| id))
+}
+
+/// Envía el código de restablecimiento. No revela si el usuario existe: responde `Ok`
+/// también para usuarios desconocidos o sin contraseña local.
+pub async fn request_password_reset(
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    sql_collate_clause: &str,
+) -> Result<(), NotificationError> {
+    match find_local_user_id(pool, usuario, sql_collate_clause).await? {
+        Some(usuario_id) => {
+            match send_verification_code(pool, usuario_id, NotificationKind::RestablecerContrasena, None).await {
+                Ok(()) | Err(NotificationError::Validation(_)) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+        None => Ok(()),
+    }
+}
+
+/// Valida el código de restablecimiento y guarda la nueva contraseña (bcrypt).
+pub async fn confirm_password_reset(
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    codigo: i32,
+    nueva_clave: &str,
+    sql_collate_clause: &str,
+) -> Result<(), NotificationError> {
+    if nueva_clave.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(NotificationError::Validation(format!(
+            "La contraseña debe tener al menos {} caracteres", MIN_PASSWORD_LENGTH
+        )));
+    }
+    let usuario_id = find_local_user_id(pool, usuario, sql_collate_clause)
+        .await?
+        .ok_or_else(|| NotificationError::InvalidCode("El código es incorrecto o ya venció".to_string()))?;
+    consume_verification_code(pool, usuario_id, codigo).await?;
+
+    let hash = bcrypt::hash(nueva_clave, bcrypt::DEFAULT_COST)
+        .map_err(|e| NotificationError::Validation(format!("No se pudo cifrar la contraseña: {}", e)))?;
+    sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET clave = @p1, modificado_por = @p2, fecha_modificacion = GETDATE()
+          WHERE usuario_id = @p3"
+    )
+    .bind(hash)
+    .bind(usuario.trim())
+    .bind(usuario_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Encola el aviso de licencia por vencer para los destinatarios administrativos,
+/// como máximo una vez por día.
+pub async fn enqueue_license_warning(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    aplicativo: &str,
+    config: &SmtpConfig,
+) -> Result<(), NotificationError> {
+    if config.license_warning_days <= 0 || config.admin_recipients.is_empty() {
+        return Ok(());
+    }
+    let license: Option<(String, i32)> = sqlx::query_as(
+        "SELECT TOP 1 CONVERT(VARCHAR(10), fechaCaducidad, 120), DATEDIFF(DAY, GETDATE(), fechaCaducidad)
+           FROM riy.riy_licencia WITH(NOLOCK)
+          WHERE aplicativoID = @p1
+          ORDER BY fechaCaducidad DESC"
+    )
+    .bind(aplicativo_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+
+    let (fecha_caducidad, dias) = match license {
+        Some(l) => l,
+        None => return Ok(()),
+    };
+    if dias < 0 || i64::from(dias) > config.license_warning_days {
+        return Ok(());
+    }
+
+    let (ya_avisado,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_notificacion_cola WITH(NOLOCK)
+          WHERE tipo = @p1 AND CAST(fechaCreacion AS DATE) = CAST(GETDATE() AS DATE)"
+    )
+    .bind(NotificationKind::LicenciaPorVencer.as_str())
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if ya_avisado > 0 {
+        return Ok(());
+    }
+
+    let dias = dias.to_string();
+    for destinatario in &config.admin_recipients {
+        enqueue(
+            pool,
+            NotificationKind::LicenciaPorVencer,
+            destinatario,
+            &[("aplicativo", aplicativo), ("fecha_caducidad", &fecha_caducidad), ("dias", &dias)],
+        ).await?;
+    }
+    Ok(())
+}
+
+/// Lanza el worker que vacía la cola y revisa el vencimiento de la licencia.
+/// Sin `SMTP_HOST` solo se encola (el worker no arranca).
+pub fn spawn_queue_worker(pool: Pool<Mssql>, aplicativo_id: i32, aplicativo: String, config: SmtpConfig) {
+    if !config.is_enabled() {
+        println!("notification_logic: SMTP_HOST no definido; las notificaciones quedarán en cola.");
+        return;
+    }
+    let period = std::time::Duration::from_secs(config.queue_interval_seconds.max(5));
+    println!(
+        "notification_logic: worker de correo cada {} s contra {}:{}.",
+        period.as_secs(), config.host, config.port
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = enqueue_license_warning(&pool, aplicativo_id, &aplicativo, &config).await {
+                eprintln!("notification_logic: Error al revisar el vencimiento de la licencia: {}", e);
+            }
+            match process_queue(&pool, &config).await {
+                Ok(s) if s.enviados + s.reintentos + s.fallidos > 0 => println!(
+                    "notification_logic: enviados {}, reintentos {}, fallidos {}",
+                    s.enviados, s.reintentos, s.fallidos
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("notification_logic: Error al procesar la cola: {}", e),
+            }
+        }
+    });
+}
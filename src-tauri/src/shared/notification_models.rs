@@ -0,0 +1,102 @@
+// src/shared/notification_models.rs
+// Modelos de las notificaciones por correo y de su cola de envío.
+
+use serde::{Deserialize, Serialize};
+
+/// Tipos de notificación con plantilla propia.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    NuevaCuenta,
+    CodigoVerificacion,
+    RestablecerContrasena,
+    LicenciaPorVencer,
+    CuentaBloqueada,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::NuevaCuenta => "nueva_cuenta",
+            NotificationKind::CodigoVerificacion => "codigo_verificacion",
+            NotificationKind::RestablecerContrasena => "restablecer_contrasena",
+            NotificationKind::LicenciaPorVencer => "licencia_por_vencer",
+            NotificationKind::CuentaBloqueada => "cuenta_bloqueada",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "nueva_cuenta" => Some(NotificationKind::NuevaCuenta),
+            "codigo_verificacion" => Some(NotificationKind::CodigoVerificacion),
+            "restablecer_contrasena" => Some(NotificationKind::RestablecerContrasena),
+            "licencia_por_vencer" => Some(NotificationKind::LicenciaPorVencer),
+            "cuenta_bloqueada" => Some(NotificationKind::CuentaBloqueada),
+            _ => None,
+        }
+    }
+}
+
+/// Correo ya renderizado a partir de una plantilla.
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub body_text: String,
+    pub body_html: String,
+}
+
+/// Fila de `riy.riy_notificacion_cola`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedNotification {
+    pub notificacion_id: i32,
+    pub tipo: String,
+    pub destinatario: String,
+    pub asunto: String,
+    #[serde(skip_serializing)]
+    pub cuerpo_texto: String,
+    #[serde(skip_serializing)]
+    pub cuerpo_html: String,
+    /// `Pendiente`, `Enviado` o `Fallido`.
+    pub estado: String,
+    pub intentos: i32,
+    pub ultimo_error: Option<String>,
+    pub fecha_creacion: String,
+    pub proximo_intento: Option<String>,
+    pub fecha_envio: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationQueueQuery {
+    pub estado: Option<String>,
+    pub limit: Option<i32>,
+}
+
+/// Resultado de una pasada del worker de la cola.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueRunSummary {
+    pub enviados: i32,
+    pub reintentos: i32,
+    pub fallidos: i32,
+}
+
+/// Solicitud de restablecimiento de contraseña (cuentas locales).
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordResetRequest {
+    pub usuario: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordResetConfirm {
+    pub usuario: String,
+    pub codigo: i32,
+    pub nueva_clave: String,
+}
+
+/// Confirmación del código de verificación enviado al correo del usuario.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerificationCodeConfirm {
+    pub codigo: i32,
+}
@@ -0,0 +1,311 @@
+// src/shared/oauth_loopback_logic.rs
+/*
+Login del escritorio con el navegador del sistema (patrón de aplicaciones nativas, RFC 8252).
+
+1. Se abre un listener HTTP de un solo uso en `127.0.0.1` con un puerto libre al azar; su
+   `http://127.0.0.1:{puerto}/callback` es el redirect_uri (Google y Microsoft aceptan cualquier
+   puerto de loopback para clientes de escritorio).
+2. Se abre el navegador en la página de autorización del proveedor con PKCE (S256) y `state`.
+3. El proveedor redirige al listener con `code` y `state`; se valida el `state`, se responde una
+   página para cerrar la pestaña y se cierra el listener.
+4. El código se canjea y el resultado entra al mismo flujo que el resto de logins
+   (user_logic::authenticate_user): Google con el código y el verificador PKCE; Microsoft con el
+   ID token obtenido aquí, ya que el cliente público no tiene secreto.
+
+La espera termina con la respuesta, con el tiempo máximo (OAUTH_LOGIN_TIMEOUT_SECONDS) o al
+cancelar desde la pantalla.
+*/
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use url::Url;
+
+use crate::config::AppConfig;
+use crate::models::{AuthRequestPayload, LoginType};
+use crate::oauth_loopback_models::{OAuthProvider, PkceChallenge};
+
+/// Ruta del redirect_uri en el listener.
+pub const CALLBACK_PATH: &str = "/callback";
+
+const GOOGLE_AUTHORIZE_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_SCOPES: &str = "openid email profile";
+/// Tamaño máximo de la cabecera de la solicitud del navegador.
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+#[derive(Debug)]
+pub enum OAuthLoopbackError {
+    Validation(String),
+    /// El proveedor devolvió un error (p. ej. el usuario canceló en la página).
+    Provider(String),
+    Io(String),
+    Timeout,
+    Cancelled,
+}
+
+impl fmt::Display for OAuthLoopbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OAuthLoopbackError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            OAuthLoopbackError::Provider(msg) => write!(f, "El proveedor rechazó el login: {}", msg),
+            OAuthLoopbackError::Io(msg) => write!(f, "Error del listener local: {}", msg),
+            OAuthLoopbackError::Timeout => write!(f, "Se agotó el tiempo de espera del login en el navegador"),
+            OAuthLoopbackError::Cancelled => write!(f, "Login en el navegador cancelado"),
+        }
+    }
+}
+
+impl std::error::Error for OAuthLoopbackError {}
+
+fn io_error(e: std::io::Error) -> OAuthLoopbackError {
+    OAuthLoopbackError::Io(e.to_string())
+}
+
+fn random_urlsafe(bytes: usize) -> String {
+    let valores: Vec<u8> = (0..bytes).map(|_| rand::random::<u8>()).collect();
+    URL_SAFE_NO_PAD.encode(valores)
+}
+
+/// Verificador de 32 bytes aleatorios (43 caracteres), desafío S256 y `state`.
+pub fn new_pkce() -> PkceChallenge {
+    let verifier = random_urlsafe(32);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    PkceChallenge {
+        verifier,
+        challenge,
+        state: random_urlsafe(16),
+    }
+}
+
+/// URL de la página de autorización del proveedor.
+pub fn authorize_url(
+    proveedor: OAuthProvider,
+    config: &AppConfig,
+    redirect_uri: &str,
+    pkce: &PkceChallenge,
+) -> Result<String, OAuthLoopbackError> {
+    let (base, client_id, scopes) = match proveedor {
+        OAuthProvider::Google => (GOOGLE_AUTHORIZE_URL.to_string(), &config.google_client_id, GOOGLE_SCOPES),
+        OAuthProvider::Microsoft => (
+            format!("{}/oauth2/v2.0/authorize", config.oauth_loopback.msal_authority),
+            &config.msal_client_id,
+            config.oauth_loopback.msal_scopes.as_str(),
+        ),
+    };
+    if client_id.is_empty() {
+        return Err(OAuthLoopbackError::Validation(format!(
+            "Falta el Client ID de {:?} en la configuración.",
+            proveedor
+        )));
+    }
+    let mut url = Url::parse(&base).map_err(|e| OAuthLoopbackError::Validation(e.to_string()))?;
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("response_type", "code")
+        .append_pair("scope", scopes)
+        .append_pair("code_challenge", &pkce.challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", &pkce.state)
+        .append_pair("prompt", "select_account");
+    if proveedor == OAuthProvider::Microsoft {
+        url.query_pairs_mut().append_pair("response_mode", "query");
+    }
+    Ok(url.to_string())
+}
+
+// -------------------------------------------------------------------------
+// LISTENER DE LOOPBACK
+// -------------------------------------------------------------------------
+
+/// Listener de un solo uso para la redirección del proveedor.
+pub struct LoopbackListener {
+    listener: TcpListener,
+    redirect_uri: String,
+}
+
+impl LoopbackListener {
+    /// Escucha en `127.0.0.1` con un puerto libre elegido por el sistema.
+    pub async fn bind() -> Result<Self, OAuthLoopbackError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let puerto = listener.local_addr().map_err(io_error)?.port();
+        Ok(LoopbackListener {
+            listener,
+            redirect_uri: format!("http://127.0.0.1:{}{}", puerto, CALLBACK_PATH),
+        })
+    }
+
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// Espera la redirección con el código; termina antes si vence `timeout` o llega `cancel`.
+    pub async fn wait_for_code(
+        self,
+        expected_state: &str,
+        timeout: Duration,
+        cancel: oneshot::Receiver<()>,
+    ) -> Result<String, OAuthLoopbackError> {
+        tokio::select! {
+            resultado = self.accept_callback(expected_state) => resultado,
+            _ = tokio::time::sleep(timeout) => Err(OAuthLoopbackError::Timeout),
+            _ = cancel => Err(OAuthLoopbackError::Cancelled),
+        }
+    }
+
+    /// Atiende conexiones hasta recibir `/callback`; otras rutas (p. ej. favicon) reciben 404.
+    async fn accept_callback(&self, expected_state: &str) -> Result<String, OAuthLoopbackError> {
+        loop {
+            let (mut stream, _) = self.listener.accept().await.map_err(io_error)?;
+            let target = match read_request_target(&mut stream).await {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            let url = match Url::parse(&format!("http://127.0.0.1{}", target)) {
+                Ok(url) if url.path() == CALLBACK_PATH => url,
+                _ => {
+                    let _ = write_response(&mut stream, "404 Not Found", "").await;
+                    continue;
+                }
+            };
+
+            let param = |nombre: &str| {
+                url.query_pairs().find(|(k, _)| k == nombre).map(|(_, v)| v.into_owned())
+            };
+            let resultado = if let Some(error) = param("error") {
+                Err(OAuthLoopbackError::Provider(param("error_description").unwrap_or(error)))
+            } else if param("state").as_deref() != Some(expected_state) {
+                Err(OAuthLoopbackError::Validation("El parámetro state no coincide.".to_string()))
+            } else {
+                param("code").ok_or_else(|| OAuthLoopbackError::Validation("La respuesta no trae el código.".to_string()))
+            };
+
+            let pagina = match &resultado {
+                Ok(_) => callback_page("Inicio de sesión completado", "Puede cerrar esta pestaña y volver a la aplicación."),
+                Err(_) => callback_page("No se pudo iniciar sesión", "Vuelva a la aplicación e intente de nuevo."),
+            };
+            let _ = write_response(&mut stream, "200 OK", &pagina).await;
+            return resultado;
+        }
+    }
+}
+
+/// `GET /callback?code=... HTTP/1.1` -> `/callback?code=...`.
+async fn read_request_target(stream: &mut TcpStream) -> Result<String, OAuthLoopbackError> {
+    let mut buffer = Vec::with_capacity(2048);
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Err(OAuthLoopbackError::Validation("Solicitud demasiado grande".to_string()));
+        }
+        let leidos = stream.read(&mut chunk).await.map_err(io_error)?;
+        if leidos == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..leidos]);
+    }
+    let cabecera = String::from_utf8_lossy(&buffer);
+    let mut partes = cabecera.lines().next().unwrap_or_default().split_whitespace();
+    match (partes.next(), partes.next()) {
+        (Some("GET"), Some(target)) if target.starts_with('/') => Ok(target.to_string()),
+        _ => Err(OAuthLoopbackError::Validation("Solicitud inválida".to_string())),
+    }
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, html: &str) -> Result<(), OAuthLoopbackError> {
+    let respuesta = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        html.len(),
+        html
+    );
+    stream.write_all(respuesta.as_bytes()).await.map_err(io_error)?;
+    stream.shutdown().await.map_err(io_error)
+}
+
+fn callback_page(titulo: &str, mensaje: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html lang=\"es\"><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center;margin-top:4em\"><h2>{0}</h2><p>{1}</p></body></html>",
+        titulo, mensaje
+    )
+}
+
+// -------------------------------------------------------------------------
+// CANJE DEL CÓDIGO
+// -------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct MicrosoftTokenResponse {
+    id_token: Option<String>,
+}
+
+/// Canjea el código de Microsoft por el ID token (cliente público: sin secreto, con PKCE).
+async fn exchange_microsoft_code(
+    http_client: &Arc<Client>,
+    config: &AppConfig,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<String, OAuthLoopbackError> {
+    let token_url = format!("{}/oauth2/v2.0/token", config.oauth_loopback.msal_authority);
+    let params = [
+        ("client_id", config.msal_client_id.as_str()),
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", code_verifier),
+        ("scope", config.oauth_loopback.msal_scopes.as_str()),
+    ];
+    let respuesta = http_client
+        .post(&token_url)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| OAuthLoopbackError::Provider(e.to_string()))?;
+    if !respuesta.status().is_success() {
+        let detalle = respuesta.text().await.unwrap_or_default();
+        return Err(OAuthLoopbackError::Provider(format!("Canje del código rechazado: {}", detalle)));
+    }
+    respuesta
+        .json::<MicrosoftTokenResponse>()
+        .await
+        .map_err(|e| OAuthLoopbackError::Provider(e.to_string()))?
+        .id_token
+        .ok_or_else(|| OAuthLoopbackError::Provider("La respuesta de Microsoft no trae id_token (¿falta el scope openid?)".to_string()))
+}
+
+/// Solicitud de autenticación para user_logic::authenticate_user a partir del código recibido.
+pub async fn auth_payload(
+    proveedor: OAuthProvider,
+    http_client: &Arc<Client>,
+    config: &AppConfig,
+    code: String,
+    redirect_uri: &str,
+    pkce: &PkceChallenge,
+) -> Result<AuthRequestPayload, OAuthLoopbackError> {
+    let (login_type, proof_of_identity, code_verifier) = match proveedor {
+        // El canje lo hace google::validate_google_code con el verificador
+        OAuthProvider::Google => (LoginType::Google, code, Some(pkce.verifier.clone())),
+        OAuthProvider::Microsoft => {
+            let id_token = exchange_microsoft_code(http_client, config, &code, redirect_uri, &pkce.verifier).await?;
+            (LoginType::MsftMsal, id_token, None)
+        }
+    };
+    Ok(AuthRequestPayload {
+        login_type,
+        username: None,
+        password: None,
+        proof_of_identity,
+        redirect_uri: Some(redirect_uri.to_string()),
+        code_verifier,
+    })
+}
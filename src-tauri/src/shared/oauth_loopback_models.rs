@@ -0,0 +1,20 @@
+// src/shared/oauth_loopback_models.rs
+// Modelos del login del escritorio con el navegador del sistema.
+
+use serde::{Deserialize, Serialize};
+
+/// Proveedor con el que se inicia sesión en el navegador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OAuthProvider {
+    Google,
+    Microsoft,
+}
+
+/// Verificador PKCE (RFC 7636), su desafío S256 y el `state` de la solicitud.
+#[derive(Debug, Clone)]
+pub struct PkceChallenge {
+    pub verifier: String,
+    pub challenge: String,
+    pub state: String,
+}
@@ -0,0 +1,286 @@
+// src/shared/offline_cache_logic.rs
+/*
+Caché local (SQLite) de la aplicación de escritorio para trabajar sin conexión.
+
+Cada vez que los menús, los permisos o el perfil se obtienen de SQL Server se guarda una copia en
+`offline_cache.db` (carpeta de configuración). Si luego el servidor no responde, los comandos
+devuelven esa copia de solo lectura con `offline = true`; al volver la conexión el monitor de
+conectividad la resincroniza.
+
+Las copias se cifran con AES-256-GCM con una clave por usuario (HMAC de la clave del equipo con el
+usuario) y la clave de la fila (ámbito, usuario, tipo) como datos asociados: el archivo copiado a
+otro equipo no se descifra y una fila copiada a otra clave tampoco. No es una protección entre
+cuentas del mismo equipo: la clave sale del identificador del equipo y del nombre de usuario, que
+cualquiera con acceso al equipo conoce; el aislamiento entre usuarios depende de los permisos de la
+carpeta de configuración.
+
+La caché solo acompaña a una sesión abierta: las copias se sirven al usuario de `usuario_conectado`,
+que se establece al iniciar sesión contra SQL Server. Si la aplicación se reinicia mientras el
+servidor no responde no hay sesión que restaurar y no se sirve nada hasta que vuelva la conexión.
+
+El ámbito es `{servidor}/{base de datos}/{aplicativoID}`, para no mezclar perfiles de conexión.
+
+CREATE TABLE IF NOT EXISTS cache_usuario (
+    ambito               TEXT NOT NULL,
+    usuario              TEXT NOT NULL,
+    tipo                 TEXT NOT NULL,   -- menus | permisos | perfil
+    datos                TEXT NOT NULL,   -- Base64(nonce || cifrado)
+    fechaSincronizacion  TEXT NOT NULL,
+    PRIMARY KEY (ambito, usuario, tipo)
+);
+*/
+
+use std::fmt;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+
+use crate::offline_cache_models::{CacheKind, CachedResponse};
+use crate::secret_store_logic;
+
+/// Nombre del archivo SQLite dentro de la carpeta de configuración.
+pub const CACHE_FILE_NAME: &str = "offline_cache.db";
+
+#[derive(Debug)]
+pub enum OfflineCacheError {
+    Storage(String),
+    Crypto(String),
+}
+
+impl fmt::Display for OfflineCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OfflineCacheError::Storage(msg) => write!(f, "Error de la caché local: {}", msg),
+            OfflineCacheError::Crypto(msg) => write!(f, "Error de cifrado de la caché local: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for OfflineCacheError {}
+
+fn storage_error(e: sqlx::Error) -> OfflineCacheError {
+    OfflineCacheError::Storage(e.to_string())
+}
+
+/// Ámbito de la caché para la conexión y el aplicativo actuales.
+pub fn scope(servidor: &str, base_datos: &str, aplicativo_id: i32) -> String {
+    format!("{}/{}/{}", servidor.to_lowercase(), base_datos.to_lowercase(), aplicativo_id)
+}
+
+pub struct OfflineCache {
+    pool: Pool<Sqlite>,
+    machine_key: [u8; 32],
+}
+
+impl OfflineCache {
+    /// Abre (o crea) el archivo y la tabla.
+    pub async fn open(config_dir: &Path) -> Result<Self, OfflineCacheError> {
+        std::fs::create_dir_all(config_dir).map_err(|e| OfflineCacheError::Storage(e.to_string()))?;
+        let options = SqliteConnectOptions::new()
+            .filename(config_dir.join(CACHE_FILE_NAME))
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .map_err(storage_error)?;
+        let machine_key = secret_store_logic::machine_key("cache").map_err(OfflineCacheError::Crypto)?;
+        Self::with_pool(pool, machine_key).await
+    }
+
+    /// Crea la tabla si falta y arma la caché sobre `pool` con la clave indicada.
+    async fn with_pool(pool: Pool<Sqlite>, machine_key: [u8; 32]) -> Result<Self, OfflineCacheError> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS cache_usuario (
+                ambito TEXT NOT NULL,
+                usuario TEXT NOT NULL,
+                tipo TEXT NOT NULL,
+                datos TEXT NOT NULL,
+                fechaSincronizacion TEXT NOT NULL,
+                PRIMARY KEY (ambito, usuario, tipo)
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(OfflineCache { pool, machine_key })
+    }
+
+    fn cipher_for(&self, usuario: &str) -> Aes256Gcm {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.machine_key).expect("HMAC admite claves de cualquier longitud");
+        mac.update(usuario.to_lowercase().as_bytes());
+        let key: [u8; 32] = mac.finalize().into_bytes().into();
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+    }
+
+    fn row_aad(ambito: &str, usuario: &str, tipo: CacheKind) -> String {
+        format!("{}|{}|{}", ambito, usuario.to_lowercase(), tipo.as_str())
+    }
+
+    /// Guarda (o reemplaza) la copia de `tipo` del usuario.
+    pub async fn store(
+        &self,
+        ambito: &str,
+        usuario: &str,
+        tipo: CacheKind,
+        datos: &serde_json::Value,
+    ) -> Result<(), OfflineCacheError> {
+        let json = serde_json::to_vec(datos).map_err(|e| OfflineCacheError::Storage(e.to_string()))?;
+        let aad = Self::row_aad(ambito, usuario, tipo);
+        let nonce_bytes: [u8; 12] = rand::random();
+        let ciphertext = self
+            .cipher_for(usuario)
+            .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: &json, aad: aad.as_bytes() })
+            .map_err(|_| OfflineCacheError::Crypto("Fallo al cifrar la copia local".to_string()))?;
+        let mut payload = nonce_bytes.to_vec();
+        payload.extend_from_slice(&ciphertext);
+
+        sqlx::query(
+            "INSERT INTO cache_usuario (ambito, usuario, tipo, datos, fechaSincronizacion)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (ambito, usuario, tipo)
+             DO UPDATE SET datos = excluded.datos, fechaSincronizacion = excluded.fechaSincronizacion",
+        )
+        .bind(ambito)
+        .bind(usuario.to_lowercase())
+        .bind(tipo.as_str())
+        .bind(general_purpose::STANDARD.encode(payload))
+        .bind(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Copia local de `tipo` del usuario como respuesta sin conexión, si existe.
+    pub async fn load(
+        &self,
+        ambito: &str,
+        usuario: &str,
+        tipo: CacheKind,
+    ) -> Result<Option<CachedResponse>, OfflineCacheError> {
+        let fila = sqlx::query_as::<_, (String, String)>(
+            "SELECT datos, fechaSincronizacion FROM cache_usuario WHERE ambito = ?1 AND usuario = ?2 AND tipo = ?3",
+        )
+        .bind(ambito)
+        .bind(usuario.to_lowercase())
+        .bind(tipo.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        let (datos, fecha) = match fila {
+            Some(fila) => fila,
+            None => return Ok(None),
+        };
+
+        let payload = general_purpose::STANDARD
+            .decode(datos)
+            .map_err(|e| OfflineCacheError::Crypto(format!("Copia local con Base64 inválido: {}", e)))?;
+        if payload.len() < 12 + 16 {
+            return Err(OfflineCacheError::Crypto("Copia local demasiado corta".to_string()));
+        }
+        let aad = Self::row_aad(ambito, usuario, tipo);
+        let json = self
+            .cipher_for(usuario)
+            .decrypt(Nonce::from_slice(&payload[..12]), Payload { msg: &payload[12..], aad: aad.as_bytes() })
+            .map_err(|_| OfflineCacheError::Crypto("La copia local no es válida para este usuario".to_string()))?;
+        let datos = serde_json::from_slice(&json).map_err(|e| OfflineCacheError::Storage(e.to_string()))?;
+        Ok(Some(CachedResponse {
+            datos,
+            offline: true,
+            fecha_sincronizacion: Some(fecha),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMBITO: &str = "srv/erp/1";
+
+    /// Caché sobre SQLite en memoria (una sola conexión, para que todas vean la misma base).
+    async fn cache(machine_key: [u8; 32]) -> OfflineCache {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        OfflineCache::with_pool(pool, machine_key).await.unwrap()
+    }
+
+    async fn datos_guardados(cache: &OfflineCache) -> String {
+        sqlx::query_scalar("SELECT datos FROM cache_usuario")
+            .fetch_one(&cache.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn guarda_y_recupera_la_copia() {
+        let cache = cache([7; 32]).await;
+        let datos = serde_json::json!({ "permisos": ["ver_menu", "lista_usuarios"] });
+
+        cache.store(AMBITO, "JPerez", CacheKind::Permisos, &datos).await.unwrap();
+        let copia = cache.load(AMBITO, "jperez", CacheKind::Permisos).await.unwrap().unwrap();
+
+        assert_eq!(copia.datos, datos);
+        assert!(copia.offline);
+        assert!(copia.fecha_sincronizacion.is_some());
+        assert!(!datos_guardados(&cache).await.contains("ver_menu"));
+        assert!(cache.load(AMBITO, "jperez", CacheKind::Menus).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rechaza_una_copia_alterada() {
+        let cache = cache([7; 32]).await;
+        cache.store(AMBITO, "jperez", CacheKind::Perfil, &serde_json::json!({ "nombre": "Juan" })).await.unwrap();
+
+        let mut payload = general_purpose::STANDARD.decode(datos_guardados(&cache).await).unwrap();
+        let ultimo = payload.len() - 1;
+        payload[ultimo] ^= 0x01;
+        sqlx::query("UPDATE cache_usuario SET datos = ?1")
+            .bind(general_purpose::STANDARD.encode(payload))
+            .execute(&cache.pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            cache.load(AMBITO, "jperez", CacheKind::Perfil).await,
+            Err(OfflineCacheError::Crypto(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rechaza_una_copia_movida_a_otro_usuario_o_tipo() {
+        let cache = cache([7; 32]).await;
+        cache.store(AMBITO, "jperez", CacheKind::Permisos, &serde_json::json!(["ver_menu"])).await.unwrap();
+        sqlx::query("UPDATE cache_usuario SET usuario = 'mlopez', tipo = 'menus'")
+            .execute(&cache.pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            cache.load(AMBITO, "mlopez", CacheKind::Menus).await,
+            Err(OfflineCacheError::Crypto(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn otra_clave_de_equipo_no_descifra_la_copia() {
+        let cache_original = cache([7; 32]).await;
+        cache_original.store(AMBITO, "jperez", CacheKind::Menus, &serde_json::json!([1, 2])).await.unwrap();
+        let otro_equipo = OfflineCache { pool: cache_original.pool.clone(), machine_key: [8; 32] };
+
+        assert!(matches!(
+            otro_equipo.load(AMBITO, "jperez", CacheKind::Menus).await,
+            Err(OfflineCacheError::Crypto(_))
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+// src/shared/offline_cache_models.rs
+// Modelos de la caché local (sin conexión) de la aplicación de escritorio.
+
+use serde::Serialize;
+
+/// Datos que se guardan por usuario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Menus,
+    Permisos,
+    Perfil,
+}
+
+impl CacheKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheKind::Menus => "menus",
+            CacheKind::Permisos => "permisos",
+            CacheKind::Perfil => "perfil",
+        }
+    }
+}
+
+/// Respuesta de los comandos con caché: `offline` indica que `datos` salen de la copia local
+/// (de solo lectura) porque no hay conexión con SQL Server.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedResponse {
+    pub datos: serde_json::Value,
+    pub offline: bool,
+    /// Cuándo se obtuvieron los datos del servidor por última vez.
+    pub fecha_sincronizacion: Option<String>,
+}
+
+/// Evento `conectividad` que recibe la pantalla al perder o recuperar la conexión.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityEvent {
+    pub online: bool,
+    /// Se volvió a sincronizar la caché del usuario conectado al recuperar la conexión.
+    pub resincronizado: bool,
+}
@@ -0,0 +1,417 @@
+// src/shared/permission_catalog_logic.rs
+/*
+Catálogo de permisos por aplicativo: un único lugar donde cada código tiene
+descripción, módulo y nivel de riesgo, en vez de cadenas sueltas en
+riy_SeguridadMenu.codigoPermiso, en utils.rs y en el enum PermissionKey del frontend.
+
+El catálogo se carga desde un archivo declarativo (PERMISSION_CATALOG_FILE, ver
+src-tauri/permisos.json) al iniciar el servidor API o con POST /permissions/catalog/sync.
+
+CREATE TABLE riy.riy_permiso (
+    permisoID          INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID       INT           NOT NULL,
+    codigoPermiso      VARCHAR(50)   NOT NULL,
+    descripcion        NVARCHAR(200) NOT NULL,
+    modulo             VARCHAR(50)   NOT NULL,
+    nivelRiesgo        VARCHAR(10)   NOT NULL, -- Bajo, Medio, Alto, Critico
+    autor              VARCHAR(50)   NOT NULL,
+    fechaCreacion      DATETIME      NOT NULL DEFAULT GETDATE(),
+    modificadoPor      VARCHAR(50)   NULL,
+    fechaModificacion  DATETIME      NULL,
+    CONSTRAINT UQ_riy_permiso_codigo UNIQUE (aplicativoID, codigoPermiso)
+);
+*/
+
+use std::collections::HashSet;
+use std::fmt;
+
+use sqlx::{Mssql, Pool};
+
+use crate::permission_catalog_models::{
+    CatalogConsistencyReport, CatalogFile, CatalogPermission, CatalogPermissionInput, CatalogSyncRequest,
+    CatalogSyncSummary, UnknownMenuPermission,
+};
+
+/// Permiso para administrar el catálogo.
+pub const PERMISO_CATALOGO: &str = "administrar_permisos";
+
+pub const NIVELES_RIESGO: &[&str] = &["Bajo", "Medio", "Alto", "Critico"];
+
+/// Autor de los cambios hechos al sincronizar desde el archivo al iniciar.
+const AUTOR_SINCRONIZACION: &str = "sincronizacion";
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Validation(String),
+    NotFound(String),
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatalogError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            CatalogError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            CatalogError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            CatalogError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+fn db_error(e: sqlx::Error) -> CatalogError {
+    CatalogError::Database(e.to_string())
+}
+
+/// Normaliza y valida una entrada; el código solo admite minúsculas, dígitos y `_`.
+fn validate(input: &CatalogPermissionInput) -> Result<CatalogPermissionInput, CatalogError> {
+    let codigo = input.codigo_permiso.trim();
+    if codigo.is_empty() || codigo.len() > 50 {
+        return Err(CatalogError::Validation("El código debe tener entre 1 y 50 caracteres".to_string()));
+    }
+    if !codigo.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        return Err(CatalogError::Validation(format!(
+            "El código '{}' solo puede tener minúsculas, dígitos y '_'", codigo
+        )));
+    }
+    let descripcion = input.descripcion.trim();
+    if descripcion.is_empty() {
+        return Err(CatalogError::Validation(format!("La descripción de '{}' es obligatoria", codigo)));
+    }
+    let modulo = input.modulo.trim();
+    if modulo.is_empty() {
+        return Err(CatalogError::Validation(format!("El módulo de '{}' es obligatorio", codigo)));
+    }
+    let nivel_riesgo = NIVELES_RIESGO
+        .iter()
+        .find(|n| n.eq_ignore_ascii_case(input.nivel_riesgo.trim()))
+        .ok_or_else(|| CatalogError::Validation(format!(
+            "Nivel de riesgo inválido para '{}': use {}", codigo, NIVELES_RIESGO.join(", ")
+        )))?;
+    Ok(CatalogPermissionInput {
+        codigo_permiso: codigo.to_string(),
+        descripcion: descripcion.to_string(),
+        modulo: modulo.to_string(),
+        nivel_riesgo: nivel_riesgo.to_string(),
+    })
+}
+
+fn catalog_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT permisoID as permiso_id,
+                aplicativoID as aplicativo_id,
+                codigoPermiso {0} as codigo_permiso,
+                descripcion,
+                modulo {0} as modulo,
+                nivelRiesgo {0} as nivel_riesgo,
+                autor {0} as autor,
+                CONVERT(VARCHAR(19), fechaCreacion, 120) as fecha_creacion,
+                modificadoPor {0} as modificado_por,
+                CONVERT(VARCHAR(19), fechaModificacion, 120) as fecha_modificacion
+           FROM riy.riy_permiso WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+pub async fn list_permissions(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<CatalogPermission>, CatalogError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 ORDER BY modulo, codigoPermiso",
+        catalog_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, CatalogPermission>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn get_permission(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    permiso_id: i32,
+    sql_collate_clause: &str,
+) -> Result<CatalogPermission, CatalogError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND permisoID = @p2",
+        catalog_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, CatalogPermission>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(permiso_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| CatalogError::NotFound(format!("Permiso {} no encontrado", permiso_id)))
+}
+
+async fn find_by_code(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    codigo_permiso: &str,
+    sql_collate_clause: &str,
+) -> Result<Option<CatalogPermission>, CatalogError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND codigoPermiso = @p2",
+        catalog_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, CatalogPermission>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(codigo_permiso)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn create_permission(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &CatalogPermissionInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<CatalogPermission, CatalogError> {
+    let input = validate(input)?;
+    if find_by_code(pool, aplicativo_id, &input.codigo_permiso, sql_collate_clause).await?.is_some() {
+        return Err(CatalogError::Conflict(format!("El código '{}' ya existe", input.codigo_permiso)));
+    }
+    let (permiso_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_permiso (aplicativoID, codigoPermiso, descripcion, modulo, nivelRiesgo, autor, fechaCreacion)
+         OUTPUT INSERTED.permisoID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(&input.codigo_permiso)
+    .bind(&input.descripcion)
+    .bind(&input.modulo)
+    .bind(&input.nivel_riesgo)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    get_permission(pool, aplicativo_id, permiso_id, sql_collate_clause).await
+}
+
+/// Modifica descripción, módulo y riesgo. El código no cambia: está referenciado
+/// por menús, roles y tokens ya emitidos.
+pub async fn update_permission(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    permiso_id: i32,
+    input: &CatalogPermissionInput,
+    modificado_por: &str,
+    sql_collate_clause: &str,
+) -> Result<CatalogPermission, CatalogError> {
+    let actual = get_permission(pool, aplicativo_id, permiso_id, sql_collate_clause).await?;
+    let input = validate(input)?;
+    if input.codigo_permiso != actual.codigo_permiso {
+        return Err(CatalogError::Validation("El código de un permiso no se puede cambiar".to_string()));
+    }
+    sqlx::query(
+        "UPDATE riy.riy_permiso
+            SET descripcion = @p1, modulo = @p2, nivelRiesgo = @p3, modificadoPor = @p4, fechaModificacion = GETDATE()
+          WHERE permisoID = @p5"
+    )
+    .bind(&input.descripcion)
+    .bind(&input.modulo)
+    .bind(&input.nivel_riesgo)
+    .bind(modificado_por)
+    .bind(permiso_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    get_permission(pool, aplicativo_id, permiso_id, sql_collate_clause).await
+}
+
+/// Elimina un permiso que ningún menú ni rol referencia.
+pub async fn delete_permission(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    permiso_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(), CatalogError> {
+    let permiso = get_permission(pool, aplicativo_id, permiso_id, sql_collate_clause).await?;
+    let (referencias,): (i32,) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM riy.riy_SeguridadMenu WITH(NOLOCK) WHERE codigoPermiso = @p1)
+              + (SELECT COUNT(*) FROM riy.riy_SeguridadRolPermiso rp WITH(NOLOCK)
+                   JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = rp.rolID
+                  WHERE rp.codigoPermiso = @p1 AND r.aplicativoID = @p2)"
+    )
+    .bind(&permiso.codigo_permiso)
+    .bind(aplicativo_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if referencias > 0 {
+        return Err(CatalogError::Conflict(format!(
+            "El código '{}' está referenciado por {} menús o roles", permiso.codigo_permiso, referencias
+        )));
+    }
+    sqlx::query("DELETE FROM riy.riy_permiso WHERE permisoID = @p1")
+        .bind(permiso_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Compara los códigos del menú con el catálogo en ambos sentidos.
+pub async fn consistency_report(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<CatalogConsistencyReport, CatalogError> {
+    let sql_query = format!(
+        "SELECT m.menuID as menu_id,
+                m.nombre {0} as nombre,
+                m.ruta {0} as ruta,
+                m.codigoPermiso {0} as codigo_permiso
+           FROM riy.riy_SeguridadMenu m WITH(NOLOCK)
+          WHERE m.codigoPermiso IS NOT NULL AND LTRIM(RTRIM(m.codigoPermiso)) <> ''
+            AND NOT EXISTS (SELECT 1 FROM riy.riy_permiso p WITH(NOLOCK)
+                             WHERE p.aplicativoID = @p1 AND p.codigoPermiso = m.codigoPermiso)
+          ORDER BY m.codigoPermiso, m.menuID",
+        sql_collate_clause
+    );
+    let menus_con_codigo_desconocido = sqlx::query_as::<_, UnknownMenuPermission>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1
+            AND NOT EXISTS (SELECT 1 FROM riy.riy_SeguridadMenu m WITH(NOLOCK)
+                             WHERE m.codigoPermiso = riy_permiso.codigoPermiso)
+          ORDER BY modulo, codigoPermiso",
+        catalog_select(sql_collate_clause)
+    );
+    let codigos_sin_referencia = sqlx::query_as::<_, CatalogPermission>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    Ok(CatalogConsistencyReport {
+        menus_con_codigo_desconocido,
+        codigos_sin_referencia,
+    })
+}
+
+/// Lleva el catálogo al contenido del archivo declarativo: crea los códigos nuevos,
+/// actualiza los que cambiaron y, si se pide, elimina los que ya no están.
+pub async fn sync_catalog(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    request: &CatalogSyncRequest,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<CatalogSyncSummary, CatalogError> {
+    // Valida todo el archivo antes de tocar la base
+    let mut entradas = Vec::with_capacity(request.permisos.len());
+    let mut codigos = HashSet::new();
+    for input in &request.permisos {
+        let entrada = validate(input)?;
+        if !codigos.insert(entrada.codigo_permiso.clone()) {
+            return Err(CatalogError::Validation(format!(
+                "El código '{}' está repetido en el archivo", entrada.codigo_permiso
+            )));
+        }
+        entradas.push(entrada);
+    }
+
+    let actuales = list_permissions(pool, aplicativo_id, sql_collate_clause).await?;
+    let mut summary = CatalogSyncSummary::default();
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    for entrada in &entradas {
+        match actuales.iter().find(|p| p.codigo_permiso == entrada.codigo_permiso) {
+            None => {
+                sqlx::query(
+                    "INSERT INTO riy.riy_permiso (aplicativoID, codigoPermiso, descripcion, modulo, nivelRiesgo, autor, fechaCreacion)
+                     VALUES (@p1, @p2, @p3, @p4, @p5, @p6, GETDATE())"
+                )
+                .bind(aplicativo_id)
+                .bind(&entrada.codigo_permiso)
+                .bind(&entrada.descripcion)
+                .bind(&entrada.modulo)
+                .bind(&entrada.nivel_riesgo)
+                .bind(autor)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+                summary.creados += 1;
+            }
+            Some(actual)
+                if actual.descripcion != entrada.descripcion
+                    || actual.modulo != entrada.modulo
+                    || actual.nivel_riesgo != entrada.nivel_riesgo =>
+            {
+                sqlx::query(
+                    "UPDATE riy.riy_permiso
+                        SET descripcion = @p1, modulo = @p2, nivelRiesgo = @p3, modificadoPor = @p4, fechaModificacion = GETDATE()
+                      WHERE permisoID = @p5"
+                )
+                .bind(&entrada.descripcion)
+                .bind(&entrada.modulo)
+                .bind(&entrada.nivel_riesgo)
+                .bind(autor)
+                .bind(actual.permiso_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+                summary.actualizados += 1;
+            }
+            Some(_) => summary.sin_cambios += 1,
+        }
+    }
+
+    if request.eliminar_ausentes {
+        for actual in actuales.iter().filter(|p| !codigos.contains(&p.codigo_permiso)) {
+            sqlx::query("DELETE FROM riy.riy_permiso WHERE permisoID = @p1")
+                .bind(actual.permiso_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+            summary.eliminados += 1;
+        }
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(summary)
+}
+
+/// Lee el archivo declarativo del catálogo.
+pub fn read_catalog_file(path: &str) -> Result<CatalogFile, CatalogError> {
+    let contenido = std::fs::read_to_string(path)
+        .map_err(|e| CatalogError::Validation(format!("No se pudo leer '{}': {}", path, e)))?;
+    serde_json::from_str(&contenido)
+        .map_err(|e| CatalogError::Validation(format!("Formato inválido en '{}': {}", path, e)))
+}
+
+/// Sincroniza al iniciar si PERMISSION_CATALOG_FILE está definida. Nunca elimina códigos:
+/// la poda solo se hace a pedido desde el endpoint.
+pub async fn sync_from_env(pool: &Pool<Mssql>, aplicativo_id: i32, sql_collate_clause: &str) {
+    let path = match std::env::var("PERMISSION_CATALOG_FILE") {
+        Ok(path) if !path.trim().is_empty() => path,
+        _ => return,
+    };
+    let resultado = match read_catalog_file(&path) {
+        Ok(archivo) => {
+            let request = CatalogSyncRequest { permisos: archivo.permisos, eliminar_ausentes: false };
+            sync_catalog(pool, aplicativo_id, &request, AUTOR_SINCRONIZACION, sql_collate_clause).await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(s) => println!(
+            "permission_catalog: '{}' sincronizado ({} creados, {} actualizados, {} sin cambios)",
+            path, s.creados, s.actualizados, s.sin_cambios
+        ),
+        Err(e) => eprintln!("permission_catalog: no se sincronizó '{}': {}", path, e),
+    }
+}
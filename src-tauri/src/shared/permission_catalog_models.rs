@@ -0,0 +1,77 @@
+// src/shared/permission_catalog_models.rs
+// Modelos del catálogo de permisos por aplicativo.
+
+use serde::{Deserialize, Serialize};
+
+/// Permiso registrado en el catálogo (`riy.riy_permiso`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPermission {
+    pub permiso_id: i32,
+    pub aplicativo_id: i32,
+    pub codigo_permiso: String,
+    pub descripcion: String,
+    pub modulo: String,
+    /// `Bajo`, `Medio`, `Alto` o `Critico`.
+    pub nivel_riesgo: String,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+/// Alta o modificación de un permiso; también es cada entrada del archivo declarativo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPermissionInput {
+    pub codigo_permiso: String,
+    pub descripcion: String,
+    pub modulo: String,
+    pub nivel_riesgo: String,
+}
+
+/// Archivo declarativo del catálogo (`permisos.json`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogFile {
+    pub permisos: Vec<CatalogPermissionInput>,
+}
+
+/// Cuerpo de `POST /permissions/catalog/sync`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogSyncRequest {
+    pub permisos: Vec<CatalogPermissionInput>,
+    /// Elimina del catálogo los códigos que no están en el archivo.
+    #[serde(default)]
+    pub eliminar_ausentes: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogSyncSummary {
+    pub creados: usize,
+    pub actualizados: usize,
+    pub sin_cambios: usize,
+    pub eliminados: usize,
+}
+
+/// Ítem de menú que exige un código ausente del catálogo.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownMenuPermission {
+    pub menu_id: i32,
+    pub nombre: String,
+    pub ruta: Option<String>,
+    pub codigo_permiso: String,
+}
+
+/// Resultado de `GET /permissions/catalog/consistency`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogConsistencyReport {
+    /// Ítems de riy_SeguridadMenu con un código que no está en el catálogo.
+    pub menus_con_codigo_desconocido: Vec<UnknownMenuPermission>,
+    /// Códigos del catálogo que ningún ítem de menú referencia.
+    pub codigos_sin_referencia: Vec<CatalogPermission>,
+}
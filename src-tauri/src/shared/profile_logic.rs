@@ -0,0 +1,364 @@
+// src/shared/profile_logic.rs
+/*
+Perfil del usuario conectado: datos propios, preferencias, sesiones e identidades.
+
+El cambio de correo no se aplica de inmediato: queda en correoPendiente hasta que el
+usuario confirma el código enviado a la dirección nueva.
+
+CREATE TABLE riy.riy_usuario_perfil (
+    usuarioID          INT           PRIMARY KEY,
+    idioma             VARCHAR(10)   NULL,
+    rutaInicio         VARCHAR(200)  NULL,
+    correoPendiente    VARCHAR(255)  NULL,
+    fechaModificacion  DATETIME      NOT NULL DEFAULT GETDATE()
+);
+
+CREATE TABLE riy.riy_usuario_sesion (
+    sesionID     INT IDENTITY(1,1) PRIMARY KEY,
+    usuarioID    INT           NOT NULL,
+    tokenHash    CHAR(64)      NOT NULL,     -- SHA-256 del JWT de sesión
+    origen       VARCHAR(20)   NOT NULL,     -- web | escritorio
+    userAgent    VARCHAR(300)  NULL,
+    direccionIP  VARCHAR(45)   NULL,
+    fechaInicio  DATETIME      NOT NULL DEFAULT GETDATE(),
+    fechaExpira  DATETIME      NOT NULL
+);
+CREATE INDEX IX_riy_usuario_sesion_usuario ON riy.riy_usuario_sesion (usuarioID, fechaExpira);
+*/
+
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+use sqlx::{Mssql, Pool};
+
+use crate::identity_link_logic;
+use crate::models::LoggedInUser;
+use crate::notification_logic::{self, NotificationError};
+use crate::notification_models::NotificationKind;
+use crate::profile_models::{ProfileUpdateRequest, UserPreferences, UserProfile, UserSession};
+use crate::{role_logic, utils};
+
+pub const ORIGEN_WEB: &str = "web";
+pub const ORIGEN_ESCRITORIO: &str = "escritorio";
+
+/// Vigencia de la sesión; coincide con la expiración de `utils::generate_jwt`.
+const SESSION_HOURS: i32 = 24;
+const MAX_NOMBRE_LENGTH: usize = 100;
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Validation(String),
+    /// Código de verificación del correo incorrecto o vencido.
+    InvalidCode(String),
+    NotFound(String),
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            ProfileError::InvalidCode(msg) => write!(f, "Código inválido: {}", msg),
+            ProfileError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            ProfileError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            ProfileError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<NotificationError> for ProfileError {
+    fn from(e: NotificationError) -> Self {
+        match e {
+            NotificationError::Validation(msg) => ProfileError::Validation(msg),
+            NotificationError::InvalidCode(msg) => ProfileError::InvalidCode(msg),
+            NotificationError::NotFound(msg) => ProfileError::NotFound(msg),
+            NotificationError::Smtp(msg) | NotificationError::Database(msg) => ProfileError::Database(msg),
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> ProfileError {
+    ProfileError::Database(e.to_string())
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// -------------------------------------------------------------------------
+// SESIONES
+// -------------------------------------------------------------------------
+
+/// Registra un JWT de sesión recién emitido.
+pub async fn record_session(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    token: &str,
+    origen: &str,
+    user_agent: Option<&str>,
+    direccion_ip: Option<&str>,
+) -> Result<(), ProfileError> {
+    sqlx::query(
+        "INSERT INTO riy.riy_usuario_sesion (usuarioID, tokenHash, origen, userAgent, direccionIP, fechaInicio, fechaExpira)
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE(), DATEADD(HOUR, @p6, GETDATE()))"
+    )
+    .bind(usuario_id)
+    .bind(token_hash(token))
+    .bind(origen)
+    .bind(user_agent.map(|ua| ua.chars().take(300).collect::<String>()))
+    .bind(direccion_ip)
+    .bind(SESSION_HOURS)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Sesiones no vencidas del usuario; marca como `actual` la del token indicado.
+pub async fn list_active_sessions(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    current_token: Option<&str>,
+) -> Result<Vec<UserSession>, ProfileError> {
+    let mut sessions = sqlx::query_as::<_, UserSession>(
+        "SELECT sesionID as sesion_id, origen, userAgent as user_agent, direccionIP as direccion_ip,
+                CONVERT(VARCHAR(19), fechaInicio, 120) as fecha_inicio,
+                CONVERT(VARCHAR(19), fechaExpira, 120) as fecha_expira,
+                tokenHash as token_hash
+           FROM riy.riy_usuario_sesion WITH(NOLOCK)
+          WHERE usuarioID = @p1 AND fechaExpira > GETDATE()
+          ORDER BY fechaInicio DESC"
+    )
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    if let Some(current) = current_token.map(token_hash) {
+        for session in sessions.iter_mut() {
+            session.actual = session.token_hash == current;
+        }
+    }
+    Ok(sessions)
+}
+
+// -------------------------------------------------------------------------
+// PERFIL
+// -------------------------------------------------------------------------
+
+async fn load_preferences(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+) -> Result<(UserPreferences, Option<String>), ProfileError> {
+    let row: Option<(Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT idioma, rutaInicio, correoPendiente FROM riy.riy_usuario_perfil WITH(NOLOCK) WHERE usuarioID = @p1"
+    )
+    .bind(usuario_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    Ok(match row {
+        Some((idioma, ruta_inicio, correo_pendiente)) => (UserPreferences { idioma, ruta_inicio }, correo_pendiente),
+        None => (UserPreferences::default(), None),
+    })
+}
+
+/// Perfil completo del usuario: datos, roles, permisos efectivos, preferencias,
+/// sesiones activas e identidades vinculadas.
+pub async fn get_profile(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    aplicativo_id: i32,
+    current_token: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<UserProfile, ProfileError> {
+    let sql_query = format!(
+        "SELECT usuario {0}, nombre {0}, correo {0} FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario_id = @p1",
+        sql_collate_clause
+    );
+    let (usuario, nombre, correo): (String, String, String) = sqlx::query_as(&sql_query)
+        .bind(usuario_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| ProfileError::NotFound(format!("Usuario {} no encontrado", usuario_id)))?;
+
+    let roles = role_logic::get_user_roles(pool, usuario_id, aplicativo_id, sql_collate_clause)
+        .await
+        .map_err(ProfileError::Database)?;
+    let permissions = utils::get_permissions_by_app(pool, usuario_id, aplicativo_id)
+        .await
+        .map_err(|e| ProfileError::Database(e.to_string()))?;
+    let (preferences, correo_pendiente) = load_preferences(pool, usuario_id).await?;
+    let sessions = list_active_sessions(pool, usuario_id, current_token).await?;
+    let identities = identity_link_logic::list_user_identities(pool, usuario_id, sql_collate_clause)
+        .await
+        .map_err(|e| ProfileError::Database(e.to_string()))?;
+
+    Ok(UserProfile {
+        user: LoggedInUser {
+            usuario_id: Some(usuario_id),
+            usuario: Some(usuario),
+            nombre: Some(nombre),
+            correo: Some(correo),
+            roles: roles.clone(),
+        },
+        roles,
+        permissions,
+        preferences,
+        correo_pendiente,
+        sessions,
+        identities,
+        suplantado_por: None,
+    })
+}
+
+fn validate_update(request: &ProfileUpdateRequest) -> Result<(), ProfileError> {
+    if let Some(nombre) = &request.nombre {
+        let nombre = nombre.trim();
+        if nombre.is_empty() || nombre.chars().count() > MAX_NOMBRE_LENGTH {
+            return Err(ProfileError::Validation(format!(
+                "El nombre es obligatorio y no puede superar {} caracteres", MAX_NOMBRE_LENGTH
+            )));
+        }
+    }
+    if let Some(correo) = &request.correo {
+        if !utils::is_valid_email(correo) {
+            return Err(ProfileError::Validation(format!("Correo inválido: '{}'", correo)));
+        }
+    }
+    if let Some(idioma) = request.idioma.as_deref().map(str::trim).filter(|i| !i.is_empty()) {
+        let valid = idioma.len() <= 10 && idioma.chars().all(|c| c.is_ascii_alphabetic() || c == '-');
+        if !valid {
+            return Err(ProfileError::Validation(format!("Código de idioma inválido: '{}'", idioma)));
+        }
+    }
+    if let Some(ruta) = request.ruta_inicio.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        if !ruta.starts_with('/') || ruta.starts_with("//") || ruta.len() > 200 {
+            return Err(ProfileError::Validation(format!("Ruta de inicio inválida: '{}'", ruta)));
+        }
+    }
+    Ok(())
+}
+
+/// Actualiza nombre y preferencias. Si el correo cambia, lo deja pendiente y envía
+/// el código de verificación a la dirección nueva.
+pub async fn update_profile(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    request: &ProfileUpdateRequest,
+    sql_collate_clause: &str,
+) -> Result<(), ProfileError> {
+    validate_update(request)?;
+    let (usuario, correo_actual): (String, String) = sqlx::query_as(
+        "SELECT usuario, correo FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario_id = @p1"
+    )
+    .bind(usuario_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| ProfileError::NotFound(format!("Usuario {} no encontrado", usuario_id)))?;
+
+    let correo_nuevo = request
+        .correo
+        .as_deref()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.eq_ignore_ascii_case(correo_actual.trim()));
+
+    if let Some(correo) = &correo_nuevo {
+        let sql_query = format!(
+            "SELECT COUNT(*) FROM riy.riy_usuario WITH(NOLOCK) WHERE correo = @p1 {} AND usuario_id <> @p2",
+            sql_collate_clause
+        );
+        let (en_uso,): (i32,) = sqlx::query_as(&sql_query)
+            .bind(correo)
+            .bind(usuario_id)
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?;
+        if en_uso > 0 {
+            return Err(ProfileError::Conflict(format!("El correo '{}' ya está en uso", correo)));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    if let Some(nombre) = &request.nombre {
+        sqlx::query(
+            "UPDATE riy.riy_usuario
+                SET nombre = @p1, modificado_por = @p2, fecha_modificacion = GETDATE()
+              WHERE usuario_id = @p3"
+        )
+        .bind(nombre.trim())
+        .bind(&usuario)
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    // Un campo vacío ("") borra la preferencia; un campo ausente la conserva.
+    let normalize = |v: &Option<String>| v.as_deref().map(|s| s.trim().to_string());
+    sqlx::query(
+        "MERGE riy.riy_usuario_perfil AS t
+         USING (SELECT @p1 AS usuarioID) AS s ON t.usuarioID = s.usuarioID
+         WHEN MATCHED THEN UPDATE SET
+              idioma = CASE WHEN @p2 IS NULL THEN t.idioma ELSE NULLIF(@p2, '') END,
+              rutaInicio = CASE WHEN @p3 IS NULL THEN t.rutaInicio ELSE NULLIF(@p3, '') END,
+              correoPendiente = COALESCE(@p4, t.correoPendiente),
+              fechaModificacion = GETDATE()
+         WHEN NOT MATCHED THEN INSERT (usuarioID, idioma, rutaInicio, correoPendiente, fechaModificacion)
+              VALUES (@p1, NULLIF(@p2, ''), NULLIF(@p3, ''), @p4, GETDATE());"
+    )
+    .bind(usuario_id)
+    .bind(normalize(&request.idioma))
+    .bind(normalize(&request.ruta_inicio))
+    .bind(&correo_nuevo)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    if let Some(correo) = &correo_nuevo {
+        notification_logic::send_verification_code(
+            pool,
+            usuario_id,
+            NotificationKind::CodigoVerificacion,
+            Some(correo),
+        ).await?;
+    }
+    Ok(())
+}
+
+/// Aplica el correo pendiente si el código es correcto.
+pub async fn confirm_email_change(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    codigo: i32,
+) -> Result<(), ProfileError> {
+    let (_, correo_pendiente) = load_preferences(pool, usuario_id).await?;
+    let correo = correo_pendiente
+        .ok_or_else(|| ProfileError::NotFound("No hay un cambio de correo pendiente".to_string()))?;
+    notification_logic::consume_verification_code(pool, usuario_id, codigo).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET correo = @p1, modificado_por = usuario, fecha_modificacion = GETDATE()
+          WHERE usuario_id = @p2"
+    )
+    .bind(&correo)
+    .bind(usuario_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query("UPDATE riy.riy_usuario_perfil SET correoPendiente = NULL, fechaModificacion = GETDATE() WHERE usuarioID = @p1")
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
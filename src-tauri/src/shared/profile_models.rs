@@ -0,0 +1,67 @@
+// src/shared/profile_models.rs
+// Modelos del perfil del usuario conectado (`/me`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{IdentityLink, LoggedInUser};
+
+/// Preferencias personales; `None` usa el valor por defecto del aplicativo.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPreferences {
+    /// Código de idioma, p. ej. `es` o `en-US`.
+    pub idioma: Option<String>,
+    /// Ruta del frontend que se abre después del login, p. ej. `/usuarios`.
+    pub ruta_inicio: Option<String>,
+}
+
+/// Sesión emitida para el usuario (`riy.riy_usuario_sesion`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSession {
+    pub sesion_id: i32,
+    /// `web` o `escritorio`.
+    pub origen: String,
+    pub user_agent: Option<String>,
+    pub direccion_ip: Option<String>,
+    pub fecha_inicio: String,
+    pub fecha_expira: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// Es la sesión con la que se hizo la consulta.
+    #[sqlx(default)]
+    pub actual: bool,
+}
+
+/// Respuesta de `GET /me`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserProfile {
+    pub user: LoggedInUser,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    pub preferences: UserPreferences,
+    /// Correo nuevo a la espera del código de verificación.
+    pub correo_pendiente: Option<String>,
+    pub sessions: Vec<UserSession>,
+    pub identities: Vec<IdentityLink>,
+    /// Administrador que actúa en nombre del usuario (token de suplantación).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suplantado_por: Option<String>,
+}
+
+/// Cuerpo de `PUT /me`; los campos ausentes no se modifican.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileUpdateRequest {
+    pub nombre: Option<String>,
+    /// Si cambia, se envía un código al correo nuevo y el cambio queda pendiente.
+    pub correo: Option<String>,
+    pub idioma: Option<String>,
+    pub ruta_inicio: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailChangeConfirm {
+    pub codigo: i32,
+}
@@ -0,0 +1,185 @@
+// src/shared/role_logic.rs
+/*
+Roles por aplicativo, su asignación a usuarios y los permisos que otorga cada rol.
+
+Los permisos de un usuario en el aplicativo salen de sus roles (riy_SeguridadRolPermiso) más las
+concesiones temporales vigentes (grant_logic); ver utils::get_permissions_by_app. Reemplaza a la
+lista fija que daba todos los permisos al usuario 1 del aplicativo 1: antes de desplegar hay que
+cargar los permisos de los roles existentes, por ejemplo para el rol de administración:
+
+INSERT INTO riy.riy_SeguridadRolPermiso (rolID, codigoPermiso, autor)
+SELECT r.rolID, p.codigo, 'migracion'
+  FROM riy.riy_SeguridadRol r
+ CROSS JOIN (VALUES ('administracion'), ('lista_usuarios'), ('agregar_usuario')) p(codigo)
+ WHERE r.aplicativoID = 1 AND r.rol = 'Administrador';
+
+CREATE TABLE riy.riy_SeguridadRol (
+    rolID         INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID  INT          NOT NULL,
+    rol           VARCHAR(50)  NOT NULL,
+    descripcion   VARCHAR(200) NULL,
+    autor         VARCHAR(50)  NOT NULL,
+    fechaCreacion DATETIME     NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT UQ_riy_SeguridadRol UNIQUE (aplicativoID, rol)
+);
+
+CREATE TABLE riy.riy_SeguridadRolUsuario (
+    rolID         INT         NOT NULL,
+    usuarioID     INT         NOT NULL,
+    autor         VARCHAR(50) NOT NULL,
+    fechaCreacion DATETIME    NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT PK_riy_SeguridadRolUsuario PRIMARY KEY (rolID, usuarioID)
+);
+
+CREATE TABLE riy.riy_SeguridadRolPermiso (
+    rolID          INT         NOT NULL,
+    codigoPermiso  VARCHAR(50) NOT NULL,
+    autor          VARCHAR(50) NOT NULL,
+    fechaCreacion  DATETIME    NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT PK_riy_SeguridadRolPermiso PRIMARY KEY (rolID, codigoPermiso)
+);
+*/
+
+use std::collections::HashMap;
+
+use sqlx::{Executor, Mssql, Pool};
+
+use crate::models::Rol;
+
+/// Lista los roles definidos para el aplicativo.
+pub async fn get_roles_by_app(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<Rol>, String> {
+    let sql_query = format!(
+        "SELECT rolID as rol_id,
+                aplicativoID as aplicativo_id,
+                rol {0} as rol,
+                descripcion {0} as descripcion
+           FROM riy.riy_SeguridadRol WITH(NOLOCK)
+          WHERE aplicativoID = @p1
+          ORDER BY rol",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, Rol>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los roles: {}", e))
+}
+
+/// Devuelve, para cada `usuario_id`, los nombres de sus roles en el aplicativo.
+pub async fn get_user_roles_map(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<HashMap<i32, Vec<String>>, String> {
+    let sql_query = format!(
+        "SELECT ru.usuarioID, r.rol {0} as rol
+           FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = ru.rolID
+          WHERE r.aplicativoID = @p1
+          ORDER BY ru.usuarioID, r.rol",
+        sql_collate_clause
+    );
+    let rows: Vec<(i32, String)> = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los roles de los usuarios: {}", e))?;
+
+    let mut roles_by_user: HashMap<i32, Vec<String>> = HashMap::new();
+    for (usuario_id, rol) in rows {
+        roles_by_user.entry(usuario_id).or_default().push(rol);
+    }
+    Ok(roles_by_user)
+}
+
+/// Devuelve los nombres de los roles de un usuario en el aplicativo.
+pub async fn get_user_roles(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<String>, String> {
+    let sql_query = format!(
+        "SELECT r.rol {0} as rol
+           FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = ru.rolID
+          WHERE ru.usuarioID = @p1 AND r.aplicativoID = @p2
+          ORDER BY r.rol",
+        sql_collate_clause
+    );
+    let rows: Vec<(String,)> = sqlx::query_as(&sql_query)
+        .bind(usuario_id)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los roles del usuario: {}", e))?;
+    Ok(rows.into_iter().map(|(rol,)| rol).collect())
+}
+
+/// Códigos de permiso que otorgan los roles permanentes del usuario en el aplicativo.
+pub async fn get_user_role_permissions(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    aplicativo_id: i32,
+) -> Result<Vec<String>, String> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT rp.codigoPermiso
+           FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = ru.rolID
+           JOIN riy.riy_SeguridadRolPermiso rp WITH(NOLOCK) ON rp.rolID = r.rolID
+          WHERE ru.usuarioID = @p1 AND r.aplicativoID = @p2"
+    )
+    .bind(usuario_id)
+    .bind(aplicativo_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Error al leer los permisos de los roles del usuario: {}", e))?;
+    Ok(rows.into_iter().map(|(codigo,)| codigo).collect())
+}
+
+/// Asigna un rol a un usuario si aún no lo tiene.
+/// Acepta el pool o una transacción abierta.
+pub async fn assign_role<'e, E>(
+    executor: E,
+    usuario_id: i32,
+    rol_id: i32,
+    autor: &str,
+) -> Result<(), String>
+where
+    E: Executor<'e, Database = Mssql>,
+{
+    sqlx::query(
+        "IF NOT EXISTS (SELECT 1 FROM riy.riy_SeguridadRolUsuario WHERE rolID = @p1 AND usuarioID = @p2)
+            INSERT INTO riy.riy_SeguridadRolUsuario (rolID, usuarioID, autor, fechaCreacion)
+            VALUES (@p1, @p2, @p3, GETDATE())"
+    )
+    .bind(rol_id)
+    .bind(usuario_id)
+    .bind(autor)
+    .execute(executor)
+    .await
+    .map_err(|e| format!("Error al asignar el rol {} al usuario {}: {}", rol_id, usuario_id, e))?;
+    Ok(())
+}
+
+/// Quita un rol a un usuario. Acepta el pool o una transacción abierta.
+pub async fn remove_role<'e, E>(
+    executor: E,
+    usuario_id: i32,
+    rol_id: i32,
+) -> Result<(), String>
+where
+    E: Executor<'e, Database = Mssql>,
+{
+    sqlx::query("DELETE FROM riy.riy_SeguridadRolUsuario WHERE rolID = @p1 AND usuarioID = @p2")
+        .bind(rol_id)
+        .bind(usuario_id)
+        .execute(executor)
+        .await
+        .map_err(|e| format!("Error al quitar el rol {} al usuario {}: {}", rol_id, usuario_id, e))?;
+    Ok(())
+}
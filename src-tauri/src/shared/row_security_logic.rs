@@ -0,0 +1,526 @@
+// src/shared/row_security_logic.rs
+/*
+Seguridad por fila: conceptos y valores permitidos por usuario.
+
+Un concepto (empresa, almacén, centro de costo...) apunta a una tabla de consulta del
+ERP y a la columna con sus valores. A cada usuario se le asigna el conjunto de valores
+que puede ver, o `todos` (valor VALOR_TODOS). El resolvedor (resolve_allowed_values)
+es lo que usan las consultas del aplicativo para filtrar filas.
+
+Tabla y columnas del concepto se interpolan en SQL: solo se aceptan identificadores
+simples (quote_identifier) y se verifica que existan antes de guardarlos.
+
+CREATE TABLE riy.riy_concepto (
+    conceptoID          INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID        INT           NOT NULL,
+    codigo              VARCHAR(50)   NOT NULL,
+    nombre              NVARCHAR(100) NOT NULL,
+    tablaOrigen         VARCHAR(128)  NOT NULL,
+    columnaValor        VARCHAR(128)  NOT NULL,
+    columnaDescripcion  VARCHAR(128)  NULL,
+    activo              BIT           NOT NULL DEFAULT 1,
+    autor               VARCHAR(50)   NOT NULL,
+    fechaCreacion       DATETIME      NOT NULL DEFAULT GETDATE(),
+    modificadoPor       VARCHAR(50)   NULL,
+    fechaModificacion   DATETIME      NULL,
+    CONSTRAINT UQ_riy_concepto_codigo UNIQUE (aplicativoID, codigo)
+);
+
+CREATE TABLE riy.riy_concepto_valor_usuario (
+    conceptoID     INT           NOT NULL,
+    usuarioID      INT           NOT NULL,
+    valor          VARCHAR(100)  NOT NULL,   -- '*' = todos los valores
+    autor          VARCHAR(50)   NOT NULL,
+    fechaCreacion  DATETIME      NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT PK_riy_concepto_valor_usuario PRIMARY KEY (conceptoID, usuarioID, valor)
+);
+*/
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use sqlx::{Mssql, Pool};
+
+use crate::row_security_models::{
+    AllowedValues, ConceptLookupValue, SecurityConcept, SecurityConceptInput, UserConceptValues,
+};
+
+/// Permiso para administrar la lista de conceptos.
+pub const PERMISO_CONCEPTOS: &str = "sxf_conceptos";
+/// Permiso para asignar valores a usuarios y consultar los de otros.
+pub const PERMISO_VALORES: &str = "sxf_valores";
+
+/// Valor reservado que da acceso a todos los valores del concepto.
+pub const VALOR_TODOS: &str = "*";
+
+const MAX_LOOKUP_ROWS: i32 = 200;
+
+#[derive(Debug)]
+pub enum RowSecurityError {
+    Validation(String),
+    NotFound(String),
+    Conflict(String),
+    Database(String),
+}
+
+impl fmt::Display for RowSecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RowSecurityError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            RowSecurityError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            RowSecurityError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            RowSecurityError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RowSecurityError {}
+
+fn db_error(e: sqlx::Error) -> RowSecurityError {
+    RowSecurityError::Database(e.to_string())
+}
+
+fn is_simple_identifier(part: &str) -> bool {
+    let mut chars = part.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && part.len() <= 128
+}
+
+/// Convierte `esquema.tabla` o `columna` en `[esquema].[tabla]` / `[columna]`,
+/// rechazando cualquier otra cosa.
+pub fn quote_identifier(name: &str) -> Result<String, RowSecurityError> {
+    let parts: Vec<&str> = name.trim().split('.').collect();
+    if parts.is_empty() || parts.len() > 2 || !parts.iter().all(|p| is_simple_identifier(p)) {
+        return Err(RowSecurityError::Validation(format!("Identificador SQL inválido: '{}'", name)));
+    }
+    Ok(parts.iter().map(|p| format!("[{}]", p)).collect::<Vec<_>>().join("."))
+}
+
+fn concept_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT conceptoID as concepto_id,
+                aplicativoID as aplicativo_id,
+                codigo {0} as codigo,
+                nombre,
+                tablaOrigen {0} as tabla_origen,
+                columnaValor {0} as columna_valor,
+                columnaDescripcion {0} as columna_descripcion,
+                activo,
+                autor {0} as autor,
+                CONVERT(VARCHAR(19), fechaCreacion, 120) as fecha_creacion,
+                modificadoPor {0} as modificado_por,
+                CONVERT(VARCHAR(19), fechaModificacion, 120) as fecha_modificacion
+           FROM riy.riy_concepto WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+pub async fn list_concepts(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<SecurityConcept>, RowSecurityError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1 ORDER BY nombre", concept_select(sql_collate_clause));
+    sqlx::query_as::<_, SecurityConcept>(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn get_concept(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    concepto_id: i32,
+    sql_collate_clause: &str,
+) -> Result<SecurityConcept, RowSecurityError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND conceptoID = @p2",
+        concept_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, SecurityConcept>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(concepto_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| RowSecurityError::NotFound(format!("Concepto {} no encontrado", concepto_id)))
+}
+
+pub async fn get_concept_by_code(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    codigo: &str,
+    sql_collate_clause: &str,
+) -> Result<SecurityConcept, RowSecurityError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND codigo = @p2",
+        concept_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, SecurityConcept>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(codigo.trim())
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| RowSecurityError::NotFound(format!("Concepto '{}' no encontrado", codigo)))
+}
+
+/// Valida el concepto y comprueba que la tabla y las columnas existan en la base.
+async fn validate_concept(
+    pool: &Pool<Mssql>,
+    input: &SecurityConceptInput,
+) -> Result<SecurityConceptInput, RowSecurityError> {
+    let codigo = input.codigo.trim().to_lowercase();
+    if codigo.is_empty() || !is_simple_identifier(&codigo) || codigo.len() > 50 {
+        return Err(RowSecurityError::Validation(
+            "El código solo puede tener letras, dígitos y '_' (máx. 50)".to_string(),
+        ));
+    }
+    let nombre = input.nombre.trim();
+    if nombre.is_empty() {
+        return Err(RowSecurityError::Validation("El nombre es obligatorio".to_string()));
+    }
+    let tabla_origen = input.tabla_origen.trim();
+    let columna_valor = input.columna_valor.trim();
+    let columna_descripcion = input
+        .columna_descripcion
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    quote_identifier(tabla_origen)?;
+    for columna in std::iter::once(columna_valor).chain(columna_descripcion) {
+        if !is_simple_identifier(columna) {
+            return Err(RowSecurityError::Validation(format!("Columna inválida: '{}'", columna)));
+        }
+    }
+
+    let (existe_tabla,): (Option<i32>,) = sqlx::query_as("SELECT OBJECT_ID(@p1)")
+        .bind(tabla_origen)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if existe_tabla.is_none() {
+        return Err(RowSecurityError::Validation(format!("La tabla '{}' no existe", tabla_origen)));
+    }
+    for columna in std::iter::once(columna_valor).chain(columna_descripcion) {
+        let (largo,): (Option<i16>,) = sqlx::query_as("SELECT CAST(COL_LENGTH(@p1, @p2) AS SMALLINT)")
+            .bind(tabla_origen)
+            .bind(columna)
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?;
+        if largo.is_none() {
+            return Err(RowSecurityError::Validation(format!(
+                "La columna '{}' no existe en '{}'", columna, tabla_origen
+            )));
+        }
+    }
+
+    Ok(SecurityConceptInput {
+        codigo,
+        nombre: nombre.to_string(),
+        tabla_origen: tabla_origen.to_string(),
+        columna_valor: columna_valor.to_string(),
+        columna_descripcion: columna_descripcion.map(str::to_string),
+        activo: input.activo,
+    })
+}
+
+pub async fn create_concept(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &SecurityConceptInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<SecurityConcept, RowSecurityError> {
+    let input = validate_concept(pool, input).await?;
+    if get_concept_by_code(pool, aplicativo_id, &input.codigo, sql_collate_clause).await.is_ok() {
+        return Err(RowSecurityError::Conflict(format!("El concepto '{}' ya existe", input.codigo)));
+    }
+    let (concepto_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_concepto
+            (aplicativoID, codigo, nombre, tablaOrigen, columnaValor, columnaDescripcion, activo, autor, fechaCreacion)
+         OUTPUT INSERTED.conceptoID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, @p7, @p8, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(&input.codigo)
+    .bind(&input.nombre)
+    .bind(&input.tabla_origen)
+    .bind(&input.columna_valor)
+    .bind(input.columna_descripcion.as_deref())
+    .bind(input.activo)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    get_concept(pool, aplicativo_id, concepto_id, sql_collate_clause).await
+}
+
+/// Modifica el concepto; el código no cambia porque las consultas lo referencian.
+pub async fn update_concept(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    concepto_id: i32,
+    input: &SecurityConceptInput,
+    modificado_por: &str,
+    sql_collate_clause: &str,
+) -> Result<SecurityConcept, RowSecurityError> {
+    let actual = get_concept(pool, aplicativo_id, concepto_id, sql_collate_clause).await?;
+    let input = validate_concept(pool, input).await?;
+    if input.codigo != actual.codigo {
+        return Err(RowSecurityError::Validation("El código de un concepto no se puede cambiar".to_string()));
+    }
+    sqlx::query(
+        "UPDATE riy.riy_concepto
+            SET nombre = @p1, tablaOrigen = @p2, columnaValor = @p3, columnaDescripcion = @p4, activo = @p5,
+                modificadoPor = @p6, fechaModificacion = GETDATE()
+          WHERE conceptoID = @p7"
+    )
+    .bind(&input.nombre)
+    .bind(&input.tabla_origen)
+    .bind(&input.columna_valor)
+    .bind(input.columna_descripcion.as_deref())
+    .bind(input.activo)
+    .bind(modificado_por)
+    .bind(concepto_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    get_concept(pool, aplicativo_id, concepto_id, sql_collate_clause).await
+}
+
+/// Elimina el concepto junto con todas sus asignaciones.
+pub async fn delete_concept(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    concepto_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(), RowSecurityError> {
+    get_concept(pool, aplicativo_id, concepto_id, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_concepto_valor_usuario WHERE conceptoID = @p1")
+        .bind(concepto_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_concepto WHERE conceptoID = @p1")
+        .bind(concepto_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+/// Valores posibles del concepto, leídos de su tabla del ERP.
+pub async fn lookup_values(
+    pool: &Pool<Mssql>,
+    concepto: &SecurityConcept,
+    filtro: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<Vec<ConceptLookupValue>, RowSecurityError> {
+    let tabla = quote_identifier(&concepto.tabla_origen)?;
+    let valor = quote_identifier(&concepto.columna_valor)?;
+    let descripcion = match concepto.columna_descripcion.as_deref() {
+        Some(columna) => format!("CAST({} AS NVARCHAR(200)) {}", quote_identifier(columna)?, sql_collate_clause),
+        None => "CAST(NULL AS NVARCHAR(200))".to_string(),
+    };
+    let filtro = filtro.map(str::trim).filter(|f| !f.is_empty()).map(|f| format!("%{}%", f));
+    let sql_query = format!(
+        "SELECT DISTINCT TOP ({lim}) CAST({valor} AS VARCHAR(100)) {c} as valor, {descripcion} as descripcion
+           FROM {tabla} WITH(NOLOCK)
+          WHERE {valor} IS NOT NULL
+            AND (@p1 IS NULL OR CAST({valor} AS VARCHAR(100)) LIKE @p1 OR {descripcion} LIKE @p1)
+          ORDER BY valor",
+        lim = MAX_LOOKUP_ROWS,
+        valor = valor,
+        descripcion = descripcion,
+        tabla = tabla,
+        c = sql_collate_clause
+    );
+    sqlx::query_as::<_, ConceptLookupValue>(&sql_query)
+        .bind(filtro)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+fn to_user_values(concepto_id: i32, usuario_id: i32, usuario: String, valores: Vec<String>) -> UserConceptValues {
+    let todos = valores.iter().any(|v| v == VALOR_TODOS);
+    UserConceptValues {
+        concepto_id,
+        usuario_id,
+        usuario,
+        todos,
+        valores: if todos { Vec::new() } else { valores },
+    }
+}
+
+/// Asignaciones de todos los usuarios para un concepto.
+pub async fn list_concept_assignments(
+    pool: &Pool<Mssql>,
+    concepto_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<UserConceptValues>, RowSecurityError> {
+    let sql_query = format!(
+        "SELECT cv.usuarioID, u.usuario {0}, cv.valor {0}
+           FROM riy.riy_concepto_valor_usuario cv WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = cv.usuarioID
+          WHERE cv.conceptoID = @p1
+          ORDER BY u.usuario, cv.valor",
+        sql_collate_clause
+    );
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(&sql_query)
+        .bind(concepto_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let mut por_usuario: BTreeMap<(String, i32), Vec<String>> = BTreeMap::new();
+    for (usuario_id, usuario, valor) in rows {
+        por_usuario.entry((usuario, usuario_id)).or_default().push(valor);
+    }
+    Ok(por_usuario
+        .into_iter()
+        .map(|((usuario, usuario_id), valores)| to_user_values(concepto_id, usuario_id, usuario, valores))
+        .collect())
+}
+
+pub async fn get_user_values(
+    pool: &Pool<Mssql>,
+    concepto_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<UserConceptValues, RowSecurityError> {
+    let sql_query = format!(
+        "SELECT valor {0} FROM riy.riy_concepto_valor_usuario WITH(NOLOCK)
+          WHERE conceptoID = @p1 AND usuarioID = @p2 ORDER BY valor",
+        sql_collate_clause
+    );
+    let valores: Vec<(String,)> = sqlx::query_as(&sql_query)
+        .bind(concepto_id)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(to_user_values(
+        concepto_id,
+        usuario_id,
+        String::new(),
+        valores.into_iter().map(|(v,)| v).collect(),
+    ))
+}
+
+/// Reemplaza el conjunto de valores del usuario. Cada valor debe existir en la tabla
+/// de origen del concepto.
+pub async fn set_user_values(
+    pool: &Pool<Mssql>,
+    concepto: &SecurityConcept,
+    usuario_id: i32,
+    todos: bool,
+    valores: &[String],
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<UserConceptValues, RowSecurityError> {
+    let mut nuevos: Vec<String> = if todos {
+        vec![VALOR_TODOS.to_string()]
+    } else {
+        valores.iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+    };
+    nuevos.sort();
+    nuevos.dedup();
+    if !todos && nuevos.iter().any(|v| v == VALOR_TODOS) {
+        return Err(RowSecurityError::Validation(format!(
+            "'{}' está reservado; use todos = true", VALOR_TODOS
+        )));
+    }
+
+    if !todos && !nuevos.is_empty() {
+        let tabla = quote_identifier(&concepto.tabla_origen)?;
+        let columna = quote_identifier(&concepto.columna_valor)?;
+        let sql_query = format!(
+            "SELECT COUNT(*) FROM {} WITH(NOLOCK) WHERE CAST({} AS VARCHAR(100)) = @p1",
+            tabla, columna
+        );
+        for valor in &nuevos {
+            let (total,): (i32,) = sqlx::query_as(&sql_query)
+                .bind(valor)
+                .fetch_one(pool)
+                .await
+                .map_err(db_error)?;
+            if total == 0 {
+                return Err(RowSecurityError::Validation(format!(
+                    "El valor '{}' no existe en {}", valor, concepto.tabla_origen
+                )));
+            }
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_concepto_valor_usuario WHERE conceptoID = @p1 AND usuarioID = @p2")
+        .bind(concepto.concepto_id)
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    for valor in &nuevos {
+        sqlx::query(
+            "INSERT INTO riy.riy_concepto_valor_usuario (conceptoID, usuarioID, valor, autor, fechaCreacion)
+             VALUES (@p1, @p2, @p3, @p4, GETDATE())"
+        )
+        .bind(concepto.concepto_id)
+        .bind(usuario_id)
+        .bind(valor)
+        .bind(autor)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    get_user_values(pool, concepto.concepto_id, usuario_id, sql_collate_clause).await
+}
+
+/// Valores que el usuario puede ver para el concepto. Un concepto inactivo no filtra.
+pub async fn resolve_allowed_values(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    codigo_concepto: &str,
+    sql_collate_clause: &str,
+) -> Result<AllowedValues, RowSecurityError> {
+    let concepto = get_concept_by_code(pool, aplicativo_id, codigo_concepto, sql_collate_clause).await?;
+    resolve_for_concept(pool, &concepto, usuario_id, sql_collate_clause).await
+}
+
+async fn resolve_for_concept(
+    pool: &Pool<Mssql>,
+    concepto: &SecurityConcept,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<AllowedValues, RowSecurityError> {
+    let asignados = get_user_values(pool, concepto.concepto_id, usuario_id, sql_collate_clause).await?;
+    Ok(AllowedValues {
+        concepto: concepto.codigo.clone(),
+        concepto_id: concepto.concepto_id,
+        todos: !concepto.activo || asignados.todos,
+        valores: asignados.valores,
+    })
+}
+
+/// Resuelve todos los conceptos del aplicativo para el usuario.
+pub async fn resolve_all(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<AllowedValues>, RowSecurityError> {
+    let mut resultado = Vec::new();
+    for concepto in list_concepts(pool, aplicativo_id, sql_collate_clause).await? {
+        resultado.push(resolve_for_concept(pool, &concepto, usuario_id, sql_collate_clause).await?);
+    }
+    Ok(resultado)
+}
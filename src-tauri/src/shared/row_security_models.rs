@@ -0,0 +1,99 @@
+// src/shared/row_security_models.rs
+// Modelos de la seguridad por fila: conceptos y valores permitidos por usuario.
+
+use serde::{Deserialize, Serialize};
+
+/// Concepto de seguridad (empresa, almacén, centro de costo...) ligado a una
+/// tabla de consulta del ERP (`riy.riy_concepto`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityConcept {
+    pub concepto_id: i32,
+    pub aplicativo_id: i32,
+    /// Identificador estable usado por las consultas, p. ej. `centro_costo`.
+    pub codigo: String,
+    pub nombre: String,
+    /// Tabla del ERP con los valores posibles, p. ej. `dbo.CentroCosto`.
+    pub tabla_origen: String,
+    pub columna_valor: String,
+    pub columna_descripcion: Option<String>,
+    pub activo: bool,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+/// Alta o modificación de un concepto.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityConceptInput {
+    pub codigo: String,
+    pub nombre: String,
+    pub tabla_origen: String,
+    pub columna_valor: String,
+    pub columna_descripcion: Option<String>,
+    #[serde(default = "default_activo")]
+    pub activo: bool,
+}
+
+fn default_activo() -> bool {
+    true
+}
+
+/// Valor posible de un concepto, leído de su tabla de origen.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ConceptLookupValue {
+    pub valor: String,
+    pub descripcion: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConceptLookupQuery {
+    /// Texto a buscar en el valor o la descripción.
+    pub filtro: Option<String>,
+}
+
+/// Valores asignados a un usuario para un concepto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserConceptValues {
+    pub concepto_id: i32,
+    pub usuario_id: i32,
+    #[serde(default)]
+    pub usuario: String,
+    /// Sin restricción: ve todos los valores del concepto.
+    #[serde(default)]
+    pub todos: bool,
+    #[serde(default)]
+    pub valores: Vec<String>,
+}
+
+/// Cuerpo de `PUT /row-security/concepts/{id}/values/{usuario_id}`: reemplaza el conjunto.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserConceptValuesInput {
+    #[serde(default)]
+    pub todos: bool,
+    #[serde(default)]
+    pub valores: Vec<String>,
+}
+
+/// Respuesta del resolvedor: lo que una consulta debe filtrar para el usuario.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedValues {
+    pub concepto: String,
+    pub concepto_id: i32,
+    pub todos: bool,
+    /// Vacío y `todos = false` significa que el usuario no ve ninguna fila.
+    pub valores: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveQuery {
+    /// Otro usuario (requiere `sxf_valores`); por defecto, el usuario conectado.
+    pub usuario_id: Option<i32>,
+}
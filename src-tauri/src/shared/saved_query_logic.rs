@@ -0,0 +1,693 @@
+// src/shared/saved_query_logic.rs
+/*
+Consultas guardadas: SELECT parametrizados de solo lectura sobre el ERP, propiedad de
+un usuario y opcionalmente compartidos con roles. Se ejecutan siempre en el sandbox de
+solo lectura (sql_sandbox_logic), que aplica la seguridad por fila del usuario que
+ejecuta, con un tiempo máximo y paginación.
+
+Los parámetros se referencian en el SQL por posición: el primero (según `orden`) es @p1.
+
+`objetoOrigen` y los filtros por concepto agregan predicados, pero no son lo que protege
+los datos: el sandbox rechaza el SQL que lee directamente un objeto con seguridad por fila
+(hay que usar su vista de riy_sxf) y el login de solo lectura no tiene SELECT sobre esas
+tablas, así que una consulta sin objeto de origen no puede saltarse la seguridad.
+
+CREATE TABLE riy.riy_consulta (
+    consultaID         INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID       INT           NOT NULL,
+    nombre             NVARCHAR(100) NOT NULL,
+    descripcion        NVARCHAR(500) NULL,
+    sentenciaSql       NVARCHAR(MAX) NOT NULL,
+    objetoOrigen       VARCHAR(256)  NULL,
+    timeoutSegundos    INT           NOT NULL DEFAULT 30,
+    propietarioID      INT           NOT NULL,
+    fechaCreacion      DATETIME      NOT NULL DEFAULT GETDATE(),
+    modificadoPor      VARCHAR(50)   NULL,
+    fechaModificacion  DATETIME      NULL
+);
+
+CREATE TABLE riy.riy_consulta_parametro (
+    consultaID     INT           NOT NULL,
+    orden          INT           NOT NULL,
+    nombre         VARCHAR(50)   NOT NULL,
+    etiqueta       NVARCHAR(100) NOT NULL,
+    tipo           VARCHAR(10)   NOT NULL, -- texto, entero, decimal, fecha, booleano
+    requerido      BIT           NOT NULL DEFAULT 0,
+    valorDefecto   NVARCHAR(200) NULL,
+    origenLookup   VARCHAR(50)   NULL,     -- código de concepto (riy_concepto)
+    CONSTRAINT PK_riy_consulta_parametro PRIMARY KEY (consultaID, orden)
+);
+
+CREATE TABLE riy.riy_consulta_filtro (
+    consultaID      INT          NOT NULL,
+    codigoConcepto  VARCHAR(50)  NOT NULL,
+    columna         VARCHAR(128) NOT NULL,
+    CONSTRAINT PK_riy_consulta_filtro PRIMARY KEY (consultaID, codigoConcepto, columna)
+);
+
+CREATE TABLE riy.riy_consulta_rol (
+    consultaID  INT NOT NULL,
+    rolID       INT NOT NULL,
+    CONSTRAINT PK_riy_consulta_rol PRIMARY KEY (consultaID, rolID)
+);
+*/
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde_json::Value;
+use sqlx::{Mssql, Pool};
+
+use crate::row_security_logic::{self, quote_identifier, RowSecurityError};
+use crate::row_security_models::ConceptLookupValue;
+use crate::saved_query_models::{
+    QueryExecutionRequest, QueryExecutionResult, QueryParamType, QueryParameter, SavedQuery, SavedQueryInput,
+};
+use crate::secured_query_logic::quote_column;
+use crate::secured_query_models::{RowFilter, SecuredQuery, SqlValue};
+use crate::sql_sandbox_logic::{self, PreparedExport, SandboxError, SqlSandbox};
+use crate::role_logic;
+
+/// Crear consultas propias y ejecutar las propias o compartidas.
+pub const PERMISO_MIS_CONSULTAS: &str = "mis_consultas";
+/// Ver, modificar y ejecutar todas las consultas del aplicativo.
+pub const PERMISO_TODAS_LAS_CONSULTAS: &str = "todas_las_consultas";
+
+const DEFAULT_TIMEOUT: i32 = 30;
+const MAX_TIMEOUT: i32 = 300;
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug)]
+pub enum SavedQueryError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Timeout(String),
+    Database(String),
+}
+
+impl fmt::Display for SavedQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SavedQueryError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            SavedQueryError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            SavedQueryError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            SavedQueryError::Timeout(msg) => write!(f, "Tiempo agotado: {}", msg),
+            SavedQueryError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SavedQueryError {}
+
+impl From<RowSecurityError> for SavedQueryError {
+    fn from(e: RowSecurityError) -> Self {
+        match e {
+            RowSecurityError::Validation(msg) | RowSecurityError::Conflict(msg) => SavedQueryError::Validation(msg),
+            RowSecurityError::NotFound(msg) => SavedQueryError::NotFound(msg),
+            RowSecurityError::Database(msg) => SavedQueryError::Database(msg),
+        }
+    }
+}
+
+impl From<SandboxError> for SavedQueryError {
+    fn from(e: SandboxError) -> Self {
+        match e {
+            SandboxError::Validation(msg) => SavedQueryError::Validation(msg),
+            SandboxError::Timeout(msg) => SavedQueryError::Timeout(msg),
+            SandboxError::Unavailable(msg) | SandboxError::Database(msg) => SavedQueryError::Database(msg),
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> SavedQueryError {
+    SavedQueryError::Database(e.to_string())
+}
+
+/// Usuario que consulta, modifica o ejecuta.
+pub struct QueryUser<'a> {
+    pub usuario: &'a str,
+    pub usuario_id: i32,
+    /// Tiene PERMISO_TODAS_LAS_CONSULTAS.
+    pub ve_todas: bool,
+}
+
+type QueryRow = (i32, i32, String, Option<String>, String, Option<String>, i32, i32, String, String, Option<String>, Option<String>);
+
+fn query_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT c.consultaID, c.aplicativoID, c.nombre, c.descripcion, c.sentenciaSql,
+                c.objetoOrigen {0}, c.timeoutSegundos, c.propietarioID, u.usuario {0},
+                CONVERT(VARCHAR(19), c.fechaCreacion, 120),
+                c.modificadoPor {0},
+                CONVERT(VARCHAR(19), c.fechaModificacion, 120)
+           FROM riy.riy_consulta c WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = c.propietarioID",
+        sql_collate_clause
+    )
+}
+
+/// Completa la consulta con parámetros, filtros y roles.
+async fn to_saved_query(
+    pool: &Pool<Mssql>,
+    row: QueryRow,
+    sql_collate_clause: &str,
+) -> Result<SavedQuery, SavedQueryError> {
+    let (consulta_id, aplicativo_id, nombre, descripcion, sql, objeto_origen, timeout_segundos,
+         propietario_id, propietario, fecha_creacion, modificado_por, fecha_modificacion) = row;
+
+    let sql_query = format!(
+        "SELECT nombre {0}, etiqueta, tipo {0}, requerido, valorDefecto, origenLookup {0}
+           FROM riy.riy_consulta_parametro WITH(NOLOCK)
+          WHERE consultaID = @p1 ORDER BY orden",
+        sql_collate_clause
+    );
+    let filas: Vec<(String, String, String, bool, Option<String>, Option<String>)> = sqlx::query_as(&sql_query)
+        .bind(consulta_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut parametros = Vec::with_capacity(filas.len());
+    for (nombre, etiqueta, tipo, requerido, valor_defecto, origen_lookup) in filas {
+        let tipo = QueryParamType::parse(&tipo)
+            .ok_or_else(|| SavedQueryError::Database(format!("Tipo de parámetro desconocido: '{}'", tipo)))?;
+        parametros.push(QueryParameter { nombre, etiqueta, tipo, requerido, valor_defecto, origen_lookup });
+    }
+
+    let sql_query = format!(
+        "SELECT codigoConcepto {0}, columna {0} FROM riy.riy_consulta_filtro WITH(NOLOCK) WHERE consultaID = @p1",
+        sql_collate_clause
+    );
+    let filtros: Vec<(String, String)> = sqlx::query_as(&sql_query)
+        .bind(consulta_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let roles: Vec<(i32,)> = sqlx::query_as("SELECT rolID FROM riy.riy_consulta_rol WITH(NOLOCK) WHERE consultaID = @p1")
+        .bind(consulta_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    Ok(SavedQuery {
+        consulta_id,
+        aplicativo_id,
+        nombre,
+        descripcion,
+        sql,
+        objeto_origen,
+        timeout_segundos,
+        propietario_id,
+        propietario,
+        parametros,
+        filtros: filtros.into_iter().map(|(concepto, columna)| RowFilter { concepto, columna }).collect(),
+        roles: roles.into_iter().map(|(r,)| r).collect(),
+        fecha_creacion,
+        modificado_por,
+        fecha_modificacion,
+    })
+}
+
+async fn fetch_queries(
+    pool: &Pool<Mssql>,
+    sql_query: &str,
+    aplicativo_id: i32,
+    usuario_id: Option<i32>,
+    sql_collate_clause: &str,
+) -> Result<Vec<SavedQuery>, SavedQueryError> {
+    let rows: Vec<QueryRow> = sqlx::query_as(sql_query)
+        .bind(aplicativo_id)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut consultas = Vec::with_capacity(rows.len());
+    for row in rows {
+        consultas.push(to_saved_query(pool, row, sql_collate_clause).await?);
+    }
+    Ok(consultas)
+}
+
+/// "Todas las consultas" del aplicativo.
+pub async fn list_all(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<SavedQuery>, SavedQueryError> {
+    let sql_query = format!(
+        "{} WHERE c.aplicativoID = @p1 ORDER BY c.nombre",
+        query_select(sql_collate_clause)
+    );
+    fetch_queries(pool, &sql_query, aplicativo_id, None, sql_collate_clause).await
+}
+
+/// "Mis consultas": las propias y las compartidas con alguno de los roles del usuario.
+pub async fn list_visible(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<SavedQuery>, SavedQueryError> {
+    let sql_query = format!(
+        "{} WHERE c.aplicativoID = @p1
+            AND (c.propietarioID = @p2
+                 OR EXISTS (SELECT 1 FROM riy.riy_consulta_rol cr WITH(NOLOCK)
+                              JOIN riy.riy_SeguridadRolUsuario ru WITH(NOLOCK) ON ru.rolID = cr.rolID
+                             WHERE cr.consultaID = c.consultaID AND ru.usuarioID = @p2))
+          ORDER BY c.nombre",
+        query_select(sql_collate_clause)
+    );
+    fetch_queries(pool, &sql_query, aplicativo_id, Some(usuario_id), sql_collate_clause).await
+}
+
+pub async fn get_query(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    sql_collate_clause: &str,
+) -> Result<SavedQuery, SavedQueryError> {
+    let sql_query = format!(
+        "{} WHERE c.aplicativoID = @p1 AND c.consultaID = @p2",
+        query_select(sql_collate_clause)
+    );
+    let row: QueryRow = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(consulta_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| SavedQueryError::NotFound(format!("Consulta {} no encontrada", consulta_id)))?;
+    to_saved_query(pool, row, sql_collate_clause).await
+}
+
+/// Carga la consulta si el usuario puede verla (dueño, rol compartido o ve_todas).
+pub async fn get_visible_query(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<SavedQuery, SavedQueryError> {
+    let consulta = get_query(pool, aplicativo_id, consulta_id, sql_collate_clause).await?;
+    if user.ve_todas || consulta.propietario_id == user.usuario_id {
+        return Ok(consulta);
+    }
+    let (compartida,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_consulta_rol cr WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRolUsuario ru WITH(NOLOCK) ON ru.rolID = cr.rolID
+          WHERE cr.consultaID = @p1 AND ru.usuarioID = @p2"
+    )
+    .bind(consulta_id)
+    .bind(user.usuario_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if compartida == 0 {
+        return Err(SavedQueryError::NotFound(format!("Consulta {} no encontrada", consulta_id)));
+    }
+    Ok(consulta)
+}
+
+/// Mayor N de los `@pN` que aparecen en el SQL.
+fn max_positional_param(sql: &str) -> usize {
+    let bytes = sql.as_bytes();
+    let mut max = 0;
+    let mut i = 0;
+    while i + 2 < bytes.len() {
+        if bytes[i] == b'@' && (bytes[i + 1] == b'p' || bytes[i + 1] == b'P') {
+            let digits: String = sql[i + 2..].chars().take_while(|c| c.is_ascii_digit()).collect();
+            if let Ok(n) = digits.parse::<usize>() {
+                max = max.max(n);
+            }
+        }
+        i += 1;
+    }
+    max
+}
+
+async fn validate_input(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &SavedQueryInput,
+    sql_collate_clause: &str,
+) -> Result<(), SavedQueryError> {
+    if input.nombre.trim().is_empty() {
+        return Err(SavedQueryError::Validation("El nombre es obligatorio".to_string()));
+    }
+    // Misma validación que aplica el sandbox al ejecutar
+    sql_sandbox_logic::validate_sql(&input.sql)?;
+    let timeout = input.timeout_segundos.unwrap_or(DEFAULT_TIMEOUT);
+    if !(1..=MAX_TIMEOUT).contains(&timeout) {
+        return Err(SavedQueryError::Validation(format!("El tiempo máximo debe estar entre 1 y {} segundos", MAX_TIMEOUT)));
+    }
+    if let Some(objeto) = input.objeto_origen.as_deref().filter(|o| !o.trim().is_empty()) {
+        quote_identifier(objeto)?;
+    }
+
+    let usados = max_positional_param(&input.sql);
+    if usados > input.parametros.len() {
+        return Err(SavedQueryError::Validation(format!(
+            "El SQL usa @p{} pero solo hay {} parámetros definidos", usados, input.parametros.len()
+        )));
+    }
+    let mut nombres = std::collections::HashSet::new();
+    for parametro in &input.parametros {
+        let nombre = parametro.nombre.trim();
+        if nombre.is_empty() || !nombre.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(SavedQueryError::Validation(format!("Nombre de parámetro inválido: '{}'", nombre)));
+        }
+        if !nombres.insert(nombre.to_string()) {
+            return Err(SavedQueryError::Validation(format!("Parámetro repetido: '{}'", nombre)));
+        }
+        if let Some(defecto) = parametro.valor_defecto.as_deref() {
+            convert_value(parametro, Some(&Value::String(defecto.to_string())))?;
+        }
+        if let Some(concepto) = parametro.origen_lookup.as_deref() {
+            row_security_logic::get_concept_by_code(pool, aplicativo_id, concepto, sql_collate_clause).await?;
+        }
+    }
+
+    for filtro in &input.filtros {
+        row_security_logic::get_concept_by_code(pool, aplicativo_id, &filtro.concepto, sql_collate_clause).await?;
+        quote_column(&filtro.columna)?;
+    }
+    if !input.roles.is_empty() {
+        let roles = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+            .await
+            .map_err(SavedQueryError::Database)?;
+        if let Some(rol_id) = input.roles.iter().find(|id| !roles.iter().any(|r| r.rol_id == **id)) {
+            return Err(SavedQueryError::NotFound(format!("El rol {} no existe en el aplicativo", rol_id)));
+        }
+    }
+    Ok(())
+}
+
+/// Reemplaza parámetros, filtros y roles de la consulta dentro de la transacción.
+async fn replace_children(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    consulta_id: i32,
+    input: &SavedQueryInput,
+) -> Result<(), SavedQueryError> {
+    for tabla in ["riy.riy_consulta_parametro", "riy.riy_consulta_filtro", "riy.riy_consulta_rol"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE consultaID = @p1", tabla))
+            .bind(consulta_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    for (orden, parametro) in input.parametros.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO riy.riy_consulta_parametro
+                (consultaID, orden, nombre, etiqueta, tipo, requerido, valorDefecto, origenLookup)
+             VALUES (@p1, @p2, @p3, @p4, @p5, @p6, @p7, @p8)"
+        )
+        .bind(consulta_id)
+        .bind(orden as i32 + 1)
+        .bind(parametro.nombre.trim())
+        .bind(parametro.etiqueta.trim())
+        .bind(parametro.tipo.as_str())
+        .bind(parametro.requerido)
+        .bind(parametro.valor_defecto.as_deref())
+        .bind(parametro.origen_lookup.as_deref())
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+    for filtro in &input.filtros {
+        sqlx::query("INSERT INTO riy.riy_consulta_filtro (consultaID, codigoConcepto, columna) VALUES (@p1, @p2, @p3)")
+            .bind(consulta_id)
+            .bind(filtro.concepto.trim())
+            .bind(filtro.columna.trim())
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    for rol_id in &input.roles {
+        sqlx::query("INSERT INTO riy.riy_consulta_rol (consultaID, rolID) VALUES (@p1, @p2)")
+            .bind(consulta_id)
+            .bind(rol_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+pub async fn create_query(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &SavedQueryInput,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<SavedQuery, SavedQueryError> {
+    validate_input(pool, aplicativo_id, input, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let (consulta_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_consulta
+            (aplicativoID, nombre, descripcion, sentenciaSql, objetoOrigen, timeoutSegundos, propietarioID, fechaCreacion)
+         OUTPUT INSERTED.consultaID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, @p7, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(input.nombre.trim())
+    .bind(input.descripcion.as_deref())
+    .bind(input.sql.trim())
+    .bind(input.objeto_origen.as_deref().map(str::trim).filter(|o| !o.is_empty()))
+    .bind(input.timeout_segundos.unwrap_or(DEFAULT_TIMEOUT))
+    .bind(user.usuario_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    replace_children(&mut tx, consulta_id, input).await?;
+    tx.commit().await.map_err(db_error)?;
+    get_query(pool, aplicativo_id, consulta_id, sql_collate_clause).await
+}
+
+/// Solo el dueño o quien ve todas las consultas puede modificarla.
+pub async fn update_query(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    input: &SavedQueryInput,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<SavedQuery, SavedQueryError> {
+    let actual = get_query(pool, aplicativo_id, consulta_id, sql_collate_clause).await?;
+    if !user.ve_todas && actual.propietario_id != user.usuario_id {
+        return Err(SavedQueryError::Forbidden("Solo el dueño puede modificar la consulta".to_string()));
+    }
+    validate_input(pool, aplicativo_id, input, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_consulta
+            SET nombre = @p1, descripcion = @p2, sentenciaSql = @p3, objetoOrigen = @p4, timeoutSegundos = @p5,
+                modificadoPor = @p6, fechaModificacion = GETDATE()
+          WHERE consultaID = @p7"
+    )
+    .bind(input.nombre.trim())
+    .bind(input.descripcion.as_deref())
+    .bind(input.sql.trim())
+    .bind(input.objeto_origen.as_deref().map(str::trim).filter(|o| !o.is_empty()))
+    .bind(input.timeout_segundos.unwrap_or(DEFAULT_TIMEOUT))
+    .bind(user.usuario)
+    .bind(consulta_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    replace_children(&mut tx, consulta_id, input).await?;
+    tx.commit().await.map_err(db_error)?;
+    get_query(pool, aplicativo_id, consulta_id, sql_collate_clause).await
+}
+
+pub async fn delete_query(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<(), SavedQueryError> {
+    let actual = get_query(pool, aplicativo_id, consulta_id, sql_collate_clause).await?;
+    if !user.ve_todas && actual.propietario_id != user.usuario_id {
+        return Err(SavedQueryError::Forbidden("Solo el dueño puede eliminar la consulta".to_string()));
+    }
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    for tabla in ["riy.riy_consulta_parametro", "riy.riy_consulta_filtro", "riy.riy_consulta_rol", "riy.riy_consulta"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE consultaID = @p1", tabla))
+            .bind(consulta_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+/// Convierte el valor recibido (o el valor por defecto) al tipo del parámetro.
+fn convert_value(parametro: &QueryParameter, valor: Option<&Value>) -> Result<SqlValue, SavedQueryError> {
+    let defecto = parametro.valor_defecto.clone().map(Value::String);
+    let valor = match valor.filter(|v| !v.is_null()).or(defecto.as_ref()) {
+        Some(v) => v,
+        None if parametro.requerido => {
+            return Err(SavedQueryError::Validation(format!("El parámetro '{}' es obligatorio", parametro.nombre)))
+        }
+        None => return Ok(SqlValue::Null),
+    };
+    let invalido = || SavedQueryError::Validation(format!(
+        "Valor inválido para '{}' ({}): {}", parametro.nombre, parametro.tipo.as_str(), valor
+    ));
+    let texto = match valor {
+        Value::String(s) => s.trim().to_string(),
+        otro => otro.to_string(),
+    };
+    match parametro.tipo {
+        QueryParamType::Texto => Ok(SqlValue::Text(texto)),
+        QueryParamType::Entero => valor.as_i64().or_else(|| texto.parse().ok()).map(SqlValue::Int).ok_or_else(invalido),
+        QueryParamType::Decimal => valor.as_f64().or_else(|| texto.parse().ok()).map(SqlValue::Float).ok_or_else(invalido),
+        QueryParamType::Fecha => NaiveDate::parse_from_str(&texto, "%Y-%m-%d")
+            .map(|d| SqlValue::Text(d.format("%Y-%m-%d").to_string()))
+            .map_err(|_| invalido()),
+        QueryParamType::Booleano => valor.as_bool().or_else(|| texto.parse().ok()).map(SqlValue::Bool).ok_or_else(invalido),
+    }
+}
+
+fn require_sandbox(sandbox: Option<&SqlSandbox>) -> Result<&SqlSandbox, SavedQueryError> {
+    sandbox.ok_or_else(|| SavedQueryError::from(SandboxError::Unavailable(
+        "La ejecución de consultas no está configurada (READONLY_DATABASE_URL)".to_string(),
+    )))
+}
+
+/// Convierte los parámetros recibidos; los de lookup deben estar entre los permitidos al usuario.
+async fn resolve_params(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta: &SavedQuery,
+    user: &QueryUser<'_>,
+    request: &QueryExecutionRequest,
+    sql_collate_clause: &str,
+) -> Result<Vec<SqlValue>, SavedQueryError> {
+    let mut params = Vec::with_capacity(consulta.parametros.len());
+    for parametro in &consulta.parametros {
+        let valor = convert_value(parametro, request.parametros.get(&parametro.nombre))?;
+        if let (Some(concepto), SqlValue::Text(_) | SqlValue::Int(_)) = (parametro.origen_lookup.as_deref(), &valor) {
+            let permitidos = row_security_logic::resolve_allowed_values(
+                pool, aplicativo_id, user.usuario_id, concepto, sql_collate_clause,
+            ).await?;
+            let texto = match &valor {
+                SqlValue::Text(s) => s.clone(),
+                SqlValue::Int(n) => n.to_string(),
+                _ => String::new(),
+            };
+            if !permitidos.todos && !permitidos.valores.contains(&texto) {
+                return Err(SavedQueryError::Forbidden(format!(
+                    "El valor '{}' de '{}' no está permitido", texto, parametro.nombre
+                )));
+            }
+        }
+        params.push(valor);
+    }
+    Ok(params)
+}
+
+/// Ejecuta la consulta en el sandbox con la seguridad por fila del usuario, paginada y
+/// con tiempo máximo.
+pub async fn execute_query(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    user: &QueryUser<'_>,
+    request: &QueryExecutionRequest,
+    sql_collate_clause: &str,
+) -> Result<QueryExecutionResult, SavedQueryError> {
+    let sandbox = require_sandbox(sandbox)?;
+    let consulta = get_visible_query(pool, aplicativo_id, consulta_id, user, sql_collate_clause).await?;
+    let params = resolve_params(pool, aplicativo_id, &consulta, user, request, sql_collate_clause).await?;
+
+    let pagina = request.pagina.unwrap_or(1).max(1);
+    let tamano_pagina = request.tamano_pagina.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let query = SecuredQuery {
+        sql: consulta.sql.clone(),
+        params,
+        objeto: consulta.objeto_origen.clone(),
+        filters: consulta.filtros.clone(),
+        order_by: request.order_by.clone(),
+        offset: Some((pagina - 1) * tamano_pagina),
+        limit: Some(tamano_pagina),
+    };
+
+    let timeout = Some(consulta.timeout_segundos as u64);
+    let total = sandbox.count(pool, aplicativo_id, user.usuario_id, &query, timeout, sql_collate_clause).await?;
+    let resultado = sandbox.execute(pool, aplicativo_id, user.usuario_id, &query, timeout, sql_collate_clause).await?;
+    Ok(QueryExecutionResult {
+        columnas: resultado.columnas,
+        filas: resultado.filas,
+        total,
+        truncado: resultado.truncado,
+        pagina,
+        tamano_pagina,
+    })
+}
+
+/// Prepara la exportación completa (sin paginar) de la consulta. Devuelve el nombre de la
+/// consulta, para el archivo, y la exportación lista para escribirse en streaming.
+pub async fn prepare_export(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    user: &QueryUser<'_>,
+    request: &QueryExecutionRequest,
+    sql_collate_clause: &str,
+) -> Result<(String, PreparedExport), SavedQueryError> {
+    let sandbox = require_sandbox(sandbox)?;
+    let consulta = get_visible_query(pool, aplicativo_id, consulta_id, user, sql_collate_clause).await?;
+    let params = resolve_params(pool, aplicativo_id, &consulta, user, request, sql_collate_clause).await?;
+    let query = SecuredQuery {
+        sql: consulta.sql.clone(),
+        params,
+        objeto: consulta.objeto_origen.clone(),
+        filters: consulta.filtros.clone(),
+        order_by: request.order_by.clone(),
+        offset: None,
+        limit: None,
+    };
+    let exportacion = sandbox.prepare_export(
+        pool,
+        aplicativo_id,
+        user.usuario_id,
+        &query,
+        Some(consulta.timeout_segundos as u64),
+        sql_collate_clause,
+    ).await?;
+    Ok((consulta.nombre, exportacion))
+}
+
+/// Valores posibles de un parámetro con origen de lookup, limitados a los permitidos.
+pub async fn parameter_lookup(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    consulta_id: i32,
+    nombre: &str,
+    user: &QueryUser<'_>,
+    filtro: Option<&str>,
+    sql_collate_clause: &str,
+) -> Result<Vec<ConceptLookupValue>, SavedQueryError> {
+    let consulta = get_visible_query(pool, aplicativo_id, consulta_id, user, sql_collate_clause).await?;
+    let codigo = consulta
+        .parametros
+        .iter()
+        .find(|p| p.nombre == nombre)
+        .ok_or_else(|| SavedQueryError::NotFound(format!("Parámetro '{}' no encontrado", nombre)))?
+        .origen_lookup
+        .clone()
+        .ok_or_else(|| SavedQueryError::Validation(format!("El parámetro '{}' no tiene lista de valores", nombre)))?;
+    let concepto = row_security_logic::get_concept_by_code(pool, aplicativo_id, &codigo, sql_collate_clause).await?;
+    let permitidos = row_security_logic::resolve_allowed_values(pool, aplicativo_id, user.usuario_id, &codigo, sql_collate_clause).await?;
+    let valores = row_security_logic::lookup_values(pool, &concepto, filtro, sql_collate_clause).await?;
+    Ok(valores
+        .into_iter()
+        .filter(|v| permitidos.todos || permitidos.valores.contains(&v.valor))
+        .collect())
+}
@@ -0,0 +1,125 @@
+// src/shared/saved_query_models.rs
+// Modelos de las consultas guardadas ("Mis consultas" / "Todas las consultas").
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::secured_query_models::RowFilter;
+use crate::sql_sandbox_models::SandboxColumn;
+
+/// Tipo de un parámetro de consulta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryParamType {
+    Texto,
+    Entero,
+    Decimal,
+    /// `YYYY-MM-DD`.
+    Fecha,
+    Booleano,
+}
+
+impl QueryParamType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryParamType::Texto => "texto",
+            QueryParamType::Entero => "entero",
+            QueryParamType::Decimal => "decimal",
+            QueryParamType::Fecha => "fecha",
+            QueryParamType::Booleano => "booleano",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "texto" => Some(QueryParamType::Texto),
+            "entero" => Some(QueryParamType::Entero),
+            "decimal" => Some(QueryParamType::Decimal),
+            "fecha" => Some(QueryParamType::Fecha),
+            "booleano" => Some(QueryParamType::Booleano),
+            _ => None,
+        }
+    }
+}
+
+/// Parámetro de una consulta; el i-ésimo (por `orden`) se usa como `@p{i}` en el SQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParameter {
+    pub nombre: String,
+    pub etiqueta: String,
+    pub tipo: QueryParamType,
+    #[serde(default)]
+    pub requerido: bool,
+    /// Valor por defecto en texto, convertido según `tipo`.
+    pub valor_defecto: Option<String>,
+    /// Código de concepto de seguridad por fila que provee la lista de valores.
+    pub origen_lookup: Option<String>,
+}
+
+/// Consulta guardada (`riy.riy_consulta`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQuery {
+    pub consulta_id: i32,
+    pub aplicativo_id: i32,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub sql: String,
+    /// Objeto del ERP registrado como vista por concepto, para aplicar sus filtros.
+    pub objeto_origen: Option<String>,
+    pub timeout_segundos: i32,
+    pub propietario_id: i32,
+    pub propietario: String,
+    pub parametros: Vec<QueryParameter>,
+    pub filtros: Vec<RowFilter>,
+    /// Roles con los que se comparte.
+    pub roles: Vec<i32>,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+/// Alta o modificación de una consulta.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQueryInput {
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub sql: String,
+    pub objeto_origen: Option<String>,
+    pub timeout_segundos: Option<i32>,
+    #[serde(default)]
+    pub parametros: Vec<QueryParameter>,
+    #[serde(default)]
+    pub filtros: Vec<RowFilter>,
+    #[serde(default)]
+    pub roles: Vec<i32>,
+}
+
+/// Cuerpo de `POST /queries/{id}/execute`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExecutionRequest {
+    #[serde(default)]
+    pub parametros: HashMap<String, Value>,
+    /// Empieza en 1.
+    pub pagina: Option<i64>,
+    pub tamano_pagina: Option<i64>,
+    #[serde(default)]
+    pub order_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExecutionResult {
+    pub columnas: Vec<SandboxColumn>,
+    pub filas: Vec<Map<String, Value>>,
+    pub total: i64,
+    /// La página se recortó al máximo de filas del sandbox.
+    pub truncado: bool,
+    pub pagina: i64,
+    pub tamano_pagina: i64,
+}
@@ -0,0 +1,985 @@
+// src/shared/scim_logic.rs
+/*
+Aprovisionamiento SCIM 2.0 (Entra ID) sobre las tablas de riy:
+    Users  -> riy.riy_usuario (userName = usuario, externalId = external_id)
+    Groups -> riy.riy_SeguridadRol del aplicativo, miembros en riy.riy_SeguridadRolUsuario
+
+`active` corresponde a estado = 'Vigente' (el único estado con el que se inicia sesión);
+`active = false` lo deja en 'Inactivo'. El borrado de un usuario es lógico
+(estado = 'Eliminado') para conservar las referencias de auditoría; los usuarios
+eliminados no se exponen por SCIM y, si se vuelve a aprovisionar el mismo userName,
+se reutiliza su registro en lugar de chocar con el nombre ocupado.
+
+La baja retira todo lo que daba acceso al registro (roles de cualquier aplicativo,
+identidades externas vinculadas, MFA y códigos de recuperación, concesiones temporales,
+valores de concepto, designaciones de aprobador, preferencias y sesiones), y se vuelve a
+retirar antes de reutilizarlo: quien recibe de nuevo el userName empieza sin nada del
+titular anterior.
+
+ALTER TABLE riy.riy_usuario ADD external_id VARCHAR(255) NULL;
+CREATE INDEX IX_riy_usuario_external_id ON riy.riy_usuario (external_id) WHERE external_id IS NOT NULL;
+*/
+
+use serde_json::Value;
+use sqlx::{FromRow, Mssql, Pool, Row};
+
+use crate::notification_logic;
+use crate::role_logic;
+use crate::user_repository::ESTADO_VIGENTE;
+use crate::scim_models::{
+    ScimEmail, ScimError, ScimGroup, ScimListQuery, ScimListResponse, ScimMeta, ScimName,
+    ScimPatchOperation, ScimReference, ScimUser, SCHEMA_GROUP, SCHEMA_LIST_RESPONSE, SCHEMA_USER,
+};
+
+const ESTADO_INACTIVO: &str = "Inactivo";
+const ESTADO_ELIMINADO: &str = "Eliminado";
+const AUTOR_SCIM: &str = "SCIM";
+const DEFAULT_COUNT: i64 = 100;
+const MAX_COUNT: i64 = 500;
+
+#[derive(Debug, FromRow)]
+struct ScimUserRow {
+    usuario_id: i32,
+    usuario: String,
+    nombre: String,
+    correo: String,
+    estado: String,
+    external_id: Option<String>,
+    fecha_creacion: Option<String>,
+    fecha_modificacion: Option<String>,
+}
+
+fn db_error(e: impl std::fmt::Display) -> ScimError {
+    ScimError::internal(format!("Error de base de datos: {}", e))
+}
+
+fn parse_id(id: &str) -> Result<i32, ScimError> {
+    id.trim().parse::<i32>().map_err(|_| ScimError::not_found(format!("Recurso {} no encontrado", id)))
+}
+
+fn paging(query: &ScimListQuery) -> (i64, i64) {
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(DEFAULT_COUNT).clamp(0, MAX_COUNT);
+    (start_index, count)
+}
+
+// -------------------------------------------------------------------------
+// FILTROS
+// -------------------------------------------------------------------------
+
+/// Interpreta un filtro SCIM simple de la forma `atributo eq "valor"`.
+/// Devuelve el atributo en minúsculas y el valor sin comillas. Dentro de las comillas `\`
+/// escapa el carácter siguiente; una comilla sin escapar antes del final (p. ej. un `or` entre
+/// dos comparaciones) hace el filtro inválido.
+pub fn parse_eq_filter(filter: &str) -> Result<(String, String), ScimError> {
+    let invalid = || ScimError::bad_request("invalidFilter", format!("Filtro no soportado: {}", filter));
+    let (attr, rest) = filter.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let (op, raw_value) = rest.trim_start().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let raw_value = raw_value.trim();
+
+    if !op.eq_ignore_ascii_case("eq") {
+        return Err(invalid());
+    }
+    if raw_value.eq_ignore_ascii_case("true") || raw_value.eq_ignore_ascii_case("false") {
+        return Ok((attr.to_lowercase(), raw_value.to_lowercase()));
+    }
+    let quoted = raw_value.strip_prefix('"').ok_or_else(invalid)?;
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next().ok_or_else(invalid)?),
+            '"' if chars.as_str().is_empty() => return Ok((attr.to_lowercase(), value)),
+            '"' => return Err(invalid()),
+            _ => value.push(c),
+        }
+    }
+    // Falta la comilla de cierre
+    Err(invalid())
+}
+
+fn user_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT usuario_id,
+                usuario {0} as usuario,
+                nombre {0} as nombre,
+                correo {0} as correo,
+                estado {0} as estado,
+                external_id {0} as external_id,
+                CONVERT(VARCHAR(19), fecha_creacion, 126) {0} as fecha_creacion,
+                CONVERT(VARCHAR(19), fecha_modificacion, 126) {0} as fecha_modificacion
+           FROM riy.riy_usuario WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+fn row_to_scim_user(row: ScimUserRow, groups: Vec<ScimReference>, location_base: &str) -> ScimUser {
+    ScimUser {
+        schemas: vec![SCHEMA_USER.to_string()],
+        id: Some(row.usuario_id.to_string()),
+        external_id: row.external_id,
+        user_name: row.usuario,
+        name: Some(ScimName {
+            formatted: Some(row.nombre.clone()),
+            ..Default::default()
+        }),
+        display_name: Some(row.nombre),
+        emails: vec![ScimEmail {
+            value: row.correo,
+            email_type: Some("work".to_string()),
+            primary: true,
+        }],
+        active: row.estado.eq_ignore_ascii_case(ESTADO_VIGENTE),
+        groups,
+        meta: Some(ScimMeta {
+            resource_type: "User".to_string(),
+            created: row.fecha_creacion,
+            last_modified: row.fecha_modificacion,
+            location: Some(format!("{}/Users/{}", location_base, row.usuario_id)),
+        }),
+    }
+}
+
+async fn fetch_user_row(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<ScimUserRow, ScimError> {
+    let sql_query = format!("{} WHERE usuario_id = @p1 AND estado <> @p2", user_select(sql_collate_clause));
+    sqlx::query_as::<_, ScimUserRow>(&sql_query)
+        .bind(usuario_id)
+        .bind(ESTADO_ELIMINADO)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| ScimError::not_found(format!("Usuario {} no encontrado", usuario_id)))
+}
+
+async fn fetch_user_groups(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<ScimReference>, ScimError> {
+    let sql_query = format!(
+        "SELECT r.rolID, r.rol {0} as rol
+           FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRol r WITH(NOLOCK) ON r.rolID = ru.rolID
+          WHERE ru.usuarioID = @p1 AND r.aplicativoID = @p2",
+        sql_collate_clause
+    );
+    let rows: Vec<(i32, String)> = sqlx::query_as(&sql_query)
+        .bind(usuario_id)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|(rol_id, rol)| ScimReference { value: rol_id.to_string(), display: Some(rol) })
+        .collect())
+}
+
+/// GET /Users con filtro opcional (`userName eq`, `externalId eq`, `emails.value eq`).
+pub async fn list_users(
+    pool: &Pool<Mssql>,
+    query: &ScimListQuery,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimListResponse<ScimUser>, ScimError> {
+    let (start_index, count) = paging(query);
+    let filter = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(f) => {
+            let (attr, value) = parse_eq_filter(f)?;
+            let column = match attr.as_str() {
+                "username" => "usuario",
+                "externalid" => "external_id",
+                "emails.value" | "emails" => "correo",
+                _ => return Err(ScimError::bad_request("invalidFilter", format!("Atributo de filtro no soportado: {}", attr))),
+            };
+            Some((column, value))
+        }
+        None => None,
+    };
+
+    let where_clause = match &filter {
+        Some((column, _)) => format!("WHERE estado <> @p1 AND {} = @p2 {}", column, sql_collate_clause),
+        None => "WHERE estado <> @p1".to_string(),
+    };
+
+    let count_query = format!("SELECT CAST(COUNT(*) AS BIGINT) FROM riy.riy_usuario WITH(NOLOCK) {}", where_clause);
+    let mut count_q = sqlx::query_as::<_, (i64,)>(&count_query).bind(ESTADO_ELIMINADO);
+    if let Some((_, value)) = &filter {
+        count_q = count_q.bind(value.clone());
+    }
+    let (total,) = count_q.fetch_one(pool).await.map_err(db_error)?;
+
+    let (offset_param, fetch_param) = if filter.is_some() { ("@p3", "@p4") } else { ("@p2", "@p3") };
+    let page_query = format!(
+        "{} {} ORDER BY usuario_id OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+        user_select(sql_collate_clause), where_clause, offset_param, fetch_param
+    );
+    let mut page_q = sqlx::query_as::<_, ScimUserRow>(&page_query).bind(ESTADO_ELIMINADO);
+    if let Some((_, value)) = &filter {
+        page_q = page_q.bind(value.clone());
+    }
+    let rows = page_q
+        .bind(start_index - 1)
+        .bind(count)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let resources: Vec<ScimUser> = rows
+        .into_iter()
+        .map(|row| row_to_scim_user(row, Vec::new(), location_base))
+        .collect();
+
+    Ok(ScimListResponse {
+        schemas: vec![SCHEMA_LIST_RESPONSE.to_string()],
+        total_results: total,
+        start_index,
+        items_per_page: resources.len() as i64,
+        resources,
+    })
+}
+
+/// GET /Users/{id}
+pub async fn get_user(
+    pool: &Pool<Mssql>,
+    id: &str,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimUser, ScimError> {
+    let usuario_id = parse_id(id)?;
+    let row = fetch_user_row(pool, usuario_id, sql_collate_clause).await?;
+    let groups = fetch_user_groups(pool, usuario_id, aplicativo_id, sql_collate_clause).await?;
+    Ok(row_to_scim_user(row, groups, location_base))
+}
+
+/// Campos editables de un usuario durante POST/PATCH.
+struct UserFields {
+    usuario: String,
+    nombre: String,
+    correo: String,
+    external_id: Option<String>,
+    active: bool,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    nombre_explicito: bool,
+}
+
+impl UserFields {
+    /// Si solo llegaron nombre y apellido, se compone el nombre completo.
+    fn finish(mut self) -> Self {
+        if !self.nombre_explicito && (self.given_name.is_some() || self.family_name.is_some()) {
+            self.nombre = [self.given_name.as_deref(), self.family_name.as_deref()]
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<&str>>()
+                .join(" ");
+        }
+        self
+    }
+}
+
+fn value_as_string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Entra ID envía `active` como booleano o como texto ("True"/"False").
+fn value_as_bool(value: Option<&Value>) -> Option<bool> {
+    match value? {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Extrae el correo principal (o el primero) de un arreglo `emails`.
+fn email_from_array(value: Option<&Value>) -> Option<String> {
+    let emails = value?.as_array()?;
+    emails
+        .iter()
+        .find(|e| e.get("primary").and_then(Value::as_bool).unwrap_or(false))
+        .or_else(|| emails.first())
+        .and_then(|e| value_as_string(e.get("value")))
+}
+
+/// Aplica el valor de un atributo (ruta en minúsculas) a los campos del usuario.
+fn apply_user_attr(fields: &mut UserFields, path: &str, value: Option<&Value>, remove: bool) -> Result<(), ScimError> {
+    let invalid = |attr: &str| ScimError::bad_request("invalidValue", format!("Valor inválido para '{}'", attr));
+    match path {
+        "username" => {
+            let usuario = value_as_string(value).filter(|v| !v.is_empty()).ok_or_else(|| invalid("userName"))?;
+            fields.usuario = usuario;
+        }
+        "externalid" => {
+            fields.external_id = if remove { None } else { value_as_string(value) };
+        }
+        "displayname" | "name.formatted" => {
+            if let Some(nombre) = value_as_string(value).filter(|v| !v.is_empty()) {
+                fields.nombre = nombre;
+                fields.nombre_explicito = true;
+            }
+        }
+        "name.givenname" => fields.given_name = value_as_string(value),
+        "name.familyname" => fields.family_name = value_as_string(value),
+        "name" => {
+            if let Some(obj) = value.and_then(Value::as_object) {
+                for (key, v) in obj {
+                    apply_user_attr(fields, &format!("name.{}", key.to_lowercase()), Some(v), remove)?;
+                }
+            }
+        }
+        "active" => {
+            fields.active = value_as_bool(value).ok_or_else(|| invalid("active"))?;
+        }
+        "emails" => {
+            if let Some(correo) = email_from_array(value) {
+                fields.correo = correo.to_lowercase();
+            }
+        }
+        p if p.starts_with("emails[") || p == "emails.value" => {
+            if let Some(correo) = value_as_string(value).filter(|v| !v.is_empty()) {
+                fields.correo = correo.to_lowercase();
+            }
+        }
+        other => {
+            // Atributos no mapeados a riy (title, addresses, etc.) se ignoran.
+            println!("scim_logic: atributo '{}' ignorado.", other);
+        }
+    }
+    Ok(())
+}
+
+/// Comprueba que el userName no lo use otro usuario. Devuelve el ID del usuario eliminado
+/// que lo tenga, si lo hay: al aprovisionar se reutiliza; al renombrar se rechaza.
+async fn ensure_user_name_free(
+    pool: &Pool<Mssql>,
+    usuario: &str,
+    except_id: Option<i32>,
+) -> Result<Option<i32>, ScimError> {
+    let existing: Option<(i32, String)> = sqlx::query_as(
+        "SELECT usuario_id, estado FROM riy.riy_usuario WITH(NOLOCK)
+          WHERE usuario = @p1 AND usuario_id <> @p2"
+    )
+    .bind(usuario)
+    .bind(except_id.unwrap_or(0))
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    match existing {
+        None => Ok(None),
+        Some((usuario_id, estado)) if estado.eq_ignore_ascii_case(ESTADO_ELIMINADO) => Ok(Some(usuario_id)),
+        Some(_) => Err(ScimError::new(409, Some("uniqueness"), format!("El userName '{}' ya existe", usuario))),
+    }
+}
+
+/// Tablas con lo que da acceso a un usuario; se vacían al darlo de baja y al reutilizar su registro.
+const TABLAS_ACCESO_USUARIO: [&str; 9] = [
+    "riy.riy_SeguridadRolUsuario",
+    "riy.riy_usuario_identidad",
+    "riy.riy_usuario_mfa",
+    "riy.riy_usuario_mfa_recuperacion",
+    "riy.riy_permiso_temporal",
+    "riy.riy_concepto_valor_usuario",
+    "riy.riy_aprobador",
+    "riy.riy_usuario_perfil",
+    "riy.riy_usuario_sesion",
+];
+
+/// Retira, dentro de la transacción, todo lo vinculado al usuario en `TABLAS_ACCESO_USUARIO`.
+async fn purge_user_access(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    usuario_id: i32,
+) -> Result<(), ScimError> {
+    for tabla in TABLAS_ACCESO_USUARIO {
+        sqlx::query(&format!("DELETE FROM {} WHERE usuarioID = @p1", tabla))
+            .bind(usuario_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// POST /Users
+pub async fn create_user(
+    pool: &Pool<Mssql>,
+    user: ScimUser,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimUser, ScimError> {
+    let user_name = user.user_name.trim().to_string();
+    if user_name.is_empty() {
+        return Err(ScimError::bad_request("invalidValue", "userName es obligatorio"));
+    }
+    let eliminado_id = ensure_user_name_free(pool, &user_name, None).await?;
+
+    let mut fields = UserFields {
+        usuario: user_name.clone(),
+        nombre: String::new(),
+        correo: String::new(),
+        external_id: user.external_id.clone(),
+        active: user.active,
+        given_name: None,
+        family_name: None,
+        nombre_explicito: false,
+    };
+    if let Some(name) = &user.name {
+        fields.given_name = name.given_name.clone();
+        fields.family_name = name.family_name.clone();
+        if let Some(formatted) = name.formatted.clone().filter(|f| !f.trim().is_empty()) {
+            fields.nombre = formatted;
+            fields.nombre_explicito = true;
+        }
+    }
+    if let Some(display) = user.display_name.clone().filter(|d| !d.trim().is_empty()) {
+        fields.nombre = display;
+        fields.nombre_explicito = true;
+    }
+    fields.correo = user
+        .emails
+        .iter()
+        .find(|e| e.primary)
+        .or_else(|| user.emails.first())
+        .map(|e| e.value.trim().to_lowercase())
+        .unwrap_or_else(|| if user_name.contains('@') { user_name.to_lowercase() } else { String::new() });
+    let mut fields = fields.finish();
+    if fields.nombre.is_empty() {
+        fields.nombre = user_name.clone();
+    }
+
+    let estado = if fields.active { ESTADO_VIGENTE } else { ESTADO_INACTIVO };
+
+    // Un userName dado de baja antes se vuelve a aprovisionar sobre el mismo registro, sin
+    // nada de lo que tenía su titular anterior.
+    if let Some(usuario_id) = eliminado_id {
+        let mut tx = pool.begin().await.map_err(db_error)?;
+        purge_user_access(&mut tx, usuario_id).await?;
+        sqlx::query(
+            "UPDATE riy.riy_usuario
+                SET nombre = @p1, correo = @p2, estado = @p3, external_id = @p4,
+                    modificado_por = @p5, fecha_modificacion = GETDATE()
+              WHERE usuario_id = @p6"
+        )
+        .bind(&fields.nombre)
+        .bind(&fields.correo)
+        .bind(estado)
+        .bind(&fields.external_id)
+        .bind(AUTOR_SCIM)
+        .bind(usuario_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+        println!("scim_logic: usuario '{}' (ID {}) aprovisionado de nuevo", fields.usuario, usuario_id);
+        return get_user(pool, &usuario_id.to_string(), aplicativo_id, location_base, sql_collate_clause).await;
+    }
+
+    let row = sqlx::query(
+        "INSERT INTO riy.riy_usuario (usuario, nombre, correo, estado, autor, fecha_creacion, external_id)
+         VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE(), @p6);
+         SELECT CAST(SCOPE_IDENTITY() AS INT) AS usuario_id;"
+    )
+    .bind(&fields.usuario)
+    .bind(&fields.nombre)
+    .bind(&fields.correo)
+    .bind(estado)
+    .bind(AUTOR_SCIM)
+    .bind(&fields.external_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    let usuario_id: i32 = row.try_get("usuario_id").map_err(db_error)?;
+
+    println!("scim_logic: usuario '{}' aprovisionado con ID {}", fields.usuario, usuario_id);
+    if let Err(e) = notification_logic::notify_new_account(pool, usuario_id).await {
+        eprintln!("scim_logic: no se pudo encolar el correo de bienvenida: {}", e);
+    }
+    get_user(pool, &usuario_id.to_string(), aplicativo_id, location_base, sql_collate_clause).await
+}
+
+/// PATCH /Users/{id}
+pub async fn patch_user(
+    pool: &Pool<Mssql>,
+    id: &str,
+    operations: &[ScimPatchOperation],
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimUser, ScimError> {
+    let usuario_id = parse_id(id)?;
+    let row = fetch_user_row(pool, usuario_id, sql_collate_clause).await?;
+    let mut fields = UserFields {
+        usuario: row.usuario.clone(),
+        nombre: row.nombre.clone(),
+        correo: row.correo.clone(),
+        external_id: row.external_id.clone(),
+        active: row.estado.eq_ignore_ascii_case(ESTADO_VIGENTE),
+        given_name: None,
+        family_name: None,
+        nombre_explicito: false,
+    };
+
+    for operation in operations {
+        let op = operation.op.to_lowercase();
+        let remove = match op.as_str() {
+            "add" | "replace" => false,
+            "remove" => true,
+            _ => return Err(ScimError::bad_request("invalidSyntax", format!("Operación no soportada: {}", operation.op))),
+        };
+        match operation.path.as_deref() {
+            Some(path) => apply_user_attr(&mut fields, &path.trim().to_lowercase(), operation.value.as_ref(), remove)?,
+            None => {
+                let obj = operation.value.as_ref().and_then(Value::as_object)
+                    .ok_or_else(|| ScimError::bad_request("invalidValue", "Se esperaba un objeto en 'value'"))?;
+                for (key, value) in obj {
+                    apply_user_attr(&mut fields, &key.to_lowercase(), Some(value), remove)?;
+                }
+            }
+        }
+    }
+    let fields = fields.finish();
+    if fields.usuario != row.usuario && ensure_user_name_free(pool, &fields.usuario, Some(usuario_id)).await?.is_some() {
+        return Err(ScimError::new(
+            409,
+            Some("uniqueness"),
+            format!("El userName '{}' pertenece a un usuario eliminado", fields.usuario),
+        ));
+    }
+
+    // Se conserva un estado distinto de Vigente/Inactivo si SCIM no cambió `active`.
+    let estado = match (fields.active, row.estado.eq_ignore_ascii_case(ESTADO_VIGENTE)) {
+        (true, true) | (false, false) => row.estado.clone(),
+        (true, false) => ESTADO_VIGENTE.to_string(),
+        (false, true) => ESTADO_INACTIVO.to_string(),
+    };
+
+    sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET usuario = @p1, nombre = @p2, correo = @p3, estado = @p4, external_id = @p5,
+                modificado_por = @p6, fecha_modificacion = GETDATE()
+          WHERE usuario_id = @p7"
+    )
+    .bind(&fields.usuario)
+    .bind(&fields.nombre)
+    .bind(&fields.correo)
+    .bind(&estado)
+    .bind(&fields.external_id)
+    .bind(AUTOR_SCIM)
+    .bind(usuario_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    get_user(pool, id, aplicativo_id, location_base, sql_collate_clause).await
+}
+
+/// DELETE /Users/{id}: baja lógica y retiro de todo su acceso, en cualquier aplicativo
+/// (el registro de usuario es global).
+pub async fn delete_user(
+    pool: &Pool<Mssql>,
+    id: &str,
+    sql_collate_clause: &str,
+) -> Result<(), ScimError> {
+    let usuario_id = parse_id(id)?;
+    fetch_user_row(pool, usuario_id, sql_collate_clause).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    purge_user_access(&mut tx, usuario_id).await?;
+    sqlx::query(
+        "UPDATE riy.riy_usuario
+            SET estado = @p1, modificado_por = @p2, fecha_modificacion = GETDATE()
+          WHERE usuario_id = @p3"
+    )
+    .bind(ESTADO_ELIMINADO)
+    .bind(AUTOR_SCIM)
+    .bind(usuario_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+// -------------------------------------------------------------------------
+// GROUPS
+// -------------------------------------------------------------------------
+
+async fn fetch_group(
+    pool: &Pool<Mssql>,
+    rol_id: i32,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimGroup, ScimError> {
+    let rol_query = format!(
+        "SELECT rol {0} as rol FROM riy.riy_SeguridadRol WITH(NOLOCK)
+          WHERE rolID = @p1 AND aplicativoID = @p2",
+        sql_collate_clause
+    );
+    let (rol,): (String,) = sqlx::query_as(&rol_query)
+        .bind(rol_id)
+        .bind(aplicativo_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| ScimError::not_found(format!("Grupo {} no encontrado", rol_id)))?;
+
+    let members_query = format!(
+        "SELECT u.usuario_id, u.usuario {0} as usuario
+           FROM riy.riy_SeguridadRolUsuario ru WITH(NOLOCK)
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = ru.usuarioID
+          WHERE ru.rolID = @p1 AND u.estado <> @p2",
+        sql_collate_clause
+    );
+    let members: Vec<(i32, String)> = sqlx::query_as(&members_query)
+        .bind(rol_id)
+        .bind(ESTADO_ELIMINADO)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    Ok(ScimGroup {
+        schemas: vec![SCHEMA_GROUP.to_string()],
+        id: Some(rol_id.to_string()),
+        display_name: rol,
+        members: members
+            .into_iter()
+            .map(|(usuario_id, usuario)| ScimReference { value: usuario_id.to_string(), display: Some(usuario) })
+            .collect(),
+        meta: Some(ScimMeta {
+            resource_type: "Group".to_string(),
+            location: Some(format!("{}/Groups/{}", location_base, rol_id)),
+            ..Default::default()
+        }),
+    })
+}
+
+/// GET /Groups con filtro opcional `displayName eq`.
+pub async fn list_groups(
+    pool: &Pool<Mssql>,
+    query: &ScimListQuery,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimListResponse<ScimGroup>, ScimError> {
+    let (start_index, count) = paging(query);
+    let display_name = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(f) => match parse_eq_filter(f)? {
+            (attr, value) if attr == "displayname" => Some(value),
+            (attr, _) => return Err(ScimError::bad_request("invalidFilter", format!("Atributo de filtro no soportado: {}", attr))),
+        },
+        None => None,
+    };
+
+    let roles: Vec<_> = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+        .await
+        .map_err(ScimError::internal)?
+        .into_iter()
+        .filter(|r| display_name.as_ref().map_or(true, |d| r.rol.eq_ignore_ascii_case(d)))
+        .collect();
+    let total = roles.len() as i64;
+
+    let mut resources = Vec::new();
+    for rol in roles.into_iter().skip((start_index - 1) as usize).take(count as usize) {
+        resources.push(fetch_group(pool, rol.rol_id, aplicativo_id, location_base, sql_collate_clause).await?);
+    }
+
+    Ok(ScimListResponse {
+        schemas: vec![SCHEMA_LIST_RESPONSE.to_string()],
+        total_results: total,
+        start_index,
+        items_per_page: resources.len() as i64,
+        resources,
+    })
+}
+
+/// GET /Groups/{id}
+pub async fn get_group(
+    pool: &Pool<Mssql>,
+    id: &str,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimGroup, ScimError> {
+    fetch_group(pool, parse_id(id)?, aplicativo_id, location_base, sql_collate_clause).await
+}
+
+/// Convierte la lista de miembros recibida en IDs de usuario existentes.
+async fn member_ids(pool: &Pool<Mssql>, value: Option<&Value>) -> Result<Vec<i32>, ScimError> {
+    let members = match value {
+        Some(Value::Array(items)) => items.clone(),
+        Some(item @ Value::Object(_)) => vec![item.clone()],
+        _ => return Err(ScimError::bad_request("invalidValue", "Se esperaba una lista de miembros")),
+    };
+    let mut ids = Vec::with_capacity(members.len());
+    for member in members {
+        let id = value_as_string(member.get("value"))
+            .and_then(|v| v.parse::<i32>().ok())
+            .ok_or_else(|| ScimError::bad_request("invalidValue", "Miembro con 'value' inválido"))?;
+        let exists: Option<(i32,)> = sqlx::query_as(
+            "SELECT usuario_id FROM riy.riy_usuario WITH(NOLOCK) WHERE usuario_id = @p1 AND estado <> @p2"
+        )
+        .bind(id)
+        .bind(ESTADO_ELIMINADO)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+        if exists.is_none() {
+            return Err(ScimError::not_found(format!("Usuario miembro {} no encontrado", id)));
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+/// Extrae el ID de un path del tipo `members[value eq "12"]`.
+fn member_id_from_path(path: &str) -> Option<i32> {
+    let start = path.find('[')?;
+    let end = path.rfind(']')?;
+    let (attr, value) = parse_eq_filter(&path[start + 1..end]).ok()?;
+    if attr != "value" {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// POST /Groups: crea un rol en el aplicativo con sus miembros.
+pub async fn create_group(
+    pool: &Pool<Mssql>,
+    group: ScimGroup,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimGroup, ScimError> {
+    let display_name = group.display_name.trim().to_string();
+    if display_name.is_empty() {
+        return Err(ScimError::bad_request("invalidValue", "displayName es obligatorio"));
+    }
+    let exists: Option<(i32,)> = sqlx::query_as(
+        "SELECT rolID FROM riy.riy_SeguridadRol WITH(NOLOCK) WHERE aplicativoID = @p1 AND rol = @p2"
+    )
+    .bind(aplicativo_id)
+    .bind(&display_name)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    if exists.is_some() {
+        return Err(ScimError::new(409, Some("uniqueness"), format!("El grupo '{}' ya existe", display_name)));
+    }
+
+    let members_value = serde_json::to_value(&group.members).map_err(|e| ScimError::internal(e.to_string()))?;
+    let members = member_ids(pool, Some(&members_value)).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let row = sqlx::query(
+        "INSERT INTO riy.riy_SeguridadRol (aplicativoID, rol, autor, fechaCreacion)
+         VALUES (@p1, @p2, @p3, GETDATE());
+         SELECT CAST(SCOPE_IDENTITY() AS INT) AS rol_id;"
+    )
+    .bind(aplicativo_id)
+    .bind(&display_name)
+    .bind(AUTOR_SCIM)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    let rol_id: i32 = row.try_get("rol_id").map_err(db_error)?;
+    for usuario_id in members {
+        role_logic::assign_role(&mut tx, usuario_id, rol_id, AUTOR_SCIM).await.map_err(ScimError::internal)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    fetch_group(pool, rol_id, aplicativo_id, location_base, sql_collate_clause).await
+}
+
+/// PATCH /Groups/{id}: cambio de nombre y alta/baja de miembros.
+pub async fn patch_group(
+    pool: &Pool<Mssql>,
+    id: &str,
+    operations: &[ScimPatchOperation],
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<ScimGroup, ScimError> {
+    let rol_id = parse_id(id)?;
+    let current = fetch_group(pool, rol_id, aplicativo_id, location_base, sql_collate_clause).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    for operation in operations {
+        let op = operation.op.to_lowercase();
+        let path = operation.path.as_deref().map(|p| p.trim().to_lowercase());
+
+        // Sin path: el valor es un objeto con los atributos a reemplazar.
+        let targets: Vec<(String, Option<Value>)> = match path {
+            Some(p) => vec![(p, operation.value.clone())],
+            None => operation
+                .value
+                .as_ref()
+                .and_then(Value::as_object)
+                .ok_or_else(|| ScimError::bad_request("invalidValue", "Se esperaba un objeto en 'value'"))?
+                .iter()
+                .map(|(k, v)| (k.to_lowercase(), Some(v.clone())))
+                .collect(),
+        };
+
+        for (target, value) in targets {
+            match (op.as_str(), target.as_str()) {
+                ("add" | "replace", "displayname") => {
+                    let rol = value_as_string(value.as_ref()).filter(|v| !v.is_empty())
+                        .ok_or_else(|| ScimError::bad_request("invalidValue", "displayName inválido"))?;
+                    sqlx::query("UPDATE riy.riy_SeguridadRol SET rol = @p1 WHERE rolID = @p2")
+                        .bind(rol)
+                        .bind(rol_id)
+                        .execute(&mut tx)
+                        .await
+                        .map_err(db_error)?;
+                }
+                ("add", "members") => {
+                    for usuario_id in member_ids(pool, value.as_ref()).await? {
+                        role_logic::assign_role(&mut tx, usuario_id, rol_id, AUTOR_SCIM).await.map_err(ScimError::internal)?;
+                    }
+                }
+                ("replace", "members") => {
+                    let new_members = member_ids(pool, value.as_ref()).await?;
+                    for member in &current.members {
+                        if let Ok(usuario_id) = member.value.parse::<i32>() {
+                            role_logic::remove_role(&mut tx, usuario_id, rol_id).await.map_err(ScimError::internal)?;
+                        }
+                    }
+                    for usuario_id in new_members {
+                        role_logic::assign_role(&mut tx, usuario_id, rol_id, AUTOR_SCIM).await.map_err(ScimError::internal)?;
+                    }
+                }
+                ("remove", "members") => {
+                    let ids = match value.as_ref() {
+                        Some(_) => member_ids(pool, value.as_ref()).await?,
+                        // Sin valor se quitan todos los miembros
+                        None => current.members.iter().filter_map(|m| m.value.parse().ok()).collect(),
+                    };
+                    for usuario_id in ids {
+                        role_logic::remove_role(&mut tx, usuario_id, rol_id).await.map_err(ScimError::internal)?;
+                    }
+                }
+                ("remove", p) if p.starts_with("members[") => {
+                    let usuario_id = member_id_from_path(p)
+                        .ok_or_else(|| ScimError::bad_request("invalidPath", format!("Path inválido: {}", p)))?;
+                    role_logic::remove_role(&mut tx, usuario_id, rol_id).await.map_err(ScimError::internal)?;
+                }
+                ("add" | "replace" | "remove", other) => {
+                    println!("scim_logic: atributo de grupo '{}' ignorado.", other);
+                }
+                (other, _) => {
+                    return Err(ScimError::bad_request("invalidSyntax", format!("Operación no soportada: {}", other)));
+                }
+            }
+        }
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    fetch_group(pool, rol_id, aplicativo_id, location_base, sql_collate_clause).await
+}
+
+/// DELETE /Groups/{id}: elimina el rol y sus asignaciones.
+pub async fn delete_group(
+    pool: &Pool<Mssql>,
+    id: &str,
+    aplicativo_id: i32,
+    location_base: &str,
+    sql_collate_clause: &str,
+) -> Result<(), ScimError> {
+    let rol_id = parse_id(id)?;
+    fetch_group(pool, rol_id, aplicativo_id, location_base, sql_collate_clause).await?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_SeguridadRolUsuario WHERE rolID = @p1")
+        .bind(rol_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_SeguridadRol WHERE rolID = @p1 AND aplicativoID = @p2")
+        .bind(rol_id)
+        .bind(aplicativo_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filtro(filter: &str) -> (String, String) {
+        parse_eq_filter(filter).unwrap()
+    }
+
+    fn es_invalid_filter(filter: &str) -> bool {
+        match parse_eq_filter(filter) {
+            Err(e) => e.status_code() == 400 && e.scim_type.as_deref() == Some("invalidFilter"),
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn acepta_valores_entre_comillas_con_espacios() {
+        assert_eq!(filtro(r#"userName eq "jperez@empresa.com""#), ("username".to_string(), "jperez@empresa.com".to_string()));
+        assert_eq!(filtro(r#"displayName eq "Juan  Pérez López""#), ("displayname".to_string(), "Juan  Pérez López".to_string()));
+        assert_eq!(filtro(r#"  externalId   eq   "a b"  "#), ("externalid".to_string(), "a b".to_string()));
+        assert_eq!(filtro(r#"userName eq """#), ("username".to_string(), String::new()));
+    }
+
+    #[test]
+    fn interpreta_comillas_y_barras_escapadas() {
+        assert_eq!(filtro(r#"displayName eq "Pérez, \"Jr\"""#).1, r#"Pérez, "Jr""#);
+        assert_eq!(filtro(r#"externalId eq "dominio\\jperez""#).1, r"dominio\jperez");
+        assert_eq!(filtro(r#"externalId eq "termina\\""#).1, r"termina\");
+    }
+
+    #[test]
+    fn el_operador_eq_no_distingue_mayusculas() {
+        for op in ["eq", "EQ", "Eq", "eQ"] {
+            assert_eq!(filtro(&format!(r#"userName {} "jperez""#, op)).1, "jperez");
+        }
+        assert_eq!(filtro("active EQ TRUE"), ("active".to_string(), "true".to_string()));
+        assert_eq!(filtro("active eq false").1, "false");
+    }
+
+    #[test]
+    fn rechaza_operadores_y_formas_no_soportadas() {
+        let casos = [
+            r#"userName ne "jperez""#,
+            r#"userName co "jp""#,
+            r#"userName sw "j""#,
+            r#"userName gt "a""#,
+            "userName pr",
+            r#"userName eq "a" or userName eq "b""#,
+            r#"userName eq "a" and active eq true"#,
+            r#"userName eq "a"b""#,
+            r#"userName eq "sin cerrar"#,
+            r#"userName eq "termina en barra\""#,
+            "userName eq jperez",
+            "userName eq",
+            "userName",
+            "",
+        ];
+        for caso in casos {
+            assert!(es_invalid_filter(caso), "debió rechazarse: {}", caso);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+// src/shared/secret_store_logic.rs
+/*
+Secretos locales de la aplicación de escritorio.
+
+Las palabras clave de la licencia, el secreto de Google, la contraseña SMTP, etc. dejan de vivir en
+texto plano en el .env junto al ejecutable: se guardan en `secrets.dat` (carpeta de configuración
+de la aplicación), cifrado con AES-256-GCM. El archivo es un JSON con la sal y los datos cifrados:
+
+    { "version": 1, "conFrase": true, "sal": "<hex>", "datos": "<Base64(nonce || cifrado)>" }
+
+La clave se deriva con PBKDF2-HMAC-SHA256 del identificador del equipo que genera el sistema
+operativo al instalarse (MachineGuid en Windows, /etc/machine-id en Linux, IOPlatformUUID en macOS)
+más la frase opcional (SECRETS_PASSPHRASE o la que se indique al cambiarla), así que el archivo
+copiado a otro equipo no se puede abrir. Cada escritura usa sal y nonce nuevos.
+
+El identificador no es secreto para quien ya usa el equipo: sin frase, el cifrado ata el archivo a la
+máquina pero no lo protege de otra cuenta local con acceso a la carpeta de configuración. Para eso
+hay que usar la frase o restringir los permisos de la carpeta.
+
+Al iniciar, los valores del archivo reemplazan a las variables de entorno del mismo nombre
+(AppConfig::from_env_with_secrets); las variables siguen sirviendo mientras el secreto no se guarde.
+*/
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::secret_store_models::{
+    SecretOrigin, SecretStoreStatus, SecretSummary, SecretValues, StoredSecret,
+};
+
+/// Administrar los secretos locales.
+pub const PERMISO_ADMINISTRAR_SECRETOS: &str = "administrar_secretos";
+
+/// Nombre del archivo cifrado dentro de la carpeta de configuración.
+pub const SECRETS_FILE_NAME: &str = "secrets.dat";
+
+/// Secretos admitidos: nombre de la variable de entorno que reemplazan y descripción.
+pub const SECRETOS: &[(&str, &str)] = &[
+    ("PALABRA_CLAVE_1", "Palabra clave 1 de la licencia"),
+    ("PALABRA_CLAVE_2", "Palabra clave 2 de la licencia"),
+    ("JWT_SECRET", "Firma de los JWT de sesión"),
+    ("GOOGLE_CLIENT_SECRET", "Secreto del cliente OAuth de Google"),
+    ("SMTP_PASSWORD", "Contraseña del servidor de correo"),
+    ("MFA_ENCRYPTION_KEY", "Clave de cifrado de los secretos TOTP"),
+    ("DATABASE_URL", "Conexión por defecto si no hay un perfil de conexión activo"),
+];
+
+const FORMATO_VERSION: u8 = 1;
+const PBKDF2_ITERACIONES: u32 = 100_000;
+
+#[derive(Debug)]
+pub enum SecretStoreError {
+    Validation(String),
+    NotFound(String),
+    Storage(String),
+    Crypto(String),
+}
+
+impl fmt::Display for SecretStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecretStoreError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            SecretStoreError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            SecretStoreError::Storage(msg) => write!(f, "Error de almacenamiento: {}", msg),
+            SecretStoreError::Crypto(msg) => write!(f, "Error de cifrado: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SecretStoreError {}
+
+fn storage_error(e: std::io::Error) -> SecretStoreError {
+    SecretStoreError::Storage(e.to_string())
+}
+
+// -------------------------------------------------------------------------
+// CIFRADO (también lo usan los perfiles de conexión)
+// -------------------------------------------------------------------------
+
+/// Identificador del equipo generado por el sistema operativo; no depende de variables de entorno.
+#[cfg(target_os = "windows")]
+fn read_machine_id() -> Option<String> {
+    use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_64KEY};
+    winreg::RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags("SOFTWARE\\Microsoft\\Cryptography", KEY_READ | KEY_WOW64_64KEY)
+        .and_then(|key| key.get_value::<String, _>("MachineGuid"))
+        .ok()
+}
+
+#[cfg(target_os = "macos")]
+fn read_machine_id() -> Option<String> {
+    let salida = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&salida.stdout)
+        .lines()
+        .find(|l| l.contains("IOPlatformUUID"))
+        .and_then(|l| l.split('"').nth(3))
+        .map(|v| v.to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn read_machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|p| std::fs::read_to_string(p).ok())
+}
+
+/// Valor ligado al equipo (identificador del sistema operativo); se lee una sola vez.
+pub fn machine_value() -> Result<String, String> {
+    static VALOR: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+    VALOR
+        .get_or_init(|| read_machine_id().map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()))
+        .clone()
+        .ok_or_else(|| "No se pudo leer el identificador del equipo para derivar la clave local.".to_string())
+}
+
+/// Clave del equipo para un uso (`proposito`), sin frase.
+pub fn machine_key(proposito: &str) -> Result<[u8; 32], String> {
+    Ok(Sha256::digest(format!("riy-datos|{}|{}", proposito, machine_value()?).as_bytes()).into())
+}
+
+/// Clave derivada del valor del equipo y la frase opcional.
+pub fn derive_key(sal: &[u8], frase: Option<&str>) -> Result<[u8; 32], String> {
+    let password = format!("riy-datos|secretos|{}|{}", machine_value()?, frase.unwrap_or_default());
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), sal, PBKDF2_ITERACIONES, &mut key);
+    Ok(key)
+}
+
+pub fn cipher_from_key(key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+}
+
+/// `Base64(nonce || texto cifrado)` con un nonce aleatorio.
+pub fn encrypt_payload(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String, String> {
+    let nonce_bytes: [u8; 12] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|_| "Fallo al cifrar".to_string())?;
+    let mut payload = nonce_bytes.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+/// Inverso de `encrypt_payload`; falla si la clave no es la misma.
+pub fn decrypt_payload(cipher: &Aes256Gcm, encoded: &str) -> Result<Vec<u8>, String> {
+    let payload = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Base64 inválido: {}", e))?;
+    if payload.len() < 12 + 16 {
+        return Err("Contenido cifrado demasiado corto".to_string());
+    }
+    cipher
+        .decrypt(Nonce::from_slice(&payload[..12]), &payload[12..])
+        .map_err(|_| "No se pudo descifrar (¿otra clave u otro equipo?)".to_string())
+}
+
+/// Escribe a un temporal y lo renombra para no dejar el archivo a medias.
+pub fn write_file_atomic(path: &Path, contenido: &str) -> Result<(), std::io::Error> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temporal = path.with_extension("tmp");
+    std::fs::write(&temporal, contenido)?;
+    std::fs::rename(&temporal, path)
+}
+
+// -------------------------------------------------------------------------
+// ARCHIVO DE SECRETOS
+// -------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    version: u8,
+    con_frase: bool,
+    sal: String,
+    datos: String,
+}
+
+/// Archivo de secretos y la frase con que se abre. Quien lo use debe serializar el acceso.
+pub struct SecretStore {
+    path: PathBuf,
+    frase: Option<String>,
+}
+
+impl SecretStore {
+    /// Archivo dentro de `config_dir`; `frase` vacía equivale a sin frase.
+    pub fn new(config_dir: &Path, frase: Option<String>) -> Self {
+        SecretStore {
+            path: config_dir.join(SECRETS_FILE_NAME),
+            frase: frase.filter(|f| !f.is_empty()),
+        }
+    }
+
+    fn read_envelope(&self) -> Result<Option<Envelope>, SecretStoreError> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contenido) => serde_json::from_str(&contenido)
+                .map(Some)
+                .map_err(|e| SecretStoreError::Storage(format!("Archivo de secretos inválido: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    /// Secretos guardados; si el archivo todavía no existe, ninguno.
+    pub fn load(&self) -> Result<SecretValues, SecretStoreError> {
+        let envelope = match self.read_envelope()? {
+            Some(envelope) => envelope,
+            None => return Ok(SecretValues::default()),
+        };
+        if envelope.version != FORMATO_VERSION {
+            return Err(SecretStoreError::Storage(format!(
+                "Versión {} del archivo de secretos no soportada",
+                envelope.version
+            )));
+        }
+        if envelope.con_frase && self.frase.is_none() {
+            return Err(SecretStoreError::Crypto(
+                "El archivo de secretos está protegido con una frase (SECRETS_PASSPHRASE).".to_string(),
+            ));
+        }
+        let sal = hex::decode(&envelope.sal)
+            .map_err(|e| SecretStoreError::Storage(format!("Sal inválida en el archivo de secretos: {}", e)))?;
+        let frase = if envelope.con_frase { self.frase.as_deref() } else { None };
+        let cipher = cipher_from_key(&derive_key(&sal, frase).map_err(SecretStoreError::Crypto)?);
+        let plaintext = decrypt_payload(&cipher, &envelope.datos)
+            .map_err(|e| SecretStoreError::Crypto(format!("Archivo de secretos: {}", e)))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| SecretStoreError::Storage(format!("Archivo de secretos inválido: {}", e)))
+    }
+
+    fn save(&self, valores: &SecretValues) -> Result<(), SecretStoreError> {
+        let sal: [u8; 16] = rand::random();
+        let cipher = cipher_from_key(&derive_key(&sal, self.frase.as_deref()).map_err(SecretStoreError::Crypto)?);
+        let json = serde_json::to_vec(valores).map_err(|e| SecretStoreError::Storage(e.to_string()))?;
+        let envelope = Envelope {
+            version: FORMATO_VERSION,
+            con_frase: self.frase.is_some(),
+            sal: hex::encode(sal),
+            datos: encrypt_payload(&cipher, &json).map_err(SecretStoreError::Crypto)?,
+        };
+        let contenido = serde_json::to_string_pretty(&envelope).map_err(|e| SecretStoreError::Storage(e.to_string()))?;
+        write_file_atomic(&self.path, &contenido).map_err(storage_error)
+    }
+
+    /// Qué secretos hay y de dónde sale cada uno, sin valores.
+    pub fn status(&self) -> Result<SecretStoreStatus, SecretStoreError> {
+        let envelope = self.read_envelope()?;
+        let valores = self.load()?;
+        let secretos = SECRETOS
+            .iter()
+            .map(|(nombre, descripcion)| {
+                let guardado = valores.secretos.get(*nombre).filter(|s| !s.valor.is_empty());
+                let origen = match guardado {
+                    Some(_) => SecretOrigin::Archivo,
+                    None if std::env::var(nombre).map(|v| !v.trim().is_empty()).unwrap_or(false) => {
+                        SecretOrigin::Entorno
+                    }
+                    None => SecretOrigin::Ninguno,
+                };
+                SecretSummary {
+                    nombre: nombre.to_string(),
+                    descripcion: descripcion.to_string(),
+                    origen,
+                    fecha_modificacion: guardado.map(|s| s.fecha_modificacion.clone()),
+                }
+            })
+            .collect();
+        Ok(SecretStoreStatus {
+            existe: envelope.is_some(),
+            con_frase: envelope.map(|e| e.con_frase).unwrap_or(false),
+            secretos,
+        })
+    }
+
+    /// Guarda o rota el valor del secreto.
+    pub fn set(&self, nombre: &str, valor: &str) -> Result<(), SecretStoreError> {
+        let nombre = known_secret(nombre)?;
+        if valor.is_empty() {
+            return Err(SecretStoreError::Validation("El valor del secreto no puede quedar vacío.".to_string()));
+        }
+        let mut valores = self.load()?;
+        valores.secretos.insert(
+            nombre.to_string(),
+            StoredSecret {
+                valor: valor.to_string(),
+                fecha_modificacion: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            },
+        );
+        self.save(&valores)
+    }
+
+    /// Quita el secreto del archivo; vuelve a usarse la variable de entorno, si existe.
+    pub fn delete(&self, nombre: &str) -> Result<(), SecretStoreError> {
+        let nombre = known_secret(nombre)?;
+        let mut valores = self.load()?;
+        if valores.secretos.remove(nombre).is_none() {
+            return Err(SecretStoreError::NotFound(format!("El secreto '{}' no está guardado.", nombre)));
+        }
+        self.save(&valores)
+    }
+
+    /// Vuelve a cifrar todo con otra frase (o solo con la clave del equipo, si `nueva` es None).
+    pub fn change_passphrase(&mut self, nueva: Option<String>) -> Result<(), SecretStoreError> {
+        let valores = self.load()?;
+        let anterior = std::mem::replace(&mut self.frase, nueva.filter(|f| !f.is_empty()));
+        self.save(&valores).map_err(|e| {
+            self.frase = anterior;
+            e
+        })
+    }
+}
+
+fn known_secret(nombre: &str) -> Result<&'static str, SecretStoreError> {
+    let nombre = nombre.trim();
+    SECRETOS
+        .iter()
+        .map(|(n, _)| *n)
+        .find(|n| n.eq_ignore_ascii_case(nombre))
+        .ok_or_else(|| SecretStoreError::Validation(format!("Secreto desconocido: '{}'", nombre)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn carpeta_temporal(nombre: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("riy-secretos-{}-{}", nombre, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn alterar_byte(encoded: &str, posicion: usize) -> String {
+        let mut payload = general_purpose::STANDARD.decode(encoded).unwrap();
+        payload[posicion] ^= 0x01;
+        general_purpose::STANDARD.encode(payload)
+    }
+
+    #[test]
+    fn cifra_y_descifra_el_mismo_contenido() {
+        let cipher = cipher_from_key(&[1u8; 32]);
+        let primero = encrypt_payload(&cipher, b"palabra clave").unwrap();
+        let segundo = encrypt_payload(&cipher, b"palabra clave").unwrap();
+        // Nonce aleatorio: el mismo texto no produce el mismo cifrado.
+        assert_ne!(primero, segundo);
+        assert_eq!(decrypt_payload(&cipher, &primero).unwrap(), b"palabra clave");
+        assert_eq!(decrypt_payload(&cipher, &segundo).unwrap(), b"palabra clave");
+        assert_eq!(decrypt_payload(&cipher, &encrypt_payload(&cipher, b"").unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn otra_clave_no_descifra() {
+        let encoded = encrypt_payload(&cipher_from_key(&[1u8; 32]), b"palabra clave").unwrap();
+        let mut otra = [1u8; 32];
+        otra[31] = 2;
+        assert!(decrypt_payload(&cipher_from_key(&otra), &encoded).is_err());
+    }
+
+    #[test]
+    fn contenido_alterado_no_se_descifra() {
+        let cipher = cipher_from_key(&[1u8; 32]);
+        let encoded = encrypt_payload(&cipher, b"palabra clave").unwrap();
+        let largo = general_purpose::STANDARD.decode(&encoded).unwrap().len();
+
+        // Nonce, texto cifrado y etiqueta.
+        for posicion in [0, 11, 12, largo - 1] {
+            assert!(decrypt_payload(&cipher, &alterar_byte(&encoded, posicion)).is_err(), "byte {}", posicion);
+        }
+        let mut recortado = general_purpose::STANDARD.decode(&encoded).unwrap();
+        recortado.truncate(largo - 1);
+        assert!(decrypt_payload(&cipher, &general_purpose::STANDARD.encode(&recortado)).is_err());
+        assert!(decrypt_payload(&cipher, &general_purpose::STANDARD.encode([0u8; 27])).is_err());
+        assert!(decrypt_payload(&cipher, "no es base64!").is_err());
+        assert!(decrypt_payload(&cipher, "").is_err());
+    }
+
+    #[test]
+    fn la_frase_cambia_la_clave_derivada() {
+        let sal = [7u8; 16];
+        assert_eq!(derive_key(&sal, Some("frase")).unwrap(), derive_key(&sal, Some("frase")).unwrap());
+        assert_ne!(derive_key(&sal, Some("frase")).unwrap(), derive_key(&sal, Some("otra")).unwrap());
+        assert_ne!(derive_key(&sal, Some("frase")).unwrap(), derive_key(&sal, None).unwrap());
+        assert_ne!(derive_key(&sal, None).unwrap(), derive_key(&[8u8; 16], None).unwrap());
+    }
+
+    #[test]
+    fn el_archivo_solo_se_abre_con_la_misma_frase() {
+        let dir = carpeta_temporal("frase");
+        let store = SecretStore::new(&dir, Some("frase".to_string()));
+        assert!(store.load().unwrap().secretos.is_empty());
+        store.set("smtp_password", "s3creto").unwrap();
+
+        let releido = SecretStore::new(&dir, Some("frase".to_string())).load().unwrap();
+        assert_eq!(releido.get("SMTP_PASSWORD"), Some("s3creto"));
+        assert!(!std::fs::read_to_string(dir.join(SECRETS_FILE_NAME)).unwrap().contains("s3creto"));
+
+        assert!(matches!(
+            SecretStore::new(&dir, Some("otra".to_string())).load(),
+            Err(SecretStoreError::Crypto(_))
+        ));
+        assert!(matches!(SecretStore::new(&dir, None).load(), Err(SecretStoreError::Crypto(_))));
+
+        let mut store = store;
+        store.change_passphrase(None).unwrap();
+        assert_eq!(SecretStore::new(&dir, None).load().unwrap().get("SMTP_PASSWORD"), Some("s3creto"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn el_archivo_alterado_no_se_abre() {
+        let dir = carpeta_temporal("alterado");
+        let store = SecretStore::new(&dir, None);
+        store.set("JWT_SECRET", "firma").unwrap();
+        let path = dir.join(SECRETS_FILE_NAME);
+        let original: Envelope = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let datos_alterados = Envelope {
+            version: original.version,
+            con_frase: original.con_frase,
+            sal: original.sal.clone(),
+            datos: alterar_byte(&original.datos, 20),
+        };
+        std::fs::write(&path, serde_json::to_string(&datos_alterados).unwrap()).unwrap();
+        assert!(matches!(store.load(), Err(SecretStoreError::Crypto(_))));
+
+        let mut sal = hex::decode(&original.sal).unwrap();
+        sal[0] ^= 0x01;
+        let sal_alterada = Envelope { sal: hex::encode(sal), ..original };
+        std::fs::write(&path, serde_json::to_string(&sal_alterada).unwrap()).unwrap();
+        assert!(matches!(store.load(), Err(SecretStoreError::Crypto(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
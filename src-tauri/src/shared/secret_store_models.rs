@@ -0,0 +1,59 @@
+// src/shared/secret_store_models.rs
+// Modelos del archivo cifrado de secretos de la aplicación de escritorio.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Valor guardado de un secreto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSecret {
+    pub valor: String,
+    pub fecha_modificacion: String,
+}
+
+/// Contenido descifrado del archivo: secretos por nombre de variable de entorno.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretValues {
+    #[serde(default)]
+    pub secretos: BTreeMap<String, StoredSecret>,
+}
+
+impl SecretValues {
+    pub fn get(&self, nombre: &str) -> Option<&str> {
+        self.secretos.get(nombre).map(|s| s.valor.as_str()).filter(|v| !v.is_empty())
+    }
+}
+
+/// De dónde sale el valor que usa la aplicación.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretOrigin {
+    /// Archivo cifrado.
+    Archivo,
+    /// Variable de entorno (.env), sin valor en el archivo.
+    Entorno,
+    Ninguno,
+}
+
+/// Secreto como lo ve la pantalla de administración: nunca incluye el valor.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretSummary {
+    pub nombre: String,
+    pub descripcion: String,
+    pub origen: SecretOrigin,
+    pub fecha_modificacion: Option<String>,
+}
+
+/// Estado del archivo de secretos.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStoreStatus {
+    pub existe: bool,
+    /// Si además de la clave del equipo pide una frase.
+    pub con_frase: bool,
+    pub secretos: Vec<SecretSummary>,
+}
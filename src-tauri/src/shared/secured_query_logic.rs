@@ -0,0 +1,626 @@
+// src/shared/secured_query_logic.rs
+/*
+Ejecutor central de consultas con seguridad por fila.
+
+Toda consulta del aplicativo sobre datos del ERP debe pasar por fetch_secured:
+  1. junta los filtros por concepto (explícitos y los registrados para el objeto de
+     origen en riy_vista_concepto),
+  2. resuelve los valores permitidos del usuario (row_security_logic),
+  3. envuelve la consulta como tabla derivada y agrega un predicado parametrizado por
+     filtro: `IN (@p..)` si la lista es corta, `EXISTS` contra
+     riy_concepto_valor_usuario si es larga,
+  4. la ejecuta en una conexión con SESSION_CONTEXT('riy_usuario_id') fijado, de modo
+     que las vistas por concepto (secured_view_logic) filtran por el mismo usuario.
+     Si la consulta falla, se cancela o vence antes de limpiar el contexto, la conexión
+     no vuelve al pool: se separa y se cierra (SessionConnection).
+
+Las columnas de fecha se piden como texto (CONVERT(VARCHAR(23), columna, 126)): sqlx no
+decodifica DATETIME de SQL Server.
+
+El SQL envuelto no puede terminar en ORDER BY (salvo con TOP u OFFSET, que SQL Server
+admite en una tabla derivada): el orden se pide con `order_by`.
+
+Un usuario sin valores asignados en un concepto activo no ve ninguna fila.
+*/
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use sqlx::mssql::{MssqlArguments, MssqlConnection, MssqlRow};
+use sqlx::pool::PoolConnection;
+use sqlx::query::Query;
+use sqlx::{Column, Mssql, Pool, Row};
+
+use crate::row_security_logic::{self, quote_identifier, RowSecurityError, VALOR_TODOS};
+use crate::row_security_models::AllowedValues;
+use crate::secured_query_models::{RowFilter, SecuredQuery, SqlValue};
+use crate::secured_view_logic;
+
+/// Clave de SESSION_CONTEXT que leen las vistas por concepto.
+pub const SESSION_KEY_USUARIO: &str = "riy_usuario_id";
+
+/// Por encima de esta cantidad de valores se usa EXISTS en lugar de IN
+/// (SQL Server admite 2100 parámetros por petición).
+const MAX_IN_VALUES: usize = 500;
+
+/// Alias de la tabla derivada que envuelve la consulta original.
+const ALIAS: &str = "sxf";
+
+fn db_error(e: sqlx::Error) -> RowSecurityError {
+    RowSecurityError::Database(e.to_string())
+}
+
+/// Columna simple (sin esquema ni tabla) entre corchetes.
+pub fn quote_column(columna: &str) -> Result<String, RowSecurityError> {
+    if columna.contains('.') {
+        return Err(RowSecurityError::Validation(format!(
+            "La columna de filtro debe ser un nombre simple: '{}'", columna
+        )));
+    }
+    quote_identifier(columna)
+}
+
+fn order_clause(order_by: &[String]) -> Result<String, RowSecurityError> {
+    if order_by.is_empty() {
+        return Ok(String::new());
+    }
+    let mut partes = Vec::with_capacity(order_by.len());
+    for item in order_by {
+        let mut tokens = item.split_whitespace();
+        let columna = quote_column(tokens.next().unwrap_or_default())?;
+        let direccion = match tokens.next().map(|d| d.to_ascii_uppercase()) {
+            None => "",
+            Some(d) if d == "ASC" => " ASC",
+            Some(d) if d == "DESC" => " DESC",
+            Some(d) => return Err(RowSecurityError::Validation(format!("Dirección de orden inválida: '{}'", d))),
+        };
+        if tokens.next().is_some() {
+            return Err(RowSecurityError::Validation(format!("Orden inválido: '{}'", item)));
+        }
+        partes.push(format!("{}.{}{}", ALIAS, columna, direccion));
+    }
+    Ok(format!(" ORDER BY {}", partes.join(", ")))
+}
+
+/// Palabras del nivel superior de la consulta (fuera de paréntesis), en mayúsculas, sin
+/// comentarios, literales ni identificadores delimitados.
+fn top_level_words(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut palabras = Vec::new();
+    let mut nivel = 0i32;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let siguiente = chars.get(i + 1).copied();
+        if c == '-' && siguiente == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && siguiente == Some('*') {
+            let mut comentarios = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    comentarios += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    comentarios -= 1;
+                    i += 2;
+                    if comentarios == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c == '\'' || c == '[' || c == '"' {
+            let cierre = if c == '[' { ']' } else { c };
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == cierre && chars.get(i + 1) == Some(&cierre) {
+                    i += 2;
+                } else if chars[i] == cierre {
+                    i += 1;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c == '(' {
+            nivel += 1;
+            i += 1;
+        } else if c == ')' {
+            nivel -= 1;
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' || c == '@' || c == '#' {
+            let inicio = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '@' | '#' | '$')) {
+                i += 1;
+            }
+            if nivel == 0 {
+                palabras.push(chars[inicio..i].iter().collect::<String>().to_uppercase());
+            }
+        } else {
+            i += 1;
+        }
+    }
+    palabras
+}
+
+/// Un ORDER BY del nivel superior solo es válido en una tabla derivada junto con TOP u OFFSET.
+fn check_order_by(sql: &str) -> Result<(), RowSecurityError> {
+    let palabras = top_level_words(sql);
+    let tiene_order_by = palabras.windows(2).any(|w| w[0] == "ORDER" && w[1] == "BY");
+    let tiene_limite = palabras.iter().any(|p| p == "TOP" || p == "OFFSET");
+    if tiene_order_by && !tiene_limite {
+        return Err(RowSecurityError::Validation(
+            "La consulta no puede terminar en ORDER BY: indique el orden con orderBy".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reescribe la consulta con los predicados de seguridad. Función pura: recibe los
+/// valores ya resueltos para cada filtro y devuelve el SQL y la lista completa de parámetros.
+pub fn build_secured_sql(
+    query: &SecuredQuery,
+    permitidos: &[(RowFilter, AllowedValues)],
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(String, Vec<SqlValue>), RowSecurityError> {
+    let base = query.sql.trim().trim_end_matches(';').trim();
+    if !base.to_ascii_uppercase().starts_with("SELECT") {
+        return Err(RowSecurityError::Validation("La consulta debe ser un SELECT".to_string()));
+    }
+    if base.contains(';') {
+        return Err(RowSecurityError::Validation("La consulta no puede tener varias sentencias".to_string()));
+    }
+    check_order_by(base)?;
+
+    let mut params = query.params.clone();
+    let mut predicados = Vec::new();
+    for (filtro, permitido) in permitidos {
+        if permitido.todos {
+            continue;
+        }
+        let columna = format!(
+            "CAST({}.{} AS VARCHAR(100)) {}",
+            ALIAS,
+            quote_column(&filtro.columna)?,
+            sql_collate_clause
+        );
+        if permitido.valores.is_empty() {
+            predicados.push("1 = 0".to_string());
+        } else if permitido.valores.len() <= MAX_IN_VALUES {
+            let mut marcadores = Vec::with_capacity(permitido.valores.len());
+            for valor in &permitido.valores {
+                params.push(SqlValue::Text(valor.clone()));
+                marcadores.push(format!("@p{}", params.len()));
+            }
+            predicados.push(format!("{} IN ({})", columna, marcadores.join(", ")));
+        } else {
+            params.push(SqlValue::Int(permitido.concepto_id as i64));
+            let p_concepto = params.len();
+            params.push(SqlValue::Int(usuario_id as i64));
+            let p_usuario = params.len();
+            predicados.push(format!(
+                "EXISTS (SELECT 1 FROM riy.riy_concepto_valor_usuario cv WITH(NOLOCK)
+                          WHERE cv.conceptoID = @p{} AND cv.usuarioID = @p{}
+                            AND (cv.valor = '{}' OR cv.valor {} = {}))",
+                p_concepto, p_usuario, VALOR_TODOS, sql_collate_clause, columna
+            ));
+        }
+    }
+
+    let mut sql = format!("SELECT {0}.* FROM ({1}) AS {0}", ALIAS, base);
+    if !predicados.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&predicados.join(" AND "));
+    }
+    let orden = order_clause(&query.order_by)?;
+    match query.limit {
+        Some(limit) => {
+            let offset = query.offset.unwrap_or(0);
+            if limit < 1 || offset < 0 {
+                return Err(RowSecurityError::Validation("Paginación inválida".to_string()));
+            }
+            // OFFSET/FETCH exige ORDER BY
+            sql.push_str(if orden.is_empty() { " ORDER BY (SELECT NULL)" } else { &orden });
+            sql.push_str(&format!(" OFFSET {} ROWS FETCH NEXT {} ROWS ONLY", offset, limit));
+        }
+        None => sql.push_str(&orden),
+    }
+    Ok((sql, params))
+}
+
+/// Filtros explícitos más los registrados para el objeto de origen, sin repetir.
+async fn collect_filters(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    query: &SecuredQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<RowFilter>, RowSecurityError> {
+    let mut filtros = query.filters.clone();
+    if let Some(objeto) = query.objeto.as_deref().filter(|o| !o.trim().is_empty()) {
+        for filtro in secured_view_logic::filters_for_object(pool, aplicativo_id, objeto, sql_collate_clause).await? {
+            if !filtros.contains(&filtro) {
+                filtros.push(filtro);
+            }
+        }
+    }
+    Ok(filtros)
+}
+
+/// Valores permitidos del usuario para cada filtro de la consulta.
+pub async fn resolve_filters(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    query: &SecuredQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<(RowFilter, AllowedValues)>, RowSecurityError> {
+    let filtros = collect_filters(pool, aplicativo_id, query, sql_collate_clause).await?;
+    let mut resueltos: HashMap<String, AllowedValues> = HashMap::new();
+    let mut permitidos = Vec::with_capacity(filtros.len());
+    for filtro in filtros {
+        let permitido = match resueltos.get(&filtro.concepto) {
+            Some(p) => p.clone(),
+            None => {
+                let p = row_security_logic::resolve_allowed_values(
+                    pool,
+                    aplicativo_id,
+                    usuario_id,
+                    &filtro.concepto,
+                    sql_collate_clause,
+                ).await?;
+                resueltos.insert(filtro.concepto.clone(), p.clone());
+                p
+            }
+        };
+        permitidos.push((filtro, permitido));
+    }
+    Ok(permitidos)
+}
+
+/// Ejecuta la consulta aplicando la seguridad por fila del usuario.
+pub async fn fetch_secured(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    query: &SecuredQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<MssqlRow>, RowSecurityError> {
+    let permitidos = resolve_filters(pool, aplicativo_id, usuario_id, query, sql_collate_clause).await?;
+    let (sql, params) = build_secured_sql(query, &permitidos, usuario_id, sql_collate_clause)?;
+    fetch_with_session_context(pool, usuario_id, &sql, &params).await
+}
+
+/// Total de filas visibles para el usuario, sin orden ni paginación.
+pub async fn count_secured(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    query: &SecuredQuery,
+    sql_collate_clause: &str,
+) -> Result<i64, RowSecurityError> {
+    let sin_paginar = SecuredQuery {
+        order_by: Vec::new(),
+        offset: None,
+        limit: None,
+        ..query.clone()
+    };
+    let permitidos = resolve_filters(pool, aplicativo_id, usuario_id, &sin_paginar, sql_collate_clause).await?;
+    let (sql, params) = build_secured_sql(&sin_paginar, &permitidos, usuario_id, sql_collate_clause)?;
+    let rows = fetch_with_session_context(pool, usuario_id, &format!("SELECT COUNT_BIG(*) FROM ({}) AS total", sql), &params).await?;
+    rows.first()
+        .map(|row| row.try_get::<i64, _>(0).map_err(db_error))
+        .unwrap_or(Ok(0))
+}
+
+/// Igual que fetch_secured, con cada fila convertida a un objeto JSON.
+pub async fn fetch_secured_json(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    query: &SecuredQuery,
+    sql_collate_clause: &str,
+) -> Result<Vec<Map<String, Value>>, RowSecurityError> {
+    let rows = fetch_secured(pool, aplicativo_id, usuario_id, query, sql_collate_clause).await?;
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+/// Enlaza los parámetros en orden (@p1..@pN).
+pub fn bind_params<'q>(
+    mut consulta: Query<'q, Mssql, MssqlArguments>,
+    params: &[SqlValue],
+) -> Query<'q, Mssql, MssqlArguments> {
+    for param in params {
+        consulta = match param {
+            SqlValue::Null => consulta.bind(None::<String>),
+            SqlValue::Bool(v) => consulta.bind(*v),
+            SqlValue::Int(v) => consulta.bind(*v),
+            SqlValue::Float(v) => consulta.bind(*v),
+            SqlValue::Text(v) => consulta.bind(v.clone()),
+        };
+    }
+    consulta
+}
+
+/// Fija (o limpia, con `None`) el usuario en SESSION_CONTEXT de la conexión.
+pub async fn set_session_user(conn: &mut MssqlConnection, usuario_id: Option<i32>) -> Result<(), RowSecurityError> {
+    let set_context = format!("EXEC sp_set_session_context @key = N'{}', @value = @p1", SESSION_KEY_USUARIO);
+    sqlx::query(&set_context)
+        .bind(usuario_id)
+        .execute(conn)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Conexión del pool con SESSION_CONTEXT fijado al usuario. Solo vuelve al pool si se
+/// limpia con `release`; si se suelta antes (error, tiempo agotado o tarea cancelada) se
+/// separa del pool y se cierra, para que ninguna otra consulta herede la identidad.
+pub struct SessionConnection {
+    conn: Option<PoolConnection<Mssql>>,
+}
+
+impl SessionConnection {
+    pub async fn acquire(pool: &Pool<Mssql>, usuario_id: i32) -> Result<Self, RowSecurityError> {
+        let conn = pool.acquire().await.map_err(db_error)?;
+        let mut sesion = SessionConnection { conn: Some(conn) };
+        set_session_user(sesion.conn(), Some(usuario_id)).await?;
+        Ok(sesion)
+    }
+
+    pub fn conn(&mut self) -> &mut MssqlConnection {
+        self.conn.as_mut().expect("conexión ya liberada")
+    }
+
+    /// Limpia el contexto y devuelve la conexión al pool.
+    pub async fn release(mut self) -> Result<(), RowSecurityError> {
+        set_session_user(self.conn(), None).await?;
+        self.conn.take();
+        Ok(())
+    }
+}
+
+impl Drop for SessionConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            // Al soltar la conexión separada se cierra el socket y SQL Server aborta la consulta
+            drop(conn.detach());
+        }
+    }
+}
+
+/// Ejecuta `sql` en una sola conexión con SESSION_CONTEXT fijado al usuario y lo limpia
+/// al terminar, para que la conexión vuelva al pool sin identidad.
+pub async fn fetch_with_session_context(
+    pool: &Pool<Mssql>,
+    usuario_id: i32,
+    sql: &str,
+    params: &[SqlValue],
+) -> Result<Vec<MssqlRow>, RowSecurityError> {
+    let mut sesion = SessionConnection::acquire(pool, usuario_id).await?;
+    let filas = bind_params(sqlx::query(sql), params)
+        .fetch_all(sesion.conn())
+        .await
+        .map_err(db_error)?;
+    sesion.release().await?;
+    Ok(filas)
+}
+
+/// Convierte una fila a JSON probando los tipos que maneja el driver. sqlx no decodifica
+/// fechas ni DECIMAL/MONEY de SQL Server: esas columnas deben convertirse en la consulta,
+/// las fechas con `CONVERT(VARCHAR(23), columna, 126)`; si no, llegan como null.
+pub fn row_to_json(row: &MssqlRow) -> Map<String, Value> {
+    let mut objeto = Map::new();
+    for (i, columna) in row.columns().iter().enumerate() {
+        let valor = if let Ok(v) = row.try_get::<Option<i32>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<i64>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<i16>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<u8>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<f64>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<f32>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<bool>, _>(i) {
+            v.map(Value::from)
+        } else if let Ok(v) = row.try_get::<Option<String>, _>(i) {
+            v.map(Value::from)
+        } else {
+            None
+        };
+        objeto.insert(columna.name().to_string(), valor.unwrap_or(Value::Null));
+    }
+    objeto
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLATE: &str = "COLLATE SQL_Latin1_General_CP1_CI_AS";
+
+    fn filtro(concepto: &str, columna: &str) -> RowFilter {
+        RowFilter { concepto: concepto.to_string(), columna: columna.to_string() }
+    }
+
+    fn permitido(concepto_id: i32, todos: bool, valores: &[&str]) -> AllowedValues {
+        AllowedValues {
+            concepto: format!("C{}", concepto_id),
+            concepto_id,
+            todos,
+            valores: valores.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    fn consulta(sql: &str) -> SecuredQuery {
+        SecuredQuery { sql: sql.to_string(), ..SecuredQuery::default() }
+    }
+
+    #[test]
+    fn agrega_in_con_los_valores_permitidos_despues_de_los_parametros() {
+        let query = SecuredQuery {
+            params: vec![SqlValue::Int(2024)],
+            ..consulta("SELECT * FROM dbo.Ventas WHERE Ejercicio = @p1")
+        };
+        let permitidos = vec![(filtro("SUCURSAL", "Sucursal"), permitido(7, false, &["01", "02"]))];
+
+        let (sql, params) = build_secured_sql(&query, &permitidos, 15, COLLATE).unwrap();
+
+        assert_eq!(
+            sql,
+            format!(
+                "SELECT sxf.* FROM (SELECT * FROM dbo.Ventas WHERE Ejercicio = @p1) AS sxf \
+                 WHERE CAST(sxf.[Sucursal] AS VARCHAR(100)) {} IN (@p2, @p3)",
+                COLLATE
+            )
+        );
+        assert_eq!(
+            params,
+            vec![SqlValue::Int(2024), SqlValue::Text("01".to_string()), SqlValue::Text("02".to_string())]
+        );
+    }
+
+    #[test]
+    fn sin_valores_permitidos_no_devuelve_filas() {
+        let permitidos = vec![(filtro("SUCURSAL", "Sucursal"), permitido(7, false, &[]))];
+
+        let (sql, params) = build_secured_sql(&consulta("SELECT * FROM dbo.Ventas"), &permitidos, 15, COLLATE).unwrap();
+
+        assert!(sql.ends_with(" WHERE 1 = 0"));
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn con_todos_los_valores_no_agrega_predicado() {
+        let permitidos = vec![(filtro("SUCURSAL", "Sucursal"), permitido(7, true, &[]))];
+
+        let (sql, params) = build_secured_sql(&consulta("SELECT * FROM dbo.Ventas"), &permitidos, 15, COLLATE).unwrap();
+
+        assert_eq!(sql, "SELECT sxf.* FROM (SELECT * FROM dbo.Ventas) AS sxf");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn lista_larga_usa_exists_con_concepto_y_usuario() {
+        let valores: Vec<String> = (0..=MAX_IN_VALUES).map(|v| v.to_string()).collect();
+        let valores: Vec<&str> = valores.iter().map(String::as_str).collect();
+        let permitidos = vec![(filtro("CLIENTE", "Cliente"), permitido(9, false, &valores))];
+
+        let (sql, params) = build_secured_sql(&consulta("SELECT * FROM dbo.Ventas"), &permitidos, 15, COLLATE).unwrap();
+
+        assert!(sql.contains("EXISTS (SELECT 1 FROM riy.riy_concepto_valor_usuario cv"));
+        assert!(sql.contains("cv.conceptoID = @p1 AND cv.usuarioID = @p2"));
+        assert!(!sql.contains(" IN ("));
+        assert_eq!(params, vec![SqlValue::Int(9), SqlValue::Int(15)]);
+    }
+
+    #[test]
+    fn varios_filtros_se_combinan_con_and() {
+        let permitidos = vec![
+            (filtro("SUCURSAL", "Sucursal"), permitido(7, false, &["01"])),
+            (filtro("ALMACEN", "Almacen"), permitido(8, false, &["A"])),
+        ];
+
+        let (sql, params) = build_secured_sql(&consulta("SELECT * FROM dbo.Ventas"), &permitidos, 15, COLLATE).unwrap();
+
+        assert!(sql.contains(&format!("[Sucursal] AS VARCHAR(100)) {} IN (@p1) AND ", COLLATE)));
+        assert!(sql.contains(&format!("[Almacen] AS VARCHAR(100)) {} IN (@p2)", COLLATE)));
+        assert_eq!(params, vec![SqlValue::Text("01".to_string()), SqlValue::Text("A".to_string())]);
+    }
+
+    #[test]
+    fn pagina_fuera_de_la_tabla_derivada() {
+        let query = SecuredQuery {
+            order_by: vec!["Fecha DESC".to_string()],
+            offset: Some(20),
+            limit: Some(10),
+            ..consulta("SELECT * FROM dbo.Ventas")
+        };
+
+        let (sql, _) = build_secured_sql(&query, &[], 15, COLLATE).unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT sxf.* FROM (SELECT * FROM dbo.Ventas) AS sxf ORDER BY sxf.[Fecha] DESC OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn rechaza_columna_de_filtro_con_tabla() {
+        let permitidos = vec![(filtro("SUCURSAL", "v.Sucursal"), permitido(7, false, &["01"]))];
+
+        assert!(build_secured_sql(&consulta("SELECT * FROM dbo.Ventas v"), &permitidos, 15, COLLATE).is_err());
+    }
+
+    #[test]
+    fn rechaza_order_by_sin_top_ni_offset() {
+        assert!(build_secured_sql(&consulta("SELECT * FROM dbo.Ventas ORDER BY Fecha"), &[], 15, COLLATE).is_err());
+        assert!(build_secured_sql(&consulta("SELECT TOP 10 * FROM dbo.Ventas ORDER BY Fecha"), &[], 15, COLLATE).is_ok());
+        assert!(build_secured_sql(
+            &consulta("SELECT * FROM (SELECT ROW_NUMBER() OVER (ORDER BY Fecha) n FROM dbo.Ventas) x"),
+            &[],
+            15,
+            COLLATE
+        )
+        .is_ok());
+        assert!(build_secured_sql(&consulta("SELECT 'ORDER BY' AS texto FROM dbo.Ventas"), &[], 15, COLLATE).is_ok());
+    }
+
+    /// Ejecuta el SQL generado sobre una tabla de SQLite en memoria y devuelve los folios
+    /// visibles. Sin COLLATE ni paginación, el SQL que arma build_secured_sql es válido en
+    /// ambos motores.
+    async fn folios_visibles(permitidos: &[(RowFilter, AllowedValues)]) -> Vec<i64> {
+        use sqlx::Connection;
+
+        let mut conn = sqlx::SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE Ventas (Folio INTEGER, Sucursal TEXT, Almacen INTEGER)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO Ventas VALUES (1, '01', 10), (2, '02', 10), (3, '03', 20), (4, '01', 20), (5, '02', 20)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let query = SecuredQuery {
+            params: vec![SqlValue::Int(1)],
+            order_by: vec!["Folio".to_string()],
+            ..consulta("SELECT Folio, Sucursal, Almacen FROM Ventas WHERE Folio > @p1")
+        };
+        let (sql, params) = build_secured_sql(&query, permitidos, 15, "").unwrap();
+
+        let mut ejecucion = sqlx::query_scalar::<_, i64>(&sql);
+        for param in &params {
+            ejecucion = match param {
+                SqlValue::Int(v) => ejecucion.bind(*v),
+                SqlValue::Text(v) => ejecucion.bind(v.clone()),
+                otro => panic!("parámetro inesperado: {:?}", otro),
+            };
+        }
+        ejecucion.fetch_all(&mut conn).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn el_usuario_solo_ve_las_filas_de_sus_valores() {
+        let sucursal = |valores: &[&str]| (filtro("SUCURSAL", "Sucursal"), permitido(7, false, valores));
+        let almacen = |valores: &[&str]| (filtro("ALMACEN", "Almacen"), permitido(8, false, valores));
+
+        // El parámetro de la consulta original (Folio > 1) se respeta junto con el filtro
+        assert_eq!(folios_visibles(&[sucursal(&["01"])]).await, vec![4]);
+        assert_eq!(folios_visibles(&[sucursal(&["01", "02"])]).await, vec![2, 4, 5]);
+        // Columna numérica comparada como texto, combinada con AND
+        assert_eq!(folios_visibles(&[sucursal(&["01", "02"]), almacen(&["20"])]).await, vec![4, 5]);
+        // Sin valores asignados no ve nada; con todos los valores ve todo
+        assert!(folios_visibles(&[sucursal(&[])]).await.is_empty());
+        assert_eq!(
+            folios_visibles(&[(filtro("SUCURSAL", "Sucursal"), permitido(7, true, &[]))]).await,
+            vec![2, 3, 4, 5]
+        );
+    }
+}
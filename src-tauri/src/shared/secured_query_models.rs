@@ -0,0 +1,80 @@
+// src/shared/secured_query_models.rs
+// Modelos del ejecutor central con seguridad por fila y de las vistas por concepto.
+
+use serde::{Deserialize, Serialize};
+
+/// Parámetro de una consulta dinámica.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SqlValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// Filtro por concepto: la columna `columna` del resultado debe tener un valor
+/// permitido del concepto `concepto` para el usuario.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowFilter {
+    pub concepto: String,
+    pub columna: String,
+}
+
+/// Consulta a ejecutar con seguridad por fila.
+///
+/// `sql` es un SELECT sin ORDER BY que usa `@p1..@pN` para `params`; el ejecutor lo
+/// envuelve como tabla derivada y agrega los filtros después de esos parámetros.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecuredQuery {
+    pub sql: String,
+    #[serde(default)]
+    pub params: Vec<SqlValue>,
+    /// Objeto de origen registrado como vista por concepto (p. ej. `dbo.Ventas`):
+    /// sus filtros se agregan a `filters`.
+    pub objeto: Option<String>,
+    #[serde(default)]
+    pub filters: Vec<RowFilter>,
+    /// Columnas de orden (`col` o `col DESC`), aplicadas fuera de la tabla derivada.
+    #[serde(default)]
+    pub order_by: Vec<String>,
+    /// Paginación: filas a saltar y filas a devolver (OFFSET / FETCH).
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Vista por concepto registrada (`riy.riy_vista_concepto`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecuredView {
+    pub vista_id: i32,
+    pub aplicativo_id: i32,
+    /// Nombre de la vista generada dentro del esquema `riy_sxf`.
+    pub nombre: String,
+    pub objeto_origen: String,
+    pub filtros: Vec<RowFilter>,
+    pub autor: String,
+    pub fecha_creacion: String,
+    pub fecha_generacion: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecuredViewInput {
+    pub nombre: String,
+    pub objeto_origen: String,
+    pub filtros: Vec<RowFilter>,
+}
+
+/// Script de una vista por concepto (vista previa o resultado de generarla).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecuredViewScript {
+    pub vista_id: i32,
+    pub nombre_completo: String,
+    pub script: String,
+    pub generada: bool,
+}
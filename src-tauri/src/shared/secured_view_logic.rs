@@ -0,0 +1,409 @@
+// src/shared/secured_view_logic.rs
+/*
+Vistas por concepto: para un objeto del ERP (tabla o vista) se registran las columnas
+que corresponden a cada concepto y se genera una vista en el esquema riy_sxf que solo
+devuelve las filas permitidas al usuario de SESSION_CONTEXT('riy_usuario_id').
+
+Sin SESSION_CONTEXT la vista no devuelve filas; el ejecutor central
+(secured_query_logic::fetch_with_session_context) lo fija en cada consulta. El mismo
+registro indica al ejecutor qué filtros aplicar cuando una consulta declara el objeto.
+
+CREATE SCHEMA riy_sxf AUTHORIZATION dbo;
+
+CREATE TABLE riy.riy_vista_concepto (
+    vistaID          INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID     INT          NOT NULL,
+    nombre           VARCHAR(128) NOT NULL,
+    objetoOrigen     VARCHAR(256) NOT NULL,
+    autor            VARCHAR(50)  NOT NULL,
+    fechaCreacion    DATETIME     NOT NULL DEFAULT GETDATE(),
+    fechaGeneracion  DATETIME     NULL,
+    CONSTRAINT UQ_riy_vista_concepto_nombre UNIQUE (nombre)
+);
+
+CREATE TABLE riy.riy_vista_concepto_filtro (
+    vistaID     INT          NOT NULL,
+    conceptoID  INT          NOT NULL,
+    columna     VARCHAR(128) NOT NULL,
+    CONSTRAINT PK_riy_vista_concepto_filtro PRIMARY KEY (vistaID, conceptoID, columna)
+);
+*/
+
+use sqlx::{Mssql, Pool};
+
+use crate::row_security_logic::{self, quote_identifier, RowSecurityError, VALOR_TODOS};
+use crate::secured_query_logic::{quote_column, SESSION_KEY_USUARIO};
+use crate::secured_query_models::{RowFilter, SecuredView, SecuredViewInput, SecuredViewScript};
+
+/// Esquema donde se generan las vistas por concepto.
+pub const ESQUEMA_VISTAS: &str = "riy_sxf";
+
+fn db_error(e: sqlx::Error) -> RowSecurityError {
+    RowSecurityError::Database(e.to_string())
+}
+
+fn full_view_name(nombre: &str) -> Result<String, RowSecurityError> {
+    if nombre.contains('.') {
+        return Err(RowSecurityError::Validation(format!(
+            "El nombre de la vista no lleva esquema: '{}'", nombre
+        )));
+    }
+    Ok(format!("[{}].{}", ESQUEMA_VISTAS, quote_identifier(nombre)?))
+}
+
+/// Filtros de una vista: (conceptoID, código del concepto, columna).
+async fn view_filters(
+    pool: &Pool<Mssql>,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<(i32, RowFilter)>, RowSecurityError> {
+    let sql_query = format!(
+        "SELECT f.conceptoID, c.codigo {0}, f.columna {0}
+           FROM riy.riy_vista_concepto_filtro f WITH(NOLOCK)
+           JOIN riy.riy_concepto c WITH(NOLOCK) ON c.conceptoID = f.conceptoID
+          WHERE f.vistaID = @p1
+          ORDER BY c.codigo, f.columna",
+        sql_collate_clause
+    );
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(&sql_query)
+        .bind(vista_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|(concepto_id, concepto, columna)| (concepto_id, RowFilter { concepto, columna }))
+        .collect())
+}
+
+type ViewRow = (i32, i32, String, String, String, String, Option<String>);
+
+async fn to_view(pool: &Pool<Mssql>, row: ViewRow, sql_collate_clause: &str) -> Result<SecuredView, RowSecurityError> {
+    let (vista_id, aplicativo_id, nombre, objeto_origen, autor, fecha_creacion, fecha_generacion) = row;
+    let filtros = view_filters(pool, vista_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .map(|(_, filtro)| filtro)
+        .collect();
+    Ok(SecuredView {
+        vista_id,
+        aplicativo_id,
+        nombre,
+        objeto_origen,
+        filtros,
+        autor,
+        fecha_creacion,
+        fecha_generacion,
+    })
+}
+
+fn view_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT vistaID, aplicativoID, nombre {0}, objetoOrigen {0}, autor {0},
+                CONVERT(VARCHAR(19), fechaCreacion, 120),
+                CONVERT(VARCHAR(19), fechaGeneracion, 120)
+           FROM riy.riy_vista_concepto WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+pub async fn list_views(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<SecuredView>, RowSecurityError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1 ORDER BY nombre", view_select(sql_collate_clause));
+    let rows: Vec<ViewRow> = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut vistas = Vec::with_capacity(rows.len());
+    for row in rows {
+        vistas.push(to_view(pool, row, sql_collate_clause).await?);
+    }
+    Ok(vistas)
+}
+
+pub async fn get_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<SecuredView, RowSecurityError> {
+    let sql_query = format!("{} WHERE aplicativoID = @p1 AND vistaID = @p2", view_select(sql_collate_clause));
+    let row: ViewRow = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(vista_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| RowSecurityError::NotFound(format!("Vista {} no encontrada", vista_id)))?;
+    to_view(pool, row, sql_collate_clause).await
+}
+
+/// Filtros registrados para un objeto de origen (los usa el ejecutor central).
+pub async fn filters_for_object(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    objeto: &str,
+    sql_collate_clause: &str,
+) -> Result<Vec<RowFilter>, RowSecurityError> {
+    let sql_query = format!(
+        "SELECT DISTINCT c.codigo {0}, f.columna {0}
+           FROM riy.riy_vista_concepto v WITH(NOLOCK)
+           JOIN riy.riy_vista_concepto_filtro f WITH(NOLOCK) ON f.vistaID = v.vistaID
+           JOIN riy.riy_concepto c WITH(NOLOCK) ON c.conceptoID = f.conceptoID
+          WHERE v.aplicativoID = @p1 AND v.objetoOrigen = @p2",
+        sql_collate_clause
+    );
+    let rows: Vec<(String, String)> = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(objeto.trim())
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|(concepto, columna)| RowFilter { concepto, columna })
+        .collect())
+}
+
+/// Objetos de origen con seguridad por fila y su vista: (objetoOrigen, nombre de la vista).
+/// De todos los aplicativos: el login de solo lectura es uno solo para la base.
+pub async fn secured_objects(
+    pool: &Pool<Mssql>,
+    sql_collate_clause: &str,
+) -> Result<Vec<(String, String)>, RowSecurityError> {
+    let sql_query = format!(
+        "SELECT objetoOrigen {0}, nombre {0} FROM riy.riy_vista_concepto WITH(NOLOCK)",
+        sql_collate_clause
+    );
+    sqlx::query_as(&sql_query)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn create_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &SecuredViewInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<SecuredView, RowSecurityError> {
+    let nombre = input.nombre.trim();
+    full_view_name(nombre)?;
+    let objeto = input.objeto_origen.trim();
+    quote_identifier(objeto)?;
+    if input.filtros.is_empty() {
+        return Err(RowSecurityError::Validation("Indique al menos un filtro por concepto".to_string()));
+    }
+    let (existe,): (Option<i32>,) = sqlx::query_as("SELECT OBJECT_ID(@p1)")
+        .bind(objeto)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if existe.is_none() {
+        return Err(RowSecurityError::Validation(format!("El objeto '{}' no existe", objeto)));
+    }
+
+    let mut filtros = Vec::with_capacity(input.filtros.len());
+    for filtro in &input.filtros {
+        let concepto = row_security_logic::get_concept_by_code(pool, aplicativo_id, &filtro.concepto, sql_collate_clause).await?;
+        let columna = filtro.columna.trim();
+        quote_column(columna)?;
+        let (largo,): (Option<i16>,) = sqlx::query_as("SELECT CAST(COL_LENGTH(@p1, @p2) AS SMALLINT)")
+            .bind(objeto)
+            .bind(columna)
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?;
+        if largo.is_none() {
+            return Err(RowSecurityError::Validation(format!(
+                "La columna '{}' no existe en '{}'", columna, objeto
+            )));
+        }
+        filtros.push((concepto.concepto_id, columna.to_string()));
+    }
+
+    let (duplicada,): (i32,) = sqlx::query_as("SELECT COUNT(*) FROM riy.riy_vista_concepto WITH(NOLOCK) WHERE nombre = @p1")
+        .bind(nombre)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if duplicada > 0 {
+        return Err(RowSecurityError::Conflict(format!("Ya existe la vista '{}'", nombre)));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let (vista_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_vista_concepto (aplicativoID, nombre, objetoOrigen, autor, fechaCreacion)
+         OUTPUT INSERTED.vistaID
+         VALUES (@p1, @p2, @p3, @p4, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(nombre)
+    .bind(objeto)
+    .bind(autor)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    for (concepto_id, columna) in &filtros {
+        sqlx::query(
+            "INSERT INTO riy.riy_vista_concepto_filtro (vistaID, conceptoID, columna) VALUES (@p1, @p2, @p3)"
+        )
+        .bind(vista_id)
+        .bind(concepto_id)
+        .bind(columna)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await
+}
+
+/// Elimina el registro y, si fue generada, la vista de la base.
+pub async fn delete_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(), RowSecurityError> {
+    let vista = get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    let nombre_completo = full_view_name(&vista.nombre)?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(&format!("DROP VIEW IF EXISTS {}", nombre_completo))
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_vista_concepto_filtro WHERE vistaID = @p1")
+        .bind(vista_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM riy.riy_vista_concepto WHERE vistaID = @p1")
+        .bind(vista_id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+/// Script `CREATE OR ALTER VIEW`. Función pura sobre el objeto, los filtros
+/// (conceptoID, columna) y la cláusula de collation.
+pub fn build_view_script(
+    nombre: &str,
+    objeto_origen: &str,
+    filtros: &[(i32, String)],
+    sql_collate_clause: &str,
+) -> Result<String, RowSecurityError> {
+    let nombre_completo = full_view_name(nombre)?;
+    let objeto = quote_identifier(objeto_origen)?;
+    let mut predicados = Vec::with_capacity(filtros.len());
+    for (concepto_id, columna) in filtros {
+        let columna = quote_column(columna)?;
+        predicados.push(format!(
+            "(EXISTS (SELECT 1 FROM riy.riy_concepto c WHERE c.conceptoID = {id} AND c.activo = 0)
+        OR EXISTS (SELECT 1 FROM riy.riy_concepto_valor_usuario cv
+                    WHERE cv.conceptoID = {id}
+                      AND cv.usuarioID = CAST(SESSION_CONTEXT(N'{clave}') AS INT)
+                      AND (cv.valor = '{todos}' OR cv.valor {c} = CAST(t.{col} AS VARCHAR(100)) {c})))",
+            id = concepto_id,
+            clave = SESSION_KEY_USUARIO,
+            todos = VALOR_TODOS,
+            col = columna,
+            c = sql_collate_clause
+        ));
+    }
+    Ok(format!(
+        "CREATE OR ALTER VIEW {} AS\nSELECT t.*\n  FROM {} t\n WHERE {}",
+        nombre_completo,
+        objeto,
+        predicados.join("\n   AND ")
+    ))
+}
+
+pub async fn view_script(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<SecuredViewScript, RowSecurityError> {
+    let vista = get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    let filtros: Vec<(i32, String)> = view_filters(pool, vista_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .map(|(concepto_id, filtro)| (concepto_id, filtro.columna))
+        .collect();
+    Ok(SecuredViewScript {
+        vista_id,
+        nombre_completo: full_view_name(&vista.nombre)?,
+        script: build_view_script(&vista.nombre, &vista.objeto_origen, &filtros, sql_collate_clause)?,
+        generada: false,
+    })
+}
+
+/// Crea o actualiza la vista en la base y registra la fecha de generación.
+pub async fn generate_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<SecuredViewScript, RowSecurityError> {
+    let mut script = view_script(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    sqlx::query(&script.script)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("UPDATE riy.riy_vista_concepto SET fechaGeneracion = GETDATE() WHERE vistaID = @p1")
+        .bind(vista_id)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    script.generada = true;
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLATE: &str = "COLLATE SQL_Latin1_General_CP1_CI_AS";
+
+    #[test]
+    fn genera_un_exists_por_filtro_con_el_usuario_de_session_context() {
+        let filtros = vec![(7, "Sucursal".to_string()), (8, "Almacen".to_string())];
+
+        let script = build_view_script("VentasSeguras", "dbo.Ventas", &filtros, COLLATE).unwrap();
+
+        assert!(script.starts_with("CREATE OR ALTER VIEW [riy_sxf].[VentasSeguras] AS\nSELECT t.*\n  FROM [dbo].[Ventas] t\n WHERE "));
+        for (id, columna) in [(7, "Sucursal"), (8, "Almacen")] {
+            assert!(script.contains(&format!("WHERE c.conceptoID = {} AND c.activo = 0", id)));
+            assert!(script.contains(&format!("WHERE cv.conceptoID = {}\n", id)));
+            assert!(script.contains(&format!(
+                "AND (cv.valor = '{}' OR cv.valor {c} = CAST(t.[{}] AS VARCHAR(100)) {c})",
+                VALOR_TODOS,
+                columna,
+                c = COLLATE
+            )));
+        }
+        assert_eq!(
+            script.matches(&format!("cv.usuarioID = CAST(SESSION_CONTEXT(N'{}') AS INT)", SESSION_KEY_USUARIO)).count(),
+            2
+        );
+        assert_eq!(script.matches("\n   AND ").count(), 1);
+    }
+
+    #[test]
+    fn rechaza_nombres_invalidos() {
+        let filtros = vec![(7, "Sucursal".to_string())];
+
+        assert!(build_view_script("riy_sxf.Ventas", "dbo.Ventas", &filtros, COLLATE).is_err());
+        assert!(build_view_script("Ventas", "dbo.Ventas; DROP TABLE x", &filtros, COLLATE).is_err());
+        assert!(build_view_script("Ventas", "dbo.Ventas", &[(7, "t.Sucursal".to_string())], COLLATE).is_err());
+    }
+}
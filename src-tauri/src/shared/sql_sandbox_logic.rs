@@ -0,0 +1,619 @@
+// src/shared/sql_sandbox_logic.rs
+/*
+Ejecutor de SQL definido por usuarios (consultas guardadas y similares).
+
+El SQL del usuario nunca corre en el pool principal, que tiene acceso a las tablas de
+seguridad. Antes de ejecutarlo:
+  1. se analiza léxicamente (comentarios, literales e identificadores entre corchetes o
+     comillas) y se rechaza lo que no sea un único SELECT (sin WITH: el ejecutor lo
+     envuelve como tabla derivada, donde una CTE no es válida), cualquier separador de
+     lotes (`;` intermedio, GO), palabras clave de escritura o ejecución y toda
+     referencia al esquema riy,
+  2. se rechaza si lee directamente un objeto con seguridad por fila (registrado en
+     riy_vista_concepto): esos datos solo se leen a través de su vista de riy_sxf, que
+     filtra por el usuario aunque la consulta no declare objeto de origen ni filtros,
+  3. se le aplica la seguridad por fila con el ejecutor central (secured_query_logic),
+     resolviendo los valores permitidos con el pool principal,
+  4. se ejecuta con el pool de solo lectura (READONLY_DATABASE_URL), con límite de filas
+     y de tiempo.
+
+Si se agota el tiempo, la conexión no vuelve al pool: se separa y se cierra
+(secured_query_logic::SessionConnection), y SQL Server aborta la sentencia al perder el cliente.
+
+El login de solo lectura no es miembro de db_datareader: solo lee lo que se le concede. Las
+vistas de riy_sxf (dueño dbo, por encadenamiento de propiedad leen las tablas de riy y del ERP),
+la tabla de valores que usa el predicado EXISTS para listas largas y, uno por uno, los objetos
+del ERP sin seguridad por fila que se puedan consultar. Los objetos con seguridad por fila
+(riy_vista_concepto.objetoOrigen) no se conceden; el DENY cubre permisos heredados de otros roles.
+Las entidades de las vistas de usuario (riy_entidad_erp) sobre esos objetos deben apuntar a
+su vista de riy_sxf.
+
+CREATE LOGIN riy_lectura WITH PASSWORD = '...';
+CREATE USER riy_lectura FOR LOGIN riy_lectura;
+GRANT SELECT ON SCHEMA::riy_sxf TO riy_lectura;
+GRANT SELECT ON riy.riy_concepto_valor_usuario TO riy_lectura;
+-- objetos del ERP sin seguridad por fila, por ejemplo:
+GRANT SELECT ON dbo.Art TO riy_lectura;
+-- objetos con seguridad por fila, por ejemplo:
+DENY SELECT ON dbo.Venta TO riy_lectura;
+*/
+
+use std::fmt;
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use sqlx::mssql::MssqlRow;
+use sqlx::{Column, Executor, Mssql, Pool, Row, TypeInfo};
+
+use crate::config::SandboxConfig;
+use crate::db;
+use crate::export_logic::ExportWriter;
+use crate::row_security_logic::RowSecurityError;
+use crate::secured_query_logic;
+use crate::secured_query_models::{SecuredQuery, SqlValue};
+use crate::secured_view_logic;
+use crate::sql_sandbox_models::{SandboxColumn, SandboxResult};
+
+/// Palabras clave que no pueden aparecer en una consulta de solo lectura.
+const PALABRAS_PROHIBIDAS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "TRUNCATE", "DROP", "ALTER", "CREATE", "GRANT",
+    "REVOKE", "DENY", "EXEC", "EXECUTE", "INTO", "DECLARE", "SET", "USE", "GO", "BACKUP",
+    "RESTORE", "DBCC", "KILL", "SHUTDOWN", "WAITFOR", "BULK", "OPENROWSET", "OPENQUERY",
+    "OPENDATASOURCE", "OPENXML", "RECONFIGURE", "CHECKPOINT",
+];
+
+/// Esquema de las tablas de seguridad, vedado al SQL de usuarios.
+const ESQUEMA_SEGURIDAD: &str = "riy";
+
+#[derive(Debug)]
+pub enum SandboxError {
+    Validation(String),
+    /// El sandbox no está configurado (READONLY_DATABASE_URL).
+    Unavailable(String),
+    Timeout(String),
+    Database(String),
+}
+
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SandboxError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            SandboxError::Unavailable(msg) => write!(f, "No disponible: {}", msg),
+            SandboxError::Timeout(msg) => write!(f, "Tiempo agotado: {}", msg),
+            SandboxError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SandboxError {}
+
+impl From<RowSecurityError> for SandboxError {
+    fn from(e: RowSecurityError) -> Self {
+        match e {
+            RowSecurityError::Validation(msg)
+            | RowSecurityError::NotFound(msg)
+            | RowSecurityError::Conflict(msg) => SandboxError::Validation(msg),
+            RowSecurityError::Database(msg) => SandboxError::Database(msg),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// Palabra sin delimitar, en mayúsculas.
+    Palabra(String),
+    /// Identificador entre corchetes o comillas dobles.
+    Delimitado(String),
+    Simbolo(char),
+}
+
+fn invalid(msg: &str) -> SandboxError {
+    SandboxError::Validation(msg.to_string())
+}
+
+/// Tokeniza el SQL descartando comentarios y literales de texto.
+fn tokenize(sql: &str) -> Result<Vec<Token>, SandboxError> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let siguiente = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && siguiente == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && siguiente == Some('*') {
+            // T-SQL admite comentarios de bloque anidados
+            let mut nivel = 0;
+            loop {
+                match (chars.get(i), chars.get(i + 1)) {
+                    (Some('/'), Some('*')) => { nivel += 1; i += 2; }
+                    (Some('*'), Some('/')) => {
+                        nivel -= 1;
+                        i += 2;
+                        if nivel == 0 { break; }
+                    }
+                    (Some(_), _) => i += 1,
+                    (None, _) => return Err(invalid("Comentario sin cerrar")),
+                }
+            }
+        } else if c == '\'' || ((c == 'N' || c == 'n') && siguiente == Some('\'')) {
+            i += if c == '\'' { 1 } else { 2 };
+            loop {
+                match (chars.get(i), chars.get(i + 1)) {
+                    (Some('\''), Some('\'')) => i += 2,
+                    (Some('\''), _) => { i += 1; break; }
+                    (Some(_), _) => i += 1,
+                    (None, _) => return Err(invalid("Literal de texto sin cerrar")),
+                }
+            }
+            tokens.push(Token::Simbolo('\''));
+        } else if c == '[' || c == '"' {
+            let cierre = if c == '[' { ']' } else { '"' };
+            let mut nombre = String::new();
+            i += 1;
+            loop {
+                match (chars.get(i), chars.get(i + 1)) {
+                    (Some(a), Some(b)) if *a == cierre && *b == cierre => { nombre.push(cierre); i += 2; }
+                    (Some(a), _) if *a == cierre => { i += 1; break; }
+                    (Some(a), _) => { nombre.push(*a); i += 1; }
+                    (None, _) => return Err(invalid("Identificador sin cerrar")),
+                }
+            }
+            tokens.push(Token::Delimitado(nombre));
+        } else if c.is_alphanumeric() || c == '_' || c == '@' || c == '#' {
+            let inicio = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '@' | '#' | '$')) {
+                i += 1;
+            }
+            tokens.push(Token::Palabra(chars[inicio..i].iter().collect::<String>().to_uppercase()));
+        } else {
+            tokens.push(Token::Simbolo(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Valida que el SQL sea un único SELECT de solo lectura sin referencias al esquema riy.
+pub fn validate_sql(sql: &str) -> Result<(), SandboxError> {
+    let mut tokens = tokenize(sql)?;
+    while tokens.last() == Some(&Token::Simbolo(';')) {
+        tokens.pop();
+    }
+    match tokens.first() {
+        Some(Token::Palabra(p)) if p == "SELECT" => {}
+        Some(Token::Palabra(p)) if p == "WITH" => {
+            return Err(invalid("La consulta no puede usar WITH (CTE): escríbala como tabla derivada"));
+        }
+        _ => return Err(invalid("La consulta debe ser un SELECT")),
+    }
+    if tokens.contains(&Token::Simbolo(';')) {
+        return Err(invalid("La consulta no puede tener varias sentencias"));
+    }
+    for token in &tokens {
+        match token {
+            Token::Palabra(p) if PALABRAS_PROHIBIDAS.contains(&p.as_str()) => {
+                return Err(SandboxError::Validation(format!("La consulta no puede usar {}", p)));
+            }
+            Token::Palabra(p) if p.eq_ignore_ascii_case(ESQUEMA_SEGURIDAD) => {
+                return Err(invalid("La consulta no puede leer el esquema riy"));
+            }
+            Token::Delimitado(nombre) if nombre.trim().eq_ignore_ascii_case(ESQUEMA_SEGURIDAD) => {
+                return Err(invalid("La consulta no puede leer el esquema riy"));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Nombres de objeto de la consulta (`a`, `a.b`, `a.b.c`), en mayúsculas y por partes.
+fn referenced_names(sql: &str) -> Result<Vec<Vec<String>>, SandboxError> {
+    let tokens = tokenize(sql)?;
+    let parte = |token: &Token| match token {
+        Token::Palabra(p) => Some(p.clone()),
+        Token::Delimitado(d) => Some(d.trim().to_uppercase()),
+        Token::Simbolo(_) => None,
+    };
+    let mut nombres = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(primera) = parte(&tokens[i]) {
+            let mut partes = vec![primera];
+            while tokens.get(i + 1) == Some(&Token::Simbolo('.')) {
+                match tokens.get(i + 2).and_then(parte) {
+                    Some(siguiente) => {
+                        partes.push(siguiente);
+                        i += 2;
+                    }
+                    None => break,
+                }
+            }
+            nombres.push(partes);
+        }
+        i += 1;
+    }
+    Ok(nombres)
+}
+
+/// Esquema y nombre de un objeto en mayúsculas; sin esquema se asume dbo.
+fn object_key(objeto: &str) -> (String, String) {
+    let partes: Vec<String> = objeto
+        .split('.')
+        .map(|p| p.trim().trim_start_matches('[').trim_end_matches(']').to_uppercase())
+        .collect();
+    match partes.as_slice() {
+        [.., esquema, nombre] => (esquema.clone(), nombre.clone()),
+        [nombre] => ("DBO".to_string(), nombre.clone()),
+        [] => (String::new(), String::new()),
+    }
+}
+
+/// Rechaza la consulta si nombra un objeto con seguridad por fila en lugar de su vista.
+/// Un nombre sin esquema cuenta como dbo, así que también se rechaza una columna o alias
+/// que se llame igual que una tabla protegida de dbo.
+async fn check_secured_objects(
+    pool: &Pool<Mssql>,
+    sql: &str,
+    sql_collate_clause: &str,
+) -> Result<(), SandboxError> {
+    let protegidos = secured_view_logic::secured_objects(pool, sql_collate_clause).await?;
+    if protegidos.is_empty() {
+        return Ok(());
+    }
+    for partes in referenced_names(sql)? {
+        let clave = match partes.as_slice() {
+            [nombre] => ("DBO".to_string(), nombre.clone()),
+            [.., esquema, nombre] => (esquema.clone(), nombre.clone()),
+            [] => continue,
+        };
+        if let Some((objeto, vista)) = protegidos.iter().find(|(objeto, _)| object_key(objeto) == clave) {
+            return Err(SandboxError::Validation(format!(
+                "La consulta lee '{}', que tiene seguridad por fila: use la vista {}.{}",
+                objeto,
+                secured_view_logic::ESQUEMA_VISTAS,
+                vista
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Tipo para el frontend a partir del tipo de SQL Server.
+fn column_kind(tipo_sql: &str) -> &'static str {
+    match tipo_sql.to_ascii_uppercase().as_str() {
+        "TINYINT" | "SMALLINT" | "INT" | "BIGINT" | "INTN" => "entero",
+        "DECIMAL" | "NUMERIC" | "MONEY" | "SMALLMONEY" | "FLOAT" | "REAL" | "FLOATN" | "DECIMALN" | "NUMERICN" | "MONEYN" => "decimal",
+        "BIT" | "BITN" => "booleano",
+        "DATE" | "TIME" | "DATETIME" | "DATETIME2" | "SMALLDATETIME" | "DATETIMEOFFSET" | "DATETIMEN" => "fecha",
+        "CHAR" | "VARCHAR" | "NCHAR" | "NVARCHAR" | "TEXT" | "NTEXT" | "UNIQUEIDENTIFIER" | "XML" => "texto",
+        "BINARY" | "VARBINARY" | "IMAGE" => "binario",
+        _ => "otro",
+    }
+}
+
+fn to_column<C: Column<Database = Mssql>>(columna: &C) -> SandboxColumn {
+    let tipo_sql = columna.type_info().name().to_string();
+    SandboxColumn {
+        nombre: columna.name().to_string(),
+        tipo: column_kind(&tipo_sql).to_string(),
+        tipo_sql,
+    }
+}
+
+/// Pool de solo lectura y límites del sandbox.
+#[derive(Clone)]
+pub struct SqlSandbox {
+    pool: Pool<Mssql>,
+    max_rows: i64,
+    max_export_rows: i64,
+    timeout: Duration,
+}
+
+impl SqlSandbox {
+    /// Conecta el pool de solo lectura; `None` si el sandbox no está configurado.
+    pub async fn connect(config: &SandboxConfig) -> Result<Option<SqlSandbox>, String> {
+        if !config.is_enabled() {
+            println!("[SANDBOX] READONLY_DATABASE_URL no definida; la ejecución de SQL de usuarios queda deshabilitada.");
+            return Ok(None);
+        }
+        let pool = db::connect_db(&config.database_url).await?;
+        Ok(Some(SqlSandbox {
+            pool,
+            max_rows: config.max_rows.max(1),
+            max_export_rows: config.max_export_rows.max(1),
+            timeout: Duration::from_secs(config.timeout_seconds.max(1)),
+        }))
+    }
+
+    pub fn max_rows(&self) -> i64 {
+        self.max_rows
+    }
+
+    /// El menor entre el tiempo pedido y el máximo del sandbox.
+    fn effective_timeout(&self, timeout_segundos: Option<u64>) -> Duration {
+        timeout_segundos
+            .map(|s| Duration::from_secs(s.max(1)).min(self.timeout))
+            .unwrap_or(self.timeout)
+    }
+
+    /// Corta la espera a los `timeout` segundos. El futuro se descarta y con él la conexión
+    /// de la consulta, que se cierra sin volver al pool (ver SessionConnection).
+    async fn with_timeout<T, F>(&self, timeout: Duration, future: F) -> Result<T, SandboxError>
+    where
+        F: std::future::Future<Output = Result<T, SandboxError>>,
+    {
+        tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| SandboxError::Timeout(format!("La consulta superó {} segundos", timeout.as_secs())))?
+    }
+
+    /// Ejecuta la consulta con seguridad por fila sobre el pool de solo lectura.
+    /// `pool` (el principal) solo se usa para resolver los valores permitidos.
+    pub async fn execute(
+        &self,
+        pool: &Pool<Mssql>,
+        aplicativo_id: i32,
+        usuario_id: i32,
+        query: &SecuredQuery,
+        timeout_segundos: Option<u64>,
+        sql_collate_clause: &str,
+    ) -> Result<SandboxResult, SandboxError> {
+        validate_sql(&query.sql)?;
+        check_secured_objects(pool, &query.sql, sql_collate_clause).await?;
+        self.execute_unchecked(pool, aplicativo_id, usuario_id, query, timeout_segundos, sql_collate_clause).await
+    }
+
+    /// Igual que execute, sin validar el SQL: solo para SQL que arma el backend a partir de
+    /// definiciones estructuradas (user_view_logic), nunca para texto del usuario.
+    pub(crate) async fn execute_unchecked(
+        &self,
+        pool: &Pool<Mssql>,
+        aplicativo_id: i32,
+        usuario_id: i32,
+        query: &SecuredQuery,
+        timeout_segundos: Option<u64>,
+        sql_collate_clause: &str,
+    ) -> Result<SandboxResult, SandboxError> {
+        let limite = query.limit.unwrap_or(self.max_rows).clamp(1, self.max_rows);
+        // Una fila de más para saber si el resultado quedó truncado
+        let limitada = SecuredQuery { limit: Some(limite + 1), ..query.clone() };
+
+        let permitidos = secured_query_logic::resolve_filters(pool, aplicativo_id, usuario_id, &limitada, sql_collate_clause).await?;
+        let (sql, params) = secured_query_logic::build_secured_sql(&limitada, &permitidos, usuario_id, sql_collate_clause)?;
+
+        let timeout = self.effective_timeout(timeout_segundos);
+        let mut rows = self.with_timeout(timeout, async {
+            Ok(secured_query_logic::fetch_with_session_context(&self.pool, usuario_id, &sql, &params).await?)
+        }).await?;
+
+        let truncado = rows.len() as i64 > limite;
+        rows.truncate(limite as usize);
+        let columnas = match rows.first() {
+            Some(row) => row.columns().iter().map(to_column).collect(),
+            None => self.describe_columns_within(&sql, timeout).await?,
+        };
+        Ok(SandboxResult {
+            columnas,
+            filas: rows.iter().map(secured_query_logic::row_to_json).collect(),
+            truncado,
+        })
+    }
+
+    /// Total de filas visibles para el usuario, con el mismo tiempo máximo.
+    pub async fn count(
+        &self,
+        pool: &Pool<Mssql>,
+        aplicativo_id: i32,
+        usuario_id: i32,
+        query: &SecuredQuery,
+        timeout_segundos: Option<u64>,
+        sql_collate_clause: &str,
+    ) -> Result<i64, SandboxError> {
+        validate_sql(&query.sql)?;
+        check_secured_objects(pool, &query.sql, sql_collate_clause).await?;
+        self.count_unchecked(pool, aplicativo_id, usuario_id, query, timeout_segundos, sql_collate_clause).await
+    }
+
+    /// Igual que count, sin validar el SQL (ver execute_unchecked).
+    pub(crate) async fn count_unchecked(
+        &self,
+        pool: &Pool<Mssql>,
+        aplicativo_id: i32,
+        usuario_id: i32,
+        query: &SecuredQuery,
+        timeout_segundos: Option<u64>,
+        sql_collate_clause: &str,
+    ) -> Result<i64, SandboxError> {
+        let sin_paginar = SecuredQuery {
+            order_by: Vec::new(),
+            offset: None,
+            limit: None,
+            ..query.clone()
+        };
+        let permitidos = secured_query_logic::resolve_filters(pool, aplicativo_id, usuario_id, &sin_paginar, sql_collate_clause).await?;
+        let (sql, params) = secured_query_logic::build_secured_sql(&sin_paginar, &permitidos, usuario_id, sql_collate_clause)?;
+        let sql = format!("SELECT COUNT_BIG(*) FROM ({}) AS total", sql);
+
+        let timeout = self.effective_timeout(timeout_segundos);
+        let rows: Vec<MssqlRow> = self.with_timeout(timeout, async {
+            Ok(secured_query_logic::fetch_with_session_context(&self.pool, usuario_id, &sql, &params).await?)
+        }).await?;
+        rows.first()
+            .map(|row| row.try_get::<i64, _>(0).map_err(|e| SandboxError::Database(e.to_string())))
+            .unwrap_or(Ok(0))
+    }
+
+    /// Prepara una exportación: valida, resuelve la seguridad por fila y describe las
+    /// columnas antes de enviar nada, para poder responder con error si algo falla.
+    /// El resultado se limita a SANDBOX_MAX_EXPORT_ROWS filas.
+    pub async fn prepare_export(
+        &self,
+        pool: &Pool<Mssql>,
+        aplicativo_id: i32,
+        usuario_id: i32,
+        query: &SecuredQuery,
+        timeout_segundos: Option<u64>,
+        sql_collate_clause: &str,
+    ) -> Result<PreparedExport, SandboxError> {
+        validate_sql(&query.sql)?;
+        check_secured_objects(pool, &query.sql, sql_collate_clause).await?;
+        let limitada = SecuredQuery {
+            offset: None,
+            limit: Some(self.max_export_rows),
+            ..query.clone()
+        };
+        let permitidos = secured_query_logic::resolve_filters(pool, aplicativo_id, usuario_id, &limitada, sql_collate_clause).await?;
+        let (sql, params) = secured_query_logic::build_secured_sql(&limitada, &permitidos, usuario_id, sql_collate_clause)?;
+        let timeout = self.effective_timeout(timeout_segundos);
+        let columnas = self.describe_columns_within(&sql, timeout).await?;
+        Ok(PreparedExport {
+            sandbox: self.clone(),
+            usuario_id,
+            sql,
+            params,
+            columnas,
+            timeout,
+        })
+    }
+
+    /// Columnas del primer resultado de `sql`, sin ejecutarlo, con el tiempo máximo del sandbox.
+    pub async fn describe_columns(&self, sql: &str) -> Result<Vec<SandboxColumn>, SandboxError> {
+        self.describe_columns_within(sql, self.timeout).await
+    }
+
+    /// describe_columns con el tiempo máximo indicado. Si se agota, la conexión se cierra en
+    /// lugar de volver al pool con la descripción a medias.
+    async fn describe_columns_within(&self, sql: &str, timeout: Duration) -> Result<Vec<SandboxColumn>, SandboxError> {
+        let mut conn = self.pool.acquire().await.map_err(|e| SandboxError::Database(e.to_string()))?;
+        match tokio::time::timeout(timeout, (&mut *conn).describe(sql)).await {
+            Ok(descripcion) => {
+                let descripcion = descripcion.map_err(|e| SandboxError::Database(e.to_string()))?;
+                Ok(descripcion.columns().iter().map(to_column).collect())
+            }
+            Err(_) => {
+                drop(conn.detach());
+                Err(SandboxError::Timeout(format!("La consulta superó {} segundos", timeout.as_secs())))
+            }
+        }
+    }
+}
+
+/// Exportación lista para ejecutarse en la tarea del productor (ver export_logic).
+pub struct PreparedExport {
+    sandbox: SqlSandbox,
+    usuario_id: i32,
+    sql: String,
+    params: Vec<SqlValue>,
+    pub columnas: Vec<SandboxColumn>,
+    /// Tiempo máximo de espera por cada fila, no por la descarga completa.
+    timeout: Duration,
+}
+
+impl PreparedExport {
+    /// Recorre el resultado fila a fila y lo escribe; no acumula filas en memoria.
+    pub async fn write_to(self, writer: &mut ExportWriter) -> Result<(), String> {
+        let mut sesion = secured_query_logic::SessionConnection::acquire(&self.sandbox.pool, self.usuario_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        {
+            let mut filas = secured_query_logic::bind_params(sqlx::query(&self.sql), &self.params).fetch(sesion.conn());
+            loop {
+                let siguiente = tokio::time::timeout(self.timeout, filas.try_next())
+                    .await
+                    .map_err(|_| format!("La consulta no devolvió filas en {} segundos", self.timeout.as_secs()))?
+                    .map_err(|e| e.to_string())?;
+                match siguiente {
+                    Some(row) => writer.write_row(&secured_query_logic::row_to_json(&row)).await?,
+                    None => break,
+                }
+            }
+        }
+        sesion.release().await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rechazada(sql: &str) -> bool {
+        matches!(validate_sql(sql), Err(SandboxError::Validation(_)))
+    }
+
+    #[test]
+    fn rechaza_lo_que_no_es_un_unico_select_de_lectura() {
+        let casos = [
+            // Comentarios: no ocultan lo que sigue y deben cerrarse
+            "SELECT 1 --\nDROP TABLE dbo.Art",
+            "SELECT 1 /* comentario */ DELETE FROM dbo.Art",
+            "SELECT 1 /* /* anidado */ */ DELETE FROM dbo.Art",
+            "SELECT 1 /* /* anidado */ DELETE FROM dbo.Art",
+            "SELECT 1 /* sin cerrar",
+            // Literales N'..': el contenido se descarta, lo de fuera no
+            "SELECT N'it''s' AS a; DELETE FROM dbo.Art",
+            "SELECT N'x' AS a FROM riy.riy_usuario",
+            "SELECT N'sin cerrar",
+            // Esquema riy con o sin delimitar
+            "SELECT * FROM riy.riy_usuario",
+            "SELECT * FROM [riy].[riy_usuario]",
+            "SELECT * FROM [ riy ].[riy_usuario]",
+            "SELECT * FROM \"riy\".\"riy_usuario\"",
+            "SELECT * FROM [riy_usuario",
+            // Separadores de lote y varias sentencias
+            "SELECT 1\nGO\nSELECT 2",
+            "SELECT 1; SELECT 2",
+            "SELECT 1;; SELECT 2",
+            // Escritura y ejecución
+            "SELECT * INTO #copia FROM dbo.Art",
+            "SELECT 1 EXEC sp_who",
+            "EXEC sp_who",
+            "SELECT 1 EXECUTE ('DROP TABLE dbo.Art')",
+            "SELECT 1 WAITFOR DELAY '00:01:00'",
+            "SELECT * FROM OPENROWSET('SQLNCLI', 'x', 'SELECT 1')",
+            "DELETE FROM dbo.Art",
+            // CTE y texto que no es SELECT
+            "WITH x AS (SELECT 1 AS a) SELECT * FROM x",
+            "",
+            "-- solo un comentario",
+        ];
+        for sql in casos {
+            assert!(rechazada(sql), "debió rechazarse: {}", sql);
+        }
+    }
+
+    #[test]
+    fn acepta_selects_de_solo_lectura() {
+        let casos = [
+            "SELECT * FROM dbo.Art",
+            "select Articulo, Descripcion1 from dbo.Art where Estatus = 'ALTA';",
+            "SELECT a.Articulo, 'DELETE' AS texto FROM dbo.Art a WHERE a.Descripcion1 LIKE N'%riy%'",
+            "SELECT [Update], \"Exec\" FROM riy_sxf.Venta -- filtrada por usuario",
+            "SELECT x FROM (SELECT 1 AS x) d /* /* ; DROP TABLE dbo.Art */ */",
+            "SELECT TOP 10 v.Mov, v.Importe FROM riy_sxf.Venta v JOIN dbo.Art a ON a.Articulo = v.Articulo ORDER BY v.Importe DESC",
+        ];
+        for sql in casos {
+            assert!(validate_sql(sql).is_ok(), "debió aceptarse: {}", sql);
+        }
+    }
+
+    #[test]
+    fn los_nombres_referenciados_ignoran_literales_y_comentarios() {
+        let nombres = referenced_names("SELECT v.Mov FROM [dbo].[Venta] v /* dbo.Art */ WHERE v.Mov = 'dbo.Cte'").unwrap();
+
+        assert!(nombres.contains(&vec!["DBO".to_string(), "VENTA".to_string()]));
+        assert!(nombres.contains(&vec!["V".to_string(), "MOV".to_string()]));
+        assert!(!nombres.iter().any(|n| n.contains(&"ART".to_string()) || n.contains(&"CTE".to_string())));
+    }
+
+    #[test]
+    fn object_key_asume_dbo_sin_esquema() {
+        assert_eq!(object_key("Venta"), ("DBO".to_string(), "VENTA".to_string()));
+        assert_eq!(object_key("[ventas].[Venta]"), ("VENTAS".to_string(), "VENTA".to_string()));
+        assert_eq!(object_key("erp.dbo.Venta"), ("DBO".to_string(), "VENTA".to_string()));
+    }
+}
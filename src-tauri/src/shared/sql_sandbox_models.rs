@@ -0,0 +1,25 @@
+// src/shared/sql_sandbox_models.rs
+// Modelos del ejecutor de SQL de solo lectura (sandbox).
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Columna del resultado, para que la grilla sepa cómo mostrarla.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxColumn {
+    pub nombre: String,
+    /// Tipo informado por SQL Server (`INT`, `NVARCHAR`, `DATETIME`...).
+    pub tipo_sql: String,
+    /// Tipo para el frontend: texto, entero, decimal, fecha, booleano, binario u otro.
+    pub tipo: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxResult {
+    pub columnas: Vec<SandboxColumn>,
+    pub filas: Vec<Map<String, Value>>,
+    /// Hay más filas que el límite aplicado.
+    pub truncado: bool,
+}
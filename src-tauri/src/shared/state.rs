@@ -2,11 +2,14 @@
 
 use sqlx::{Pool, Mssql};
 use tokio::sync::Mutex;
-use std::collections::HashSet;
 // librería especializada que gestione el cliente JWKS de forma nativa.
 //use reqwest::Client; // Cliente HTTP
 use std::sync::Arc;
 
+use crate::aplicativo_logic::{self, AplicativoCache};
+use crate::config::HelpConfig;
+use crate::sql_sandbox_logic::SqlSandbox;
+
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: Pool<Mssql>,
@@ -23,7 +26,6 @@ pub struct AppState {
     // ⭐ NUEVOS CAMPOS MSAL ⭐
     pub msal_client_id: String,
     pub msal_audience_uri: String,
-    pub whitelisted_domains: HashSet<String>,
 
     // ⭐️ CORRECCIÓN NECESARIA: AÑADE ESTOS CAMPOS ⭐️
     pub google_client_id: String,
@@ -32,4 +34,38 @@ pub struct AppState {
     // ✅ AÑADIR:
     pub http_client: Arc<reqwest::Client>, // Cliente HTTP
     pub msal_jwks_url: String,           // URL para descargar las claves
+
+    // Token compartido con Entra ID para el aprovisionamiento SCIM (vacío = deshabilitado)
+    pub scim_bearer_token: String,
+
+    // Clave AES-256 (hex) con la que se cifran los secretos TOTP
+    pub mfa_encryption_key: String,
+
+    // Pool de solo lectura para el SQL de usuarios (None = READONLY_DATABASE_URL no definida)
+    pub sql_sandbox: Option<SqlSandbox>,
+
+    // Almacenamiento de los adjuntos de las ayudas (HELP_ATTACHMENTS_DIR)
+    pub help_config: HelpConfig,
+
+    // Caché código -> aplicativoID para elegir el aplicativo por solicitud (X-Aplicativo o /a/{codigo})
+    pub aplicativos: AplicativoCache,
+}
+
+impl AppState {
+    /// Aplicativo de la solicitud en curso: el elegido por SelectAplicativo o, si no eligió
+    /// ninguno, el del servidor (APLICATIVO_ID).
+    pub async fn current_aplicativo_id(&self) -> i32 {
+        match aplicativo_logic::selected_aplicativo() {
+            Some(actual) => actual.aplicativo_id,
+            None => *self.aplicativo_id.lock().await,
+        }
+    }
+
+    /// Código del aplicativo de la solicitud en curso (ver current_aplicativo_id).
+    pub fn current_aplicativo(&self) -> String {
+        match aplicativo_logic::selected_aplicativo() {
+            Some(actual) => actual.aplicativo,
+            None => self.aplicativo.clone(),
+        }
+    }
 }
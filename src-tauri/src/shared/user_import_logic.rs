@@ -0,0 +1,505 @@
+// src/shared/user_import_logic.rs
+/*
+Importación masiva (CSV/XLSX) de usuarios a riy.riy_usuario en dos fases
+(vista previa y confirmación) y exportación de usuarios con sus roles.
+
+Columnas reconocidas (la cabecera no distingue mayúsculas):
+    usuario (obligatoria), correo (obligatoria), nombre, estado, roles
+La columna `roles` admite varios nombres de rol separados por ';'.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose, Engine as _};
+use calamine::{open_workbook_from_rs, Reader, Xlsx};
+use lazy_static::lazy_static;
+use rust_xlsxwriter::Workbook;
+use futures::TryStreamExt;
+use serde_json::{Map, Value};
+use sqlx::{Pool, Mssql, Row};
+
+use crate::export_logic::ExportWriter;
+use crate::export_models::ExportColumn;
+use crate::role_logic;
+use crate::user_repository::ESTADO_VIGENTE;
+use crate::user_import_models::{
+    UserFileFormat, UserImportPreview, UserImportRow, UserImportResult,
+};
+use crate::utils::is_valid_email;
+
+/// Tiempo durante el que una vista previa puede confirmarse.
+const PREVIEW_TTL_MINUTES: u64 = 30;
+/// Máximo de filas aceptadas en un archivo.
+const MAX_IMPORT_ROWS: usize = 5000;
+/// Estado por defecto cuando el archivo no trae la columna `estado`.
+const ESTADO_POR_DEFECTO: &str = ESTADO_VIGENTE;
+
+/// Vista previa pendiente de confirmar.
+struct PendingImport {
+    aplicativo_id: i32,
+    autor: String,
+    filas: Vec<UserImportRow>,
+    creada: Instant,
+}
+
+lazy_static! {
+    static ref PENDING_IMPORTS: Mutex<HashMap<String, PendingImport>> = Mutex::new(HashMap::new());
+}
+
+/// Elimina las vistas previas vencidas.
+fn purge_expired(pending: &mut HashMap<String, PendingImport>) {
+    let ttl = Duration::from_secs(PREVIEW_TTL_MINUTES * 60);
+    pending.retain(|_, p| p.creada.elapsed() < ttl);
+}
+
+fn new_import_id() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+// -------------------------------------------------------------------------
+// LECTURA DE ARCHIVOS
+// -------------------------------------------------------------------------
+
+/// Convierte el archivo en filas `cabecera -> valor` (cabeceras en minúsculas).
+fn read_rows(format: UserFileFormat, bytes: &[u8]) -> Result<Vec<HashMap<String, String>>, String> {
+    let mut table: Vec<Vec<String>> = Vec::new();
+    match format {
+        UserFileFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .trim(csv::Trim::All)
+                .from_reader(bytes);
+            for record in reader.records() {
+                let record = record.map_err(|e| format!("Error al leer el CSV: {}", e))?;
+                table.push(record.iter().map(|v| v.to_string()).collect());
+            }
+        }
+        UserFileFormat::Xlsx => {
+            let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))
+                .map_err(|e| format!("Error al abrir el archivo XLSX: {}", e))?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or_else(|| "El archivo XLSX no tiene hojas.".to_string())?
+                .map_err(|e| format!("Error al leer la hoja del XLSX: {}", e))?;
+            for row in range.rows() {
+                table.push(row.iter().map(|c| c.to_string().trim().to_string()).collect());
+            }
+        }
+    }
+
+    let mut rows = table.into_iter();
+    let headers: Vec<String> = rows
+        .next()
+        .ok_or_else(|| "El archivo está vacío.".to_string())?
+        .into_iter()
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    for required in ["usuario", "correo"] {
+        if !headers.iter().any(|h| h == required) {
+            return Err(format!("Falta la columna obligatoria '{}'.", required));
+        }
+    }
+
+    let data: Vec<HashMap<String, String>> = rows
+        .filter(|r| r.iter().any(|v| !v.trim().is_empty()))
+        .map(|r| {
+            headers
+                .iter()
+                .cloned()
+                .zip(r.into_iter().chain(std::iter::repeat(String::new())))
+                .collect()
+        })
+        .collect();
+    if data.len() > MAX_IMPORT_ROWS {
+        return Err(format!("El archivo supera el máximo de {} filas.", MAX_IMPORT_ROWS));
+    }
+    Ok(data)
+}
+
+// -------------------------------------------------------------------------
+// FASE 1: VISTA PREVIA
+// -------------------------------------------------------------------------
+
+/// Interpreta y valida el archivo sin modificar la base de datos.
+/// Las filas quedan en memoria hasta su confirmación con `commit_user_import`.
+pub async fn preview_user_import(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    format: UserFileFormat,
+    content_base64: &str,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<UserImportPreview, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(content_base64.trim())
+        .map_err(|e| format!("El contenido no es Base64 válido: {}", e))?;
+    let raw_rows = read_rows(format, &bytes)?;
+
+    // Datos de referencia para la validación
+    let erp_query = format!(
+        "SELECT Usuario {0} as usuario, Nombre {0} as nombre FROM dbo.Usuario WITH(NOLOCK)",
+        sql_collate_clause
+    );
+    let erp_users: HashMap<String, Option<String>> = sqlx::query_as::<_, (String, Option<String>)>(&erp_query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios del ERP: {}", e))?
+        .into_iter()
+        .map(|(usuario, nombre)| (usuario.trim().to_lowercase(), nombre))
+        .collect();
+
+    let riy_query = format!(
+        "SELECT usuario {0} as usuario FROM riy.riy_usuario WITH(NOLOCK)",
+        sql_collate_clause
+    );
+    let riy_users: HashSet<String> = sqlx::query_as::<_, (String,)>(&riy_query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios de riy: {}", e))?
+        .into_iter()
+        .map(|(usuario,)| usuario.trim().to_lowercase())
+        .collect();
+
+    let roles: HashSet<String> = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .map(|r| r.rol.to_lowercase())
+        .collect();
+
+    let mut seen_in_file: HashSet<String> = HashSet::new();
+    let mut filas = Vec::with_capacity(raw_rows.len());
+
+    for (index, raw) in raw_rows.into_iter().enumerate() {
+        let get = |key: &str| raw.get(key).map(|v| v.trim().to_string()).unwrap_or_default();
+        let usuario = get("usuario");
+        let correo = get("correo").to_lowercase();
+        let estado = Some(get("estado")).filter(|e| !e.is_empty()).unwrap_or_else(|| ESTADO_POR_DEFECTO.to_string());
+        let row_roles: Vec<String> = get("roles")
+            .split(';')
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .collect();
+        let key = usuario.to_lowercase();
+        let mut errores = Vec::new();
+
+        if usuario.is_empty() {
+            errores.push("El usuario es obligatorio.".to_string());
+        } else {
+            if !seen_in_file.insert(key.clone()) {
+                errores.push(format!("El usuario '{}' está duplicado en el archivo.", usuario));
+            }
+            if riy_users.contains(&key) {
+                errores.push(format!("El usuario '{}' ya existe en el sistema.", usuario));
+            }
+            if !erp_users.contains_key(&key) {
+                errores.push(format!("El usuario '{}' no existe en el ERP.", usuario));
+            }
+        }
+        if !is_valid_email(&correo) {
+            errores.push(format!("El correo '{}' no tiene un formato válido.", correo));
+        }
+        for rol in &row_roles {
+            if !roles.contains(&rol.to_lowercase()) {
+                errores.push(format!("El rol '{}' no existe en el aplicativo.", rol));
+            }
+        }
+
+        // Si el archivo no trae nombre se usa el del ERP
+        let nombre = Some(get("nombre"))
+            .filter(|n| !n.is_empty())
+            .or_else(|| erp_users.get(&key).cloned().flatten())
+            .unwrap_or_default();
+
+        filas.push(UserImportRow {
+            fila: index + 2,
+            usuario,
+            nombre,
+            correo,
+            estado,
+            roles: row_roles,
+            errores,
+        });
+    }
+
+    let validas = filas.iter().filter(|f| f.is_valid()).count();
+    let import_id = new_import_id();
+
+    {
+        let mut pending = PENDING_IMPORTS.lock().map_err(|_| "Error interno de la importación.".to_string())?;
+        purge_expired(&mut pending);
+        pending.insert(import_id.clone(), PendingImport {
+            aplicativo_id,
+            autor: autor.to_string(),
+            filas: filas.clone(),
+            creada: Instant::now(),
+        });
+    }
+
+    Ok(UserImportPreview {
+        import_id,
+        total: filas.len(),
+        validas,
+        invalidas: filas.len() - validas,
+        filas,
+        expira_en_minutos: PREVIEW_TTL_MINUTES,
+    })
+}
+
+// -------------------------------------------------------------------------
+// FASE 2: CONFIRMACIÓN
+// -------------------------------------------------------------------------
+
+/// Inserta en una sola transacción las filas válidas de la vista previa y asigna sus roles.
+/// Solo quien generó la vista previa puede confirmarla.
+pub async fn commit_user_import(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    import_id: &str,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<UserImportResult, String> {
+    let pending = {
+        let mut pending = PENDING_IMPORTS.lock().map_err(|_| "Error interno de la importación.".to_string())?;
+        purge_expired(&mut pending);
+        match pending.get(import_id) {
+            Some(p) if p.autor != autor || p.aplicativo_id != aplicativo_id => {
+                return Err("La vista previa pertenece a otro usuario o aplicativo.".to_string());
+            }
+            Some(_) => pending.remove(import_id),
+            None => None,
+        }
+    }
+    .ok_or_else(|| "La vista previa no existe o ya expiró. Vuelva a cargar el archivo.".to_string())?;
+
+    let role_ids: HashMap<String, i32> = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+        .await?
+        .into_iter()
+        .map(|r| (r.rol.to_lowercase(), r.rol_id))
+        .collect();
+
+    let mut tx = pool.begin().await
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    let mut creados = 0;
+    let mut roles_asignados = 0;
+    let mut omitidas = Vec::new();
+
+    for fila in pending.filas.into_iter().filter(|f| f.is_valid()) {
+        // El usuario pudo crearse entre la vista previa y la confirmación.
+        let row = sqlx::query(
+            "IF NOT EXISTS (SELECT 1 FROM riy.riy_usuario WHERE usuario = @p1)
+             BEGIN
+                 INSERT INTO riy.riy_usuario (usuario, nombre, correo, estado, autor, fecha_creacion)
+                 VALUES (@p1, @p2, @p3, @p4, @p5, GETDATE());
+                 SELECT CAST(SCOPE_IDENTITY() AS INT) AS usuario_id;
+             END
+             ELSE
+                 SELECT CAST(NULL AS INT) AS usuario_id;"
+        )
+        .bind(&fila.usuario)
+        .bind(&fila.nombre)
+        .bind(&fila.correo)
+        .bind(&fila.estado)
+        .bind(autor)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Error al insertar el usuario '{}' (fila {}): {}", fila.usuario, fila.fila, e))?;
+
+        let usuario_id: Option<i32> = row.try_get("usuario_id")
+            .map_err(|e| format!("Error al obtener el ID del usuario '{}': {}", fila.usuario, e))?;
+        let Some(usuario_id) = usuario_id else {
+            omitidas.push(fila);
+            continue;
+        };
+        creados += 1;
+
+        for rol in &fila.roles {
+            let rol_id = role_ids.get(&rol.to_lowercase())
+                .ok_or_else(|| format!("El rol '{}' ya no existe en el aplicativo.", rol))?;
+            role_logic::assign_role(&mut tx, usuario_id, *rol_id, autor).await?;
+            roles_asignados += 1;
+        }
+    }
+
+    tx.commit().await
+        .map_err(|e| format!("Error al confirmar la importación: {}", e))?;
+    println!("user_import_logic: importación {} confirmada. Usuarios creados: {}", import_id, creados);
+
+    Ok(UserImportResult {
+        import_id: import_id.to_string(),
+        creados,
+        roles_asignados,
+        omitidas,
+    })
+}
+
+// -------------------------------------------------------------------------
+// EXPORTACIÓN
+// -------------------------------------------------------------------------
+
+const EXPORT_HEADERS: [&str; 7] = ["usuario_id", "usuario", "nombre", "correo", "estado", "roles", "fecha_creacion"];
+
+/// Genera un archivo CSV o XLSX con todos los usuarios, su estado y sus roles en el aplicativo.
+pub async fn export_users(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    format: UserFileFormat,
+    sql_collate_clause: &str,
+) -> Result<Vec<u8>, String> {
+    let sql_query = format!(
+        "SELECT usuario_id,
+                usuario {0} as usuario,
+                nombre {0} as nombre,
+                correo {0} as correo,
+                estado {0} as estado,
+                CONVERT(VARCHAR(19), fecha_creacion, 120) {0} as fecha_creacion
+           FROM riy.riy_usuario WITH(NOLOCK)
+          ORDER BY usuario",
+        sql_collate_clause
+    );
+    let users: Vec<(i32, String, String, String, String, String)> = sqlx::query_as(&sql_query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios: {}", e))?;
+    let roles_by_user = role_logic::get_user_roles_map(pool, aplicativo_id, sql_collate_clause).await?;
+
+    let rows: Vec<[String; 7]> = users
+        .into_iter()
+        .map(|(usuario_id, usuario, nombre, correo, estado, fecha_creacion)| {
+            let roles = roles_by_user.get(&usuario_id).map(|r| r.join(";")).unwrap_or_default();
+            [usuario_id.to_string(), usuario, nombre, correo, estado, roles, fecha_creacion]
+        })
+        .collect();
+
+    match format {
+        UserFileFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(EXPORT_HEADERS)
+                .map_err(|e| format!("Error al escribir el CSV: {}", e))?;
+            for row in &rows {
+                writer.write_record(row)
+                    .map_err(|e| format!("Error al escribir el CSV: {}", e))?;
+            }
+            writer.into_inner().map_err(|e| format!("Error al generar el CSV: {}", e))
+        }
+        UserFileFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            let worksheet = workbook.add_worksheet();
+            for (col, header) in EXPORT_HEADERS.iter().enumerate() {
+                worksheet.write_string(0, col as u16, *header)
+                    .map_err(|e| format!("Error al escribir el XLSX: {}", e))?;
+            }
+            for (index, row) in rows.iter().enumerate() {
+                for (col, value) in row.iter().enumerate() {
+                    worksheet.write_string(index as u32 + 1, col as u16, value)
+                        .map_err(|e| format!("Error al escribir el XLSX: {}", e))?;
+                }
+            }
+            workbook.save_to_buffer().map_err(|e| format!("Error al generar el XLSX: {}", e))
+        }
+    }
+}
+
+/// Columnas de la exportación en streaming de usuarios.
+pub fn user_export_columns() -> Vec<ExportColumn> {
+    vec![
+        ExportColumn::new("usuario_id", "entero"),
+        ExportColumn::new("usuario", "texto"),
+        ExportColumn::new("nombre", "texto"),
+        ExportColumn::new("correo", "texto"),
+        ExportColumn::new("estado", "texto"),
+        ExportColumn::new("roles", "texto"),
+        ExportColumn::new("fecha_creacion", "fecha"),
+    ]
+}
+
+/// Escribe todos los usuarios en el writer leyendo fila a fila (ver export_logic).
+pub async fn write_users_export(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+    writer: &mut ExportWriter,
+) -> Result<(), String> {
+    let roles_by_user = role_logic::get_user_roles_map(pool, aplicativo_id, sql_collate_clause).await?;
+    let sql_query = format!(
+        "SELECT usuario_id,
+                usuario {0} as usuario,
+                nombre {0} as nombre,
+                correo {0} as correo,
+                estado {0} as estado,
+                CONVERT(VARCHAR(19), fecha_creacion, 120) {0} as fecha_creacion
+           FROM riy.riy_usuario WITH(NOLOCK)
+          ORDER BY usuario",
+        sql_collate_clause
+    );
+    let mut rows = sqlx::query_as::<_, (i32, String, String, String, String, String)>(&sql_query).fetch(pool);
+    while let Some((usuario_id, usuario, nombre, correo, estado, fecha_creacion)) = rows
+        .try_next()
+        .await
+        .map_err(|e| format!("Error al leer los usuarios: {}", e))?
+    {
+        let roles = roles_by_user.get(&usuario_id).map(|r| r.join(";")).unwrap_or_default();
+        let mut fila = Map::new();
+        fila.insert("usuario_id".to_string(), Value::from(usuario_id));
+        fila.insert("usuario".to_string(), Value::from(usuario));
+        fila.insert("nombre".to_string(), Value::from(nombre));
+        fila.insert("correo".to_string(), Value::from(correo));
+        fila.insert("estado".to_string(), Value::from(estado));
+        fila.insert("roles".to_string(), Value::from(roles));
+        fila.insert("fecha_creacion".to_string(), Value::from(fecha_creacion));
+        writer.write_row(&fila).await?;
+    }
+    Ok(())
+}
+
+/// Columnas de la exportación en streaming de usuarios del ERP.
+pub fn erp_user_export_columns() -> Vec<ExportColumn> {
+    vec![
+        ExportColumn::new("usuario", "texto"),
+        ExportColumn::new("nombre", "texto"),
+        ExportColumn::new("correo", "texto"),
+        ExportColumn::new("estatus", "texto"),
+    ]
+}
+
+/// Escribe los usuarios del ERP (dbo.Usuario) en el writer leyendo fila a fila; `busqueda`
+/// filtra por usuario o nombre como GET /erp-users.
+pub async fn write_erp_users_export(
+    pool: &Pool<Mssql>,
+    busqueda: Option<&str>,
+    sql_collate_clause: &str,
+    writer: &mut ExportWriter,
+) -> Result<(), String> {
+    let sql_query = format!(
+        "SELECT Usuario {0} as usuario,
+                Nombre {0} as nombre,
+                eMail {0} as correo,
+                Estatus {0} as estatus
+           FROM dbo.Usuario WITH(NOLOCK)
+          WHERE @p1 = '' OR Usuario LIKE @p2 {0} OR Nombre LIKE @p2 {0}
+          ORDER BY Usuario",
+        sql_collate_clause
+    );
+    let busqueda = busqueda.map(str::trim).unwrap_or_default();
+    let patron = format!("%{}%", busqueda);
+    let mut rows = sqlx::query_as::<_, (String, Option<String>, Option<String>, Option<String>)>(&sql_query)
+        .bind(busqueda)
+        .bind(&patron)
+        .fetch(pool);
+    while let Some((usuario, nombre, correo, estatus)) = rows
+        .try_next()
+        .await
+        .map_err(|e| format!("Error al leer los usuarios del ERP: {}", e))?
+    {
+        let mut fila = Map::new();
+        fila.insert("usuario".to_string(), Value::from(usuario));
+        fila.insert("nombre".to_string(), Value::from(nombre));
+        fila.insert("correo".to_string(), Value::from(correo));
+        fila.insert("estatus".to_string(), Value::from(estatus));
+        writer.write_row(&fila).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// src/shared/user_import_models.rs
+
+use serde::{Deserialize, Serialize};
+
+/// Formato de archivo para importar/exportar usuarios.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserFileFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+impl UserFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            UserFileFormat::Csv => "csv",
+            UserFileFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            UserFileFormat::Csv => "text/csv; charset=utf-8",
+            UserFileFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+/// Archivo enviado por el cliente para la fase de vista previa.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserImportRequest {
+    pub format: UserFileFormat,
+    /// Contenido del archivo codificado en Base64.
+    pub content_base64: String,
+}
+
+/// Fila del archivo ya interpretada y validada.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserImportRow {
+    /// Número de fila en el archivo (la cabecera es la fila 1).
+    pub fila: usize,
+    pub usuario: String,
+    pub nombre: String,
+    pub correo: String,
+    pub estado: String,
+    pub roles: Vec<String>,
+    /// Errores de validación; la fila solo se importa si está vacío.
+    pub errores: Vec<String>,
+}
+
+impl UserImportRow {
+    pub fn is_valid(&self) -> bool {
+        self.errores.is_empty()
+    }
+}
+
+/// Resultado de la fase de vista previa. El `import_id` se usa para confirmar.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserImportPreview {
+    pub import_id: String,
+    pub total: usize,
+    pub validas: usize,
+    pub invalidas: usize,
+    pub filas: Vec<UserImportRow>,
+    /// Minutos durante los que la vista previa puede confirmarse.
+    pub expira_en_minutos: u64,
+}
+
+/// Resultado de la fase de confirmación.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserImportResult {
+    pub import_id: String,
+    pub creados: usize,
+    pub roles_asignados: usize,
+    /// Filas válidas en la vista previa que se omitieron al confirmar (ej. creadas mientras tanto).
+    pub omitidas: Vec<UserImportRow>,
+}
@@ -12,10 +12,21 @@ use crate::models::{
     User,
     LoginType,
     LoggedInUser, 
+    UsuarioResumen,
+    UserListQuery,
+    UserListPage,
+    UsuarioSortColumn,
+    SortDirection,
 };
+use sqlx::QueryBuilder;
+use chrono::NaiveDate;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use super::auth;
 use super::user_repository;
+use super::identity_link_logic;
+use super::auth_providers::{self, google};
 use super::config::AppConfig;
+use std::sync::Arc;
 pub use super::auth::DbPool; 
 
 // -------------------------------------------------------------------------
@@ -29,6 +40,7 @@ pub use super::auth::DbPool;
 pub async fn authenticate_user(
     pool: &DbPool,
     config: &AppConfig,
+    aplicativo_id: i32,
     payload: AuthRequestPayload,
 ) -> Result<AuthResponsePayload> {
     
@@ -61,6 +73,27 @@ pub async fn authenticate_user(
             let password = payload.password.as_deref().unwrap_or("");
             
             auth::authenticate_user_local_db(pool, username, password).await?
+        },
+        LoginType::Google => {
+            // En el flujo Google, 'proof_of_identity' contiene el código de autorización.
+            let redirect_uri = payload.redirect_uri.as_deref()
+                .ok_or_else(|| anyhow!("redirect_uri es obligatorio para el login con Google."))?;
+            let http_client = Arc::new(reqwest::Client::new());
+
+            let (email, unique_id) = google::validate_google_code(
+                payload.proof_of_identity.as_str(),
+                redirect_uri,
+                &http_client,
+                &config.google_client_id,
+                &config.google_client_secret,
+                payload.code_verifier.as_deref(),
+            ).await?;
+            auth_providers::to_user_info(
+                identity_link_logic::PROVEEDOR_GOOGLE,
+                email,
+                unique_id,
+                Some(google::GOOGLE_ISSUER.to_string()),
+            )
         }
     };
 
@@ -68,6 +101,7 @@ pub async fn authenticate_user(
     let local_user = user_repository::find_or_create_user(
         pool,
         &user_info, 
+        aplicativo_id,
         sql_collate_clause, 
     ).await?;
 
@@ -108,4 +142,299 @@ pub async fn authenticate_user(
         user: logged_in_user, 
         permissions: Vec::new(), 
     })
-}
\ No newline at end of file
+}
+
+
+// -------------------------------------------------------------------------
+// LISTADO DE USUARIOS (PAGINADO, ORDENABLE Y FILTRABLE)
+// -------------------------------------------------------------------------
+
+/// Permiso requerido para consultar el listado de usuarios.
+pub const PERMISO_LISTA_USUARIOS: &str = "lista_usuarios";
+
+/// Tamaño de página usado cuando el cliente no envía `pageSize`.
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Tamaño máximo de página permitido para no devolver toda la tabla de una vez.
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Devuelve la expresión SQL usada para ordenar (y comparar en el keyset) por la columna.
+/// Las columnas nulas se normalizan con ISNULL para que el orden sea determinista.
+/// Las fechas se convierten con el estilo 120 (`yyyy-mm-dd hh:mi:ss`), cuyo orden
+/// lexicográfico coincide con el cronológico.
+fn sort_expression(column: UsuarioSortColumn) -> &'static str {
+    match column {
+        UsuarioSortColumn::UsuarioId => "usuario_id",
+        UsuarioSortColumn::Usuario => "usuario",
+        UsuarioSortColumn::Nombre => "nombre",
+        UsuarioSortColumn::Correo => "correo",
+        UsuarioSortColumn::Estado => "estado",
+        UsuarioSortColumn::Autor => "autor",
+        UsuarioSortColumn::FechaCreacion => "CONVERT(VARCHAR(19), fecha_creacion, 120)",
+        UsuarioSortColumn::ModificadoPor => "ISNULL(modificado_por, '')",
+        UsuarioSortColumn::FechaModificacion => "ISNULL(CONVERT(VARCHAR(19), fecha_modificacion, 120), '')",
+    }
+}
+
+/// Valor de la columna de orden para un usuario ya leído (se guarda en el cursor).
+fn sort_value(column: UsuarioSortColumn, usuario: &UsuarioResumen) -> String {
+    match column {
+        UsuarioSortColumn::UsuarioId => usuario.usuario_id.to_string(),
+        UsuarioSortColumn::Usuario => usuario.usuario.clone(),
+        UsuarioSortColumn::Nombre => usuario.nombre.clone(),
+        UsuarioSortColumn::Correo => usuario.correo.clone(),
+        UsuarioSortColumn::Estado => usuario.estado.clone(),
+        UsuarioSortColumn::Autor => usuario.autor.clone(),
+        UsuarioSortColumn::FechaCreacion => usuario.fecha_creacion.clone(),
+        UsuarioSortColumn::ModificadoPor => usuario.modificado_por.clone().unwrap_or_default(),
+        UsuarioSortColumn::FechaModificacion => usuario.fecha_modificacion.clone().unwrap_or_default(),
+    }
+}
+
+/// Codifica el cursor de keyset como base64 (URL safe) de `[valor_orden, usuario_id]`.
+fn encode_cursor(value: &str, usuario_id: i32) -> String {
+    let raw = serde_json::json!([value, usuario_id]).to_string();
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+/// Decodifica un cursor generado por `encode_cursor`.
+fn decode_cursor(cursor: &str) -> Result<(String, i32), String> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor)
+        .map_err(|_| "Cursor de paginación inválido.".to_string())?;
+    serde_json::from_slice::<(String, i32)>(&bytes)
+        .map_err(|_| "Cursor de paginación inválido.".to_string())
+}
+
+/// Valida una fecha `YYYY-MM-DD` recibida como filtro.
+fn parse_filter_date(value: &str, field: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("El filtro '{}' debe tener el formato YYYY-MM-DD.", field))
+}
+
+/// Escapa los comodines de LIKE de SQL Server (`%`, `_`, `[`) usando `\` como escape.
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '[' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Valida los filtros de fecha y el cursor antes de construir la consulta.
+/// Permite a los handlers responder 400 en lugar de un error de base de datos.
+pub fn validate_user_list_query(query: &UserListQuery) -> Result<(), String> {
+    let dates = [
+        ("fechaCreacionDesde", &query.fecha_creacion_desde),
+        ("fechaCreacionHasta", &query.fecha_creacion_hasta),
+        ("fechaModificacionDesde", &query.fecha_modificacion_desde),
+        ("fechaModificacionHasta", &query.fecha_modificacion_hasta),
+    ];
+    for (field, value) in dates {
+        if let Some(value) = value.as_deref().filter(|s| !s.trim().is_empty()) {
+            parse_filter_date(value, field)?;
+        }
+    }
+    if let Some(cursor) = query.cursor.as_deref().filter(|c| !c.is_empty()) {
+        decode_cursor(cursor)?;
+    }
+    Ok(())
+}
+
+/// Agrega al `QueryBuilder` las condiciones WHERE de los filtros (siempre parametrizadas).
+/// Debe llamarse justo después de `... FROM riy.riy_usuario WITH(NOLOCK) WHERE 1 = 1`.
+fn push_user_filters(
+    builder: &mut QueryBuilder<'_, Mssql>,
+    query: &UserListQuery,
+    sql_collate_clause: &str,
+) -> Result<(), String> {
+    if let Some(estado) = query.estado.as_deref().filter(|s| !s.trim().is_empty()) {
+        builder.push(" AND estado = ").push_bind(estado.trim().to_string())
+            .push(format!(" {}", sql_collate_clause));
+    }
+    if let Some(autor) = query.autor.as_deref().filter(|s| !s.trim().is_empty()) {
+        builder.push(" AND autor = ").push_bind(autor.trim().to_string())
+            .push(format!(" {}", sql_collate_clause));
+    }
+
+    let date_ranges = [
+        ("fecha_creacion", "fechaCreacionDesde", &query.fecha_creacion_desde, true),
+        ("fecha_creacion", "fechaCreacionHasta", &query.fecha_creacion_hasta, false),
+        ("fecha_modificacion", "fechaModificacionDesde", &query.fecha_modificacion_desde, true),
+        ("fecha_modificacion", "fechaModificacionHasta", &query.fecha_modificacion_hasta, false),
+    ];
+    for (column, field, value, is_from) in date_ranges {
+        if let Some(value) = value.as_deref().filter(|s| !s.trim().is_empty()) {
+            let date = parse_filter_date(value, field)?.format("%Y-%m-%d").to_string();
+            if is_from {
+                builder.push(format!(" AND {} >= CAST(", column)).push_bind(date).push(" AS DATE)");
+            } else {
+                // Rango inclusivo: se compara contra el inicio del día siguiente.
+                builder.push(format!(" AND {} < DATEADD(DAY, 1, CAST(", column)).push_bind(date).push(" AS DATE))");
+            }
+        }
+    }
+
+    if let Some(search) = query.search.as_deref().filter(|s| !s.trim().is_empty()) {
+        let pattern = format!("%{}%", escape_like(search.trim()));
+        builder.push(format!(" AND (usuario {0} LIKE ", sql_collate_clause)).push_bind(pattern.clone())
+            .push(format!(" ESCAPE '\\' OR nombre {0} LIKE ", sql_collate_clause)).push_bind(pattern.clone())
+            .push(format!(" ESCAPE '\\' OR correo {0} LIKE ", sql_collate_clause)).push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+
+    Ok(())
+}
+
+/// Obtiene una página de usuarios de `riy.riy_usuario` aplicando filtros, orden y paginación
+/// (por desplazamiento o por cursor/keyset), junto con el total de filas que cumplen los filtros.
+/// Quien llama debe exigir PERMISO_LISTA_USUARIOS.
+///
+/// Todos los valores recibidos del cliente se envían como parámetros; solo las expresiones
+/// de orden (de una lista cerrada) y la cláusula de colación se interpolan en el SQL.
+pub async fn get_all_users_logic(
+    pool: &Pool<Mssql>,
+    query: &UserListQuery,
+    sql_collate_clause: &str,
+) -> Result<UserListPage, String> {
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = query.page.unwrap_or(1).max(1);
+    let sort_column = query.sort_by.unwrap_or_default();
+    let sort_dir = query.sort_dir.unwrap_or_default();
+    let (dir_sql, cmp_sql) = match sort_dir {
+        SortDirection::Asc => ("ASC", ">"),
+        SortDirection::Desc => ("DESC", "<"),
+    };
+    let sort_expr = match sort_column {
+        UsuarioSortColumn::UsuarioId => sort_expression(sort_column).to_string(),
+        _ => format!("{} {}", sort_expression(sort_column), sql_collate_clause),
+    };
+    let cursor = query.cursor.as_deref()
+        .filter(|c| !c.is_empty())
+        .map(decode_cursor)
+        .transpose()?;
+
+    // 1. TOTAL DE FILAS QUE CUMPLEN LOS FILTROS
+    let mut count_builder: QueryBuilder<Mssql> = QueryBuilder::new(
+        "SELECT CAST(COUNT(*) AS BIGINT) FROM riy.riy_usuario WITH(NOLOCK) WHERE 1 = 1"
+    );
+    push_user_filters(&mut count_builder, query, sql_collate_clause)?;
+    let (total,): (i64,) = count_builder
+        .build_query_as()
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Error al contar los usuarios: {}", e))?;
+
+    // 2. PÁGINA SOLICITADA
+    let mut builder: QueryBuilder<Mssql> = QueryBuilder::new(format!(
+        r#"
+        SELECT
+            usuario_id,
+            usuario {0} as usuario,
+            nombre {0} as nombre,
+            correo {0} as correo,
+            estado {0} as estado,
+            autor {0} as autor,
+            CONVERT(VARCHAR(19), fecha_creacion, 120) {0} as fecha_creacion,
+            modificado_por {0} as modificado_por,
+            CONVERT(VARCHAR(19), fecha_modificacion, 120) {0} as fecha_modificacion
+        FROM riy.riy_usuario WITH(NOLOCK)
+        WHERE 1 = 1"#,
+        sql_collate_clause
+    ));
+    push_user_filters(&mut builder, query, sql_collate_clause)?;
+
+    if let Some((last_value, last_id)) = &cursor {
+        // Keyset: filas estrictamente posteriores a (valor_orden, usuario_id) de la página anterior.
+        if sort_column == UsuarioSortColumn::UsuarioId {
+            builder.push(format!(" AND usuario_id {} ", cmp_sql)).push_bind(*last_id);
+        } else {
+            builder.push(format!(" AND ({} {} ", sort_expr, cmp_sql)).push_bind(last_value.clone())
+                .push(format!(" OR ({} = ", sort_expr)).push_bind(last_value.clone())
+                .push(format!(" AND usuario_id {} ", cmp_sql)).push_bind(*last_id)
+                .push("))");
+        }
+    }
+
+    if sort_column == UsuarioSortColumn::UsuarioId {
+        builder.push(format!(" ORDER BY usuario_id {}", dir_sql));
+    } else {
+        builder.push(format!(" ORDER BY {} {}, usuario_id {}", sort_expr, dir_sql, dir_sql));
+    }
+
+    let offset: i64 = if cursor.is_some() { 0 } else { i64::from(page - 1) * i64::from(page_size) };
+    builder.push(" OFFSET ").push_bind(offset)
+        .push(" ROWS FETCH NEXT ").push_bind(i64::from(page_size))
+        .push(" ROWS ONLY");
+
+    let items: Vec<UsuarioResumen> = builder
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios: {}", e))?;
+
+    debug!("Listado de usuarios: {} filas de un total de {}", items.len(), total);
+
+    let next_cursor = if items.len() as u32 == page_size {
+        items.last().map(|last| encode_cursor(&sort_value(sort_column, last), last.usuario_id))
+    } else {
+        None
+    };
+
+    Ok(UserListPage {
+        items,
+        total,
+        page,
+        page_size,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consulta_con_cursor(cursor: &str) -> UserListQuery {
+        UserListQuery { cursor: Some(cursor.to_string()), ..UserListQuery::default() }
+    }
+
+    #[test]
+    fn el_cursor_se_decodifica_como_se_codifico() {
+        for (valor, usuario_id) in [("jperez", 15), ("", 1), ("2024-01-31 10:15:00", 42), ("ñandú \"x\"", i32::MAX)] {
+            let cursor = encode_cursor(valor, usuario_id);
+            assert_eq!(decode_cursor(&cursor).unwrap(), (valor.to_string(), usuario_id));
+            assert!(validate_user_list_query(&consulta_con_cursor(&cursor)).is_ok());
+        }
+    }
+
+    #[test]
+    fn un_cursor_mal_formado_es_error_de_validacion() {
+        let malformados = [
+            "no es base64!".to_string(),
+            URL_SAFE_NO_PAD.encode("no es json"),
+            URL_SAFE_NO_PAD.encode("[\"jperez\"]"),
+            URL_SAFE_NO_PAD.encode("[15, \"jperez\"]"),
+            URL_SAFE_NO_PAD.encode("[\"jperez\", 99999999999]"),
+            "%%%".to_string(),
+        ];
+        for cursor in &malformados {
+            assert!(decode_cursor(cursor).is_err(), "cursor: {}", cursor);
+            assert_eq!(
+                validate_user_list_query(&consulta_con_cursor(cursor)),
+                Err("Cursor de paginación inválido.".to_string())
+            );
+        }
+        // Un cursor vacío equivale a no enviarlo
+        assert!(validate_user_list_query(&consulta_con_cursor("")).is_ok());
+    }
+
+    #[test]
+    fn escape_like_escapa_los_comodines() {
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("j_perez"), "j\\_perez");
+        assert_eq!(escape_like("[a-z]"), "\\[a-z]");
+        assert_eq!(escape_like("c:\\temp"), "c:\\\\temp");
+        assert_eq!(escape_like("jperez@empresa.com"), "jperez@empresa.com");
+    }
+}
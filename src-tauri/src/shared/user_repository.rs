@@ -7,6 +7,11 @@ use crate::models::{Usuario, LoggedInUser, UserInfo, NewUsuario};
 // 🏆 CORRECCIÓN: Importamos DbPool desde el módulo compartido 'auth'
 // donde ya está definida como pública (pub type DbPool = Pool<Mssql>;).
 use super::auth::DbPool;
+use super::identity_link_logic;
+use super::domain_policy_logic;
+use super::role_logic;
+use super::notification_logic;
+use crate::models::DomainPolicyMode;
 use crate::models::{User};
 
 use std::error::Error;
@@ -27,6 +32,21 @@ pub struct UserDbRecord {
     pub estado: String, // **CORREGIDO**
 }
 
+/// Estado de los usuarios habilitados para iniciar sesión.
+pub const ESTADO_VIGENTE: &str = "Vigente";
+
+/// Rechaza al usuario que no está vigente (inactivo, dado de baja por SCIM o por la
+/// sincronización con el ERP, etc.), sea cual sea el login por el que llegue.
+fn ensure_vigente(user_record: &UserDbRecord) -> Result<()> {
+    if user_record.estado.trim().eq_ignore_ascii_case(ESTADO_VIGENTE) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "El usuario {} no está vigente (estado: {}).", user_record.usuario, user_record.estado
+        ))
+    }
+}
+
 
 // --- MANEJO DE ERRORES PERSONALIZADO ---
 
@@ -74,17 +94,55 @@ impl From<SqlxError> for UserError {
 // REPOSITORIO DE USUARIOS
 // -------------------------------------------------------------------------
 
-/// Busca un usuario en la tabla `riy.riy_usuario` por correo electrónico.
-/// Si no existe, lo crea usando los datos de `UserInfo`.
+/// Busca el usuario local de un login externo o ERP/Local.
+/// Si `user_info` trae identidad externa (proveedor + sujeto) se busca primero por el
+/// vínculo en `riy.riy_usuario_identidad`; solo si no existe se busca por correo y se
+/// crea el vínculo. Si tampoco existe por correo, se crea el usuario y se vincula.
+/// Los logins externos se validan contra la política del dominio del correo
+/// (`riy.riy_dominio_permitido`), que decide además si se permite el auto-aprovisionamiento.
 pub async fn find_or_create_user(
     pool: &DbPool,
     user_info: &UserInfo,
+    aplicativo_id: i32,
     collate_clause: &str,
 ) -> Result<LoggedInUser> {
     
-    // 1. Intentar buscar el usuario por correo
     let email = user_info.email.to_lowercase();
 
+    // Política del dominio (solo logins externos; ERP y Local no la usan)
+    let domain_policy = match &user_info.identity {
+        Some(identity) => Some(
+            domain_policy_logic::evaluate_login(pool, aplicativo_id, &email, identity, collate_clause).await?
+        ),
+        None => None,
+    };
+
+    // 0. Buscar por identidad externa vinculada (independiente del correo actual)
+    if let Some(identity) = &user_info.identity {
+        if let Some(usuario_id) = identity_link_logic::find_user_id_by_identity(pool, identity).await? {
+            let user_record: UserDbRecord = query_as(
+                "SELECT usuario_id, usuario, nombre, correo, estado \
+                 FROM riy.riy_usuario WITH(NOLOCK) \
+                 WHERE usuario_id = @p1"
+            )
+            .bind(usuario_id)
+            .fetch_one(pool)
+            .await?;
+            ensure_vigente(&user_record)?;
+
+            identity_link_logic::touch_identity(pool, identity, &email).await?;
+
+            return Ok(LoggedInUser {
+                usuario_id: Some(user_record.usuario_id),
+                usuario: Some(user_record.usuario),
+                nombre: Some(user_record.nombre),
+                correo: Some(user_record.correo),
+                roles: vec!["Temporal".to_string()],
+            });
+        }
+    }
+
+    // 1. Intentar buscar el usuario por correo
     // La consulta debe incluir todos los campos de UserDbRecord
     let select_query = format!(
         "SELECT usuario_id, usuario, nombre, correo, estado \
@@ -101,8 +159,15 @@ pub async fn find_or_create_user(
     match user_record_result {
         // A. Usuario encontrado
         Ok(user_record) => {
-            
-            let roles: Vec<String> = vec!["Temporal".to_string()]; 
+            ensure_vigente(&user_record)?;
+
+            // Primer login con esta identidad: se vincula. Si el usuario ya tiene otra
+            // identidad del mismo proveedor, el correo fue reasignado en el IdP y se rechaza.
+            if let Some(identity) = &user_info.identity {
+                identity_link_logic::link_identity(pool, user_record.usuario_id, identity, Some(&email), "System").await?;
+            }
+
+            let roles: Vec<String> = vec!["Temporal".to_string()];
 
 
             // Mapear a LoggedInUser
@@ -124,13 +189,19 @@ pub async fn find_or_create_user(
         
         // B. Usuario no encontrado (NotFound) -> Crear nuevo usuario
         Err(sqlx::Error::RowNotFound) => {
+            if domain_policy.as_ref().map(|p| p.politica) == Some(DomainPolicyMode::RequireExisting) {
+                return Err(anyhow!(
+                    "El usuario {} no existe en riy y la política de su dominio no permite crearlo.", email
+                ));
+            }
+
             // Construir el nuevo usuario
             let new_user = NewUsuario {
                 // Usamos el preferred_username si existe, sino el nombre completo.
                 usuario: user_info.username.clone(),
                 nombre: user_info.name.clone().unwrap_or_default(),
                 correo: email,
-                estado: "Activo".to_string(), // Estado inicial por defecto
+                estado: ESTADO_VIGENTE.to_string(), // Estado inicial por defecto
                 autor: "System".to_string(), // Autor por defecto
                 fecha_creacion: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
             };
@@ -140,6 +211,23 @@ pub async fn find_or_create_user(
 
             match created_user_result {
                 Ok(usuario) => {
+                    if let (Some(identity), Some(usuario_id)) = (&user_info.identity, usuario.usuario_id) {
+                        identity_link_logic::link_identity(pool, usuario_id, identity, Some(&usuario.correo), "System").await?;
+                    }
+                    // Rol por defecto del dominio para los usuarios auto-aprovisionados
+                    if let (Some(rol_id), Some(usuario_id)) = (
+                        domain_policy.as_ref().and_then(|p| p.rol_por_defecto_id),
+                        usuario.usuario_id,
+                    ) {
+                        role_logic::assign_role(pool, usuario_id, rol_id, "System").await.map_err(|e| anyhow!(e))?;
+                    }
+                    // El correo de bienvenida no debe impedir el login
+                    if let Some(usuario_id) = usuario.usuario_id {
+                        if let Err(e) = notification_logic::notify_new_account(pool, usuario_id).await {
+                            eprintln!("user_repository: no se pudo encolar el correo de bienvenida: {}", e);
+                        }
+                    }
+
                     // Mapear el Usuario recién creado a LoggedInUser
                     Ok(LoggedInUser {
                         usuario_id: usuario.usuario_id,
@@ -160,6 +248,28 @@ pub async fn find_or_create_user(
 }
 
 
+/// Busca un usuario vigente por su ID (usado al resolver un vínculo de identidad externa y al
+/// completar un login con MFA). Un usuario que no está vigente se trata como inexistente.
+pub async fn find_user_by_id(
+    pool: &DbPool,
+    usuario_id: i32,
+    collate_clause: &str,
+) -> Result<Option<User>> {
+    let select_query = format!(
+        "SELECT usuario_id, usuario {0} as usuario, nombre {0} as nombre, correo {0} as correo \
+         FROM riy.riy_usuario WITH(NOLOCK) \
+         WHERE usuario_id = @p1 AND estado = @p2 {0}",
+        collate_clause
+    );
+    let user = query_as::<_, User>(&select_query)
+        .bind(usuario_id)
+        .bind(ESTADO_VIGENTE)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user)
+}
+
+
 /// Inserta un nuevo usuario en la tabla `riy.riy_usuario` y retorna la estructura `Usuario` completa.
 ///
 /// NOTA: Asume que la columna `usuario_id` es de tipo `IDENTITY(1,1)` y retorna el ID generado.
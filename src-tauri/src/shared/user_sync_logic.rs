@@ -0,0 +1,378 @@
+// src/shared/user_sync_logic.rs
+/*
+Sincronización de usuarios ERP (dbo.Usuario) -> riy (riy.riy_usuario).
+
+Tabla requerida para persistir los reportes:
+
+CREATE TABLE riy.riy_sincronizacion_usuario (
+    sincronizacionID INT IDENTITY(1,1) PRIMARY KEY,
+    origen           VARCHAR(20)   NOT NULL,  -- PROGRAMADA | MANUAL
+    autor            VARCHAR(50)   NOT NULL,
+    dryRun           BIT           NOT NULL,
+    autoDesactivar   BIT           NOT NULL,
+    fechaInicio      DATETIME      NOT NULL,
+    fechaFin         DATETIME      NOT NULL,
+    nuevos           INT           NOT NULL,
+    modificados      INT           NOT NULL,
+    desaparecidos    INT           NOT NULL,
+    desactivados     INT           NOT NULL,
+    detalle          NVARCHAR(MAX) NOT NULL   -- UserSyncReport serializado en JSON
+);
+*/
+
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveDateTime};
+use lazy_static::lazy_static;
+use sqlx::{Pool, Mssql, Row};
+use tokio::sync::Mutex;
+
+use crate::config::UserSyncConfig;
+use crate::notification_logic;
+use crate::user_repository::UserDbRecord;
+use crate::user_sync_models::{
+    ErpUsuario, UserSyncChange, UserSyncGone, UserSyncNew, UserSyncReport, UserSyncRequest,
+    UserSyncSummary,
+};
+
+/// Estado con el que se marca a un usuario desactivado por la sincronización.
+pub const ESTADO_INACTIVO: &str = "Inactivo";
+/// Estatus del ERP que indica una cuenta vigente; cualquier otro valor se considera bloqueado.
+const ERP_ESTATUS_ALTA: &str = "ALTA";
+
+lazy_static! {
+    /// Evita que una ejecución manual y una programada se solapen.
+    static ref SYNC_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Indica si hay una sincronización ejecutándose en este momento.
+pub fn is_sync_running() -> bool {
+    SYNC_LOCK.try_lock().is_err()
+}
+
+/// Normaliza el código de usuario para comparar entre ambas tablas.
+fn user_key(usuario: &str) -> String {
+    usuario.trim().to_lowercase()
+}
+
+/// Formato de las fechas del reporte.
+const FORMATO_FECHA: &str = "%Y-%m-%d %H:%M:%S";
+
+fn now_str() -> String {
+    Local::now().format(FORMATO_FECHA).to_string()
+}
+
+/// Valida la fecha del reporte y la devuelve normalizada en FORMATO_FECHA (estilo ODBC 120).
+/// Se envía como texto con CONVERT(DATETIME, @p, 120): sqlx no codifica chrono para MSSQL y
+/// así no se depende de DATEFORMAT ni del idioma del servidor.
+fn report_datetime(value: &str) -> Result<String, String> {
+    NaiveDateTime::parse_from_str(value, FORMATO_FECHA)
+        .map(|d| d.format(FORMATO_FECHA).to_string())
+        .map_err(|e| format!("Fecha del reporte inválida '{}': {}", value, e))
+}
+
+/// Lee todos los usuarios del ERP.
+async fn fetch_erp_users(
+    pool: &Pool<Mssql>,
+    sql_collate_clause: &str,
+) -> Result<Vec<ErpUsuario>, String> {
+    let sql_query = format!(
+        "SELECT Usuario {0} as usuario,
+                Nombre {0} as nombre,
+                eMail {0} as correo,
+                Estatus {0} as estatus
+           FROM dbo.Usuario WITH(NOLOCK)",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, ErpUsuario>(&sql_query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios del ERP: {}", e))
+}
+
+/// Lee todos los usuarios de riy.
+async fn fetch_riy_users(
+    pool: &Pool<Mssql>,
+    sql_collate_clause: &str,
+) -> Result<Vec<UserDbRecord>, String> {
+    let sql_query = format!(
+        "SELECT usuario_id,
+                usuario {0} as usuario,
+                nombre {0} as nombre,
+                correo {0} as correo,
+                estado {0} as estado
+           FROM riy.riy_usuario WITH(NOLOCK)",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, UserDbRecord>(&sql_query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los usuarios de riy: {}", e))
+}
+
+/// Compara ambas listas y arma el reporte (sin tocar la base de datos).
+fn compare_users(
+    erp_users: &[ErpUsuario],
+    riy_users: &[UserDbRecord],
+) -> (Vec<UserSyncNew>, Vec<UserSyncChange>, Vec<UserSyncGone>) {
+    let erp_by_key: HashMap<String, &ErpUsuario> = erp_users
+        .iter()
+        .map(|u| (user_key(&u.usuario), u))
+        .collect();
+    let riy_keys: HashMap<String, &UserDbRecord> = riy_users
+        .iter()
+        .map(|u| (user_key(&u.usuario), u))
+        .collect();
+
+    let nuevos = erp_users
+        .iter()
+        .filter(|u| !riy_keys.contains_key(&user_key(&u.usuario)))
+        .map(|u| UserSyncNew {
+            usuario: u.usuario.trim().to_string(),
+            nombre: u.nombre.clone(),
+            correo: u.correo.clone(),
+        })
+        .collect();
+
+    let mut modificados = Vec::new();
+    let mut desaparecidos = Vec::new();
+
+    for riy_user in riy_users {
+        match erp_by_key.get(&user_key(&riy_user.usuario)) {
+            Some(erp_user) => {
+                let erp_nombre = erp_user.nombre.as_deref().unwrap_or("").trim();
+                if !erp_nombre.is_empty() && erp_nombre != riy_user.nombre.trim() {
+                    modificados.push(UserSyncChange {
+                        usuario_id: riy_user.usuario_id,
+                        usuario: riy_user.usuario.clone(),
+                        campo: "nombre".to_string(),
+                        valor_riy: riy_user.nombre.clone(),
+                        valor_erp: erp_nombre.to_string(),
+                    });
+                }
+                let erp_correo = erp_user.correo.as_deref().unwrap_or("").trim();
+                if !erp_correo.is_empty() && !erp_correo.eq_ignore_ascii_case(riy_user.correo.trim()) {
+                    modificados.push(UserSyncChange {
+                        usuario_id: riy_user.usuario_id,
+                        usuario: riy_user.usuario.clone(),
+                        campo: "correo".to_string(),
+                        valor_riy: riy_user.correo.clone(),
+                        valor_erp: erp_correo.to_string(),
+                    });
+                }
+                let bloqueado = erp_user
+                    .estatus
+                    .as_deref()
+                    .map(|e| !e.trim().eq_ignore_ascii_case(ERP_ESTATUS_ALTA))
+                    .unwrap_or(false);
+                if bloqueado {
+                    desaparecidos.push(UserSyncGone {
+                        usuario_id: riy_user.usuario_id,
+                        usuario: riy_user.usuario.clone(),
+                        estado: riy_user.estado.clone(),
+                        motivo: "BLOQUEADO".to_string(),
+                        desactivado: false,
+                    });
+                }
+            }
+            None => desaparecidos.push(UserSyncGone {
+                usuario_id: riy_user.usuario_id,
+                usuario: riy_user.usuario.clone(),
+                estado: riy_user.estado.clone(),
+                motivo: "NO_EXISTE".to_string(),
+                desactivado: false,
+            }),
+        }
+    }
+
+    (nuevos, modificados, desaparecidos)
+}
+
+/// Ejecuta la sincronización ERP -> riy y persiste el reporte en
+/// `riy.riy_sincronizacion_usuario`.
+///
+/// Con `dry_run` solo se calcula el reporte; con `auto_deactivate` se marcan como
+/// inactivos los usuarios de riy cuya cuenta del ERP desapareció o está bloqueada.
+pub async fn run_user_sync(
+    pool: &Pool<Mssql>,
+    sql_collate_clause: &str,
+    request: &UserSyncRequest,
+    origen: &str,
+    autor: &str,
+) -> Result<UserSyncReport, String> {
+    let _guard = SYNC_LOCK
+        .try_lock()
+        .map_err(|_| "Ya hay una sincronización de usuarios en curso.".to_string())?;
+
+    let fecha_inicio = now_str();
+    let erp_users = fetch_erp_users(pool, sql_collate_clause).await?;
+    let riy_users = fetch_riy_users(pool, sql_collate_clause).await?;
+    let (nuevos, modificados, mut desaparecidos) = compare_users(&erp_users, &riy_users);
+
+    // DESACTIVACIÓN AUTOMÁTICA (solo si no es dry-run)
+    if request.auto_deactivate && !request.dry_run {
+        let mut tx = pool.begin().await
+            .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+        for gone in desaparecidos.iter_mut() {
+            if gone.estado.eq_ignore_ascii_case(ESTADO_INACTIVO) {
+                continue;
+            }
+            sqlx::query(
+                "UPDATE riy.riy_usuario
+                    SET estado = @p1, modificado_por = @p2, fecha_modificacion = GETDATE()
+                  WHERE usuario_id = @p3"
+            )
+            .bind(ESTADO_INACTIVO)
+            .bind(autor)
+            .bind(gone.usuario_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Error al desactivar el usuario '{}': {}", gone.usuario, e))?;
+            gone.desactivado = true;
+        }
+        tx.commit().await
+            .map_err(|e| format!("Error al confirmar la desactivación de usuarios: {}", e))?;
+
+        for gone in desaparecidos.iter().filter(|g| g.desactivado) {
+            if let Err(e) = notification_logic::notify_account_locked(
+                pool,
+                gone.usuario_id,
+                "La cuenta del ERP ya no existe o está bloqueada.",
+            ).await {
+                eprintln!("user_sync_logic: no se pudo encolar el aviso de bloqueo de '{}': {}", gone.usuario, e);
+            }
+        }
+    }
+
+    let mut report = UserSyncReport {
+        sincronizacion_id: None,
+        origen: origen.to_string(),
+        autor: autor.to_string(),
+        dry_run: request.dry_run,
+        auto_deactivate: request.auto_deactivate,
+        fecha_inicio,
+        fecha_fin: now_str(),
+        nuevos,
+        modificados,
+        desaparecidos,
+    };
+
+    report.sincronizacion_id = Some(save_report(pool, &report).await?);
+    println!(
+        "user_sync_logic: sincronización {} terminada. Nuevos: {}, modificados: {}, desaparecidos: {}",
+        report.origen, report.nuevos.len(), report.modificados.len(), report.desaparecidos.len()
+    );
+    Ok(report)
+}
+
+/// Inserta el reporte y devuelve el ID generado.
+async fn save_report(pool: &Pool<Mssql>, report: &UserSyncReport) -> Result<i32, String> {
+    let detalle = serde_json::to_string(report)
+        .map_err(|e| format!("Error al serializar el reporte de sincronización: {}", e))?;
+    let desactivados = report.desaparecidos.iter().filter(|g| g.desactivado).count() as i32;
+    let fecha_inicio = report_datetime(&report.fecha_inicio)?;
+    let fecha_fin = report_datetime(&report.fecha_fin)?;
+
+    let row = sqlx::query(
+        "INSERT INTO riy.riy_sincronizacion_usuario
+            (origen, autor, dryRun, autoDesactivar, fechaInicio, fechaFin,
+             nuevos, modificados, desaparecidos, desactivados, detalle)
+         VALUES (@p1, @p2, @p3, @p4, CONVERT(DATETIME, @p5, 120), CONVERT(DATETIME, @p6, 120),
+                 @p7, @p8, @p9, @p10, @p11);
+         SELECT CAST(SCOPE_IDENTITY() AS INT) AS sincronizacion_id;"
+    )
+    .bind(&report.origen)
+    .bind(&report.autor)
+    .bind(report.dry_run)
+    .bind(report.auto_deactivate)
+    .bind(fecha_inicio)
+    .bind(fecha_fin)
+    .bind(report.nuevos.len() as i32)
+    .bind(report.modificados.len() as i32)
+    .bind(report.desaparecidos.len() as i32)
+    .bind(desactivados)
+    .bind(detalle)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Error al guardar el reporte de sincronización: {}", e))?;
+
+    row.try_get("sincronizacion_id")
+        .map_err(|e| format!("Error al obtener el ID del reporte: {}", e))
+}
+
+/// Lista los últimos reportes de sincronización (más recientes primero).
+pub async fn list_sync_reports(
+    pool: &Pool<Mssql>,
+    limit: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<UserSyncSummary>, String> {
+    let sql_query = format!(
+        "SELECT TOP (@p1)
+                sincronizacionID as sincronizacion_id,
+                origen {0} as origen,
+                autor {0} as autor,
+                dryRun as dry_run,
+                autoDesactivar as auto_deactivate,
+                CONVERT(VARCHAR(19), fechaInicio, 120) {0} as fecha_inicio,
+                CONVERT(VARCHAR(19), fechaFin, 120) {0} as fecha_fin,
+                nuevos, modificados, desaparecidos, desactivados
+           FROM riy.riy_sincronizacion_usuario WITH(NOLOCK)
+          ORDER BY sincronizacionID DESC",
+        sql_collate_clause
+    );
+    sqlx::query_as::<_, UserSyncSummary>(&sql_query)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error al leer los reportes de sincronización: {}", e))
+}
+
+/// Obtiene el detalle completo de un reporte persistido.
+pub async fn get_sync_report(
+    pool: &Pool<Mssql>,
+    sincronizacion_id: i32,
+) -> Result<Option<UserSyncReport>, String> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT detalle FROM riy.riy_sincronizacion_usuario WITH(NOLOCK)
+          WHERE sincronizacionID = @p1"
+    )
+    .bind(sincronizacion_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Error al leer el reporte de sincronización: {}", e))?;
+
+    match row {
+        Some((detalle,)) => {
+            let mut report: UserSyncReport = serde_json::from_str(&detalle)
+                .map_err(|e| format!("El detalle del reporte está corrupto: {}", e))?;
+            report.sincronizacion_id = Some(sincronizacion_id);
+            Ok(Some(report))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Lanza la sincronización programada dentro del servidor API.
+/// No hace nada si `interval_minutes` es 0.
+pub fn spawn_scheduled_sync(pool: Pool<Mssql>, sql_collate_clause: String, config: UserSyncConfig) {
+    if config.interval_minutes == 0 {
+        println!("user_sync_logic: sincronización programada desactivada.");
+        return;
+    }
+    let period = std::time::Duration::from_secs(config.interval_minutes * 60);
+    println!("user_sync_logic: sincronización programada cada {} minutos.", config.interval_minutes);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        let request = UserSyncRequest {
+            dry_run: config.dry_run,
+            auto_deactivate: config.auto_deactivate,
+        };
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_user_sync(&pool, &sql_collate_clause, &request, "PROGRAMADA", "System").await {
+                eprintln!("user_sync_logic: Error en la sincronización programada: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,92 @@
+// src/shared/user_sync_models.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Usuario leído de la tabla del ERP (`dbo.Usuario`).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ErpUsuario {
+    pub usuario: String,
+    pub nombre: Option<String>,
+    pub correo: Option<String>,
+    pub estatus: Option<String>,
+}
+
+/// Usuario del ERP que todavía no existe en `riy.riy_usuario`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncNew {
+    pub usuario: String,
+    pub nombre: Option<String>,
+    pub correo: Option<String>,
+}
+
+/// Diferencia de un campo entre el ERP y `riy.riy_usuario`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncChange {
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub campo: String,
+    pub valor_riy: String,
+    pub valor_erp: String,
+}
+
+/// Usuario de riy cuya cuenta del ERP ya no existe o está bloqueada.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncGone {
+    pub usuario_id: i32,
+    pub usuario: String,
+    pub estado: String,
+    /// "NO_EXISTE" o "BLOQUEADO".
+    pub motivo: String,
+    /// `true` si la sincronización cambió el estado del usuario a inactivo.
+    pub desactivado: bool,
+}
+
+/// Opciones de una ejecución de la sincronización.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncRequest {
+    /// Si es `true`, solo se calcula el reporte y no se modifica `riy.riy_usuario`.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Si es `true`, desactiva los usuarios de riy cuya cuenta del ERP desapareció o está bloqueada.
+    #[serde(default)]
+    pub auto_deactivate: bool,
+}
+
+/// Resultado completo de una sincronización ERP -> riy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncReport {
+    pub sincronizacion_id: Option<i32>,
+    /// "PROGRAMADA" o "MANUAL".
+    pub origen: String,
+    pub autor: String,
+    pub dry_run: bool,
+    pub auto_deactivate: bool,
+    pub fecha_inicio: String,
+    pub fecha_fin: String,
+    pub nuevos: Vec<UserSyncNew>,
+    pub modificados: Vec<UserSyncChange>,
+    pub desaparecidos: Vec<UserSyncGone>,
+}
+
+/// Resumen de un reporte persistido (para el listado de ejecuciones).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSyncSummary {
+    pub sincronizacion_id: i32,
+    pub origen: String,
+    pub autor: String,
+    pub dry_run: bool,
+    pub auto_deactivate: bool,
+    pub fecha_inicio: String,
+    pub fecha_fin: String,
+    pub nuevos: i32,
+    pub modificados: i32,
+    pub desaparecidos: i32,
+    pub desactivados: i32,
+}
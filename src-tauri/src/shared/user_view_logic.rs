@@ -0,0 +1,818 @@
+// src/shared/user_view_logic.rs
+/*
+Vistas de usuario (sección VISTAS: "Mis vistas" / "Todas las vistas").
+
+El usuario elige una entidad del ERP (catálogo riy_entidad_erp), columnas, filtros, orden
+y agrupación; la definición se guarda como JSON y el backend la compila a un SELECT
+parametrizado. Nunca se acepta SQL del usuario: los nombres de columna se validan contra
+las columnas reales de la entidad y los valores de los filtros van como parámetros.
+
+La seguridad por fila se aplica a las filas de la entidad antes de filtrar y agrupar:
+
+  SELECT <columnas / agregados>
+    FROM (SELECT sxf.* FROM (SELECT * FROM <entidad>) AS sxf WHERE <seguridad>) AS v
+   WHERE <filtros> GROUP BY <agrupación>
+
+y el resultado se ejecuta en el sandbox de solo lectura, paginado y con tiempo máximo.
+
+CREATE TABLE riy.riy_entidad_erp (
+    entidadID      INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID   INT           NOT NULL,
+    codigo         VARCHAR(50)   NOT NULL,
+    nombre         NVARCHAR(100) NOT NULL,
+    objetoOrigen   VARCHAR(256)  NOT NULL,
+    activo         BIT           NOT NULL DEFAULT 1,
+    autor          VARCHAR(50)   NOT NULL,
+    fechaCreacion  DATETIME      NOT NULL DEFAULT GETDATE(),
+    CONSTRAINT UQ_riy_entidad_erp UNIQUE (aplicativoID, codigo)
+);
+
+CREATE TABLE riy.riy_vista_usuario (
+    vistaID            INT IDENTITY(1,1) PRIMARY KEY,
+    aplicativoID       INT           NOT NULL,
+    nombre             NVARCHAR(100) NOT NULL,
+    descripcion        NVARCHAR(500) NULL,
+    entidadID          INT           NOT NULL,
+    definicion         NVARCHAR(MAX) NOT NULL, -- ViewDefinition en JSON
+    propietarioID      INT           NOT NULL,
+    fechaCreacion      DATETIME      NOT NULL DEFAULT GETDATE(),
+    modificadoPor      VARCHAR(50)   NULL,
+    fechaModificacion  DATETIME      NULL
+);
+
+CREATE TABLE riy.riy_vista_usuario_rol (
+    vistaID  INT NOT NULL,
+    rolID    INT NOT NULL,
+    CONSTRAINT PK_riy_vista_usuario_rol PRIMARY KEY (vistaID, rolID)
+);
+*/
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde_json::Value;
+use sqlx::{Mssql, Pool};
+
+use crate::role_logic;
+use crate::row_security_logic::{quote_identifier, RowSecurityError};
+use crate::saved_query_logic::QueryUser;
+use crate::saved_query_models::QueryExecutionResult;
+use crate::secured_query_logic::{self, quote_column};
+use crate::secured_query_models::{SecuredQuery, SqlValue};
+use crate::sql_sandbox_logic::{SandboxError, SqlSandbox};
+use crate::sql_sandbox_models::SandboxColumn;
+use crate::user_view_models::{
+    ErpEntity, ErpEntityInput, UserView, UserViewInput, ViewAggregate, ViewDataQuery, ViewDefinition,
+    ViewFilter, ViewFilterOperator, ViewPreviewRequest,
+};
+
+/// Crear vistas propias y ver las propias o compartidas.
+pub const PERMISO_MIS_VISTAS: &str = "mis_vistas";
+/// Ver y modificar todas las vistas y administrar el catálogo de entidades.
+pub const PERMISO_TODAS_LAS_VISTAS: &str = "todas_las_vistas";
+
+const MAX_COLUMNAS: usize = 100;
+const MAX_VALORES_LISTA: usize = 500;
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug)]
+pub enum UserViewError {
+    Validation(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Timeout(String),
+    Database(String),
+}
+
+impl fmt::Display for UserViewError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UserViewError::Validation(msg) => write!(f, "Error de validación: {}", msg),
+            UserViewError::Forbidden(msg) => write!(f, "Acceso denegado: {}", msg),
+            UserViewError::NotFound(msg) => write!(f, "No encontrado: {}", msg),
+            UserViewError::Conflict(msg) => write!(f, "Conflicto: {}", msg),
+            UserViewError::Timeout(msg) => write!(f, "Tiempo agotado: {}", msg),
+            UserViewError::Database(msg) => write!(f, "Error de base de datos: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for UserViewError {}
+
+impl From<RowSecurityError> for UserViewError {
+    fn from(e: RowSecurityError) -> Self {
+        match e {
+            RowSecurityError::Validation(msg) => UserViewError::Validation(msg),
+            RowSecurityError::NotFound(msg) => UserViewError::NotFound(msg),
+            RowSecurityError::Conflict(msg) => UserViewError::Conflict(msg),
+            RowSecurityError::Database(msg) => UserViewError::Database(msg),
+        }
+    }
+}
+
+impl From<SandboxError> for UserViewError {
+    fn from(e: SandboxError) -> Self {
+        match e {
+            SandboxError::Validation(msg) => UserViewError::Validation(msg),
+            SandboxError::Timeout(msg) => UserViewError::Timeout(msg),
+            SandboxError::Unavailable(msg) | SandboxError::Database(msg) => UserViewError::Database(msg),
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> UserViewError {
+    UserViewError::Database(e.to_string())
+}
+
+fn require_sandbox(sandbox: Option<&SqlSandbox>) -> Result<&SqlSandbox, UserViewError> {
+    sandbox.ok_or_else(|| UserViewError::from(SandboxError::Unavailable(
+        "La ejecución de vistas no está configurada (READONLY_DATABASE_URL)".to_string(),
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// Catálogo de entidades
+// ---------------------------------------------------------------------------
+
+fn entity_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT entidadID as entidad_id,
+                aplicativoID as aplicativo_id,
+                codigo {0} as codigo,
+                nombre,
+                objetoOrigen {0} as objeto_origen,
+                activo
+           FROM riy.riy_entidad_erp WITH(NOLOCK)",
+        sql_collate_clause
+    )
+}
+
+/// Entidades del aplicativo; `solo_activas` para el armado de vistas.
+pub async fn list_entities(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    solo_activas: bool,
+    sql_collate_clause: &str,
+) -> Result<Vec<ErpEntity>, UserViewError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND (@p2 = 0 OR activo = 1) ORDER BY nombre",
+        entity_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, ErpEntity>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(solo_activas)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+pub async fn get_entity(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    entidad_id: i32,
+    sql_collate_clause: &str,
+) -> Result<ErpEntity, UserViewError> {
+    let sql_query = format!(
+        "{} WHERE aplicativoID = @p1 AND entidadID = @p2",
+        entity_select(sql_collate_clause)
+    );
+    sqlx::query_as::<_, ErpEntity>(&sql_query)
+        .bind(aplicativo_id)
+        .bind(entidad_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| UserViewError::NotFound(format!("Entidad {} no encontrada", entidad_id)))
+}
+
+fn validate_entity(input: &ErpEntityInput) -> Result<(), UserViewError> {
+    let codigo = input.codigo.trim();
+    if codigo.is_empty() || !codigo.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(UserViewError::Validation(format!("Código de entidad inválido: '{}'", codigo)));
+    }
+    if input.nombre.trim().is_empty() {
+        return Err(UserViewError::Validation("El nombre es obligatorio".to_string()));
+    }
+    let objeto = quote_identifier(&input.objeto_origen)?;
+    // Las entidades se leen desde el sandbox, que no tiene acceso al esquema riy
+    if objeto.to_ascii_lowercase().starts_with("[riy].") {
+        return Err(UserViewError::Validation("Una entidad no puede ser una tabla del esquema riy".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn create_entity(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    input: &ErpEntityInput,
+    autor: &str,
+    sql_collate_clause: &str,
+) -> Result<ErpEntity, UserViewError> {
+    validate_entity(input)?;
+    let sql_query = format!(
+        "SELECT COUNT(*) FROM riy.riy_entidad_erp WITH(NOLOCK) WHERE aplicativoID = @p1 AND codigo {} = @p2",
+        sql_collate_clause
+    );
+    let (existentes,): (i32,) = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(input.codigo.trim())
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if existentes > 0 {
+        return Err(UserViewError::Conflict(format!("La entidad '{}' ya existe", input.codigo.trim())));
+    }
+    let (entidad_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_entidad_erp (aplicativoID, codigo, nombre, objetoOrigen, activo, autor, fechaCreacion)
+         OUTPUT INSERTED.entidadID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(input.codigo.trim())
+    .bind(input.nombre.trim())
+    .bind(input.objeto_origen.trim())
+    .bind(input.activo)
+    .bind(autor)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    get_entity(pool, aplicativo_id, entidad_id, sql_collate_clause).await
+}
+
+/// Modifica la entidad; el código no cambia.
+pub async fn update_entity(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    entidad_id: i32,
+    input: &ErpEntityInput,
+    sql_collate_clause: &str,
+) -> Result<ErpEntity, UserViewError> {
+    validate_entity(input)?;
+    get_entity(pool, aplicativo_id, entidad_id, sql_collate_clause).await?;
+    sqlx::query(
+        "UPDATE riy.riy_entidad_erp SET nombre = @p1, objetoOrigen = @p2, activo = @p3 WHERE entidadID = @p4"
+    )
+    .bind(input.nombre.trim())
+    .bind(input.objeto_origen.trim())
+    .bind(input.activo)
+    .bind(entidad_id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+    get_entity(pool, aplicativo_id, entidad_id, sql_collate_clause).await
+}
+
+fn entity_base_sql(entidad: &ErpEntity) -> Result<String, UserViewError> {
+    Ok(format!("SELECT * FROM {}", quote_identifier(&entidad.objeto_origen)?))
+}
+
+/// Columnas reales de la entidad, leídas desde el sandbox.
+pub async fn entity_columns(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    entidad_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<SandboxColumn>, UserViewError> {
+    let sandbox = require_sandbox(sandbox)?;
+    let entidad = get_entity(pool, aplicativo_id, entidad_id, sql_collate_clause).await?;
+    Ok(sandbox.describe_columns(&entity_base_sql(&entidad)?).await?)
+}
+
+// ---------------------------------------------------------------------------
+// Compilación de la definición
+// ---------------------------------------------------------------------------
+
+fn to_sql_value(valor: &Value) -> Result<SqlValue, UserViewError> {
+    match valor {
+        Value::Null => Ok(SqlValue::Null),
+        Value::Bool(b) => Ok(SqlValue::Bool(*b)),
+        Value::Number(n) => Ok(n.as_i64().map(SqlValue::Int).unwrap_or_else(|| SqlValue::Float(n.as_f64().unwrap_or_default()))),
+        Value::String(s) => Ok(SqlValue::Text(s.clone())),
+        otro => Err(UserViewError::Validation(format!("Valor de filtro inválido: {}", otro))),
+    }
+}
+
+/// Nombre real (según la entidad) de una columna pedida, sin distinguir mayúsculas.
+fn resolve_column<'a>(disponibles: &'a [SandboxColumn], columna: &str) -> Result<&'a str, UserViewError> {
+    disponibles
+        .iter()
+        .find(|c| c.nombre.eq_ignore_ascii_case(columna.trim()))
+        .map(|c| c.nombre.as_str())
+        .ok_or_else(|| UserViewError::Validation(format!("La entidad no tiene la columna '{}'", columna)))
+}
+
+fn filter_predicate(
+    filtro: &ViewFilter,
+    columna: &str,
+    params: &mut Vec<SqlValue>,
+) -> Result<String, UserViewError> {
+    let mut push = |valor: &Value| -> Result<String, UserViewError> {
+        params.push(to_sql_value(valor)?);
+        Ok(format!("@p{}", params.len()))
+    };
+    let valor = || {
+        filtro.valor.as_ref().filter(|v| !v.is_null()).ok_or_else(|| {
+            UserViewError::Validation(format!("El filtro sobre '{}' requiere un valor", filtro.columna))
+        })
+    };
+    let comparacion = |op: &str, marcador: String| format!("{} {} {}", columna, op, marcador);
+    Ok(match filtro.operador {
+        ViewFilterOperator::Igual => comparacion("=", push(valor()?)?),
+        ViewFilterOperator::Distinto => comparacion("<>", push(valor()?)?),
+        ViewFilterOperator::Mayor => comparacion(">", push(valor()?)?),
+        ViewFilterOperator::MayorIgual => comparacion(">=", push(valor()?)?),
+        ViewFilterOperator::Menor => comparacion("<", push(valor()?)?),
+        ViewFilterOperator::MenorIgual => comparacion("<=", push(valor()?)?),
+        ViewFilterOperator::Contiene => {
+            format!("CHARINDEX({}, CAST({} AS NVARCHAR(4000))) > 0", push(valor()?)?, columna)
+        }
+        ViewFilterOperator::EmpiezaCon => {
+            let marcador = push(valor()?)?;
+            format!("LEFT(CAST({0} AS NVARCHAR(4000)), LEN({1})) = {1}", columna, marcador)
+        }
+        ViewFilterOperator::Entre => {
+            if filtro.valores.len() != 2 {
+                return Err(UserViewError::Validation(format!(
+                    "El filtro 'entre' sobre '{}' requiere dos valores", filtro.columna
+                )));
+            }
+            let desde = push(&filtro.valores[0])?;
+            let hasta = push(&filtro.valores[1])?;
+            format!("{} BETWEEN {} AND {}", columna, desde, hasta)
+        }
+        ViewFilterOperator::EnLista => {
+            if filtro.valores.is_empty() || filtro.valores.len() > MAX_VALORES_LISTA {
+                return Err(UserViewError::Validation(format!(
+                    "El filtro 'en lista' sobre '{}' requiere entre 1 y {} valores", filtro.columna, MAX_VALORES_LISTA
+                )));
+            }
+            let marcadores = filtro.valores.iter().map(&mut push).collect::<Result<Vec<_>, _>>()?;
+            format!("{} IN ({})", columna, marcadores.join(", "))
+        }
+        ViewFilterOperator::EsNulo => format!("{} IS NULL", columna),
+        ViewFilterOperator::NoEsNulo => format!("{} IS NOT NULL", columna),
+    })
+}
+
+/// Compila la definición sobre `base` (la entidad con la seguridad por fila ya aplicada,
+/// con sus parámetros en `params`). Devuelve el SQL, los parámetros y el orden para el
+/// ejecutor. Función pura.
+pub fn compile_definition(
+    base: &str,
+    mut params: Vec<SqlValue>,
+    definicion: &ViewDefinition,
+    disponibles: &[SandboxColumn],
+) -> Result<(String, Vec<SqlValue>, Vec<String>), UserViewError> {
+    if definicion.columnas.is_empty() || definicion.columnas.len() > MAX_COLUMNAS {
+        return Err(UserViewError::Validation(format!("La vista debe tener entre 1 y {} columnas", MAX_COLUMNAS)));
+    }
+
+    let agrupar_por = definicion
+        .agrupar_por
+        .iter()
+        .map(|c| resolve_column(disponibles, c).map(str::to_string))
+        .collect::<Result<Vec<_>, _>>()?;
+    let agrupada = !agrupar_por.is_empty() || definicion.columnas.iter().any(|c| c.agregado.is_some());
+
+    let mut seleccion = Vec::with_capacity(definicion.columnas.len());
+    let mut salidas: HashSet<String> = HashSet::new();
+    for columna in &definicion.columnas {
+        let nombre = resolve_column(disponibles, &columna.columna)?;
+        let (expresion, salida) = match columna.agregado {
+            Some(agregado) => {
+                let salida = format!("{}_{}", agregado.prefix(), nombre);
+                let argumento = match agregado {
+                    // AVG sobre enteros trunca; se promedia en FLOAT
+                    ViewAggregate::Avg => format!("CAST(v.{} AS FLOAT)", quote_column(nombre)?),
+                    _ => format!("v.{}", quote_column(nombre)?),
+                };
+                (format!("{}({}) AS {}", agregado.sql(), argumento, quote_column(&salida)?), salida)
+            }
+            None => {
+                if agrupada && !agrupar_por.iter().any(|g| g == nombre) {
+                    return Err(UserViewError::Validation(format!(
+                        "La columna '{}' debe agruparse o tener un agregado", nombre
+                    )));
+                }
+                (format!("v.{}", quote_column(nombre)?), nombre.to_string())
+            }
+        };
+        if !salidas.insert(salida.to_ascii_lowercase()) {
+            return Err(UserViewError::Validation(format!("Columna repetida: '{}'", salida)));
+        }
+        seleccion.push(expresion);
+    }
+
+    let mut predicados = Vec::with_capacity(definicion.filtros.len());
+    for filtro in &definicion.filtros {
+        let columna = format!("v.{}", quote_column(resolve_column(disponibles, &filtro.columna)?)?);
+        predicados.push(filter_predicate(filtro, &columna, &mut params)?);
+    }
+
+    let mut orden = Vec::with_capacity(definicion.orden.len());
+    for item in &definicion.orden {
+        if !salidas.contains(&item.columna.trim().to_ascii_lowercase()) {
+            return Err(UserViewError::Validation(format!(
+                "Solo se puede ordenar por columnas de la vista: '{}'", item.columna
+            )));
+        }
+        orden.push(format!("{}{}", item.columna.trim(), if item.descendente { " DESC" } else { "" }));
+    }
+
+    let mut sql = format!("SELECT {} FROM ({}) AS v", seleccion.join(", "), base);
+    if !predicados.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&predicados.join(" AND "));
+    }
+    if !agrupar_por.is_empty() {
+        let grupos = agrupar_por
+            .iter()
+            .map(|g| quote_column(g).map(|q| format!("v.{}", q)))
+            .collect::<Result<Vec<_>, _>>()?;
+        sql.push_str(" GROUP BY ");
+        sql.push_str(&grupos.join(", "));
+    }
+    Ok((sql, params, orden))
+}
+
+/// Contexto de ejecución: pool principal (seguridad), sandbox y aplicativo.
+struct ViewRunner<'a> {
+    pool: &'a Pool<Mssql>,
+    sandbox: &'a SqlSandbox,
+    aplicativo_id: i32,
+    sql_collate_clause: &'a str,
+}
+
+impl ViewRunner<'_> {
+    /// Compila la definición para el usuario (sin paginar).
+    async fn compile(
+        &self,
+        usuario_id: i32,
+        entidad: &ErpEntity,
+        definicion: &ViewDefinition,
+    ) -> Result<SecuredQuery, UserViewError> {
+        let base = SecuredQuery {
+            sql: entity_base_sql(entidad)?,
+            objeto: Some(entidad.objeto_origen.clone()),
+            ..SecuredQuery::default()
+        };
+        let disponibles = self.sandbox.describe_columns(&base.sql).await?;
+        let permitidos = secured_query_logic::resolve_filters(
+            self.pool, self.aplicativo_id, usuario_id, &base, self.sql_collate_clause,
+        ).await?;
+        let (base_sql, base_params) = secured_query_logic::build_secured_sql(
+            &base, &permitidos, usuario_id, self.sql_collate_clause,
+        )?;
+        let (sql, params, order_by) = compile_definition(&base_sql, base_params, definicion, &disponibles)?;
+        Ok(SecuredQuery { sql, params, order_by, ..SecuredQuery::default() })
+    }
+
+    async fn run(
+        &self,
+        usuario_id: i32,
+        entidad: &ErpEntity,
+        definicion: &ViewDefinition,
+        pagina: &ViewDataQuery,
+    ) -> Result<QueryExecutionResult, UserViewError> {
+        let numero = pagina.pagina.unwrap_or(1).max(1);
+        let tamano = pagina.tamano_pagina.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let query = SecuredQuery {
+            offset: Some((numero - 1) * tamano),
+            limit: Some(tamano),
+            ..self.compile(usuario_id, entidad, definicion).await?
+        };
+        // El SQL lo arma compile_definition; la seguridad por fila ya está en la base
+        let total = self.sandbox.count_unchecked(
+            self.pool, self.aplicativo_id, usuario_id, &query, None, self.sql_collate_clause,
+        ).await?;
+        let resultado = self.sandbox.execute_unchecked(
+            self.pool, self.aplicativo_id, usuario_id, &query, None, self.sql_collate_clause,
+        ).await?;
+        Ok(QueryExecutionResult {
+            columnas: resultado.columnas,
+            filas: resultado.filas,
+            total,
+            truncado: resultado.truncado,
+            pagina: numero,
+            tamano_pagina: tamano,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Vistas
+// ---------------------------------------------------------------------------
+
+type ViewRow = (i32, i32, String, Option<String>, i32, String, String, i32, String, String, Option<String>, Option<String>);
+
+fn view_select(sql_collate_clause: &str) -> String {
+    format!(
+        "SELECT v.vistaID, v.aplicativoID, v.nombre, v.descripcion, v.entidadID, e.nombre,
+                v.definicion, v.propietarioID, u.usuario {0},
+                CONVERT(VARCHAR(19), v.fechaCreacion, 120),
+                v.modificadoPor {0},
+                CONVERT(VARCHAR(19), v.fechaModificacion, 120)
+           FROM riy.riy_vista_usuario v WITH(NOLOCK)
+           JOIN riy.riy_entidad_erp e WITH(NOLOCK) ON e.entidadID = v.entidadID
+           JOIN riy.riy_usuario u WITH(NOLOCK) ON u.usuario_id = v.propietarioID",
+        sql_collate_clause
+    )
+}
+
+async fn to_user_view(pool: &Pool<Mssql>, row: ViewRow) -> Result<UserView, UserViewError> {
+    let (vista_id, aplicativo_id, nombre, descripcion, entidad_id, entidad, definicion, propietario_id,
+         propietario, fecha_creacion, modificado_por, fecha_modificacion) = row;
+    let definicion: ViewDefinition = serde_json::from_str(&definicion)
+        .map_err(|e| UserViewError::Database(format!("Definición de la vista {} ilegible: {}", vista_id, e)))?;
+    let roles: Vec<(i32,)> = sqlx::query_as("SELECT rolID FROM riy.riy_vista_usuario_rol WITH(NOLOCK) WHERE vistaID = @p1")
+        .bind(vista_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    Ok(UserView {
+        vista_id,
+        aplicativo_id,
+        nombre,
+        descripcion,
+        entidad_id,
+        entidad,
+        definicion,
+        propietario_id,
+        propietario,
+        roles: roles.into_iter().map(|(r,)| r).collect(),
+        fecha_creacion,
+        modificado_por,
+        fecha_modificacion,
+    })
+}
+
+async fn fetch_views(
+    pool: &Pool<Mssql>,
+    sql_query: &str,
+    aplicativo_id: i32,
+    usuario_id: Option<i32>,
+) -> Result<Vec<UserView>, UserViewError> {
+    let rows: Vec<ViewRow> = sqlx::query_as(sql_query)
+        .bind(aplicativo_id)
+        .bind(usuario_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    let mut vistas = Vec::with_capacity(rows.len());
+    for row in rows {
+        vistas.push(to_user_view(pool, row).await?);
+    }
+    Ok(vistas)
+}
+
+/// "Todas las vistas" del aplicativo.
+pub async fn list_all(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<UserView>, UserViewError> {
+    let sql_query = format!("{} WHERE v.aplicativoID = @p1 ORDER BY v.nombre", view_select(sql_collate_clause));
+    fetch_views(pool, &sql_query, aplicativo_id, None).await
+}
+
+/// "Mis vistas": las propias y las compartidas con alguno de los roles del usuario.
+pub async fn list_visible(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<Vec<UserView>, UserViewError> {
+    let sql_query = format!(
+        "{} WHERE v.aplicativoID = @p1
+            AND (v.propietarioID = @p2
+                 OR EXISTS (SELECT 1 FROM riy.riy_vista_usuario_rol vr WITH(NOLOCK)
+                              JOIN riy.riy_SeguridadRolUsuario ru WITH(NOLOCK) ON ru.rolID = vr.rolID
+                             WHERE vr.vistaID = v.vistaID AND ru.usuarioID = @p2))
+          ORDER BY v.nombre",
+        view_select(sql_collate_clause)
+    );
+    fetch_views(pool, &sql_query, aplicativo_id, Some(usuario_id)).await
+}
+
+pub async fn get_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    sql_collate_clause: &str,
+) -> Result<UserView, UserViewError> {
+    let sql_query = format!("{} WHERE v.aplicativoID = @p1 AND v.vistaID = @p2", view_select(sql_collate_clause));
+    let row: ViewRow = sqlx::query_as(&sql_query)
+        .bind(aplicativo_id)
+        .bind(vista_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| UserViewError::NotFound(format!("Vista {} no encontrada", vista_id)))?;
+    to_user_view(pool, row).await
+}
+
+/// Carga la vista si el usuario puede verla (dueño, rol compartido o ve_todas).
+pub async fn get_visible_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<UserView, UserViewError> {
+    let vista = get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    if user.ve_todas || vista.propietario_id == user.usuario_id {
+        return Ok(vista);
+    }
+    let (compartida,): (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM riy.riy_vista_usuario_rol vr WITH(NOLOCK)
+           JOIN riy.riy_SeguridadRolUsuario ru WITH(NOLOCK) ON ru.rolID = vr.rolID
+          WHERE vr.vistaID = @p1 AND ru.usuarioID = @p2"
+    )
+    .bind(vista_id)
+    .bind(user.usuario_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+    if compartida == 0 {
+        return Err(UserViewError::NotFound(format!("Vista {} no encontrada", vista_id)));
+    }
+    Ok(vista)
+}
+
+/// Valida nombre, roles y que la definición compile contra las columnas reales de la entidad.
+async fn validate_input(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    input: &UserViewInput,
+    usuario_id: i32,
+    sql_collate_clause: &str,
+) -> Result<(), UserViewError> {
+    if input.nombre.trim().is_empty() {
+        return Err(UserViewError::Validation("El nombre es obligatorio".to_string()));
+    }
+    let entidad = get_entity(pool, aplicativo_id, input.entidad_id, sql_collate_clause).await?;
+    if !entidad.activo {
+        return Err(UserViewError::Validation(format!("La entidad '{}' está inactiva", entidad.codigo)));
+    }
+    let runner = ViewRunner { pool, sandbox: require_sandbox(sandbox)?, aplicativo_id, sql_collate_clause };
+    runner.compile(usuario_id, &entidad, &input.definicion).await?;
+
+    if !input.roles.is_empty() {
+        let roles = role_logic::get_roles_by_app(pool, aplicativo_id, sql_collate_clause)
+            .await
+            .map_err(UserViewError::Database)?;
+        if let Some(rol_id) = input.roles.iter().find(|id| !roles.iter().any(|r| r.rol_id == **id)) {
+            return Err(UserViewError::NotFound(format!("El rol {} no existe en el aplicativo", rol_id)));
+        }
+    }
+    Ok(())
+}
+
+fn definition_json(definicion: &ViewDefinition) -> Result<String, UserViewError> {
+    serde_json::to_string(definicion).map_err(|e| UserViewError::Validation(e.to_string()))
+}
+
+async fn replace_roles(
+    tx: &mut sqlx::Transaction<'_, Mssql>,
+    vista_id: i32,
+    roles: &[i32],
+) -> Result<(), UserViewError> {
+    sqlx::query("DELETE FROM riy.riy_vista_usuario_rol WHERE vistaID = @p1")
+        .bind(vista_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    for rol_id in roles {
+        sqlx::query("INSERT INTO riy.riy_vista_usuario_rol (vistaID, rolID) VALUES (@p1, @p2)")
+            .bind(vista_id)
+            .bind(rol_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+pub async fn create_view(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    input: &UserViewInput,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<UserView, UserViewError> {
+    validate_input(pool, sandbox, aplicativo_id, input, user.usuario_id, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let (vista_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO riy.riy_vista_usuario
+            (aplicativoID, nombre, descripcion, entidadID, definicion, propietarioID, fechaCreacion)
+         OUTPUT INSERTED.vistaID
+         VALUES (@p1, @p2, @p3, @p4, @p5, @p6, GETDATE())"
+    )
+    .bind(aplicativo_id)
+    .bind(input.nombre.trim())
+    .bind(input.descripcion.as_deref())
+    .bind(input.entidad_id)
+    .bind(definition_json(&input.definicion)?)
+    .bind(user.usuario_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    replace_roles(&mut tx, vista_id, &input.roles).await?;
+    tx.commit().await.map_err(db_error)?;
+    get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await
+}
+
+/// Solo el dueño o quien ve todas las vistas puede modificarla.
+pub async fn update_view(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    input: &UserViewInput,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<UserView, UserViewError> {
+    let actual = get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    if !user.ve_todas && actual.propietario_id != user.usuario_id {
+        return Err(UserViewError::Forbidden("Solo el dueño puede modificar la vista".to_string()));
+    }
+    validate_input(pool, sandbox, aplicativo_id, input, user.usuario_id, sql_collate_clause).await?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "UPDATE riy.riy_vista_usuario
+            SET nombre = @p1, descripcion = @p2, entidadID = @p3, definicion = @p4,
+                modificadoPor = @p5, fechaModificacion = GETDATE()
+          WHERE vistaID = @p6"
+    )
+    .bind(input.nombre.trim())
+    .bind(input.descripcion.as_deref())
+    .bind(input.entidad_id)
+    .bind(definition_json(&input.definicion)?)
+    .bind(user.usuario)
+    .bind(vista_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    replace_roles(&mut tx, vista_id, &input.roles).await?;
+    tx.commit().await.map_err(db_error)?;
+    get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await
+}
+
+pub async fn delete_view(
+    pool: &Pool<Mssql>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    user: &QueryUser<'_>,
+    sql_collate_clause: &str,
+) -> Result<(), UserViewError> {
+    let actual = get_view(pool, aplicativo_id, vista_id, sql_collate_clause).await?;
+    if !user.ve_todas && actual.propietario_id != user.usuario_id {
+        return Err(UserViewError::Forbidden("Solo el dueño puede eliminar la vista".to_string()));
+    }
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    for tabla in ["riy.riy_vista_usuario_rol", "riy.riy_vista_usuario"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE vistaID = @p1", tabla))
+            .bind(vista_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+    Ok(())
+}
+
+/// Datos de una vista guardada, con la seguridad por fila del usuario que la consulta.
+pub async fn view_data(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    vista_id: i32,
+    user: &QueryUser<'_>,
+    pagina: &ViewDataQuery,
+    sql_collate_clause: &str,
+) -> Result<QueryExecutionResult, UserViewError> {
+    let runner = ViewRunner { pool, sandbox: require_sandbox(sandbox)?, aplicativo_id, sql_collate_clause };
+    let vista = get_visible_view(pool, aplicativo_id, vista_id, user, sql_collate_clause).await?;
+    let entidad = get_entity(pool, aplicativo_id, vista.entidad_id, sql_collate_clause).await?;
+    runner.run(user.usuario_id, &entidad, &vista.definicion, pagina).await
+}
+
+/// Datos de una definición sin guardar, para el armador de vistas.
+pub async fn preview(
+    pool: &Pool<Mssql>,
+    sandbox: Option<&SqlSandbox>,
+    aplicativo_id: i32,
+    usuario_id: i32,
+    request: &ViewPreviewRequest,
+    sql_collate_clause: &str,
+) -> Result<QueryExecutionResult, UserViewError> {
+    let runner = ViewRunner { pool, sandbox: require_sandbox(sandbox)?, aplicativo_id, sql_collate_clause };
+    let entidad = get_entity(pool, aplicativo_id, request.entidad_id, sql_collate_clause).await?;
+    let pagina = ViewDataQuery { pagina: request.pagina, tamano_pagina: request.tamano_pagina };
+    runner.run(usuario_id, &entidad, &request.definicion, &pagina).await
+}
@@ -0,0 +1,176 @@
+// src/shared/user_view_models.rs
+// Modelos de las vistas de usuario ("Mis vistas" / "Todas las vistas").
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Entidad del ERP sobre la que se pueden armar vistas (`riy.riy_entidad_erp`).
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ErpEntity {
+    pub entidad_id: i32,
+    pub aplicativo_id: i32,
+    pub codigo: String,
+    pub nombre: String,
+    /// Tabla o vista del ERP (`esquema.objeto`).
+    pub objeto_origen: String,
+    pub activo: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErpEntityInput {
+    pub codigo: String,
+    pub nombre: String,
+    pub objeto_origen: String,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Función de agregación de una columna.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewAggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl ViewAggregate {
+    pub fn sql(&self) -> &'static str {
+        match self {
+            ViewAggregate::Count => "COUNT",
+            ViewAggregate::Sum => "SUM",
+            ViewAggregate::Avg => "AVG",
+            ViewAggregate::Min => "MIN",
+            ViewAggregate::Max => "MAX",
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ViewAggregate::Count => "count",
+            ViewAggregate::Sum => "sum",
+            ViewAggregate::Avg => "avg",
+            ViewAggregate::Min => "min",
+            ViewAggregate::Max => "max",
+        }
+    }
+}
+
+/// Columna seleccionada. Con agregado, su nombre en el resultado es `{agregado}_{columna}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewColumn {
+    pub columna: String,
+    /// Encabezado para la grilla; no afecta el SQL.
+    pub titulo: Option<String>,
+    pub agregado: Option<ViewAggregate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewFilterOperator {
+    Igual,
+    Distinto,
+    Mayor,
+    MayorIgual,
+    Menor,
+    MenorIgual,
+    Contiene,
+    EmpiezaCon,
+    /// `valores` con exactamente dos elementos.
+    Entre,
+    /// `valores` con al menos un elemento.
+    EnLista,
+    EsNulo,
+    NoEsNulo,
+}
+
+/// Filtro sobre las filas de la entidad (antes de agrupar).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewFilter {
+    pub columna: String,
+    pub operador: ViewFilterOperator,
+    pub valor: Option<Value>,
+    #[serde(default)]
+    pub valores: Vec<Value>,
+}
+
+/// Orden sobre una columna del resultado (nombre de salida).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewSort {
+    pub columna: String,
+    #[serde(default)]
+    pub descendente: bool,
+}
+
+/// Definición de la vista, guardada como JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewDefinition {
+    pub columnas: Vec<ViewColumn>,
+    #[serde(default)]
+    pub filtros: Vec<ViewFilter>,
+    #[serde(default)]
+    pub orden: Vec<ViewSort>,
+    #[serde(default)]
+    pub agrupar_por: Vec<String>,
+}
+
+/// Vista de usuario (`riy.riy_vista_usuario`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserView {
+    pub vista_id: i32,
+    pub aplicativo_id: i32,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub entidad_id: i32,
+    pub entidad: String,
+    pub definicion: ViewDefinition,
+    pub propietario_id: i32,
+    pub propietario: String,
+    /// Roles con los que se comparte.
+    pub roles: Vec<i32>,
+    pub fecha_creacion: String,
+    pub modificado_por: Option<String>,
+    pub fecha_modificacion: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserViewInput {
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub entidad_id: i32,
+    pub definicion: ViewDefinition,
+    #[serde(default)]
+    pub roles: Vec<i32>,
+}
+
+/// Vista previa de una definición sin guardar.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewPreviewRequest {
+    pub entidad_id: i32,
+    pub definicion: ViewDefinition,
+    pub pagina: Option<i64>,
+    pub tamano_pagina: Option<i64>,
+}
+
+/// Página de datos de una vista guardada.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewDataQuery {
+    pub pagina: Option<i64>,
+    pub tamano_pagina: Option<i64>,
+}
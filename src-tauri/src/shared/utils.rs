@@ -4,7 +4,7 @@ use anyhow::{Result, anyhow};
 use sqlx::{Pool, Mssql};
 
 // Importa tus modelos. Asumo que Claims está en el middleware.
-use crate::middleware::auth_claims::Claims; 
+use crate::middleware::auth_claims::{ActorClaim, Claims}; 
 use crate::models::LoggedInUser; 
 
 
@@ -14,34 +14,29 @@ use crate::models::LoggedInUser;
 
 /**
  * Función central para obtener los permisos de un usuario basados en el ID del aplicativo.
- * 🚨 IMPORTANTE: Ahora usa el ID del usuario y el ID del aplicativo.
+ * Todo usuario tiene "inicio"; el resto sale de los roles asignados en el aplicativo
+ * (riy_SeguridadRolPermiso, ver role_logic) y de las concesiones temporales vigentes.
  */
 pub async fn get_permissions_by_app(
     pool: &Pool<Mssql>, 
     usuario_id: i32, 
     aplicativo_id: i32, // ID CRÍTICO para filtrar por aplicación
 ) -> Result<Vec<String>> {
-    
-    // ⚠️ IMPLEMENTACIÓN REAL REQUERIDA
-    // DEBES reemplazar este MOCK con una consulta SQL que use:
-    // 1. usuario_id 
-    // 2. aplicativo_id (para filtrar los roles que son válidos para esta aplicación)
-    
-    // === MOCK/PLACEHOLDER (DEBES REEMPLAZAR) ===
-    let usuario_admin = usuario_id == 1 && aplicativo_id == 1; // Ejemplo: Si es Admin de la app 1
-    
-    let permissions = if usuario_admin {
-        vec![
-            "inicio".to_string(),
-            "administracion".to_string(),
-            "lista_usuarios".to_string(),
-            "agregar_usuario".to_string(),
-            // ... etc.
-        ]
-    } else {
-        // Permisos básicos para el resto
-        vec!["inicio".to_string()] 
-    };
+    let mut permissions = vec!["inicio".to_string()];
+
+    // Permisos de los roles asignados y de las concesiones temporales vigentes.
+    // Las concesiones vencidas o revocadas dejan de sumar en la próxima emisión del token.
+    let role_permissions = crate::role_logic::get_user_role_permissions(pool, usuario_id, aplicativo_id)
+        .await
+        .map_err(|e| anyhow!(e))?;
+    let grant_permissions = crate::grant_logic::active_grant_permissions(pool, usuario_id, aplicativo_id)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))?;
+    for permiso in role_permissions.into_iter().chain(grant_permissions) {
+        if !permissions.contains(&permiso) {
+            permissions.push(permiso);
+        }
+    }
 
     Ok(permissions)
 }
@@ -58,6 +53,7 @@ pub async fn get_permissions_by_app(
 pub fn generate_jwt(
     logged_in_user: &LoggedInUser,
     permissions: Vec<String>, 
+    aplicativo_id: i32,
     jwt_secret: &str
 ) -> Result<String> {
 
@@ -77,16 +73,68 @@ pub fn generate_jwt(
         permissions,
         // Expiración en 24 horas
         exp: (Utc::now() + Duration::hours(24)).timestamp() as u64, 
+        act: None,
+        apl: Some(aplicativo_id),
     };
     
     // 2. Codifica el token.
-    let token = encode(
+    encode_claims(&claims, jwt_secret)
+}
+
+/**
+ * Genera un JWT de suplantación: `sub` es el usuario suplantado, `act` el administrador
+ * que actúa en su nombre y la vigencia es de `minutes` minutos.
+ */
+pub fn generate_impersonation_jwt(
+    logged_in_user: &LoggedInUser,
+    permissions: Vec<String>,
+    actor: ActorClaim,
+    minutes: i64,
+    aplicativo_id: i32,
+    jwt_secret: &str,
+) -> Result<String> {
+    let user_sub = logged_in_user.usuario.clone()
+        .ok_or_else(|| anyhow!("El usuario suplantado no tiene nombre de usuario"))?;
+    let claims = Claims {
+        sub: user_sub,
+        permissions,
+        exp: (Utc::now() + Duration::minutes(minutes)).timestamp() as u64,
+        act: Some(actor),
+        apl: Some(aplicativo_id),
+    };
+    encode_claims(&claims, jwt_secret)
+}
+
+fn encode_claims(claims: &Claims, jwt_secret: &str) -> Result<String> {
+    encode(
         // Aseguramos que se usa el algoritmo HS256
         &Header::new(Algorithm::HS256), 
-        &claims, 
+        claims, 
         &EncodingKey::from_secret(jwt_secret.as_bytes())
-    ).map_err(|e| anyhow!("Error al crear el token: {}", e))?;
-
-    Ok(token)
+    ).map_err(|e| anyhow!("Error al crear el token: {}", e))
 }
 
+
+
+// ---------------------------------------------------------------------
+// VALIDACIONES
+// ---------------------------------------------------------------------
+
+/// Validación básica de formato de correo: una sola `@`, parte local no vacía,
+/// dominio con al menos un punto y sin espacios.
+pub fn is_valid_email(correo: &str) -> bool {
+    let correo = correo.trim();
+    if correo.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let mut parts = correo.split('@');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        _ => false,
+    }
+}
@@ -1,7 +1,7 @@
 // src-tauri/src/user.rs
 
 use tauri::State;
-use crate::models::{Usuario, UserSearchResult, LoginData, User}; //, LoggedInUser};
+use crate::models::{Usuario, UserSearchResult, LoginData, User, UserListQuery, UserListPage}; //, LoggedInUser};
 use crate::{AppState, user_logic}; // <-- Agrega user_logic aquí
 
 use shared_lib::user_logic::UserError;
@@ -13,6 +13,9 @@ use serde::{Deserialize, Serialize};
 use shared_lib::models::AuthRequestPayload; 
 use shared_lib::models::AuthResponsePayload; // <-- ¡Importar esta para el retorno!
 use shared_lib::auth; // Asegúrate de importar el módulo auth de la librería compartida
+use shared_lib::mfa_logic;
+use shared_lib::mfa_models::MfaChallengeResponse;
+use crate::row_security::logged_in_user_id;
 /* 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginData {
@@ -27,17 +30,46 @@ pub struct TauriLoginResponse {
     pub permissions: Vec<String>,
 }
 
+/// Resultado del login ERP/Local: la sesión o, igual que `POST /login`, el desafío MFA
+/// que hay que completar con los comandos de `mfa.rs`.
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum TauriLoginResult {
+    Session(TauriLoginResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+/// Deja al usuario como conectado y devuelve sus permisos.
+pub async fn open_session(
+    state: &tauri::State<'_, AppState>,
+    pool_ref: &sqlx::Pool<sqlx::Mssql>,
+    usuario: String,
+    nombre: String,
+) -> Result<TauriLoginResponse, String> {
+    let logged_in_user = LoggedInUser {
+        usuario: usuario.clone(),
+        nombre: Some(nombre),
+    };
+    *state.usuario_conectado.lock().await = Some(logged_in_user.clone());
+
+    let permissions = user_logic::get_user_permissions_logic(
+        pool_ref,
+        &usuario,
+    ).await.map_err(|e| e.to_string())?;
+
+    println!("logged in user: {:?}", logged_in_user);
+    Ok(TauriLoginResponse { user: logged_in_user, permissions })
+}
+
 // El comando Tauri que maneja el login
 #[tauri::command]
 pub async fn user_login(
     state: tauri::State<'_, AppState>,
     credentials: LoginData, // Asume que ya tienes este struct definido
-) -> Result<TauriLoginResponse, String> {
-
-    println!("credentials: {:?}", credentials);
+) -> Result<TauriLoginResult, String> {
 
     let pool_guard = state.db_pool.lock().await;
-    let pool_ref = pool_guard.as_ref().expect("DB Pool no disponible");
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no disponible".to_string())?;
     // 1. Llama a la lógica de autenticación centralizada
     let auth_result = user_logic::authenticate_user_logic(
         pool_ref,
@@ -49,36 +81,30 @@ pub async fn user_login(
 
     match auth_result {
         Ok(Some(user)) => {
-            // 2. Si el login es exitoso, obtén la referencia al estado y guarda el usuario
-            let mut user_state_guard = state.usuario_conectado.lock().await;
-            // Pasa el objeto `user` completo
-            //*user_state_guard = Some(user.clone());
-
-            // We will now create the LoggedInUser instance before saving to state.
-            let logged_in_user = LoggedInUser {
-                usuario: user.usuario.clone(), // Clone the string to transfer ownership
-                nombre: Some(user.nombre.clone()), // Use `Some()` to wrap the String
-            };
-
-            *user_state_guard = Some(logged_in_user.clone());
-
-            // 3. Obtén los permisos para la respuesta al frontend
-            let permissions = user_logic::get_user_permissions_logic(
+            // 2. Segundo factor: como en POST /login, si la política lo exige (o el usuario lo
+            // activó) se devuelve el desafío y la sesión no se abre hasta verificar el código.
+            let aplicativo_id = *state.aplicativo_id.lock().await;
+            if let Some(challenge) = mfa_logic::challenge_for_login(
                 pool_ref,
-                &credentials.usuario,
-            ).await.map_err(|e| e.to_string())?;
-
-            println!("logged in user: {:?}", logged_in_user);
-
-            // 4. Devuelve la respuesta completa al frontend de Tauri
-            Ok(TauriLoginResponse { user: logged_in_user, permissions })
+                aplicativo_id,
+                &user.usuario,
+                &state.config.app_jwt_secret,
+                &state.sql_collate_clause,
+            ).await.map_err(|e| e.to_string())? {
+                return Ok(TauriLoginResult::MfaRequired(challenge));
+            }
+
+            // 3. Guarda el usuario conectado y devuelve sus permisos al frontend de Tauri
+            open_session(&state, pool_ref, user.usuario, user.nombre)
+                .await
+                .map(TauriLoginResult::Session)
         },
         Ok(None) => {
-            // 5. Autenticación fallida
+            // 4. Autenticación fallida
             Err("Usuario o contraseña incorrectos".to_string())
         },
         Err(e) => {
-            // 6. Error interno del servidor
+            // 5. Error interno del servidor
             eprintln!("Error en el login: {}", e);
             Err("Error interno del servidor".to_string())
         }
@@ -89,20 +115,28 @@ pub async fn user_login(
 
 
 #[tauri::command]
-pub async fn get_users(state: State<'_, AppState>) -> Result<Vec<Usuario>, String> {
+pub async fn get_users(
+    state: State<'_, AppState>,
+    query: Option<UserListQuery>, // Filtros, orden y paginación (opcional)
+) -> Result<UserListPage, String> {
     // ...
     // 1. Confirma que el comando fue llamado
     println!("Backend: Llamada a get_users recibida.");
 
     let pool_guard = state.db_pool.lock().await;
     let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no disponible".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+    logged_in_user_id(&state, pool_ref, aplicativo_id, Some(user_logic::PERMISO_LISTA_USUARIOS)).await?;
     let sql_collate_clause_ref = &state.sql_collate_clause;
+    let query = query.unwrap_or_default();
+    user_logic::validate_user_list_query(&query)?;
     
     // 2. Confirma que la base de datos está siendo consultada
     println!("Backend: Iniciando consulta a la base de datos.");
 
     user_logic::get_all_users_logic(
         pool_ref, 
+        &query,
         sql_collate_clause_ref
     ).await
 }
@@ -262,7 +296,6 @@ pub async fn user_login_external(
 
     // 2. Secretos y configuraciones
     let http_client = &state.http_client;
-    let whitelisted_domains = &state.whitelisted_domains;
     let msal_client_id = &state.msal_client_id;
     let msal_jwks_url = &state.msal_jwks_url;
     let google_client_id = &state.google_client_id;
@@ -276,7 +309,6 @@ pub async fn user_login_external(
         payload,
         aplicativo_id,
         http_client,
-        whitelisted_domains,
         msal_client_id,
         msal_jwks_url,
         google_client_id,
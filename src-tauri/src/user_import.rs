@@ -0,0 +1,72 @@
+// src-tauri/src/user_import.rs
+
+use tauri::State;
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::AppState;
+use crate::user::get_logged_in_username;
+use shared_lib::user_import_logic;
+use shared_lib::user_import_models::{
+    UserFileFormat, UserImportPreview, UserImportResult,
+};
+
+/// Fase 1 de la importación: valida el archivo (Base64) y devuelve la vista previa.
+#[tauri::command]
+pub async fn import_users_preview(
+    state: State<'_, AppState>,
+    format: UserFileFormat,
+    content_base64: String,
+) -> Result<UserImportPreview, String> {
+    let autor = get_logged_in_username(&state).await?;
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    user_import_logic::preview_user_import(
+        pool_ref,
+        aplicativo_id,
+        format,
+        &content_base64,
+        &autor,
+        &state.sql_collate_clause,
+    ).await
+}
+
+/// Fase 2 de la importación: confirma la vista previa indicada.
+#[tauri::command]
+pub async fn import_users_commit(
+    state: State<'_, AppState>,
+    import_id: String,
+) -> Result<UserImportResult, String> {
+    let autor = get_logged_in_username(&state).await?;
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    user_import_logic::commit_user_import(
+        pool_ref,
+        aplicativo_id,
+        &import_id,
+        &autor,
+        &state.sql_collate_clause,
+    ).await
+}
+
+/// Exporta los usuarios; devuelve el archivo codificado en Base64.
+#[tauri::command]
+pub async fn export_users(
+    state: State<'_, AppState>,
+    format: Option<UserFileFormat>,
+) -> Result<String, String> {
+    let pool_guard = state.db_pool.lock().await;
+    let pool_ref = pool_guard.as_ref().ok_or_else(|| "Pool de DB no inicializado".to_string())?;
+    let aplicativo_id = *state.aplicativo_id.lock().await;
+
+    let bytes = user_import_logic::export_users(
+        pool_ref,
+        aplicativo_id,
+        format.unwrap_or_default(),
+        &state.sql_collate_clause,
+    ).await?;
+    Ok(general_purpose::STANDARD.encode(bytes))
+}
@@ -10,21 +10,10 @@ import DataForm from './DataForm';
 import DetailsModal from './DetailsModal';
 import { CustomApiError } from '../utils/api-client';
 import { useUser } from '../contexts/UserContext';
+import { Usuario } from '../types/user';
 
-// Define la estructura de datos del usuario
-interface Usuario {
-    usuario_id: number;
-    usuario: string;
-    nombre: string;
-    correo: string;
-    estado: string;
-    autor: string;
-    fecha_creacion: string;
-    modificado_por: string | null;
-    fecha_modificacion: string | null;
-    codigo_verificacion: number | null;
-    fecha_codigo_verificacion: string | null;
-}
+// Filas por página del listado
+const PAGE_SIZE = 50;
 
 // Define la estructura de datos para la búsqueda en ERP
 interface UserSearchResult {
@@ -61,6 +50,8 @@ const ListaDeUsuariosContent = () => {
     const { token } = useUser();
     const { hasPermission } = usePermissions();
     const [users, setUsers] = useState<Usuario[]>([]);
+    const [page, setPage] = useState<number>(1);
+    const [total, setTotal] = useState<number>(0);
     const [loading, setLoading] = useState<boolean>(true);
     const [error, setError] = useState<string | null>(null);
     const [addUserFlow, setAddUserFlow] = useState<AddUserFlow>('initial');
@@ -73,8 +64,9 @@ const ListaDeUsuariosContent = () => {
     const fetchUsers = async () => {
         try {
             setLoading(true);
-            const result = await getUsers(token);
-            setUsers(result);
+            const result = await getUsers({ page, pageSize: PAGE_SIZE });
+            setUsers(result.items);
+            setTotal(result.total);
             setError(null);
         } catch (e) {
             console.error("Error al obtener usuarios:", e);
@@ -86,7 +78,9 @@ const ListaDeUsuariosContent = () => {
 
     useEffect(() => {
         fetchUsers();
-    }, [token]);
+    }, [token, page]);
+
+    const totalPages = Math.max(1, Math.ceil(total / PAGE_SIZE));
 
     const handleAddUser = () => {
         setApiError(null);
@@ -256,6 +250,18 @@ const ListaDeUsuariosContent = () => {
                 </div>
             )}
 
+            {total > PAGE_SIZE && (
+                <div className="pagination">
+                    <button onClick={() => setPage(page - 1)} disabled={page <= 1}>
+                        Anterior
+                    </button>
+                    <span>Página {page} de {totalPages} ({total} usuarios)</span>
+                    <button onClick={() => setPage(page + 1)} disabled={page >= totalPages}>
+                        Siguiente
+                    </button>
+                </div>
+            )}
+
             {addUserFlow === 'search_erp' && (
                 <ERPUserSearch
                     onUserSelected={handleERPUserSelected}
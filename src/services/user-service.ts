@@ -1,16 +1,32 @@
 // api-service.ts
 import { callBackend} from '../utils/api-client'; // Importa la nueva función
-import { UserSearchResult } from '../types/user';
+import { UserSearchResult, UserListQuery, UserListPage } from '../types/user';
 import { PROTECTED_API_PATH } from '../api-config';
 
 // Define tus funciones de servicio
 
 
 
-// Modifica cada función que necesite autenticación para que reciba el token.
-export const getUsers = async (): Promise<UserSearchResult[]> => {
-    // Pasa el token a callBackend
-    return await callBackend('get_users', {}, `${PROTECTED_API_PATH}/users`, 'GET'); 
+/**
+ * Obtiene una página del listado de usuarios.
+ * Sin parámetros devuelve la primera página (50 filas); `total` indica cuántas hay en total.
+ * @param query Filtros, orden y paginación.
+ */
+export const getUsers = async (query: UserListQuery = {}): Promise<UserListPage> => {
+    // En la web los parámetros van en la query string; en Tauri como argumento `query`
+    const params = new URLSearchParams();
+    Object.entries(query).forEach(([key, value]) => {
+        if (value !== undefined && value !== null && value !== '') {
+            params.append(key, String(value));
+        }
+    });
+    const queryString = params.toString();
+    return await callBackend(
+        'get_users',
+        { query },
+        `${PROTECTED_API_PATH}/users${queryString ? `?${queryString}` : ''}`,
+        'GET'
+    );
 };
 
 /**
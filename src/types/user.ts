@@ -6,6 +6,48 @@ export interface UserSearchResult {
   nombre: string;
 }
 
+// Usuario de riy.riy_usuario tal como lo devuelve el listado
+export interface Usuario {
+    usuario_id: number;
+    usuario: string;
+    nombre: string;
+    correo: string;
+    estado: string;
+    autor: string;
+    fecha_creacion: string;
+    modificado_por: string | null;
+    fecha_modificacion: string | null;
+}
+
+export type UsuarioSortColumn =
+    | 'usuarioId' | 'usuario' | 'nombre' | 'correo' | 'estado'
+    | 'autor' | 'fechaCreacion' | 'modificadoPor' | 'fechaModificacion';
+
+// Filtros, orden y paginación del listado de usuarios (UserListQuery en Rust)
+export interface UserListQuery {
+    page?: number;
+    pageSize?: number;
+    cursor?: string;
+    sortBy?: UsuarioSortColumn;
+    sortDir?: 'asc' | 'desc';
+    estado?: string;
+    autor?: string;
+    fechaCreacionDesde?: string;
+    fechaCreacionHasta?: string;
+    fechaModificacionDesde?: string;
+    fechaModificacionHasta?: string;
+    search?: string;
+}
+
+// Página del listado de usuarios (UserListPage en Rust)
+export interface UserListPage {
+    items: Usuario[];
+    total: number;
+    page: number;
+    pageSize: number;
+    nextCursor: string | null;
+}
+
 // La interfaz de TypeScript debe coincidir con el struct de Rust
 export interface LoggedInUser {
     usuario: string;